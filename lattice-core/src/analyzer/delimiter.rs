@@ -0,0 +1,372 @@
+//! Token Delimiter Scanning
+//!
+//! A [`DelimiterSet`] is a set of ASCII bytes that separate tokens. Scanning
+//! for it is vectorized with the nibble-lookup technique: each byte's low
+//! nibble indexes a 16-entry table holding a bitmask of high nibbles, so one
+//! pair of table shuffles classifies 16 bytes at once for any ASCII set.
+//!
+//! ## Implementations
+//!
+//! | Target | Implementation |
+//! |--------|----------------|
+//! | Space only | `memchr` (SIMD on every target it supports) |
+//! | x86_64 with SSSE3 (detected at runtime) | `pshufb`, 16 bytes per step |
+//! | aarch64 | NEON `tbl`, 16 bytes per step |
+//! | Others | Scalar table lookup |
+//!
+//! Only ASCII bytes can be delimiters. Bytes of multi-byte UTF-8 sequences
+//! are all `>= 0x80`, so splitting at delimiters always yields valid UTF-8
+//! slices.
+
+use memchr::memchr_iter;
+
+/// Set of ASCII bytes treated as token boundaries.
+///
+/// Stored as a 16-byte table: bit `h` of entry `l` is set when byte
+/// `h << 4 | l` is in the set. That is both a compact bitmap and the
+/// lookup table the SIMD scanners use directly.
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::analyzer::DelimiterSet;
+///
+/// let set = DelimiterSet::WHITESPACE.with(b',');
+/// assert!(set.contains(b'\t'));
+/// assert!(set.contains(b','));
+/// assert!(!set.contains(b'a'));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DelimiterSet {
+    table: [u8; 16],
+}
+
+impl DelimiterSet {
+    /// The empty set.
+    pub const EMPTY: Self = Self { table: [0; 16] };
+
+    /// ASCII space only; the tokenizer default, matching normalized text.
+    pub const SPACE: Self = Self::EMPTY.with(b' ');
+
+    /// ASCII whitespace: space, tab, newline, vertical tab, form feed and
+    /// carriage return.
+    pub const WHITESPACE: Self = Self::from_bytes(b" \t\n\x0b\x0c\r");
+
+    /// List separators: `,` `;` `:` `|`.
+    pub const SEPARATORS: Self = Self::from_bytes(b",;:|");
+
+    /// Path and URL separators: `/` `\\`.
+    pub const PATH_SEPARATORS: Self = Self::from_bytes(b"/\\");
+
+    /// Brackets: `(` `)` `[` `]` `{` `}` `<` `>`.
+    pub const BRACKETS: Self = Self::from_bytes(b"()[]{}<>");
+
+    /// Quotes: `"` `'` `` ` ``.
+    pub const QUOTES: Self = Self::from_bytes(b"\"'`");
+
+    /// ASCII punctuation (`!"#$%&'()*+,-./:;<=>?@[\]^_`{|}~`).
+    pub const PUNCTUATION: Self = Self::from_bytes(b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~");
+
+    /// Builds a set from a list of bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any byte is not ASCII.
+    pub const fn from_bytes(bytes: &[u8]) -> Self {
+        let mut set = Self::EMPTY;
+        let mut i = 0;
+        while i < bytes.len() {
+            set = set.with(bytes[i]);
+            i += 1;
+        }
+        set
+    }
+
+    /// Returns this set with `byte` added.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `byte` is not ASCII.
+    #[must_use]
+    pub const fn with(mut self, byte: u8) -> Self {
+        assert!(byte.is_ascii(), "delimiters must be ASCII bytes");
+        self.table[(byte & 0x0f) as usize] |= 1 << (byte >> 4);
+        self
+    }
+
+    /// Returns the union of two sets.
+    #[must_use]
+    pub const fn union(mut self, other: Self) -> Self {
+        let mut i = 0;
+        while i < 16 {
+            self.table[i] |= other.table[i];
+            i += 1;
+        }
+        self
+    }
+
+    /// Returns the raw lookup table (for persistence).
+    pub(crate) const fn table(&self) -> [u8; 16] {
+        self.table
+    }
+
+    /// Rebuilds a set from [`table`](Self::table). Every table is valid.
+    pub(crate) const fn from_table(table: [u8; 16]) -> Self {
+        Self { table }
+    }
+
+    /// Returns true if `byte` is in the set.
+    #[inline(always)]
+    pub const fn contains(&self, byte: u8) -> bool {
+        byte.is_ascii() && self.table[(byte & 0x0f) as usize] & (1 << (byte >> 4)) != 0
+    }
+
+    /// Returns true if the set holds no bytes.
+    pub const fn is_empty(&self) -> bool {
+        let mut i = 0;
+        while i < 16 {
+            if self.table[i] != 0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Calls `f` with the index of every delimiter byte in `bytes`, in
+    /// order, until it returns `false`.
+    #[inline]
+    pub fn scan<F>(&self, bytes: &[u8], mut f: F)
+    where
+        F: FnMut(usize) -> bool,
+    {
+        if *self == Self::SPACE {
+            for i in memchr_iter(b' ', bytes) {
+                if !f(i) {
+                    return;
+                }
+            }
+            return;
+        }
+        if self.is_empty() {
+            return;
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if std::is_x86_feature_detected!("ssse3") {
+            // SAFETY: SSSE3 support was just checked.
+            unsafe { x86::scan(self, bytes, f) };
+            return;
+        }
+
+        #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+        {
+            // SAFETY: NEON is enabled at compile time.
+            unsafe { neon::scan(self, bytes, f) };
+        }
+
+        #[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+        self.scan_scalar(bytes, 0, f);
+    }
+
+    /// Scalar scan of `bytes[from..]`; also finishes the SIMD tails.
+    #[inline(always)]
+    fn scan_scalar<F>(&self, bytes: &[u8], from: usize, mut f: F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        for (i, &b) in bytes.iter().enumerate().skip(from) {
+            if self.contains(b) && !f(i) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Reports the set bits of a 16-byte chunk mask; false if `f` stopped.
+    #[inline(always)]
+    fn emit_mask<F>(mut mask: u32, base: usize, f: &mut F) -> bool
+    where
+        F: FnMut(usize) -> bool,
+    {
+        while mask != 0 {
+            if !f(base + mask.trailing_zeros() as usize) {
+                return false;
+            }
+            mask &= mask - 1;
+        }
+        true
+    }
+}
+
+impl Default for DelimiterSet {
+    fn default() -> Self {
+        Self::SPACE
+    }
+}
+
+/// High-nibble lookup: bit `h` for ASCII high nibbles, nothing for `>= 0x80`.
+const HIGH_NIBBLE_BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 0, 0, 0, 0, 0, 0, 0, 0];
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{DelimiterSet, HIGH_NIBBLE_BITS};
+    use core::arch::x86_64::*;
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn scan<F>(set: &DelimiterSet, bytes: &[u8], mut f: F)
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let low_table = _mm_loadu_si128(set.table.as_ptr().cast());
+        let high_table = _mm_loadu_si128(HIGH_NIBBLE_BITS.as_ptr().cast());
+        let nibble = _mm_set1_epi8(0x0f);
+        let zero = _mm_setzero_si128();
+
+        let mut i = 0;
+        while i + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(i).cast());
+            let low = _mm_and_si128(chunk, nibble);
+            let high = _mm_and_si128(_mm_srli_epi16::<4>(chunk), nibble);
+            let hits = _mm_and_si128(
+                _mm_shuffle_epi8(low_table, low),
+                _mm_shuffle_epi8(high_table, high),
+            );
+            let mask = !_mm_movemask_epi8(_mm_cmpeq_epi8(hits, zero)) as u32 & 0xffff;
+            if !DelimiterSet::emit_mask(mask, i, &mut f) {
+                return;
+            }
+            i += 16;
+        }
+        set.scan_scalar(bytes, i, f);
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use super::{DelimiterSet, HIGH_NIBBLE_BITS};
+    use core::arch::aarch64::*;
+
+    /// Per-lane weights for turning a comparison vector into a bitmask
+    const LANE_BITS: [u8; 16] = [1, 2, 4, 8, 16, 32, 64, 128, 1, 2, 4, 8, 16, 32, 64, 128];
+
+    pub(super) unsafe fn scan<F>(set: &DelimiterSet, bytes: &[u8], mut f: F)
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let low_table = vld1q_u8(set.table.as_ptr());
+        let high_table = vld1q_u8(HIGH_NIBBLE_BITS.as_ptr());
+        let lane_bits = vld1q_u8(LANE_BITS.as_ptr());
+        let nibble = vdupq_n_u8(0x0f);
+
+        let mut i = 0;
+        while i + 16 <= bytes.len() {
+            let chunk = vld1q_u8(bytes.as_ptr().add(i));
+            let low = vandq_u8(chunk, nibble);
+            let high = vshrq_n_u8::<4>(chunk);
+            let hits = vandq_u8(vqtbl1q_u8(low_table, low), vqtbl1q_u8(high_table, high));
+            let bits = vandq_u8(vtstq_u8(hits, hits), lane_bits);
+            let mask =
+                vaddv_u8(vget_low_u8(bits)) as u32 | (vaddv_u8(vget_high_u8(bits)) as u32) << 8;
+            if !DelimiterSet::emit_mask(mask, i, &mut f) {
+                return;
+            }
+            i += 16;
+        }
+        set.scan_scalar(bytes, i, f);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(set: DelimiterSet, bytes: &[u8]) -> Vec<usize> {
+        let mut out = Vec::new();
+        set.scan(bytes, |i| {
+            out.push(i);
+            true
+        });
+        out
+    }
+
+    #[test]
+    fn membership() {
+        let set = DelimiterSet::from_bytes(b" ,/");
+        for b in 0..=255u8 {
+            assert_eq!(set.contains(b), b" ,/".contains(&b), "byte {:#x}", b);
+        }
+        assert!(DelimiterSet::EMPTY.is_empty());
+        assert!(!DelimiterSet::SPACE.is_empty());
+        assert_eq!(DelimiterSet::default(), DelimiterSet::SPACE);
+
+        let all = DelimiterSet::WHITESPACE.union(DelimiterSet::PUNCTUATION);
+        for b in 0..=255u8 {
+            let expected = b.is_ascii_whitespace() || b == 0x0b || b.is_ascii_punctuation();
+            assert_eq!(all.contains(b), expected, "byte {:#x}", b);
+        }
+    }
+
+    #[test]
+    fn punctuation_classes_are_subsets() {
+        for class in [
+            DelimiterSet::SEPARATORS,
+            DelimiterSet::PATH_SEPARATORS,
+            DelimiterSet::BRACKETS,
+            DelimiterSet::QUOTES,
+        ] {
+            assert!(!class.is_empty());
+            assert_eq!(
+                class.union(DelimiterSet::PUNCTUATION),
+                DelimiterSet::PUNCTUATION
+            );
+        }
+        assert!(DelimiterSet::PATH_SEPARATORS.contains(b'\\'));
+        assert!(DelimiterSet::QUOTES.contains(b'`'));
+    }
+
+    #[test]
+    #[should_panic(expected = "ASCII")]
+    fn rejects_non_ascii() {
+        let _ = DelimiterSet::EMPTY.with(0xc3);
+    }
+
+    #[test]
+    fn scan_matches_scalar_reference() {
+        let sets = [
+            DelimiterSet::SPACE,
+            DelimiterSet::WHITESPACE,
+            DelimiterSet::PUNCTUATION,
+            DelimiterSet::from_bytes(b"\x00\x7f/"),
+        ];
+        // Covers every byte value at every chunk offset, including UTF-8
+        let mut text: Vec<u8> = (0..=255u8).collect();
+        text.extend_from_slice("a,b/c d\té ü-x".repeat(7).as_bytes());
+        text.extend((0..=255u8).rev());
+
+        for set in sets {
+            for start in 0..17 {
+                let bytes = &text[start..];
+                let expected: Vec<usize> = bytes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &b)| set.contains(b))
+                    .map(|(i, _)| i)
+                    .collect();
+                assert_eq!(positions(set, bytes), expected, "{:?} from {}", set, start);
+            }
+        }
+        assert!(positions(DelimiterSet::EMPTY, b"a b").is_empty());
+    }
+
+    #[test]
+    fn scan_stops_when_callback_returns_false() {
+        let bytes = b"a,b,c,d,e,f,g,h,i,j,k,l,m,n,o,p,q";
+        let mut seen = Vec::new();
+        DelimiterSet::PUNCTUATION.scan(bytes, |i| {
+            seen.push(i);
+            seen.len() < 3
+        });
+        assert_eq!(seen, vec![1, 3, 5]);
+    }
+}
@@ -0,0 +1,287 @@
+//! Emoji and pictographic symbols.
+//!
+//! Emoji pass through normalization untouched by default, and each one
+//! turns into a few trigrams of raw UTF-8 bytes that match nothing a user
+//! types. [`EmojiPolicy`](crate::analyzer::EmojiPolicy) drops them or
+//! replaces them with their shortcode ("👍" → "thumbsup"), which can be
+//! searched for as a word.
+//!
+//! Emoji are recognized by block: the pictographic planes U+1F000..U+1FAFF
+//! and the BMP blocks of symbols, dingbats and arrows most emoji come
+//! from. Presentation selectors, skin tone modifiers, keycap marks and
+//! the joiners inside emoji sequences carry no meaning of their own and
+//! are removed along with the emoji.
+
+use core::iter::Peekable;
+use core::str::Chars;
+
+/// Shortcodes of common emoji, sorted by character. Names follow the
+/// GitHub and Slack ones.
+const SHORTCODES: &[(char, &str)] = &[
+    ('\u{A9}', "copyright"),                         // ©
+    ('\u{AE}', "registered"),                        // ®
+    ('\u{203C}', "bangbang"),                        // ‼
+    ('\u{2049}', "interrobang"),                     // ⁉
+    ('\u{2122}', "tm"),                              // ™
+    ('\u{2139}', "information_source"),              // ℹ
+    ('\u{21A9}', "leftwards_arrow_with_hook"),       // ↩
+    ('\u{231B}', "hourglass"),                       // ⌛
+    ('\u{23F0}', "alarm_clock"),                     // ⏰
+    ('\u{23F3}', "hourglass_flowing_sand"),          // ⏳
+    ('\u{2600}', "sunny"),                           // ☀
+    ('\u{2601}', "cloud"),                           // ☁
+    ('\u{2615}', "coffee"),                          // ☕
+    ('\u{2639}', "frowning_face"),                   // ☹
+    ('\u{263A}', "relaxed"),                         // ☺
+    ('\u{26A0}', "warning"),                         // ⚠
+    ('\u{26A1}', "zap"),                             // ⚡
+    ('\u{26BD}', "soccer"),                          // ⚽
+    ('\u{26D4}', "no_entry"),                        // ⛔
+    ('\u{2705}', "white_check_mark"),                // ✅
+    ('\u{2708}', "airplane"),                        // ✈
+    ('\u{2709}', "envelope"),                        // ✉
+    ('\u{270B}', "raised_hand"),                     // ✋
+    ('\u{270F}', "pencil2"),                         // ✏
+    ('\u{2714}', "heavy_check_mark"),                // ✔
+    ('\u{2716}', "heavy_multiplication_x"),          // ✖
+    ('\u{2728}', "sparkles"),                        // ✨
+    ('\u{274C}', "cross_mark"),                      // ❌
+    ('\u{2753}', "question"),                        // ❓
+    ('\u{2757}', "exclamation"),                     // ❗
+    ('\u{2764}', "heart"),                           // ❤
+    ('\u{27A1}', "arrow_right"),                     // ➡
+    ('\u{2B05}', "arrow_left"),                      // ⬅
+    ('\u{2B06}', "arrow_up"),                        // ⬆
+    ('\u{2B07}', "arrow_down"),                      // ⬇
+    ('\u{2B50}', "star"),                            // ⭐
+    ('\u{1F192}', "cool"),                           // 🆒
+    ('\u{1F193}', "free"),                           // 🆓
+    ('\u{1F195}', "new"),                            // 🆕
+    ('\u{1F197}', "ok"),                             // 🆗
+    ('\u{1F198}', "sos"),                            // 🆘
+    ('\u{1F308}', "rainbow"),                        // 🌈
+    ('\u{1F30D}', "earth_africa"),                   // 🌍
+    ('\u{1F31E}', "sun_with_face"),                  // 🌞
+    ('\u{1F31F}', "star2"),                          // 🌟
+    ('\u{1F327}', "cloud_with_rain"),                // 🌧
+    ('\u{1F340}', "four_leaf_clover"),               // 🍀
+    ('\u{1F355}', "pizza"),                          // 🍕
+    ('\u{1F37A}', "beer"),                           // 🍺
+    ('\u{1F381}', "gift"),                           // 🎁
+    ('\u{1F382}', "birthday"),                       // 🎂
+    ('\u{1F389}', "tada"),                           // 🎉
+    ('\u{1F38A}', "confetti_ball"),                  // 🎊
+    ('\u{1F3B5}', "musical_note"),                   // 🎵
+    ('\u{1F3C6}', "trophy"),                         // 🏆
+    ('\u{1F3E0}', "house"),                          // 🏠
+    ('\u{1F41B}', "bug"),                            // 🐛
+    ('\u{1F431}', "cat"),                            // 🐱
+    ('\u{1F436}', "dog"),                            // 🐶
+    ('\u{1F440}', "eyes"),                           // 👀
+    ('\u{1F44B}', "wave"),                           // 👋
+    ('\u{1F44C}', "ok_hand"),                        // 👌
+    ('\u{1F44D}', "thumbsup"),                       // 👍
+    ('\u{1F44E}', "thumbsdown"),                     // 👎
+    ('\u{1F44F}', "clap"),                           // 👏
+    ('\u{1F480}', "skull"),                          // 💀
+    ('\u{1F494}', "broken_heart"),                   // 💔
+    ('\u{1F495}', "two_hearts"),                     // 💕
+    ('\u{1F496}', "sparkling_heart"),                // 💖
+    ('\u{1F499}', "blue_heart"),                     // 💙
+    ('\u{1F49A}', "green_heart"),                    // 💚
+    ('\u{1F49B}', "yellow_heart"),                   // 💛
+    ('\u{1F49C}', "purple_heart"),                   // 💜
+    ('\u{1F4A1}', "bulb"),                           // 💡
+    ('\u{1F4A4}', "zzz"),                            // 💤
+    ('\u{1F4A5}', "boom"),                           // 💥
+    ('\u{1F4A9}', "poop"),                           // 💩
+    ('\u{1F4AA}', "muscle"),                         // 💪
+    ('\u{1F4AF}', "100"),                            // 💯
+    ('\u{1F4C5}', "date"),                           // 📅
+    ('\u{1F4C8}', "chart_with_upwards_trend"),       // 📈
+    ('\u{1F4C9}', "chart_with_downwards_trend"),     // 📉
+    ('\u{1F4CC}', "pushpin"),                        // 📌
+    ('\u{1F4CE}', "paperclip"),                      // 📎
+    ('\u{1F4DD}', "memo"),                           // 📝
+    ('\u{1F4DE}', "telephone_receiver"),             // 📞
+    ('\u{1F4E6}', "package"),                        // 📦
+    ('\u{1F4E7}', "email"),                          // 📧
+    ('\u{1F4F1}', "iphone"),                         // 📱
+    ('\u{1F50D}', "mag"),                            // 🔍
+    ('\u{1F511}', "key"),                            // 🔑
+    ('\u{1F512}', "lock"),                           // 🔒
+    ('\u{1F513}', "unlock"),                         // 🔓
+    ('\u{1F514}', "bell"),                           // 🔔
+    ('\u{1F517}', "link"),                           // 🔗
+    ('\u{1F525}', "fire"),                           // 🔥
+    ('\u{1F527}', "wrench"),                         // 🔧
+    ('\u{1F528}', "hammer"),                         // 🔨
+    ('\u{1F534}', "red_circle"),                     // 🔴
+    ('\u{1F600}', "grinning"),                       // 😀
+    ('\u{1F601}', "grin"),                           // 😁
+    ('\u{1F602}', "joy"),                            // 😂
+    ('\u{1F603}', "smiley"),                         // 😃
+    ('\u{1F604}', "smile"),                          // 😄
+    ('\u{1F605}', "sweat_smile"),                    // 😅
+    ('\u{1F606}', "laughing"),                       // 😆
+    ('\u{1F609}', "wink"),                           // 😉
+    ('\u{1F60A}', "blush"),                          // 😊
+    ('\u{1F60B}', "yum"),                            // 😋
+    ('\u{1F60D}', "heart_eyes"),                     // 😍
+    ('\u{1F60E}', "sunglasses"),                     // 😎
+    ('\u{1F610}', "neutral_face"),                   // 😐
+    ('\u{1F611}', "expressionless"),                 // 😑
+    ('\u{1F612}', "unamused"),                       // 😒
+    ('\u{1F613}', "sweat"),                          // 😓
+    ('\u{1F614}', "pensive"),                        // 😔
+    ('\u{1F615}', "confused"),                       // 😕
+    ('\u{1F616}', "confounded"),                     // 😖
+    ('\u{1F618}', "kissing_heart"),                  // 😘
+    ('\u{1F61B}', "stuck_out_tongue"),               // 😛
+    ('\u{1F61C}', "stuck_out_tongue_winking_eye"),   // 😜
+    ('\u{1F61E}', "disappointed"),                   // 😞
+    ('\u{1F61F}', "worried"),                        // 😟
+    ('\u{1F620}', "angry"),                          // 😠
+    ('\u{1F621}', "rage"),                           // 😡
+    ('\u{1F622}', "cry"),                            // 😢
+    ('\u{1F623}', "persevere"),                      // 😣
+    ('\u{1F624}', "triumph"),                        // 😤
+    ('\u{1F629}', "weary"),                          // 😩
+    ('\u{1F62A}', "sleepy"),                         // 😪
+    ('\u{1F62B}', "tired_face"),                     // 😫
+    ('\u{1F62C}', "grimacing"),                      // 😬
+    ('\u{1F62D}', "sob"),                            // 😭
+    ('\u{1F62E}', "open_mouth"),                     // 😮
+    ('\u{1F631}', "scream"),                         // 😱
+    ('\u{1F632}', "astonished"),                     // 😲
+    ('\u{1F633}', "flushed"),                        // 😳
+    ('\u{1F634}', "sleeping"),                       // 😴
+    ('\u{1F635}', "dizzy_face"),                     // 😵
+    ('\u{1F637}', "mask"),                           // 😷
+    ('\u{1F641}', "slightly_frowning_face"),         // 🙁
+    ('\u{1F642}', "slightly_smiling_face"),          // 🙂
+    ('\u{1F643}', "upside_down_face"),               // 🙃
+    ('\u{1F644}', "roll_eyes"),                      // 🙄
+    ('\u{1F648}', "see_no_evil"),                    // 🙈
+    ('\u{1F64C}', "raised_hands"),                   // 🙌
+    ('\u{1F64F}', "pray"),                           // 🙏
+    ('\u{1F680}', "rocket"),                         // 🚀
+    ('\u{1F697}', "car"),                            // 🚗
+    ('\u{1F6A7}', "construction"),                   // 🚧
+    ('\u{1F6A8}', "rotating_light"),                 // 🚨
+    ('\u{1F6AB}', "no_entry_sign"),                  // 🚫
+    ('\u{1F6D1}', "stop_sign"),                      // 🛑
+    ('\u{1F7E1}', "yellow_circle"),                  // 🟡
+    ('\u{1F7E2}', "green_circle"),                   // 🟢
+    ('\u{1F914}', "thinking"),                       // 🤔
+    ('\u{1F917}', "hugs"),                           // 🤗
+    ('\u{1F923}', "rofl"),                           // 🤣
+    ('\u{1F926}', "facepalm"),                       // 🤦
+    ('\u{1F937}', "shrug"),                          // 🤷
+    ('\u{1F970}', "smiling_face_with_three_hearts"), // 🥰
+    ('\u{1F973}', "partying_face"),                  // 🥳
+    ('\u{1F97A}', "pleading_face"),                  // 🥺
+];
+
+/// First regional indicator symbol, 🇦; pairs of them spell flags.
+const REGIONAL_A: u32 = 0x1F1E6;
+
+/// Returns `true` for emoji and pictographic symbols.
+#[inline]
+pub(crate) fn is_emoji(c: char) -> bool {
+    matches!(
+        c as u32,
+        0xA9 | 0xAE
+            | 0x203C
+            | 0x2049
+            | 0x2122
+            | 0x2139
+            | 0x2190..=0x21FF
+            | 0x2300..=0x23FF
+            | 0x24C2
+            | 0x25A0..=0x27BF
+            | 0x2900..=0x297F
+            | 0x2B00..=0x2BFF
+            | 0x3030
+            | 0x303D
+            | 0x3297
+            | 0x3299
+            | 0x1F000..=0x1FAFF
+    )
+}
+
+/// Returns `true` for the parts of emoji sequences that are not emoji
+/// themselves: variation selectors, the keycap mark, skin tone modifiers
+/// and tag characters. U+200D ZERO WIDTH JOINER is one only between emoji,
+/// which the caller tracks.
+#[inline]
+pub(crate) fn is_component(c: char) -> bool {
+    matches!(
+        c as u32,
+        0xFE0E | 0xFE0F | 0x20E3 | 0x1F3FB..=0x1F3FF | 0xE0020..=0xE007F
+    )
+}
+
+/// Returns the shortcode of `c`, if it has one.
+pub(crate) fn shortcode(c: char) -> Option<&'static str> {
+    SHORTCODES
+        .binary_search_by_key(&c, |&(emoji, _)| emoji)
+        .ok()
+        .map(|i| SHORTCODES[i].1)
+}
+
+/// Appends the shortcode of the emoji `c` to `out`, returning `false` if
+/// it has none. A flag, two regional indicators, becomes `flag-` and its
+/// country code; the second indicator is taken from `rest`.
+pub(crate) fn push_shortcode(c: char, rest: &mut Peekable<Chars<'_>>, out: &mut String) -> bool {
+    let letter = |c: char| {
+        (c as u32)
+            .checked_sub(REGIONAL_A)
+            .filter(|&i| i < 26)
+            .map(|i| (b'a' + i as u8) as char)
+    };
+    if let Some(first) = letter(c) {
+        let Some(second) = rest.peek().copied().and_then(letter) else {
+            return false;
+        };
+        rest.next();
+        out.push_str("flag-");
+        out.push(first);
+        out.push(second);
+        return true;
+    }
+    match shortcode(c) {
+        Some(code) => {
+            out.push_str(code);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shortcode_table_is_sorted_emoji() {
+        assert!(SHORTCODES.windows(2).all(|w| w[0].0 < w[1].0));
+        for &(emoji, code) in SHORTCODES {
+            assert!(is_emoji(emoji), "{}", code);
+            assert!(!is_component(emoji), "{}", code);
+            assert!(code
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_'));
+        }
+        assert_eq!(shortcode('🔥'), Some("fire"));
+        assert_eq!(shortcode('a'), None);
+
+        let mut out = String::new();
+        let mut rest = "\u{1F1F8}!".chars().peekable();
+        assert!(push_shortcode('\u{1F1FA}', &mut rest, &mut out));
+        assert_eq!(out, "flag-us");
+        assert_eq!(rest.next(), Some('!'));
+        assert!(!push_shortcode('\u{1F1FA}', &mut rest, &mut out));
+        assert!(!push_shortcode('\u{1F9FF}', &mut rest, &mut out));
+    }
+}
@@ -0,0 +1,175 @@
+//! Language detection and per-language normalization.
+//!
+//! Whether diacritics carry meaning depends on the language: in French
+//! "élève" and "eleve" are the same word to most searchers, while German
+//! users expect "schön" and "schon" to stay apart. [`LanguageNormalizers`]
+//! maps detected languages to a [`NormalizerConfig`] each, with a default
+//! for everything else.
+//!
+//! Detection uses trigram language profiles (the `whatlang` crate) and is
+//! only trusted when it reports a reliable result; short texts usually fall
+//! back to the default.
+
+use crate::analyzer::normalizer::NormalizerConfig;
+use core::fmt;
+
+/// A language, identified by its ISO 639-3 code.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Language([u8; 3]);
+
+impl Language {
+    /// English (`eng`).
+    pub const ENGLISH: Self = Self(*b"eng");
+    /// French (`fra`).
+    pub const FRENCH: Self = Self(*b"fra");
+    /// German (`deu`).
+    pub const GERMAN: Self = Self(*b"deu");
+    /// Spanish (`spa`).
+    pub const SPANISH: Self = Self(*b"spa");
+    /// Portuguese (`por`).
+    pub const PORTUGUESE: Self = Self(*b"por");
+    /// Italian (`ita`).
+    pub const ITALIAN: Self = Self(*b"ita");
+
+    /// Parses an ISO 639-3 code such as `"fra"`.
+    ///
+    /// Returns `None` unless `code` is three ASCII letters.
+    pub fn new(code: &str) -> Option<Self> {
+        let bytes: [u8; 3] = code.as_bytes().try_into().ok()?;
+        if !bytes.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        Some(Self(bytes.map(|b| b.to_ascii_lowercase())))
+    }
+
+    /// Returns the ISO 639-3 code.
+    pub fn code(&self) -> &str {
+        // Always three ASCII letters
+        core::str::from_utf8(&self.0).unwrap_or("und")
+    }
+
+    /// Detects the language of `text`.
+    ///
+    /// Returns `None` if the detector is not confident, which is common for
+    /// texts of only a few words.
+    pub fn detect(text: &str) -> Option<Self> {
+        let info = whatlang::detect(text)?;
+        if !info.is_reliable() {
+            return None;
+        }
+        Self::new(info.lang().code())
+    }
+}
+
+impl fmt::Debug for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Language({:?})", self.code())
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// Normalizer configuration per detected language.
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::{Language, LanguageNormalizers, NormalizerConfig};
+///
+/// let normalizers = LanguageNormalizers::new(NormalizerConfig::strip_diacritics())
+///     .with(Language::GERMAN, NormalizerConfig::default());
+///
+/// assert!(!normalizers.config_for(Some(Language::GERMAN)).strip_diacritics);
+/// assert!(normalizers.config_for(Some(Language::FRENCH)).strip_diacritics);
+/// assert!(normalizers.config_for(None).strip_diacritics);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LanguageNormalizers {
+    default: NormalizerConfig,
+    overrides: Vec<(Language, NormalizerConfig)>,
+}
+
+impl LanguageNormalizers {
+    /// Uses `default` for every language until overridden with
+    /// [`with`](Self::with), and for text whose language is not detected.
+    pub fn new(default: NormalizerConfig) -> Self {
+        Self {
+            default,
+            overrides: Vec::new(),
+        }
+    }
+
+    /// Sets the configuration for `language`, replacing any earlier one.
+    #[must_use]
+    pub fn with(mut self, language: Language, config: NormalizerConfig) -> Self {
+        match self.overrides.iter_mut().find(|(l, _)| *l == language) {
+            Some(entry) => entry.1 = config,
+            None => self.overrides.push((language, config)),
+        }
+        self
+    }
+
+    /// Returns the configuration used when no override applies.
+    pub fn default_config(&self) -> NormalizerConfig {
+        self.default
+    }
+
+    /// Returns the configuration for `language` (`None` = undetected).
+    pub fn config_for(&self, language: Option<Language>) -> NormalizerConfig {
+        language
+            .and_then(|language| self.overrides.iter().find(|(l, _)| *l == language))
+            .map_or(self.default, |&(_, config)| config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_codes() {
+        assert_eq!(Language::new("FRA"), Some(Language::FRENCH));
+        assert_eq!(Language::new("fr"), None);
+        assert_eq!(Language::new("f1a"), None);
+        assert_eq!(Language::GERMAN.code(), "deu");
+        assert_eq!(Language::GERMAN.to_string(), "deu");
+        assert_eq!(format!("{:?}", Language::ENGLISH), "Language(\"eng\")");
+    }
+
+    #[test]
+    fn detects_reliable_languages_only() {
+        let french = "Le petit garçon est allé à l'école avec sa mère ce matin, \
+                      et il a beaucoup appris sur l'histoire de la France.";
+        let german = "Der kleine Junge ist heute Morgen mit seiner Mutter zur \
+                      Schule gegangen und hat viel über die Geschichte gelernt.";
+        assert_eq!(Language::detect(french), Some(Language::FRENCH));
+        assert_eq!(Language::detect(german), Some(Language::GERMAN));
+        assert_eq!(Language::detect("ok"), None);
+    }
+
+    #[test]
+    fn overrides_replace_earlier_entries() {
+        let strip = NormalizerConfig::strip_diacritics();
+        let normalizers = LanguageNormalizers::default()
+            .with(Language::FRENCH, strip)
+            .with(Language::FRENCH, NormalizerConfig::default())
+            .with(Language::SPANISH, strip);
+
+        assert_eq!(normalizers.overrides.len(), 2);
+        assert!(
+            !normalizers
+                .config_for(Some(Language::FRENCH))
+                .strip_diacritics
+        );
+        assert!(
+            normalizers
+                .config_for(Some(Language::SPANISH))
+                .strip_diacritics
+        );
+        assert_eq!(normalizers.default_config(), NormalizerConfig::default());
+    }
+}
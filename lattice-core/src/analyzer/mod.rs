@@ -3,12 +3,22 @@
 //! This module provides the text processing components:
 //! - **Normalizer**: Cleans and normalizes raw text
 //! - **Tokenizer**: Splits normalized text into tokens
+//! - **Delimiter**: SIMD scanning for configurable token delimiters
 //! - **Trigram**: Extracts 3-character sequences for indexing
+//! - **Language** (feature `language-detection`): Picks a normalizer
+//!   configuration per detected document language
 
+pub mod delimiter;
+mod emoji;
+#[cfg(feature = "language-detection")]
+pub mod language;
 pub mod normalizer;
 pub mod tokenizer;
 pub mod trigram;
 
-pub use normalizer::TextNormalizer;
-pub use tokenizer::{Field, Tokenizer};
+pub use delimiter::DelimiterSet;
+#[cfg(feature = "language-detection")]
+pub use language::{Language, LanguageNormalizers};
+pub use normalizer::{DigitPolicy, EmojiPolicy, NormalizerConfig, TextNormalizer, UnicodeForm};
+pub use tokenizer::{Field, LongTokenPolicy, TokenStats, Tokenizer, TokenizerConfig};
 pub use trigram::TrigramExtractor;
@@ -1,31 +1,51 @@
 //! Production ASCII Text Normalizer
 //!
-//! Simple, branchless scalar implementation optimized for real-world throughput.
-//! No SIMD complexity - the compiler auto-vectorizes better than hand-rolled SIMD
-//! for this workload, and we avoid the scalar fallback nightmare.
+//! Simple, branchless scalar implementation optimized for real-world throughput,
+//! with a NEON fast path on aarch64 for the ASCII lowercase+collapse loop.
 //!
 //! ## Performance Characteristics
 //!
 //! | Input Type | Throughput | Implementation |
 //! |------------|-----------|----------------|
 //! | Pure ASCII | ~2-4 GiB/s | Branchless scalar (auto-vectorized) |
+//! | Pure ASCII (aarch64) | ~4-8 GiB/s | NEON, 16 bytes per step |
 //! | Mixed/Non-ASCII | ~1-2 GiB/s | Scalar pass-through |
 //!
 //! ## What It Does
 //!
-//! - **Lowercasing**: A-Z → a-z via bit manipulation
+//! - **Lowercasing**: A-Z → a-z via bit manipulation, unless
+//!   [`NormalizerConfig::keep_case`] is set
 //! - **Whitespace Collapse**: Any ASCII whitespace → single space
 //! - **Trim**: Leading/trailing whitespace removed
+//! - **Diacritic folding** (opt-in via [`NormalizerConfig`]): Latin letters
+//!   with diacritics map to their ASCII base letter (é → e, Ł → l), and
+//!   combining marks are dropped, so decomposed input folds the same way
+//! - **Format characters** (opt-in): zero-width characters, soft hyphens,
+//!   BOMs and other invisible `Cf` characters are dropped, and non-ASCII
+//!   spaces (NBSP, ideographic space, ...) count as whitespace
+//! - **Unicode normalization** (feature `unicode-normalization`): NFKC or
+//!   NFKD is applied first, so composed and decomposed text, ligatures and
+//!   full-width forms index identically
+//! - **Digit collapsing** (opt-in via [`DigitPolicy`]): runs of ASCII digits
+//!   become a single `0`, alone or next to the verbatim word, so numeric IDs
+//!   stop filling the index with trigrams that match almost nothing
+//! - **Emoji** (opt-in via [`EmojiPolicy`]): emoji and pictographic symbols
+//!   are dropped or replaced by their shortcode ("👍" → "thumbsup")
 //!
 //! ## Design Decisions
 //!
-//! 1. **No SIMD intrinsics**: Compiler auto-vectorization beats manual SIMD for
-//!    variable-output operations (whitespace collapsing changes length).
+//! 1. **SIMD only where output length is fixed**: Whitespace collapsing changes
+//!    the output length, so the NEON path only stores whole 16-byte chunks
+//!    that need no collapsing (at most single spaces between words) and hands
+//!    every other chunk to the scalar step. Other targets rely on
+//!    auto-vectorization.
 //! 2. **Branchless where possible**: Whitespace checks use arithmetic to avoid
 //!    branch misprediction penalties (~15-20 cycles each).
 //! 3. **Single pass**: No separate detection/normalization phases.
 //! 4. **Unsafe only for buffer writes**: Bounds checked by `reserve()`.
 
+use super::emoji;
+
 /// Branchless whitespace lookup table.
 /// Matches: space (0x20), tab (0x09), newline (0x0a), carriage return (0x0d)
 const WS_TABLE: [bool; 256] = {
@@ -37,15 +57,153 @@ const WS_TABLE: [bool; 256] = {
     table
 };
 
+/// ASCII base letters for U+00C0..=U+017F (Latin-1 Supplement and Latin
+/// Extended-A), lowercased; `.` marks characters that are kept as-is.
+const FOLD_TABLE: &[u8; 192] = b"aaaaaa.ceeeeiiii.nooooo.ouuuuy..\
+aaaaaa.ceeeeiiii.nooooo.ouuuuy.y\
+aaaaaaccccccccddddeeeeeeeeeegggg\
+gggghhhhiiiiiiiiii..jjkk.lllllll\
+lllnnnnnn...oooooo..rrrrrrssssss\
+ssttttttuuuuuuuuuuuuwwyyyzzzzzz.";
+
+/// Optional normalization steps.
+///
+/// The default only lowercases ASCII and collapses whitespace. The same
+/// configuration must be used for documents and queries, so it is fixed
+/// when the engine is created (see [`Lattice::with_normalizer`](crate::Lattice::with_normalizer)).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NormalizerConfig {
+    /// Fold Latin letters with diacritics to their ASCII base letter and
+    /// drop combining marks, so "café", "cafe" and "CAFÉ" index identically.
+    pub strip_diacritics: bool,
+    /// Drop invisible format characters (Unicode category `Cf`, e.g.
+    /// U+200B ZERO WIDTH SPACE, U+00AD SOFT HYPHEN, U+FEFF BOM) and treat
+    /// non-ASCII space separators (`Zs`) as whitespace, so "foo\u{200B}bar"
+    /// indexes as "foobar" and "foo\u{A0}bar" as "foo bar".
+    pub remove_format_chars: bool,
+    /// Keep ASCII letters in the case they were written in instead of
+    /// lowercasing them, for case-sensitive text such as identifiers.
+    /// Folded letters keep the case of the original ("É" → "E"). Such text
+    /// always takes the char-by-char path.
+    pub keep_case: bool,
+    /// Unicode normalization form applied before everything else.
+    pub unicode_form: UnicodeForm,
+    /// What happens to words containing ASCII digits, applied after
+    /// everything else.
+    pub digits: DigitPolicy,
+    /// What happens to emoji and pictographic symbols.
+    pub emoji: EmojiPolicy,
+}
+
+impl NormalizerConfig {
+    /// The default configuration, usable in `const` contexts.
+    pub const fn new() -> Self {
+        Self {
+            strip_diacritics: false,
+            remove_format_chars: false,
+            keep_case: false,
+            unicode_form: UnicodeForm::None,
+            digits: DigitPolicy::Keep,
+            emoji: EmojiPolicy::Keep,
+        }
+    }
+
+    /// Folds diacritics; see [`strip_diacritics`](Self::strip_diacritics).
+    pub const fn strip_diacritics() -> Self {
+        Self {
+            strip_diacritics: true,
+            ..Self::new()
+        }
+    }
+}
+
+/// Unicode normalization form.
+///
+/// Both forms are compatibility forms: besides unifying composed and
+/// decomposed sequences they map ligatures ("ﬁ" → "fi"), full-width
+/// letters and similar variants to their plain equivalents. NFKD leaves
+/// accents as combining marks, which diacritic folding then drops.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum UnicodeForm {
+    /// Text is not Unicode-normalized.
+    #[default]
+    None,
+    /// Compatibility decomposition followed by canonical composition.
+    #[cfg(feature = "unicode-normalization")]
+    Nfkc,
+    /// Compatibility decomposition.
+    #[cfg(feature = "unicode-normalization")]
+    Nfkd,
+}
+
+/// Handling of words that contain ASCII digits.
+///
+/// Every distinct number adds trigrams of its own, and order numbers,
+/// timestamps and other IDs rarely share any worth matching on. Collapsing
+/// each run of digits to a single `0` turns "order 48213" and "order 90177"
+/// into the same "order 0", so such text adds next to nothing to the index.
+/// `0` is a letter to every [`DelimiterSet`](crate::analyzer::DelimiterSet),
+/// so the collapsed word tokenizes like the original.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DigitPolicy {
+    /// Digits are indexed verbatim.
+    #[default]
+    Keep,
+    /// Each run of digits becomes `0`: "inv-2024-0193" → "inv-0-0". Exact
+    /// numbers can no longer be searched for.
+    Collapse,
+    /// The verbatim word is followed by its collapsed form: "v12" →
+    /// "v12 v0". Exact numbers still match, and text differing only in its
+    /// numbers still shares trigrams, at the cost of a longer document.
+    Both,
+}
+
+/// Handling of emoji and pictographic symbols.
+///
+/// Kept emoji index as trigrams of their UTF-8 bytes, which only ever
+/// match the same emoji. Both other policies remove the parts of emoji
+/// sequences that are not emoji themselves (variation selectors, skin
+/// tones, joiners), and an emoji that is removed separates words like a
+/// space: "ship it🚀now" → "ship it now".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum EmojiPolicy {
+    /// Emoji are indexed verbatim.
+    #[default]
+    Keep,
+    /// Emoji are removed.
+    Drop,
+    /// Emoji are replaced by their shortcode as a word of its own: "great
+    /// job👍" → "great job thumbsup", and flags become `flag-` and the
+    /// country code ("🇫🇷" → "flag-fr"). Emoji without a known shortcode are
+    /// removed.
+    Shortcode,
+}
+
 /// Zero-copy ASCII text normalizer.
-#[derive(Clone, Copy, Debug, Default)]
-pub struct TextNormalizer;
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextNormalizer {
+    config: NormalizerConfig,
+}
 
 impl TextNormalizer {
-    /// Creates a new normalizer.
+    /// Creates a new normalizer with the default configuration.
     #[inline(always)]
     pub const fn new() -> Self {
-        Self
+        Self {
+            config: NormalizerConfig::new(),
+        }
+    }
+
+    /// Creates a normalizer with the given options.
+    #[inline(always)]
+    pub const fn with_config(config: NormalizerConfig) -> Self {
+        Self { config }
+    }
+
+    /// Returns the normalizer's options.
+    #[inline(always)]
+    pub const fn config(&self) -> NormalizerConfig {
+        self.config
     }
 
     /// Normalizes text in-place into the provided buffer.
@@ -57,6 +215,41 @@ impl TextNormalizer {
     /// - Single-pass with minimal branching
     #[inline]
     pub fn normalize_into(&self, input: &str, out: &mut String) {
+        self.normalize_text(input, out);
+        if self.config.digits != DigitPolicy::Keep && out.bytes().any(|b| b.is_ascii_digit()) {
+            self.rewrite_digits(out);
+        }
+    }
+
+    /// Every step but the digit policy.
+    #[inline]
+    fn normalize_text(&self, input: &str, out: &mut String) {
+        #[cfg(feature = "unicode-normalization")]
+        if self.config.unicode_form != UnicodeForm::None && !input.is_ascii() {
+            use unicode_normalization::UnicodeNormalization;
+
+            let normalized: String = match self.config.unicode_form {
+                UnicodeForm::Nfkd => input.nfkd().collect(),
+                _ => input.nfkc().collect(),
+            };
+            let rest = Self::with_config(NormalizerConfig {
+                unicode_form: UnicodeForm::None,
+                ..self.config
+            });
+            rest.normalize_text(&normalized, out);
+            return;
+        }
+
+        if self.config.keep_case
+            || (self.config.strip_diacritics
+                || self.config.remove_format_chars
+                || self.config.emoji != EmojiPolicy::Keep)
+                && !input.is_ascii()
+        {
+            self.normalize_chars(input, out);
+            return;
+        }
+
         out.clear();
 
         let len = input.len();
@@ -78,31 +271,13 @@ impl TextNormalizer {
             let mut w = 0usize;
             let mut in_ws = true; // Start true to trim leading whitespace
 
-            for i in 0..len {
-                let b = *bytes.get_unchecked(i);
+            #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+            let start = neon::normalize_chunks(bytes, buf, &mut w, &mut in_ws);
+            #[cfg(not(all(target_arch = "aarch64", target_feature = "neon")))]
+            let start = 0;
 
-                // Branchless ASCII whitespace detection using lookup table
-                // Matches: space (0x20), tab (0x09), newline (0x0a), carriage return (0x0d)
-                let is_ws = WS_TABLE[b as usize];
-
-                if is_ws {
-                    // Only write space if not already in whitespace run
-                    // This collapses multiple whitespaces into one
-                    if !in_ws {
-                        *buf.add(w) = b' ';
-                        w += 1;
-                        in_ws = true;
-                    }
-                } else {
-                    // Branchless lowercase for ASCII A-Z
-                    // If b is in [A-Z], set bit 5 to convert to [a-z]
-                    let is_upper = b.wrapping_sub(b'A') <= 25;
-                    let lower = if is_upper { b | 0x20 } else { b };
-
-                    *buf.add(w) = lower;
-                    w += 1;
-                    in_ws = false;
-                }
+            for i in start..len {
+                push_byte(buf, &mut w, &mut in_ws, *bytes.get_unchecked(i));
             }
 
             // Trim trailing space (if we ended in whitespace)
@@ -118,6 +293,114 @@ impl TextNormalizer {
         }
     }
 
+    /// Char-by-char path for non-ASCII input with diacritic folding,
+    /// format-character removal or an emoji policy, and for any input
+    /// keeping its case.
+    ///
+    /// Only shortcodes lengthen the text, so the output usually fits in
+    /// `input.len()`.
+    fn normalize_chars(&self, input: &str, out: &mut String) {
+        out.clear();
+        out.reserve(input.len());
+
+        let fold = self.config.strip_diacritics;
+        let remove_format = self.config.remove_format_chars;
+        let emoji = self.config.emoji;
+        let keep_case = self.config.keep_case;
+        let mut in_ws = true;
+        let mut after_emoji = false;
+        let mut chars = input.chars().peekable();
+        while let Some(c) = chars.next() {
+            if emoji != EmojiPolicy::Keep && !c.is_ascii() {
+                // A joiner only belongs to a sequence between emoji
+                if emoji::is_component(c) || (c == '\u{200D}' && after_emoji) {
+                    continue;
+                }
+                if emoji::is_emoji(c) {
+                    after_emoji = true;
+                    if !in_ws {
+                        out.push(' ');
+                        in_ws = true;
+                    }
+                    if emoji == EmojiPolicy::Shortcode && emoji::push_shortcode(c, &mut chars, out)
+                    {
+                        out.push(' ');
+                    }
+                    continue;
+                }
+            }
+            after_emoji = false;
+
+            let is_ws = if c.is_ascii() {
+                WS_TABLE[c as usize]
+            } else {
+                remove_format && is_space_separator(c)
+            };
+            if is_ws {
+                if !in_ws {
+                    out.push(' ');
+                    in_ws = true;
+                }
+                continue;
+            }
+
+            if c.is_ascii() {
+                out.push(if keep_case { c } else { c.to_ascii_lowercase() });
+            } else if remove_format && is_format_char(c) {
+                continue;
+            } else if let Some(base) = fold.then(|| fold_diacritic(c)).flatten() {
+                out.push(if keep_case && c.is_uppercase() {
+                    base.to_ascii_uppercase()
+                } else {
+                    base
+                });
+            } else if fold && is_combining_mark(c) {
+                continue;
+            } else {
+                out.push(c);
+            }
+            in_ws = false;
+        }
+
+        if in_ws && !out.is_empty() {
+            out.pop();
+        }
+    }
+
+    /// Applies the digit policy to normalized text in `out`.
+    fn rewrite_digits(&self, out: &mut String) {
+        let text = core::mem::take(out);
+        out.reserve(text.len());
+        for (i, word) in text.split(' ').enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            if !word.bytes().any(|b| b.is_ascii_digit()) {
+                out.push_str(word);
+                continue;
+            }
+            let both = self.config.digits == DigitPolicy::Both;
+            if both {
+                out.push_str(word);
+                out.push(' ');
+            }
+            let start = out.len();
+            let mut in_digits = false;
+            for c in word.chars() {
+                if !c.is_ascii_digit() {
+                    out.push(c);
+                } else if !in_digits {
+                    out.push('0');
+                }
+                in_digits = c.is_ascii_digit();
+            }
+            // A word that collapses to itself is not repeated
+            if both && out[start..] == *word {
+                out.truncate(start - 1);
+            }
+        }
+    }
+
     /// Normalizes text and returns a new String.
     #[inline(always)]
     pub fn normalize(&self, input: &str) -> String {
@@ -127,6 +410,151 @@ impl TextNormalizer {
     }
 }
 
+/// Scalar step of the ASCII loop: collapses whitespace and lowercases A-Z.
+///
+/// # Safety
+///
+/// `buf` must have room for one more byte at `*w`.
+#[inline(always)]
+unsafe fn push_byte(buf: *mut u8, w: &mut usize, in_ws: &mut bool, b: u8) {
+    // Branchless ASCII whitespace detection using lookup table
+    // Matches: space (0x20), tab (0x09), newline (0x0a), carriage return (0x0d)
+    if WS_TABLE[b as usize] {
+        // Only write space if not already in whitespace run
+        // This collapses multiple whitespaces into one
+        if !*in_ws {
+            *buf.add(*w) = b' ';
+            *w += 1;
+            *in_ws = true;
+        }
+    } else {
+        // Branchless lowercase for ASCII A-Z
+        // If b is in [A-Z], set bit 5 to convert to [a-z]
+        let is_upper = b.wrapping_sub(b'A') <= 25;
+        let lower = if is_upper { b | 0x20 } else { b };
+
+        *buf.add(*w) = lower;
+        *w += 1;
+        *in_ws = false;
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use super::push_byte;
+    use core::arch::aarch64::*;
+
+    /// Runs the lowercase+collapse loop over whole 16-byte chunks and
+    /// returns the number of input bytes consumed; the caller finishes the
+    /// tail with [`push_byte`].
+    ///
+    /// A chunk is stored in one go when no whitespace byte follows another
+    /// (counting the carried `in_ws` state as the byte before the chunk):
+    /// every whitespace byte then becomes exactly one space. Any other chunk
+    /// goes through the scalar step, so the output matches it byte for byte.
+    /// Non-ASCII bytes pass through untouched either way.
+    ///
+    /// # Safety
+    ///
+    /// `buf` must have room for `*w + bytes.len()` bytes.
+    #[inline]
+    pub(super) unsafe fn normalize_chunks(
+        bytes: &[u8],
+        buf: *mut u8,
+        w: &mut usize,
+        in_ws: &mut bool,
+    ) -> usize {
+        let space = vdupq_n_u8(b' ');
+        let tab = vdupq_n_u8(b'\t');
+        let newline = vdupq_n_u8(b'\n');
+        let carriage = vdupq_n_u8(b'\r');
+        let upper_a = vdupq_n_u8(b'A');
+        let alpha_span = vdupq_n_u8(25);
+        let case_bit = vdupq_n_u8(0x20);
+
+        let mut i = 0;
+        while i + 16 <= bytes.len() {
+            let chunk = vld1q_u8(bytes.as_ptr().add(i));
+            let ws = vorrq_u8(
+                vorrq_u8(vceqq_u8(chunk, space), vceqq_u8(chunk, tab)),
+                vorrq_u8(vceqq_u8(chunk, newline), vceqq_u8(chunk, carriage)),
+            );
+
+            // Whitespace flags shifted by one byte: [in_ws, ws[0], .., ws[14]]
+            let carry = vdupq_n_u8(if *in_ws { 0xff } else { 0 });
+            let prev_ws = vextq_u8::<15>(carry, ws);
+
+            if vmaxvq_u8(vandq_u8(ws, prev_ws)) == 0 {
+                let upper = vcleq_u8(vsubq_u8(chunk, upper_a), alpha_span);
+                let lower = vorrq_u8(chunk, vandq_u8(upper, case_bit));
+                vst1q_u8(buf.add(*w), vbslq_u8(ws, space, lower));
+                *w += 16;
+                *in_ws = vgetq_lane_u8::<15>(ws) != 0;
+            } else {
+                for &b in bytes.get_unchecked(i..i + 16) {
+                    push_byte(buf, w, in_ws, b);
+                }
+            }
+            i += 16;
+        }
+        i
+    }
+}
+
+/// Returns the lowercase ASCII base letter of a Latin letter with diacritics.
+#[inline]
+fn fold_diacritic(c: char) -> Option<char> {
+    let index = (c as u32).checked_sub(0xC0)? as usize;
+    match FOLD_TABLE.get(index) {
+        Some(&b'.') | None => None,
+        Some(&base) => Some(base as char),
+    }
+}
+
+/// Invisible format characters (Unicode general category `Cf`).
+#[inline]
+fn is_format_char(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x00AD
+            | 0x0600..=0x0605
+            | 0x061C
+            | 0x06DD
+            | 0x070F
+            | 0x0890..=0x0891
+            | 0x08E2
+            | 0x180E
+            | 0x200B..=0x200F
+            | 0x202A..=0x202E
+            | 0x2060..=0x2064
+            | 0x2066..=0x206F
+            | 0xFEFF
+            | 0xFFF9..=0xFFFB
+            | 0x110BD
+            | 0x110CD
+            | 0x13430..=0x1343F
+            | 0x1BCA0..=0x1BCA3
+            | 0x1D173..=0x1D17A
+            | 0xE0001
+            | 0xE0020..=0xE007F
+    )
+}
+
+/// Non-ASCII space separators (Unicode general category `Zs`).
+#[inline]
+fn is_space_separator(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x00A0 | 0x1680 | 0x2000..=0x200A | 0x202F | 0x205F | 0x3000
+    )
+}
+
+/// Combining Diacritical Marks block (U+0300..=U+036F).
+#[inline]
+fn is_combining_mark(c: char) -> bool {
+    ('\u{0300}'..='\u{036F}').contains(&c)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,4 +645,219 @@ mod tests {
         // Mix of all whitespace types
         assert_eq!(norm(" \t\n\r "), "");
     }
+
+    #[test]
+    fn matches_reference_across_chunk_boundaries() {
+        // Byte-at-a-time reference for the ASCII path; exercises every
+        // alignment of whitespace runs against the 16-byte SIMD chunks.
+        fn reference(input: &str) -> String {
+            let words: Vec<&str> = input.split([' ', '\t', '\n', '\r']).collect();
+            let words: Vec<String> = words
+                .into_iter()
+                .filter(|w| !w.is_empty())
+                .map(|w| w.to_ascii_lowercase())
+                .collect();
+            words.join(" ")
+        }
+
+        let pieces = ["Ab", " ", "  ", "\t\n", "CDEFGHIJ", "x", "\r", "é", "Z@[`{"];
+        let mut input = String::new();
+        let mut state = 7u32;
+        for _ in 0..600 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            input.push_str(pieces[(state >> 16) as usize % pieces.len()]);
+            for start in [0, 1, input.len().saturating_sub(40)] {
+                if let Some(slice) = input.get(start..) {
+                    assert_eq!(norm(slice), reference(slice), "input {:?}", slice);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn strips_diacritics_when_enabled() {
+        let folding = TextNormalizer::with_config(NormalizerConfig::strip_diacritics());
+        let fold = |s: &str| folding.normalize(s);
+
+        assert_eq!(fold("Café Crème"), "cafe creme");
+        assert_eq!(fold("CAFÉ"), "cafe");
+        assert_eq!(fold("Łódź  Straße"), "lodz straße");
+        assert_eq!(fold("naïve señor ÅNGSTRÖM"), "naive senor angstrom");
+        // Decomposed input folds like precomposed input
+        assert_eq!(fold("cafe\u{301}"), fold("caf\u{e9}"));
+        assert_eq!(fold("  e\u{301} \u{301} "), "e");
+        // Characters outside the table pass through
+        assert_eq!(fold("日本語 æ œ"), "日本語 æ œ");
+        assert_eq!(fold("plain ASCII"), "plain ascii");
+        assert_eq!(norm("café"), "café");
+    }
+
+    #[test]
+    fn fold_table_matches_unicode_decomposition() {
+        // Spot checks across the table's rows
+        for (c, base) in [
+            ('À', 'a'),
+            ('Ç', 'c'),
+            ('Ñ', 'n'),
+            ('Ø', 'o'),
+            ('ÿ', 'y'),
+            ('Ā', 'a'),
+            ('Ğ', 'g'),
+            ('İ', 'i'),
+            ('Ł', 'l'),
+            ('Ő', 'o'),
+            ('Ş', 's'),
+            ('Ž', 'z'),
+        ] {
+            assert_eq!(fold_diacritic(c), Some(base), "{}", c);
+        }
+        for c in ['a', '×', '÷', 'ß', 'Æ', 'Þ', 'Œ', 'ſ', '\u{180}'] {
+            assert_eq!(fold_diacritic(c), None, "{}", c);
+        }
+    }
+
+    #[test]
+    fn removes_format_characters_when_enabled() {
+        let config = NormalizerConfig {
+            remove_format_chars: true,
+            ..NormalizerConfig::new()
+        };
+        let clean = TextNormalizer::with_config(config);
+
+        // ZWSP, ZWNJ, ZWJ, BOM and soft hyphen vanish without splitting words
+        assert_eq!(clean.normalize("zero\u{200B}width"), "zerowidth");
+        assert_eq!(clean.normalize("Non\u{200C}Joiner\u{200D}"), "nonjoiner");
+        assert_eq!(
+            clean.normalize("\u{FEFF}Byte order mark"),
+            "byte order mark"
+        );
+        assert_eq!(clean.normalize("hy\u{AD}phen\u{AD}ation"), "hyphenation");
+        // Non-ASCII spaces separate words and collapse with ASCII ones
+        assert_eq!(
+            clean.normalize("no\u{A0}break \u{3000} here\u{2009}"),
+            "no break here"
+        );
+        assert_eq!(clean.normalize("\u{FEFF} \u{200B} "), "");
+        // Other non-ASCII text passes through; diacritics are not folded
+        assert_eq!(clean.normalize("Café 日本"), "café 日本");
+
+        let both = TextNormalizer::with_config(NormalizerConfig {
+            strip_diacritics: true,
+            ..config
+        });
+        assert_eq!(both.normalize("Cafe\u{301}\u{200B}s"), "cafes");
+        assert_eq!(norm("zero\u{200B}width"), "zero\u{200B}width");
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn unicode_forms_unify_variants() {
+        let config = |strip_diacritics, unicode_form| NormalizerConfig {
+            strip_diacritics,
+            unicode_form,
+            ..NormalizerConfig::new()
+        };
+        let nfkc = TextNormalizer::with_config(config(false, UnicodeForm::Nfkc));
+        let nfkd = TextNormalizer::with_config(config(false, UnicodeForm::Nfkd));
+        let nfkd_fold = TextNormalizer::with_config(config(true, UnicodeForm::Nfkd));
+
+        // Composed and decomposed input normalize identically
+        assert_eq!(nfkc.normalize("cafe\u{301}"), "caf\u{e9}");
+        assert_eq!(nfkc.normalize("caf\u{e9}"), "caf\u{e9}");
+        assert_eq!(nfkd.normalize("caf\u{e9}"), "cafe\u{301}");
+
+        // Compatibility mappings: ligatures and full-width letters
+        assert_eq!(nfkc.normalize("\u{fb01}le ＨＥＬＬＯ"), "file hello");
+        assert_eq!(nfkd_fold.normalize("Ｃａｆé"), "cafe");
+
+        // ASCII input takes the fast path unchanged
+        assert_eq!(nfkc.normalize("  Plain  ASCII "), "plain ascii");
+        assert_eq!(norm("\u{fb01}le"), "\u{fb01}le");
+    }
+
+    #[test]
+    fn digit_policies_collapse_or_duplicate_numbers() {
+        let with = |digits| {
+            TextNormalizer::with_config(NormalizerConfig {
+                digits,
+                ..NormalizerConfig::new()
+            })
+        };
+        let collapse = with(DigitPolicy::Collapse);
+        let both = with(DigitPolicy::Both);
+
+        assert_eq!(norm("Order 48213"), "order 48213");
+        assert_eq!(collapse.normalize("Order  48213"), "order 0");
+        assert_eq!(collapse.normalize("INV-2024-0193 x9y"), "inv-0-0 x0y");
+        assert_eq!(collapse.normalize("no digits"), "no digits");
+        assert_eq!(collapse.normalize("café 42"), "café 0");
+        assert_eq!(
+            both.normalize("build 1024 of v12"),
+            "build 1024 0 of v12 v0"
+        );
+        // Words that collapse to themselves are not repeated
+        assert_eq!(both.normalize("v0 0 7"), "v0 0 7 0");
+
+        // Applied after the other steps, on their output
+        let folding = TextNormalizer::with_config(NormalizerConfig {
+            strip_diacritics: true,
+            digits: DigitPolicy::Collapse,
+            ..NormalizerConfig::new()
+        });
+        assert_eq!(folding.normalize("Café  n°123"), "cafe n°0");
+    }
+
+    #[test]
+    fn keep_case_leaves_letters_as_written() {
+        let keep = TextNormalizer::with_config(NormalizerConfig {
+            keep_case: true,
+            ..NormalizerConfig::new()
+        });
+        assert_eq!(keep.normalize("  HashMap::new\t Vec "), "HashMap::new Vec");
+        assert_eq!(norm("HashMap"), "hashmap");
+
+        let folding = TextNormalizer::with_config(NormalizerConfig {
+            keep_case: true,
+            strip_diacritics: true,
+            ..NormalizerConfig::new()
+        });
+        assert_eq!(folding.normalize("Éclair  CAFÉ"), "Eclair CAFE");
+    }
+
+    #[test]
+    fn emoji_policies_drop_or_name_emoji() {
+        let with = |emoji| {
+            TextNormalizer::with_config(NormalizerConfig {
+                emoji,
+                ..NormalizerConfig::new()
+            })
+        };
+        let drop = with(EmojiPolicy::Drop);
+        let shortcode = with(EmojiPolicy::Shortcode);
+
+        assert_eq!(norm("Great job👍"), "great job👍");
+        assert_eq!(drop.normalize("Great job👍"), "great job");
+        assert_eq!(drop.normalize("ship it🚀now 🎉 🎉"), "ship it now");
+        assert_eq!(drop.normalize("👋"), "");
+        assert_eq!(shortcode.normalize("Great job👍"), "great job thumbsup");
+        assert_eq!(shortcode.normalize("🔥🔥 Deal"), "fire fire deal");
+        assert_eq!(shortcode.normalize("made in 🇫🇷!"), "made in flag-fr !");
+
+        // Selectors, skin tones and joiners go with their emoji; unknown
+        // emoji are dropped
+        assert_eq!(shortcode.normalize("❤\u{FE0F}ok"), "heart ok");
+        assert_eq!(shortcode.normalize("👍\u{1F3FD} thanks"), "thumbsup thanks");
+        assert_eq!(shortcode.normalize("🐶\u{200D}🐱 pets 🦖"), "dog cat pets");
+        // Outside emoji sequences joiners and other text are untouched
+        assert_eq!(drop.normalize("क्\u{200D}ष café"), "क्\u{200D}ष café");
+
+        // Composes with the other steps
+        let folding = TextNormalizer::with_config(NormalizerConfig {
+            strip_diacritics: true,
+            digits: DigitPolicy::Collapse,
+            emoji: EmojiPolicy::Shortcode,
+            ..NormalizerConfig::new()
+        });
+        assert_eq!(folding.normalize("Café 42 ☕"), "cafe 0 coffee");
+    }
 }
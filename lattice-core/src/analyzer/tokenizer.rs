@@ -20,7 +20,8 @@
 //!
 //! - **Zero Allocation**: Tokens are slices of the original string, not new allocations
 //! - **Streaming**: Uses a callback to emit tokens, no intermediate collection
-//! - **Fast**: Simple byte-scan for ASCII space (0x20) splitting
+//! - **Fast**: `memchr` scan for ASCII space (0x20) splitting, or a SIMD
+//!   multi-delimiter scan when extra delimiters are configured
 //! - **Field-Aware**: Can specify which document field (title, body, tag) tokens belong to
 //!
 //! ## Usage
@@ -56,8 +57,8 @@
 //!
 //! The weights are available via `Field::weight()` and are used during relevance scoring.
 
+use crate::analyzer::delimiter::DelimiterSet;
 use core::str;
-use memchr::memchr_iter;
 
 /// Logical document field.
 ///
@@ -79,6 +80,9 @@ pub enum Field {
 }
 
 impl Field {
+    /// Number of fields.
+    pub(crate) const COUNT: usize = 3;
+
     /// Static scoring weight for this field.
     ///
     /// Not stored per token; derived during scoring.
@@ -91,6 +95,221 @@ impl Field {
             Field::Tag => 2.0,
         }
     }
+
+    /// Inverse of `field as u8`.
+    pub(crate) const fn from_u8(value: u8) -> Option<Field> {
+        match value {
+            0 => Some(Field::Title),
+            1 => Some(Field::Body),
+            2 => Some(Field::Tag),
+            _ => None,
+        }
+    }
+}
+
+/// Tokenizer options.
+///
+/// The default splits on ASCII space only, matching normalized text. Add
+/// punctuation classes to break up text like "foo,bar" or "a/b/c":
+///
+/// ```
+/// use lattice_core::analyzer::{DelimiterSet, Field, Tokenizer, TokenizerConfig};
+///
+/// let config = TokenizerConfig::new()
+///     .split_on(DelimiterSet::SEPARATORS)
+///     .split_on(DelimiterSet::PATH_SEPARATORS);
+/// let mut tokens = Vec::new();
+/// Tokenizer::with_config(Field::Body, config)
+///     .tokenize("foo,bar a/b/c", |text, _, _| tokens.push(text));
+/// assert_eq!(tokens, ["foo", "bar", "a", "b", "c"]);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TokenizerConfig {
+    /// Bytes that end a token. Tokens are still slices of the input; runs of
+    /// adjacent delimiters produce no empty tokens.
+    pub delimiters: DelimiterSet,
+    /// Longest token in bytes; longer ones are handled per
+    /// [`long_token_policy`](Self::long_token_policy). `None` means no limit.
+    pub max_token_len: Option<usize>,
+    /// What happens to tokens longer than `max_token_len`.
+    pub long_token_policy: LongTokenPolicy,
+    /// Keep email addresses, URLs and version strings (`v1.2.3`,
+    /// `2.0.0-rc.1`) whole: such a word is emitted as one token, then as
+    /// the tokens the delimiters split it into. See
+    /// [`keep_compounds`](Self::keep_compounds).
+    pub keep_compounds: bool,
+    /// Index runs of CJK characters by character unigrams and bigrams
+    /// instead of byte trigrams. See [`cjk_bigrams`](Self::cjk_bigrams).
+    pub cjk_bigrams: bool,
+}
+
+impl TokenizerConfig {
+    /// The default configuration, usable in `const` contexts.
+    pub const fn new() -> Self {
+        Self {
+            delimiters: DelimiterSet::SPACE,
+            max_token_len: None,
+            long_token_policy: LongTokenPolicy::Skip,
+            keep_compounds: false,
+            cjk_bigrams: false,
+        }
+    }
+
+    /// Also splits on every byte of `class`, e.g. [`DelimiterSet::PUNCTUATION`].
+    #[must_use]
+    pub const fn split_on(mut self, class: DelimiterSet) -> Self {
+        self.delimiters = self.delimiters.union(class);
+        self
+    }
+
+    /// Limits tokens to `max_len` bytes, handling longer ones per `policy`.
+    ///
+    /// Long tokens are usually noise (base64 blobs, minified code, hashes)
+    /// that would each add hundreds of trigrams.
+    #[must_use]
+    pub const fn limit_token_len(mut self, max_len: usize, policy: LongTokenPolicy) -> Self {
+        self.max_token_len = Some(max_len);
+        self.long_token_policy = policy;
+        self
+    }
+
+    /// Keeps email addresses, URLs and version strings whole, in addition
+    /// to their components, so that searching `user@example.com` or
+    /// `v1.2.3` matches the whole string rather than any text sharing its
+    /// parts. Surrounding brackets, quotes and trailing punctuation are not
+    /// part of the whole token.
+    ///
+    /// ```
+    /// use lattice_core::analyzer::{DelimiterSet, Field, Tokenizer, TokenizerConfig};
+    ///
+    /// let config = TokenizerConfig::new()
+    ///     .split_on(DelimiterSet::PUNCTUATION)
+    ///     .keep_compounds();
+    /// let mut tokens = Vec::new();
+    /// Tokenizer::with_config(Field::Body, config)
+    ///     .tokenize("mail bob@example.com (v1.2)", |text, _, _| tokens.push(text));
+    /// assert_eq!(
+    ///     tokens,
+    ///     ["mail", "bob@example.com", "bob", "example", "com", "v1.2", "v1", "2"]
+    /// );
+    /// ```
+    #[must_use]
+    pub const fn keep_compounds(mut self) -> Self {
+        self.keep_compounds = true;
+        self
+    }
+
+    /// Indexes Chinese, Japanese and Korean text by characters and
+    /// character pairs rather than byte trigrams, for documents and queries
+    /// alike, so that a query like "東京" matches the characters it is made
+    /// of instead of fragments of their UTF-8 encoding. Other text is
+    /// indexed as before.
+    ///
+    /// This changes the terms extracted from the tokens, not the tokens:
+    /// a run of CJK characters stays one token. See
+    /// [`extract_terms`](crate::analyzer::trigram::extract_terms).
+    ///
+    /// ```
+    /// use lattice_core::analyzer::TokenizerConfig;
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::builder()
+    ///     .tokenizer(TokenizerConfig::new().cjk_bigrams())
+    ///     .build()
+    ///     .unwrap();
+    /// engine.add("東京タワーの夜景").unwrap();
+    /// engine.add("京都の寺").unwrap();
+    /// let hits = engine.search("東京タワー", 10);
+    /// assert_eq!(hits[0].doc_id, 0);
+    /// ```
+    #[must_use]
+    pub const fn cjk_bigrams(mut self) -> Self {
+        self.cjk_bigrams = true;
+        self
+    }
+}
+
+/// Returns the email address, URL or version string `word` holds, without
+/// surrounding brackets, quotes and trailing punctuation.
+fn compound(word: &str) -> Option<&str> {
+    let word = word
+        .trim_start_matches(['(', '[', '<', '"', '\''])
+        .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\'']);
+    (is_url(word) || is_email(word) || is_version(word)).then_some(word)
+}
+
+/// `scheme://rest` or `www.host`.
+fn is_url(word: &str) -> bool {
+    if let Some(host) = word.strip_prefix("www.") {
+        return host.len() > 2 && host.contains('.');
+    }
+    let Some((scheme, rest)) = word.split_once("://") else {
+        return false;
+    };
+    !rest.is_empty()
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.'))
+}
+
+/// `local@domain.tld`, ASCII only.
+fn is_email(word: &str) -> bool {
+    let Some((local, domain)) = word.split_once('@') else {
+        return false;
+    };
+    !local.is_empty()
+        && local
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'_' | b'%' | b'+' | b'-'))
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+        })
+}
+
+/// `[v]N.N[.N...]`, optionally followed by a `-` or `+` suffix.
+fn is_version(word: &str) -> bool {
+    let word = word.strip_prefix('v').unwrap_or(word);
+    let (numbers, suffix) = match word.find(['-', '+']) {
+        Some(i) => (&word[..i], Some(&word[i + 1..])),
+        None => (word, None),
+    };
+    numbers.contains('.')
+        && numbers
+            .split('.')
+            .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        && suffix.is_none_or(|suffix| {
+            !suffix.is_empty()
+                && suffix
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'.' | b'-' | b'+'))
+        })
+}
+
+/// What to do with tokens longer than [`TokenizerConfig::max_token_len`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum LongTokenPolicy {
+    /// Drop the token. It takes no position.
+    #[default]
+    Skip,
+    /// Keep the longest prefix of at most `max_token_len` bytes that ends
+    /// on a character boundary.
+    Truncate,
+}
+
+/// Token counts from one [`Tokenizer::tokenize`] call.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenStats {
+    /// Tokens passed to the callback, including truncated ones.
+    pub emitted: u32,
+    /// Tokens dropped for exceeding the length limit.
+    pub skipped: u32,
+    /// Tokens shortened to the length limit.
+    pub truncated: u32,
 }
 
 /// Streaming tokenizer - splits normalized text into tokens.
@@ -132,28 +351,43 @@ impl Field {
 ///
 /// ## How It Works
 ///
-/// It does a single forward scan looking for ASCII space bytes (0x20).
-/// Each non-space run between spaces becomes a token. Simple and fast.
+/// It does a single forward scan looking for delimiter bytes, ASCII space
+/// (0x20) by default (see [`TokenizerConfig`]). Each non-empty run between delimiters becomes a token.
+/// Simple and fast.
 #[derive(Debug, Copy, Clone)]
-#[repr(transparent)]
 pub struct Tokenizer {
     field: Field,
+    config: TokenizerConfig,
 }
 
 impl Tokenizer {
     /// Creates a new tokenizer for the specified field.
     #[inline]
     pub const fn new(field: Field) -> Self {
-        Self { field }
+        Self::with_config(field, TokenizerConfig::new())
+    }
+
+    /// Creates a tokenizer for `field` with the given options.
+    #[inline]
+    pub const fn with_config(field: Field, config: TokenizerConfig) -> Self {
+        Self { field, config }
+    }
+
+    /// Returns the tokenizer options.
+    #[inline]
+    pub const fn config(&self) -> TokenizerConfig {
+        self.config
     }
 
     /// Tokenizes normalized input and emits `(text, field, position)`.
     ///
     /// Position is `u32`. After emitting a token at position `u32::MAX`,
-    /// further emissions stop (overflow protection).
+    /// further emissions stop (overflow protection). Tokens over the
+    /// configured length limit are skipped or truncated; the returned
+    /// [`TokenStats`] counts them.
     #[inline(always)]
     #[allow(clippy::needless_lifetimes)]
-    pub fn tokenize<'n, F>(&self, normalized: &'n str, mut emit: F)
+    pub fn tokenize<'n, F>(&self, normalized: &'n str, mut emit: F) -> TokenStats
     where
         F: FnMut(&'n str, Field, u32),
     {
@@ -189,37 +423,125 @@ impl Tokenizer {
             "tokenizer: consecutive spaces — normalizer contract violated"
         );
 
+        let mut stats = TokenStats::default();
         if bytes.is_empty() {
-            return;
+            return stats;
         }
 
         let field = self.field;
+        let max_len = self.config.max_token_len.unwrap_or(usize::MAX);
+        let truncate = self.config.long_token_policy == LongTokenPolicy::Truncate;
         let mut start = 0usize;
         let mut pos = 0u32;
+        let mut exhausted = false;
+
+        // Emits one token; returns false once positions are exhausted
+        let mut push = |mut text: &'n str| -> bool {
+            if text.len() > max_len {
+                let mut cut = max_len;
+                while !text.is_char_boundary(cut) {
+                    cut -= 1;
+                }
+                if !truncate || cut == 0 {
+                    stats.skipped += 1;
+                    return true;
+                }
+                text = &text[..cut];
+                stats.truncated += 1;
+            }
+            emit(text, field, pos);
+            stats.emitted += 1;
+            if pos == u32::MAX {
+                return false;
+            }
+            pos += 1;
+            true
+        };
+
+        if self.config.keep_compounds {
+            for word in normalized.split(' ') {
+                let whole = compound(word);
+                if whole.is_some_and(|whole| !push(whole)) {
+                    return stats;
+                }
+                // The components, except one that is the whole token again
+                let mut start = 0;
+                let mut exhausted = false;
+                self.config.delimiters.scan(word.as_bytes(), |i| {
+                    if start < i && whole != Some(&word[start..i]) && !push(&word[start..i]) {
+                        exhausted = true;
+                        return false;
+                    }
+                    start = i + 1;
+                    true
+                });
+                if exhausted
+                    || (start < word.len()
+                        && whole != Some(&word[start..])
+                        && !push(&word[start..]))
+                {
+                    return stats;
+                }
+            }
+            return stats;
+        }
 
-        for i in memchr_iter(b' ', bytes) {
+        self.config.delimiters.scan(bytes, |i| {
             if start < i {
-                // SAFETY: `normalized` is valid UTF-8. We split only on ASCII space (0x20),
-                // which is never a continuation byte, so `bytes[start..i]` is always a
-                // valid UTF-8 subslice.
+                // SAFETY: `normalized` is valid UTF-8. We split only on ASCII delimiters,
+                // which are never part of a multi-byte sequence, so `bytes[start..i]` is
+                // always a valid UTF-8 subslice.
                 let text = unsafe { str::from_utf8_unchecked(&bytes[start..i]) };
-                emit(text, field, pos);
-                if pos == u32::MAX {
-                    return;
+                if !push(text) {
+                    exhausted = true;
+                    return false;
                 }
-                pos += 1;
             }
             start = i + 1;
-        }
+            true
+        });
 
-        if start < bytes.len() {
+        if !exhausted && start < bytes.len() {
             // SAFETY: same invariants as above — `bytes[start..]` is a valid UTF-8
-            // subslice since `start` was set to `i + 1` after an ASCII space byte.
+            // subslice since `start` was set to `i + 1` after an ASCII delimiter byte.
             let text = unsafe { str::from_utf8_unchecked(&bytes[start..]) };
-            emit(text, field, pos);
+            push(text);
         }
+        stats
+    }
+
+    /// Writes the tokens of `normalized` to `out`, separated by single spaces.
+    ///
+    /// The result is normalized text again, with the delimiters and length
+    /// limits already applied, so it can go to stages that only split on
+    /// spaces, such as trigram extraction.
+    ///
+    /// ```
+    /// use lattice_core::analyzer::{
+    ///     DelimiterSet, Field, LongTokenPolicy, Tokenizer, TokenizerConfig,
+    /// };
+    ///
+    /// let config = TokenizerConfig::new()
+    ///     .split_on(DelimiterSet::PUNCTUATION)
+    ///     .limit_token_len(8, LongTokenPolicy::Skip);
+    /// let mut out = String::new();
+    /// let stats = Tokenizer::with_config(Field::Body, config)
+    ///     .rewrite_into("id=aGVsbG8gd29ybGQ, ok", &mut out);
+    /// assert_eq!(out, "id ok");
+    /// assert_eq!(stats.skipped, 1);
+    /// ```
+    pub fn rewrite_into(&self, normalized: &str, out: &mut String) -> TokenStats {
+        out.clear();
+        out.reserve(normalized.len());
+        self.tokenize(normalized, |text, _, pos| {
+            if pos > 0 {
+                out.push(' ');
+            }
+            out.push_str(text);
+        })
     }
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -330,6 +652,75 @@ mod tests {
         assert_eq!(n, 3);
     }
 
+    #[test]
+    fn splits_on_configured_delimiters() {
+        let config = TokenizerConfig::new().split_on(DelimiterSet::PUNCTUATION);
+        let tokenizer = Tokenizer::with_config(Field::Body, config);
+        let mut out = Vec::new();
+        tokenizer.tokenize("foo,bar a/b/c ,,x. (y) ünï-cödé", |text, _, pos| {
+            out.push((text, pos));
+        });
+        let expected = ["foo", "bar", "a", "b", "c", "x", "y", "ünï", "cödé"];
+        assert_eq!(out.len(), expected.len());
+        for (i, (text, pos)) in out.iter().enumerate() {
+            assert_eq!(*text, expected[i]);
+            assert_eq!(*pos, i as u32);
+        }
+
+        // The default still keeps punctuation inside tokens
+        assert_eq!(collect("foo,bar", Field::Body)[0].0, "foo,bar");
+        assert_eq!(
+            Tokenizer::new(Field::Body).config(),
+            TokenizerConfig::default()
+        );
+        assert_eq!(TokenizerConfig::new().delimiters, DelimiterSet::SPACE);
+
+        // Individual classes leave the other punctuation inside tokens
+        let config = TokenizerConfig::new().split_on(DelimiterSet::SEPARATORS);
+        let mut out = Vec::new();
+        Tokenizer::with_config(Field::Body, config)
+            .tokenize("k=v;a/b|c", |text, _, _| out.push(text));
+        assert_eq!(out, ["k=v", "a/b", "c"]);
+    }
+
+    #[test]
+    fn long_tokens_are_skipped_or_truncated() {
+        let input = "ok aaaaaaaaaa héééé end";
+        let skip = TokenizerConfig::new().limit_token_len(4, LongTokenPolicy::Skip);
+        let mut out = Vec::new();
+        let stats = Tokenizer::with_config(Field::Body, skip)
+            .tokenize(input, |text, _, pos| out.push((text, pos)));
+        assert_eq!(out, [("ok", 0), ("end", 1)]);
+        assert_eq!(
+            stats,
+            TokenStats {
+                emitted: 2,
+                skipped: 2,
+                truncated: 0
+            }
+        );
+
+        // Truncation backs off to a character boundary: "hé" is 3 bytes
+        let truncate = TokenizerConfig::new().limit_token_len(4, LongTokenPolicy::Truncate);
+        let mut rewritten = String::new();
+        let stats =
+            Tokenizer::with_config(Field::Body, truncate).rewrite_into(input, &mut rewritten);
+        assert_eq!(rewritten, "ok aaaa hé end");
+        assert_eq!(stats.truncated, 2);
+        assert_eq!(stats.emitted, 4);
+
+        // A limit below the first character's width drops the token
+        let tiny = TokenizerConfig::new().limit_token_len(1, LongTokenPolicy::Truncate);
+        let stats = Tokenizer::with_config(Field::Body, tiny).rewrite_into("éa b", &mut rewritten);
+        assert_eq!(rewritten, "b");
+        assert_eq!(stats.skipped, 1);
+
+        // No limit by default
+        let stats = Tokenizer::new(Field::Body).rewrite_into(input, &mut rewritten);
+        assert_eq!(rewritten, input);
+        assert_eq!(stats.emitted, 4);
+    }
+
     #[test]
     fn composes_with_ngram_layer() {
         let mut gram_count = 0usize;
@@ -343,4 +734,56 @@ mod tests {
 
         assert_eq!(gram_count, 6);
     }
+
+    #[test]
+    fn compounds_are_kept_whole_and_split() {
+        let config = TokenizerConfig::new()
+            .split_on(DelimiterSet::PUNCTUATION)
+            .keep_compounds();
+        let tokens = |input| {
+            let mut out = Vec::new();
+            Tokenizer::with_config(Field::Body, config).tokenize(input, |text, _, pos| {
+                out.push((text, pos));
+            });
+            out
+        };
+        assert_eq!(
+            tokens("see <https://x.io/a>, 2.0.0-rc.1"),
+            [
+                ("see", 0),
+                ("https://x.io/a", 1),
+                ("https", 2),
+                ("x", 3),
+                ("io", 4),
+                ("a", 5),
+                ("2.0.0-rc.1", 6),
+                ("2", 7),
+                ("0", 8),
+                ("0", 9),
+                ("rc", 10),
+                ("1", 11),
+            ]
+        );
+        assert_eq!(tokens("www.example.org")[0].0, "www.example.org");
+
+        for plain in [
+            "a.b",
+            "v1",
+            "1.",
+            "@home",
+            "me@localhost",
+            "ftp:/x",
+            "1..2",
+            "v1.2-",
+        ] {
+            assert_eq!(compound(plain), None, "{plain}");
+        }
+
+        // Without splitting delimiters the whole token is the only one
+        let whole_only = TokenizerConfig::new().keep_compounds();
+        let mut out = Vec::new();
+        Tokenizer::with_config(Field::Body, whole_only)
+            .tokenize("v1.2.3 bob@example.com.", |text, _, _| out.push(text));
+        assert_eq!(out, ["v1.2.3", "bob@example.com", "bob@example.com."]);
+    }
 }
@@ -2,6 +2,26 @@
 //!
 //! Provides efficient extraction of 3-character sequences from text.
 //! Trigrams are the foundation of Lattice's fuzzy search capability.
+//!
+//! ## CJK text
+//!
+//! Chinese and Japanese are written without spaces, and each character
+//! takes three UTF-8 bytes, so byte trigrams mostly straddle two characters
+//! and match unrelated text sharing a lead byte. With
+//! [`TokenizerConfig::cjk_bigrams`](crate::analyzer::TokenizerConfig::cjk_bigrams),
+//! [`extract_terms`] indexes runs of Han, kana and Hangul characters by
+//! character instead: every character, and every pair of adjacent
+//! characters, becomes a term, while the rest of the text keeps its byte
+//! trigrams. The terms share the [`Trigram`] key space, using lead bytes
+//! `0xF8..=0xFF` that never occur in UTF-8:
+//!
+//! ```text
+//! 0xF8_0000 | code point                        one character (exact)
+//! 0xFC_0000 | 18-bit hash of both code points   two adjacent characters
+//! ```
+//!
+//! Every term of a substring is also a term of the text containing it, so
+//! phrase and substring searches stay exact after verification.
 
 use lattice_types::Trigram;
 
@@ -69,6 +89,100 @@ where
     }
 }
 
+/// Lead bits of single-character CJK terms.
+const CJK_UNIGRAM: u32 = 0xF8_0000;
+
+/// Lead bits of two-character CJK terms.
+const CJK_BIGRAM: u32 = 0xFC_0000;
+
+/// Returns `true` for Han ideographs, kana and Hangul: the scripts written
+/// without spaces between words (or, for Hangul, often with long compounds).
+#[inline]
+pub fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x1100..=0x11FF         // Hangul Jamo
+            | 0x3040..=0x30FF   // Hiragana, Katakana
+            | 0x3130..=0x318F   // Hangul Compatibility Jamo
+            | 0x31F0..=0x31FF   // Katakana Phonetic Extensions
+            | 0x3400..=0x4DBF   // CJK Unified Ideographs Extension A
+            | 0x4E00..=0x9FFF   // CJK Unified Ideographs
+            | 0xAC00..=0xD7AF   // Hangul Syllables
+            | 0xF900..=0xFAFF   // CJK Compatibility Ideographs
+            | 0xFF66..=0xFF9F   // Halfwidth Katakana
+            | 0x20000..=0x2FA1F // Supplementary ideographs
+    )
+}
+
+/// The term of one CJK character.
+#[inline]
+fn cjk_unigram(c: char) -> Trigram {
+    // Every CJK code point is below 0x40000, so the term is exact
+    Trigram(CJK_UNIGRAM | c as u32)
+}
+
+/// The term of two adjacent CJK characters.
+#[inline]
+fn cjk_bigram(a: char, b: char) -> Trigram {
+    let pair = ((a as u64) << 21 | b as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+    Trigram(CJK_BIGRAM | (pair >> 46) as u32)
+}
+
+/// Extracts index terms: the byte trigrams of `text`, or with
+/// `cjk_bigrams`, character unigrams and bigrams for runs of CJK characters
+/// (see the [module docs](self)) and byte trigrams for the text between
+/// them.
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::analyzer::trigram::extract_terms;
+///
+/// let mut terms = Vec::new();
+/// extract_terms("東京タワー", true, |t| terms.push(t));
+/// assert_eq!(terms.len(), 5 + 4); // five characters, four pairs
+///
+/// terms.clear();
+/// extract_terms("東京タワー", false, |t| terms.push(t));
+/// assert_eq!(terms.len(), 15 - 2); // byte trigrams
+/// ```
+#[inline]
+pub fn extract_terms<F>(text: &str, cjk_bigrams: bool, mut callback: F)
+where
+    F: FnMut(Trigram),
+{
+    extract_terms_with_pos(text, cjk_bigrams, |t, _| callback(t));
+}
+
+/// Like [`extract_terms`], with the byte offset at which each term starts.
+/// Offsets are ascending; a character's unigram and its bigram with the
+/// next character share its offset.
+pub fn extract_terms_with_pos<F>(text: &str, cjk_bigrams: bool, mut callback: F)
+where
+    F: FnMut(Trigram, usize),
+{
+    if !cjk_bigrams || text.is_ascii() {
+        extract_trigrams_with_pos(text, callback);
+        return;
+    }
+
+    // Start of the text since the last CJK character
+    let mut segment = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if !is_cjk(c) {
+            continue;
+        }
+        extract_trigrams_with_pos(&text[segment..i], |t, offset| callback(t, segment + offset));
+        callback(cjk_unigram(c), i);
+        if let Some(&(_, next)) = chars.peek().filter(|&&(_, next)| is_cjk(next)) {
+            callback(cjk_bigram(c, next), i);
+        }
+        segment = i + c.len_utf8();
+    }
+    extract_trigrams_with_pos(&text[segment..], |t, offset| callback(t, segment + offset));
+}
+
 /// Trait for types that can extract trigrams.
 ///
 /// This allows custom tokenization strategies while reusing
@@ -180,4 +294,54 @@ mod tests {
         // Trigrams: "caf" (bytes 0-2), "af<0xC3>" (bytes 1-3), "f<0xC3><0xA9>" (bytes 2-4)
         assert_eq!(trigrams.len(), 3);
     }
+
+    #[test]
+    fn cjk_runs_become_character_terms() {
+        let collect = |text: &str| {
+            let mut terms = Vec::new();
+            extract_terms_with_pos(text, true, |t, pos| terms.push((t, pos)));
+            terms
+        };
+
+        // 日本語: three unigrams, two bigrams, no byte trigrams
+        let terms = collect("日本語");
+        assert_eq!(terms.len(), 5);
+        assert_eq!(terms[0], (Trigram(CJK_UNIGRAM | '日' as u32), 0));
+        assert_eq!(terms[1], (cjk_bigram('日', '本'), 0));
+        assert_eq!(terms[4], (Trigram(CJK_UNIGRAM | '語' as u32), 6));
+        assert!(terms.iter().all(|(t, _)| t.to_bytes()[0] >= 0xF8));
+        assert_ne!(cjk_bigram('日', '本'), cjk_bigram('本', '日'));
+
+        // Text around a run keeps its byte trigrams, at their own offsets
+        let terms = collect("iphone 手机 case");
+        let ascii: Vec<(Trigram, usize)> = terms
+            .iter()
+            .copied()
+            .filter(|(t, _)| t.to_bytes()[0] < 0x80)
+            .collect();
+        assert_eq!(ascii[0], (Trigram::from_bytes(b'i', b'p', b'h'), 0));
+        assert_eq!(
+            ascii.last(),
+            Some(&(Trigram::from_bytes(b'a', b's', b'e'), 15))
+        );
+        assert_eq!(terms.len(), ascii.len() + 3);
+        assert!(terms.is_sorted_by_key(|&(_, pos)| pos));
+
+        // Every term of a substring is a term of the whole
+        let whole = collect("ab東京タワーcd");
+        for part in ["東京", "京タワ", "ワーcd", "b東"] {
+            for term in collect(part) {
+                assert!(whole.iter().any(|(t, _)| *t == term.0), "{part}");
+            }
+        }
+
+        // Off, or without CJK characters, terms are byte trigrams
+        let mut bytes = Vec::new();
+        extract_terms_with_pos("日本", false, |t, pos| bytes.push((t, pos)));
+        assert_eq!(bytes.len(), 4);
+        let mut plain = Vec::new();
+        extract_trigrams_with_pos("café crème", |t, pos| plain.push((t, pos)));
+        assert_eq!(collect("café crème"), plain);
+        assert!(!is_cjk('a') && !is_cjk('é') && is_cjk('한') && is_cjk('ｶ'));
+    }
 }
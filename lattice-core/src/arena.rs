@@ -1,27 +1,53 @@
 //! Bump Allocator for Document Storage
 //!
-//! Eliminates per-document allocations by storing all text in a single
-//! contiguous buffer. Documents are referenced by (offset, length) pairs.
+//! Eliminates per-document allocations by storing all text in a list of
+//! fixed-size pages. Documents are referenced by (page, offset, length)
+//! triples and never straddle a page boundary.
 //!
 //! ## Memory Layout
 //!
 //! ```text
-//! Arena Buffer: [doc0][doc1][doc2][doc3]...[free space]
-//!               ^     ^     ^     ^
-//!               |     |     |     |
-//! Spans:       (0,5) (5,7) (12,4) (16,8) ...
+//! Page 0: [doc0][doc1][doc2]..[unused tail]
+//! Page 1: [doc3][doc4]...[free space]
+//!          ^     ^
+//!          |     |
+//! Spans:  (1,0,7) (1,7,4) ...
 //! ```
 //!
+//! Only the tail of a page that is too short for the next document is
+//! wasted (at most 64KB per page). Large owned buffers can be adopted as
+//! pages of their own with [`Arena::push_owned`] instead of being copied.
+//! With 32-bit page indices and 4 MiB pages the arena can address 16 PiB
+//! of text; exhausting it is reported as [`ArenaError::CapacityExceeded`]
+//! rather than wrapping an offset.
+//!
 //! ## Performance
 //!
-//! - Allocation: O(1) - just bump pointer
-//! - Retrieval: O(1) - slice from buffer
-//! - Memory overhead: 6 bytes per document (u32 offset + u16 len)
+//! - Allocation: O(1) - bump pointer within the current page
+//! - Retrieval: O(1) - slice from one page
+//! - Memory overhead: 12 bytes per document (u32 page + u32 offset + u16 len)
 //! - Cache efficiency: Documents stored sequentially (good for iteration)
+//!
+//! Pages are reference-counted: cloning an arena shares every page, and
+//! only the page currently being filled is copied on the next write.
+
+use core::fmt;
+use lattice_types::DocId;
+use std::sync::Arc;
+
+/// Default page size (4 MiB).
+pub const PAGE_SIZE: usize = 4 * 1024 * 1024;
 
-/// Document reference - 6 bytes
+/// Smallest allowed page size: one maximum-length document.
+const MIN_PAGE_SIZE: usize = u16::MAX as usize;
+
+/// Smallest document [`Arena::push_owned`] adopts rather than copies.
+const MIN_ADOPTED_LEN: usize = 4 * 1024;
+
+/// Document reference - 12 bytes
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct DocSpan {
+    page: u32,
     offset: u32,
     len: u16,
 }
@@ -29,11 +55,17 @@ pub struct DocSpan {
 impl DocSpan {
     /// Creates a new document span.
     #[inline(always)]
-    pub const fn new(offset: u32, len: u16) -> Self {
-        Self { offset, len }
+    pub const fn new(page: u32, offset: u32, len: u16) -> Self {
+        Self { page, offset, len }
+    }
+
+    /// Returns the index of the page holding the document.
+    #[inline(always)]
+    pub const fn page(self) -> usize {
+        self.page as usize
     }
 
-    /// Returns the byte offset in the arena.
+    /// Returns the byte offset within the page.
     #[inline(always)]
     pub const fn offset(self) -> usize {
         self.offset as usize
@@ -44,16 +76,56 @@ impl DocSpan {
     pub const fn len(self) -> usize {
         self.len as usize
     }
+
+    /// Returns true if the span covers no bytes.
+    #[inline(always)]
+    pub const fn is_empty(self) -> bool {
+        self.len == 0
+    }
+}
+
+/// Errors returned by [`Arena::push`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArenaError {
+    /// The document is longer than a span can describe (u16::MAX bytes).
+    TooLarge {
+        /// The document length in bytes.
+        len: usize,
+    },
+    /// The arena has run out of pages or document IDs.
+    CapacityExceeded,
+}
+
+impl fmt::Display for ArenaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ArenaError::TooLarge { len } => write!(
+                f,
+                "document of {} bytes exceeds the arena limit of {} bytes",
+                len,
+                u16::MAX
+            ),
+            ArenaError::CapacityExceeded => write!(f, "arena capacity exceeded"),
+        }
+    }
 }
 
+impl core::error::Error for ArenaError {}
+
 /// Bump allocator for document text.
+#[derive(Clone)]
 pub struct Arena {
-    /// Contiguous storage buffer
-    buffer: Vec<u8>,
-    /// Document spans (offset, length pairs)
+    /// Storage pages: fixed-size pages, only the current one written to,
+    /// and adopted single-document buffers
+    pages: Vec<Arc<Vec<u8>>>,
+    /// Document spans (page, offset, length triples)
     spans: Vec<DocSpan>,
-    /// Current write position (bump pointer)
-    head: usize,
+    /// Index of the page currently being filled
+    current: usize,
+    /// Number of pages holding documents; later pages are spare
+    used: usize,
+    /// Capacity of each page in bytes
+    page_size: usize,
 }
 
 impl Default for Arena {
@@ -65,19 +137,28 @@ impl Default for Arena {
 impl Arena {
     /// Creates a new empty arena.
     pub fn new() -> Self {
-        Self {
-            buffer: Vec::with_capacity(64 * 1024), // 64KB initial
-            spans: Vec::with_capacity(1024),
-            head: 0,
-        }
+        Self::with_capacity(64 * 1024, 1024) // 64KB initial
     }
 
     /// Creates a new arena with pre-allocated capacity.
+    ///
+    /// `buffer_cap` is reserved in the first page (up to one page).
     pub fn with_capacity(buffer_cap: usize, doc_cap: usize) -> Self {
+        Self::with_page_size(PAGE_SIZE, buffer_cap, doc_cap)
+    }
+
+    /// Creates a new arena with a custom page size.
+    ///
+    /// The page size is raised to at least 64KB so any document fits in a
+    /// single page.
+    pub fn with_page_size(page_size: usize, buffer_cap: usize, doc_cap: usize) -> Self {
+        let page_size = page_size.max(MIN_PAGE_SIZE);
         Self {
-            buffer: Vec::with_capacity(buffer_cap),
+            pages: vec![Arc::new(Vec::with_capacity(buffer_cap.min(page_size)))],
             spans: Vec::with_capacity(doc_cap),
-            head: 0,
+            current: 0,
+            used: 1,
+            page_size,
         }
     }
 
@@ -93,73 +174,240 @@ impl Arena {
         self.spans.is_empty()
     }
 
-    /// Clears all documents (resets bump pointer but keeps capacity).
+    /// Returns the number of pages allocated so far.
+    #[inline(always)]
+    pub fn page_count(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Returns the total number of text bytes stored.
+    pub fn bytes_used(&self) -> usize {
+        self.pages[..self.used].iter().map(|page| page.len()).sum()
+    }
+
+    /// Returns the number of heap bytes held by the arena, including unused
+    /// page capacity.
+    pub fn capacity_bytes(&self) -> usize {
+        self.page_capacity_bytes() + self.span_capacity_bytes()
+    }
+
+    /// Returns the heap bytes held by text pages (and the page list).
+    pub fn page_capacity_bytes(&self) -> usize {
+        self.pages.iter().map(|page| page.capacity()).sum::<usize>()
+            + self.pages.capacity() * core::mem::size_of::<Arc<Vec<u8>>>()
+    }
+
+    /// Returns the heap bytes held by document spans.
+    pub fn span_capacity_bytes(&self) -> usize {
+        self.spans.capacity() * core::mem::size_of::<DocSpan>()
+    }
+
+    /// Reserves room for `docs` more documents totalling `bytes` bytes, so
+    /// that pushing them does not reallocate.
+    ///
+    /// The current page is grown to fit as much as it can hold (up to one
+    /// page), and further pages are allocated up front for the rest.
+    pub fn reserve(&mut self, docs: usize, bytes: usize) {
+        self.spans.reserve(docs);
+
+        let page = Arc::make_mut(&mut self.pages[self.current]);
+        let room = self.page_size - page.len();
+        page.reserve_exact(bytes.min(room));
+
+        // Pages kept by `clear` already count towards the reservation
+        let spare_pages = self.pages.len() - self.used;
+        let extra_pages = bytes.saturating_sub(room).div_ceil(self.page_size);
+        let max_pages = (u32::MAX as usize).saturating_sub(self.pages.len());
+        for _ in spare_pages..extra_pages.min(spare_pages + max_pages) {
+            self.pages
+                .push(Arc::new(Vec::with_capacity(self.page_size)));
+        }
+    }
+
+    /// Frees pages kept by [`clear`](Self::clear) that hold no documents.
+    pub fn release_unused_pages(&mut self) {
+        self.pages.truncate(self.used);
+    }
+
+    /// Frees all unused capacity: spare pages, the unused tail of every
+    /// page, and spare span slots.
+    pub fn shrink_to_fit(&mut self) {
+        self.release_unused_pages();
+        // Pages shared with a clone are not ours to reallocate
+        for page in &mut self.pages {
+            if let Some(page) = Arc::get_mut(page) {
+                page.shrink_to_fit();
+            }
+        }
+        self.pages.shrink_to_fit();
+        self.spans.shrink_to_fit();
+    }
+
+    /// Clears all documents (resets bump pointer but keeps allocated pages).
     pub fn clear(&mut self) {
-        self.head = 0;
         self.spans.clear();
-        // Note: we don't clear buffer to avoid re-zeroing
+        for page in &mut self.pages[..self.used] {
+            match Arc::get_mut(page) {
+                Some(page) => page.clear(),
+                None => *page = Arc::default(),
+            }
+        }
+        self.current = 0;
+        self.used = 1;
     }
 
     /// Adds a document to the arena.
     ///
     /// # Errors
     ///
-    /// Returns `None` if the document length exceeds u16::MAX (65535 bytes).
+    /// Returns `ArenaError::TooLarge` if the document length exceeds
+    /// u16::MAX (65535 bytes), or `ArenaError::CapacityExceeded` if no more
+    /// pages or document IDs are available.
     #[inline]
-    pub fn push(&mut self, text: &str) -> Option<u32> {
+    pub fn push(&mut self, text: &str) -> Result<DocId, ArenaError> {
         let bytes = text.as_bytes();
         let len = bytes.len();
-        if len > u16::MAX as usize {
-            return None;
+        let doc_id = self.check(len)?;
+
+        if self.pages[self.current].len() + len > self.page_size {
+            self.advance_page();
         }
 
-        let doc_id = self.spans.len() as u32;
-        let offset = self.head;
+        let page = Arc::make_mut(&mut self.pages[self.current]);
+        let offset = page.len();
+        page.extend_from_slice(bytes);
+
+        // Bounded by page_size, which always fits the page index and offset
+        self.spans
+            .push(DocSpan::new(self.current as u32, offset as u32, len as u16));
+        Ok(doc_id)
+    }
 
-        // Ensure capacity with 1.5x growth factor for better memory efficiency
-        if offset + len > self.buffer.capacity() {
-            let new_cap = (self.buffer.capacity() * 2).max(offset + len).max(4096);
-            self.buffer.reserve(new_cap - self.buffer.capacity());
+    /// Adds a document, taking ownership of its buffer.
+    ///
+    /// Documents of at least 4KB whose buffer has little spare capacity
+    /// become a page of their own without being copied; others are copied
+    /// as by [`push`](Self::push) and the buffer is dropped.
+    ///
+    /// # Errors
+    ///
+    /// As for [`push`](Self::push).
+    pub fn push_owned(&mut self, text: String) -> Result<DocId, ArenaError> {
+        let len = text.len();
+        // Adopting a mostly empty buffer would hold more memory than copying
+        if len < MIN_ADOPTED_LEN || text.capacity() - len > len / 8 {
+            return self.push(&text);
         }
+        let doc_id = self.check(len)?;
 
-        unsafe {
-            // SAFETY: We reserved capacity for `offset + len` above.
-            // `copy_nonoverlapping` is valid because:
-            // - `bytes.as_ptr()` is valid for `len` bytes (it's a valid string slice)
-            // - `self.buffer.as_mut_ptr().add(offset)` is valid for `len` bytes
-            //   (we just ensured capacity and offset < capacity)
-            // - Both pointers are properly aligned (u8 has align 1)
-            // - The regions don't overlap (we're writing to arena buffer, reading from input)
-            std::ptr::copy_nonoverlapping(
-                bytes.as_ptr(),
-                self.buffer.as_mut_ptr().add(offset),
-                len,
-            );
-            // SAFETY: `set_len` is valid because:
-            // - We just wrote `len` bytes starting at `offset`
-            // - `self.head = offset + len` is the new valid length
-            // - All bytes in the buffer are initialized (we only ever append)
-            self.head = offset + len;
-            self.buffer.set_len(self.head);
+        // Spare pages only ever follow the pages in use, so inserting here
+        // renumbers no document
+        let page = self.used as u32;
+        self.pages.insert(self.used, Arc::new(text.into_bytes()));
+        self.used += 1;
+        self.spans.push(DocSpan::new(page, 0, len as u16));
+        Ok(doc_id)
+    }
+
+    /// Returns the ID the next document of `len` bytes would get, or the
+    /// error pushing it would return.
+    ///
+    /// # Errors
+    ///
+    /// As for [`push`](Self::push).
+    pub fn check(&self, len: usize) -> Result<DocId, ArenaError> {
+        if len > u16::MAX as usize {
+            return Err(ArenaError::TooLarge { len });
         }
+        // One more page may be needed; page indices and offsets are u32
+        if self.used >= u32::MAX as usize || self.page_size > u32::MAX as usize {
+            return Err(ArenaError::CapacityExceeded);
+        }
+        DocId::try_from(self.spans.len()).map_err(|_| ArenaError::CapacityExceeded)
+    }
+
+    /// Checks that `count` more documents, each within the length limit,
+    /// can be pushed, so a batch can be validated before any of it is
+    /// added.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ArenaError::CapacityExceeded` if the pages or document IDs
+    /// could run out partway through.
+    pub fn check_batch(&self, count: usize) -> Result<(), ArenaError> {
+        if count == 0 {
+            return Ok(());
+        }
+        // Each push starts at most one page
+        let pages_fit = self
+            .used
+            .checked_add(count)
+            .is_some_and(|pages| pages <= u32::MAX as usize);
+        let last = self.spans.len().checked_add(count - 1);
+        if !pages_fit
+            || self.page_size > u32::MAX as usize
+            || last.is_none_or(|last| DocId::try_from(last).is_err())
+        {
+            return Err(ArenaError::CapacityExceeded);
+        }
+        Ok(())
+    }
+
+    /// Moves the bump pointer to a fresh page, reusing one kept by
+    /// [`clear`](Self::clear) if available.
+    #[cold]
+    fn advance_page(&mut self) {
+        if self.used == self.pages.len() {
+            self.pages
+                .push(Arc::new(Vec::with_capacity(self.page_size)));
+        }
+        self.current = self.used;
+        self.used += 1;
+    }
 
-        self.spans.push(DocSpan::new(offset as u32, len as u16));
-        Some(doc_id)
+    /// Returns the indices of spans that fall outside their page or do not
+    /// cover valid UTF-8.
+    pub fn invalid_spans(&self) -> impl Iterator<Item = usize> + '_ {
+        self.spans.iter().enumerate().filter_map(|(i, span)| {
+            let valid = self
+                .pages
+                .get(span.page())
+                .and_then(|page| page.get(span.offset()..span.offset() + span.len()))
+                .is_some_and(|bytes| std::str::from_utf8(bytes).is_ok());
+            (!valid).then_some(i)
+        })
     }
 
     /// Gets a document by ID.
     #[inline(always)]
-    pub fn get(&self, doc_id: u32) -> Option<&str> {
-        let span = self.spans.get(doc_id as usize)?;
+    pub fn get(&self, doc_id: DocId) -> Option<&str> {
+        let span = self.spans.get(usize::try_from(doc_id).ok()?)?;
+        Some(self.text(*span))
+    }
+
+    /// Iterates over all documents with their IDs, in insertion order.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (DocId, &str)> + DoubleEndedIterator + '_ {
+        // IDs fit: `push` never hands out one past the DocId range
+        self.spans
+            .iter()
+            .enumerate()
+            .map(|(i, span)| (i as DocId, self.text(*span)))
+    }
+
+    #[inline(always)]
+    fn text(&self, span: DocSpan) -> &str {
         let start = span.offset();
         let end = start + span.len();
+        let bytes = &self.pages[span.page()][start..end];
 
         // SAFETY: `from_utf8_unchecked` is valid because:
-        // - We only store valid UTF-8 data (verified `&str` input to `push`)
-        // - The span offsets point to contiguous bytes within the buffer
-        // - We never modify buffer contents after writing
-        // - Bounds were validated above via `get(doc_id)`
-        unsafe { Some(std::str::from_utf8_unchecked(&self.buffer[start..end])) }
+        // - We only store valid UTF-8 data (verified `&str` or `String` input
+        //   to `push` and `push_owned`)
+        // - Each span covers exactly the bytes of one pushed document within
+        //   a single page
+        // - We never modify page contents after writing (only `clear`, which
+        //   also drops every span)
+        unsafe { std::str::from_utf8_unchecked(bytes) }
     }
 }
 
@@ -204,7 +452,23 @@ mod tests {
     fn document_too_long() {
         let mut arena = Arena::new();
         let text = "x".repeat(70000);
-        assert!(arena.push(&text).is_none());
+        assert_eq!(arena.push(&text), Err(ArenaError::TooLarge { len: 70000 }));
+    }
+
+    #[test]
+    fn check_batch_bounds_pages_and_ids() {
+        let mut arena = Arena::new();
+        arena.push("doc").unwrap();
+        assert_eq!(arena.check_batch(0), Ok(()));
+        assert_eq!(arena.check_batch(1_000), Ok(()));
+        assert_eq!(
+            arena.check_batch(u32::MAX as usize),
+            Err(ArenaError::CapacityExceeded)
+        );
+        assert_eq!(
+            arena.check_batch(usize::MAX),
+            Err(ArenaError::CapacityExceeded)
+        );
     }
 
     #[test]
@@ -234,4 +498,136 @@ mod tests {
         assert!(arena.get(0).unwrap().contains("document number 0"));
         assert!(arena.get(9999).unwrap().contains("document number 9999"));
     }
+
+    #[test]
+    fn documents_fill_pages_without_straddling() {
+        let mut arena = Arena::with_page_size(0, 0, 0);
+        let a = "a".repeat(40_000);
+        let b = "b".repeat(40_000);
+        let c = "c".repeat(20_000);
+
+        let id_a = arena.push(&a).expect("should push");
+        let id_b = arena.push(&b).expect("should push");
+        let id_c = arena.push(&c).expect("should push");
+
+        // b does not fit after a in a 64KB page; c fits after b
+        assert_eq!(arena.page_count(), 2);
+        assert_eq!(arena.get(id_a), Some(a.as_str()));
+        assert_eq!(arena.get(id_b), Some(b.as_str()));
+        assert_eq!(arena.get(id_c), Some(c.as_str()));
+        assert_eq!(arena.bytes_used(), 100_000);
+    }
+
+    #[test]
+    fn clear_reuses_pages() {
+        let mut arena = Arena::with_page_size(0, 0, 0);
+        let text = "x".repeat(50_000);
+        for _ in 0..4 {
+            arena.push(&text).expect("should push");
+        }
+        assert_eq!(arena.page_count(), 4);
+
+        arena.clear();
+        assert_eq!(arena.bytes_used(), 0);
+        for _ in 0..4 {
+            arena.push(&text).expect("should push");
+        }
+        assert_eq!(arena.page_count(), 4);
+        assert_eq!(arena.get(3), Some(text.as_str()));
+    }
+
+    #[test]
+    fn shrink_to_fit_releases_pages() {
+        let mut arena = Arena::with_page_size(0, 0, 0);
+        let text = "x".repeat(50_000);
+        for _ in 0..4 {
+            arena.push(&text).expect("should push");
+        }
+        arena.clear();
+        arena.push("kept").expect("should push");
+
+        let before = arena.capacity_bytes();
+        arena.shrink_to_fit();
+        assert_eq!(arena.page_count(), 1);
+        assert!(arena.capacity_bytes() < before);
+        assert_eq!(arena.get(0), Some("kept"));
+
+        arena.push(&text).expect("should push");
+        assert_eq!(arena.get(1), Some(text.as_str()));
+    }
+
+    #[test]
+    fn clones_share_pages_until_written() {
+        let mut arena = Arena::with_page_size(0, 0, 0);
+        let text = "x".repeat(40_000);
+        arena.push(&text).expect("should push");
+        arena.push(&text).expect("should push");
+
+        let snapshot = arena.clone();
+        assert!(Arc::ptr_eq(&arena.pages[0], &snapshot.pages[0]));
+
+        arena.push("appended").expect("should push");
+        arena.clear();
+        arena.push("replaced").expect("should push");
+
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.get(1), Some(text.as_str()));
+        assert_eq!(arena.get(0), Some("replaced"));
+    }
+
+    #[test]
+    fn reserve_allocates_pages_up_front() {
+        let mut arena = Arena::with_page_size(0, 0, 0);
+        let text = "y".repeat(30_000);
+        arena.reserve(8, 8 * text.len());
+        assert_eq!(arena.page_count(), 4);
+        assert!(arena.spans.capacity() >= 8);
+
+        let first_page = arena.pages[0].as_ptr();
+        let capacity = arena.capacity_bytes();
+        for _ in 0..8 {
+            arena.push(&text).expect("should push");
+        }
+        assert_eq!(arena.pages[0].as_ptr(), first_page);
+        assert_eq!(arena.capacity_bytes(), capacity);
+        assert_eq!(arena.page_count(), 4);
+    }
+
+    #[test]
+    fn push_owned_adopts_large_buffers() {
+        let mut arena = Arena::new();
+        arena.push("before").expect("should push");
+
+        let large = "z".repeat(10_000);
+        let buffer = large.as_ptr();
+        let id = arena.push_owned(large).expect("should push");
+        assert_eq!(arena.page_count(), 2);
+        assert_eq!(arena.get(id).map(str::as_ptr), Some(buffer));
+
+        // Small documents and buffers with slack are copied
+        arena.push_owned("small".to_owned()).expect("should push");
+        let mut slack = String::with_capacity(20_000);
+        slack.push_str(&"w".repeat(5_000));
+        arena.push_owned(slack).expect("should push");
+        assert_eq!(arena.page_count(), 2);
+
+        // The filling page is still page 0
+        arena.push("after").expect("should push");
+        assert_eq!(arena.get(0), Some("before"));
+        assert_eq!(arena.get(2), Some("small"));
+        assert_eq!(arena.get(4), Some("after"));
+        assert_eq!(arena.bytes_used(), 6 + 10_000 + 5 + 5_000 + 5);
+        assert!(arena.invalid_spans().next().is_none());
+
+        arena.clear();
+        arena.push("reused").expect("should push");
+        assert_eq!(arena.get(0), Some("reused"));
+        assert_eq!(arena.bytes_used(), 6);
+    }
+
+    #[test]
+    fn missing_document() {
+        let arena = Arena::new();
+        assert_eq!(arena.get(0), None);
+    }
 }
@@ -0,0 +1,175 @@
+//! Async facade for use from async runtimes.
+//!
+//! An [`AsyncLattice`] owns a [`Lattice`] on a dedicated worker thread and
+//! forwards calls to it over a channel, so indexing and search never block
+//! the runtime's executor threads. Calls are processed one at a time, in
+//! the order they were made.
+//!
+//! ```text
+//! task ─┐
+//! task ─┼──▶ job queue ──▶ worker thread (Lattice) ──▶ oneshot reply
+//! task ─┘
+//! ```
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::AsyncLattice;
+//!
+//! # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+//! let engine = AsyncLattice::default();
+//! engine.add("hello world").await.unwrap();
+//! engine.add("help the world").await.unwrap();
+//! engine.commit().await;
+//!
+//! let results = engine.search("hello", 10).await;
+//! assert_eq!(results[0].doc_id, 0);
+//! # });
+//! ```
+
+use crate::index::CommitReport;
+use crate::index::Lattice;
+use lattice_types::{DocId, DocumentError, SearchResult};
+use std::thread;
+use tokio::sync::{mpsc, oneshot};
+
+type Job = Box<dyn FnOnce(&mut Lattice) + Send>;
+
+/// A [`Lattice`] running on its own thread, driven by async methods.
+///
+/// Cloning is cheap and yields another handle to the same engine. The
+/// worker thread exits once every handle has been dropped.
+///
+/// # Panics
+///
+/// Methods panic if the worker thread has stopped, which only happens when
+/// a closure passed to [`with`](Self::with) panicked.
+#[derive(Clone)]
+pub struct AsyncLattice {
+    jobs: mpsc::UnboundedSender<Job>,
+}
+
+impl Default for AsyncLattice {
+    fn default() -> Self {
+        Self::new(Lattice::new())
+    }
+}
+
+impl AsyncLattice {
+    /// Moves `engine` onto a new worker thread.
+    pub fn new(mut engine: Lattice) -> Self {
+        let (jobs, mut queue) = mpsc::unbounded_channel::<Job>();
+        thread::Builder::new()
+            .name("lattice-worker".into())
+            .spawn(move || {
+                while let Some(job) = queue.blocking_recv() {
+                    job(&mut engine);
+                }
+            })
+            .expect("failed to spawn lattice worker thread");
+
+        Self { jobs }
+    }
+
+    /// Runs `f` on the worker thread with exclusive access to the engine.
+    ///
+    /// Use this for anything not covered by the dedicated methods.
+    pub async fn with<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut Lattice) -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (reply, result) = oneshot::channel();
+        let job: Job = Box::new(move |engine| {
+            // The caller may have stopped waiting; the work is done regardless
+            let _ = reply.send(f(engine));
+        });
+
+        self.jobs
+            .send(job)
+            .unwrap_or_else(|_| panic!("lattice worker thread stopped"));
+        result.await.expect("lattice worker thread stopped")
+    }
+
+    /// Adds a document. See [`Lattice::add`].
+    pub async fn add(&self, content: impl Into<String>) -> Result<DocId, DocumentError> {
+        let content = content.into();
+        self.with(move |engine| engine.add(&content)).await
+    }
+
+    /// Searches the index. See [`Lattice::search`].
+    pub async fn search(&self, query: impl Into<String>, limit: usize) -> Vec<SearchResult> {
+        let query = query.into();
+        self.with(move |engine| engine.search(&query, limit)).await
+    }
+
+    /// Merges uncommitted documents into the index. See [`Lattice::commit`].
+    pub async fn commit(&self) -> CommitReport {
+        self.with(Lattice::commit).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: core::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn forwards_calls_in_order() {
+        block_on(async {
+            let engine = AsyncLattice::default();
+            let other = engine.clone();
+
+            assert_eq!(engine.add("hello world").await, Ok(0));
+            assert_eq!(other.add(String::from("hello there")).await, Ok(1));
+            engine.commit().await;
+
+            let results = other.search("hello", 10).await;
+            assert_eq!(results.len(), 2);
+            assert!(!engine.with(|e| e.has_uncommitted()).await);
+            assert_eq!(engine.with(|e| e.len()).await, 2);
+        });
+    }
+
+    #[test]
+    fn reports_add_errors() {
+        block_on(async {
+            let engine = AsyncLattice::new(Lattice::new());
+            assert!(engine.add("bad\u{0}doc").await.is_err());
+            assert!(engine.search("anything", 10).await.is_empty());
+        });
+    }
+
+    #[test]
+    fn concurrent_tasks_share_one_engine() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(4)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let engine = AsyncLattice::default();
+            let tasks: Vec<_> = (0..8)
+                .map(|t| {
+                    let engine = engine.clone();
+                    tokio::spawn(async move {
+                        for i in 0..25 {
+                            engine.add(format!("task {} doc {}", t, i)).await.unwrap();
+                        }
+                    })
+                })
+                .collect();
+            for task in tasks {
+                task.await.unwrap();
+            }
+
+            assert_eq!(engine.with(|e| e.len()).await, 200);
+            assert_eq!(engine.search("task 3 doc 24", 1).await.len(), 1);
+        });
+    }
+}
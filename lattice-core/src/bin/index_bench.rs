@@ -0,0 +1,207 @@
+//! End-to-end indexing and search benchmark.
+//!
+//! Ingests a corpus into a [`Lattice`], commits it and replays a query
+//! workload, reporting throughput, commit time, memory and query latency
+//! percentiles.
+//!
+//! ```text
+//! cargo run --release --bin index_bench -- <corpus> [queries] [limit]
+//! ```
+//!
+//! `corpus` holds one document per line. `queries` holds one query per
+//! line; without it, the first word of every hundredth document is used.
+//! `limit` is the number of results per query (default 10).
+
+use lattice_core::{CommitReport, LatencyHistogram, Lattice};
+use std::fs;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "usage: index_bench <corpus> [queries] [limit]";
+
+/// Times the workload is replayed, so short query files still yield
+/// stable percentiles
+const QUERY_ROUNDS: usize = 5;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(corpus_path) = args.first() else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let limit = match args.get(2).map(|s| s.parse::<usize>()) {
+        None => 10,
+        Some(Ok(limit)) => limit,
+        Some(Err(_)) => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let corpus = match fs::read_to_string(corpus_path) {
+        Ok(corpus) => corpus,
+        Err(e) => {
+            eprintln!("cannot read {}: {}", corpus_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let queries: Vec<String> = match args.get(1) {
+        Some(path) => match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(str::to_owned).collect(),
+            Err(e) => {
+                eprintln!("cannot read {}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => default_queries(&corpus),
+    };
+
+    let (mut engine, ingested) = ingest(&corpus);
+    let Ingested {
+        added,
+        rejected,
+        bytes,
+        ingest,
+        commit,
+        report,
+    } = ingested;
+
+    println!("== ingest ==");
+    println!("documents:   {} ({} rejected)", added, rejected);
+    println!(
+        "ingest:      {:.3?} ({:.0} docs/s, {:.1} MiB/s)",
+        ingest,
+        per_second(added as f64, ingest),
+        per_second(bytes as f64 / (1024.0 * 1024.0), ingest)
+    );
+    println!(
+        "commit:      {:.3?} (sort {:.3?}, merge {:.3?})",
+        commit, report.sort_time, report.merge_time
+    );
+    println!(
+        "total:       {:.0} docs/s",
+        per_second(added as f64, ingest + commit)
+    );
+    println!("index:       {}", engine.stats());
+    println!("memory:      {}", engine.memory_breakdown());
+
+    let (latency, hits) = run_queries(&mut engine, &queries, limit);
+
+    println!();
+    println!("== queries ==");
+    println!(
+        "queries:     {} x {} rounds, limit {}",
+        queries.len(),
+        QUERY_ROUNDS,
+        limit
+    );
+    if latency.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    println!(
+        "throughput:  {:.0} queries/s, {:.1} hits/query",
+        per_second(latency.len() as f64, latency.sum()),
+        hits as f64 / latency.len() as f64
+    );
+    println!(
+        "latency:     mean {:.1?}  p50 {:.1?}  p90 {:.1?}  p99 {:.1?}  max {:.1?}",
+        latency.mean(),
+        latency.quantile(0.5),
+        latency.quantile(0.9),
+        latency.quantile(0.99),
+        latency.max()
+    );
+    ExitCode::SUCCESS
+}
+
+/// Counts and timings of ingesting a corpus.
+struct Ingested {
+    added: usize,
+    rejected: usize,
+    bytes: usize,
+    ingest: Duration,
+    commit: Duration,
+    report: CommitReport,
+}
+
+/// The first word of every hundredth document.
+fn default_queries(corpus: &str) -> Vec<String> {
+    corpus
+        .lines()
+        .step_by(100)
+        .filter_map(|line| line.split_whitespace().next())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Adds every non-empty line of `corpus` to a new engine and commits it.
+fn ingest(corpus: &str) -> (Lattice, Ingested) {
+    let mut engine = Lattice::new();
+    let (mut added, mut rejected, mut bytes) = (0usize, 0usize, 0usize);
+    let started = Instant::now();
+    for line in corpus.lines().filter(|line| !line.is_empty()) {
+        match engine.add(line) {
+            Ok(_) => {
+                added += 1;
+                bytes += line.len();
+            }
+            Err(_) => rejected += 1,
+        }
+    }
+    let ingest = started.elapsed();
+    let report = engine.commit();
+    let commit = started.elapsed() - ingest;
+    let ingested = Ingested {
+        added,
+        rejected,
+        bytes,
+        ingest,
+        commit,
+        report,
+    };
+    (engine, ingested)
+}
+
+/// Replays `queries` [`QUERY_ROUNDS`] times, returning the latencies and
+/// the total number of hits.
+fn run_queries(
+    engine: &mut Lattice,
+    queries: &[String],
+    limit: usize,
+) -> (LatencyHistogram, usize) {
+    let mut latency = LatencyHistogram::new();
+    let mut hits = 0usize;
+    for _ in 0..QUERY_ROUNDS {
+        for query in queries {
+            let started = Instant::now();
+            hits += engine.search(query, limit).len();
+            latency.record(started.elapsed());
+        }
+    }
+    (latency, hits)
+}
+
+fn per_second(amount: f64, elapsed: Duration) -> f64 {
+    amount / elapsed.as_secs_f64().max(f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmarks_a_tiny_corpus() {
+        let corpus = "hello world\n\nhello there\nsearch engines\n";
+        let (mut engine, ingested) = ingest(corpus);
+        assert_eq!((ingested.added, ingested.rejected), (3, 0));
+        assert_eq!(ingested.bytes, 36);
+        assert_eq!(engine.len(), 3);
+        assert!(!engine.has_uncommitted());
+
+        let queries = default_queries(corpus);
+        assert_eq!(queries, ["hello"]);
+        let (latency, hits) = run_queries(&mut engine, &queries, 10);
+        assert_eq!(latency.len(), QUERY_ROUNDS as u64);
+        assert_eq!(hits, 2 * QUERY_ROUNDS);
+    }
+}
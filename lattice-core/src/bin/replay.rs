@@ -0,0 +1,99 @@
+//! Replays a query log against a saved index.
+//!
+//! ```text
+//! cargo run --release --bin replay -- <index> <queries> [concurrency] [limit] [min_overlap]
+//! ```
+//!
+//! `queries` holds one query per line. With `min_overlap`, the log is
+//! replayed twice: once with the index's saved configuration as the
+//! baseline and once with that overlap ratio, reporting the recall of the
+//! second against the first.
+
+use lattice_core::replay::{replay, ReplayOptions, ReplayReport};
+use lattice_core::Lattice;
+use lattice_types::SearchConfig;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: replay <index> <queries> [concurrency] [limit] [min_overlap]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (Some(index_path), Some(queries_path)) = (args.first(), args.get(1)) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+    let (Ok(concurrency), Ok(limit), Ok(min_overlap)) = (
+        args.get(2).map_or(Ok(1), |s| s.parse::<usize>()),
+        args.get(3).map_or(Ok(10), |s| s.parse::<usize>()),
+        args.get(4).map(|s| s.parse::<f32>()).transpose(),
+    ) else {
+        eprintln!("{}", USAGE);
+        return ExitCode::FAILURE;
+    };
+
+    let mut engine = match File::open(index_path)
+        .map_err(|e| e.to_string())
+        .and_then(|file| Lattice::load(BufReader::new(file)).map_err(|e| e.to_string()))
+    {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("cannot load {}: {}", index_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let log = match fs::read_to_string(queries_path) {
+        Ok(log) => log,
+        Err(e) => {
+            eprintln!("cannot read {}: {}", queries_path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let queries: Vec<&str> = log.lines().filter(|line| !line.is_empty()).collect();
+
+    let options = ReplayOptions::new().concurrency(concurrency).limit(limit);
+    println!(
+        "index: {}; {} queries, {} threads, limit {}",
+        engine.stats(),
+        queries.len(),
+        concurrency,
+        limit
+    );
+
+    let baseline = replay(&engine, &queries, options);
+    print_report("baseline", &baseline);
+
+    if let Some(min_overlap) = min_overlap {
+        let config = SearchConfig {
+            min_overlap_ratio: min_overlap,
+            ..engine.search_config()
+        };
+        if let Err(e) = config.validate() {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+        engine.set_search_config(config);
+        let candidate = replay(&engine, &queries, options);
+        print_report(&format!("min_overlap={}", min_overlap), &candidate);
+        println!(
+            "recall vs baseline: {:.4}",
+            candidate.recall_against(&baseline)
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_report(label: &str, report: &ReplayReport) {
+    let latency = &report.latency;
+    println!(
+        "{}: {:.0} queries/s; latency mean {:.1?} p50 {:.1?} p90 {:.1?} p99 {:.1?} max {:.1?}",
+        label,
+        report.queries_per_second(),
+        latency.mean(),
+        latency.quantile(0.5),
+        latency.quantile(0.9),
+        latency.quantile(0.99),
+        latency.max()
+    );
+}
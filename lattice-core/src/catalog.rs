@@ -0,0 +1,287 @@
+//! Named indexes and aliases.
+//!
+//! An [`IndexCatalog`] holds several [`Lattice`] indexes by name, plus
+//! aliases pointing at them, in the manner of Elasticsearch aliases.
+//! Applications query an alias such as `"products"`; reindexing builds a
+//! new index next to the live one and repoints the alias in a single
+//! [`set_alias`](IndexCatalog::set_alias) call, so every lookup sees
+//! either the old index or the new one, never a half-built state
+//! (blue/green reindexing):
+//!
+//! ```text
+//! "products" ──▶ products-v1        "products" ──┐   products-v1
+//!                products-v2 (build)   ⇒         └─▶ products-v2
+//! ```
+//!
+//! The catalog is a plain value: share it between threads behind a lock,
+//! and a swap made under the write lock is atomic for all readers.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::catalog::IndexCatalog;
+//! use lattice_core::Lattice;
+//!
+//! let mut catalog = IndexCatalog::new();
+//! let mut v1 = Lattice::new();
+//! v1.add("old product").unwrap();
+//! catalog.create("products-v1", v1).unwrap();
+//! catalog.set_alias("products", "products-v1").unwrap();
+//!
+//! // Build the replacement off to the side, then swap it in
+//! let mut v2 = Lattice::new();
+//! v2.add("new product").unwrap();
+//! catalog.create("products-v2", v2).unwrap();
+//! let previous = catalog.set_alias("products", "products-v2").unwrap();
+//! assert_eq!(previous.as_deref(), Some("products-v1"));
+//!
+//! let live = catalog.get_mut("products").unwrap();
+//! let best = live.search("product", 1)[0].doc_id;
+//! assert_eq!(live.get(best), Some("new product"));
+//! catalog.remove("products-v1").unwrap();
+//! ```
+
+use crate::index::Lattice;
+use core::fmt;
+use std::collections::BTreeMap;
+
+/// Errors returned by [`IndexCatalog`] operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatalogError {
+    /// No index or alias has this name.
+    UnknownIndex {
+        /// The name looked up.
+        name: String,
+    },
+    /// The name is already taken by an index or an alias.
+    NameTaken {
+        /// The name requested.
+        name: String,
+    },
+    /// The index cannot be removed while an alias points at it.
+    IndexAliased {
+        /// The index to remove.
+        index: String,
+        /// An alias pointing at it.
+        alias: String,
+    },
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::UnknownIndex { name } => write!(f, "no index named {:?}", name),
+            CatalogError::NameTaken { name } => write!(f, "name {:?} is already in use", name),
+            CatalogError::IndexAliased { index, alias } => {
+                write!(
+                    f,
+                    "index {:?} is still the target of alias {:?}",
+                    index, alias
+                )
+            }
+        }
+    }
+}
+
+impl core::error::Error for CatalogError {}
+
+/// Named indexes with aliases that can be repointed atomically.
+///
+/// Index and alias names share one namespace. Aliases point at indexes,
+/// never at other aliases. Every method taking a name accepts either.
+#[derive(Default)]
+pub struct IndexCatalog {
+    indexes: BTreeMap<String, Lattice>,
+    aliases: BTreeMap<String, String>,
+}
+
+impl IndexCatalog {
+    /// Creates an empty catalog.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `index` under `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CatalogError::NameTaken`] if an index or alias already
+    /// has that name.
+    pub fn create(&mut self, name: &str, index: Lattice) -> Result<(), CatalogError> {
+        self.check_free(name)?;
+        self.indexes.insert(name.to_owned(), index);
+        Ok(())
+    }
+
+    /// Removes and returns the index called `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CatalogError::UnknownIndex`] if there is no such index
+    /// (aliases are not followed), or [`CatalogError::IndexAliased`] if an
+    /// alias still points at it.
+    pub fn remove(&mut self, name: &str) -> Result<Lattice, CatalogError> {
+        if let Some((alias, _)) = self.aliases.iter().find(|(_, target)| *target == name) {
+            return Err(CatalogError::IndexAliased {
+                index: name.to_owned(),
+                alias: alias.clone(),
+            });
+        }
+        self.indexes.remove(name).ok_or_else(|| unknown(name))
+    }
+
+    /// Points `alias` at the index called `index`, creating the alias if
+    /// needed, and returns the index it pointed at before.
+    ///
+    /// The swap is a single map update, so no lookup through the alias can
+    /// observe an intermediate state.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CatalogError::UnknownIndex`] if `index` is not an index
+    /// (aliases of aliases are not allowed), or [`CatalogError::NameTaken`]
+    /// if `alias` names an index.
+    pub fn set_alias(&mut self, alias: &str, index: &str) -> Result<Option<String>, CatalogError> {
+        if !self.indexes.contains_key(index) {
+            return Err(unknown(index));
+        }
+        if self.indexes.contains_key(alias) {
+            return Err(CatalogError::NameTaken {
+                name: alias.to_owned(),
+            });
+        }
+        Ok(self.aliases.insert(alias.to_owned(), index.to_owned()))
+    }
+
+    /// Removes `alias`, returning the index it pointed at.
+    pub fn remove_alias(&mut self, alias: &str) -> Option<String> {
+        self.aliases.remove(alias)
+    }
+
+    /// Returns the index name `name` refers to: the alias target, or
+    /// `name` itself if it is an index.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        match self.aliases.get(name) {
+            Some(target) => Some(target),
+            None => self.indexes.contains_key(name).then_some(name),
+        }
+    }
+
+    /// Returns the index `name` (an index or alias) refers to.
+    pub fn get(&self, name: &str) -> Option<&Lattice> {
+        let index = self.resolve(name)?;
+        self.indexes.get(index)
+    }
+
+    /// Returns the index `name` (an index or alias) refers to, for
+    /// searching or adding documents.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Lattice> {
+        let index = match self.aliases.get(name) {
+            Some(target) => target.as_str(),
+            None => name,
+        };
+        self.indexes.get_mut(index)
+    }
+
+    /// Iterates over the index names, in order.
+    pub fn indexes(&self) -> impl Iterator<Item = &str> + '_ {
+        self.indexes.keys().map(String::as_str)
+    }
+
+    /// Iterates over (alias, index) pairs, in alias order.
+    pub fn aliases(&self) -> impl Iterator<Item = (&str, &str)> + '_ {
+        self.aliases
+            .iter()
+            .map(|(alias, index)| (alias.as_str(), index.as_str()))
+    }
+
+    /// Returns the number of indexes.
+    pub fn len(&self) -> usize {
+        self.indexes.len()
+    }
+
+    /// Returns true if the catalog holds no indexes.
+    pub fn is_empty(&self) -> bool {
+        self.indexes.is_empty()
+    }
+
+    fn check_free(&self, name: &str) -> Result<(), CatalogError> {
+        if self.indexes.contains_key(name) || self.aliases.contains_key(name) {
+            return Err(CatalogError::NameTaken {
+                name: name.to_owned(),
+            });
+        }
+        Ok(())
+    }
+}
+
+fn unknown(name: &str) -> CatalogError {
+    CatalogError::UnknownIndex {
+        name: name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aliases_swap_between_builds() {
+        let mut catalog = IndexCatalog::new();
+        let mut blue = Lattice::new();
+        blue.add("blue build").unwrap();
+        catalog.create("blue", blue).unwrap();
+        catalog.create("green", Lattice::new()).unwrap();
+
+        assert_eq!(catalog.set_alias("live", "blue"), Ok(None));
+        assert_eq!(catalog.resolve("live"), Some("blue"));
+        assert_eq!(catalog.get("live").unwrap().len(), 1);
+
+        catalog
+            .get_mut("green")
+            .unwrap()
+            .add("green build")
+            .unwrap();
+        catalog
+            .get_mut("green")
+            .unwrap()
+            .add("green again")
+            .unwrap();
+        assert_eq!(
+            catalog.set_alias("live", "green"),
+            Ok(Some("blue".to_owned()))
+        );
+        assert_eq!(catalog.get_mut("live").unwrap().search("green", 5).len(), 2);
+
+        // Names are shared between indexes and aliases
+        assert!(matches!(
+            catalog.create("live", Lattice::new()),
+            Err(CatalogError::NameTaken { .. })
+        ));
+        assert!(matches!(
+            catalog.set_alias("blue", "green"),
+            Err(CatalogError::NameTaken { .. })
+        ));
+        assert!(matches!(
+            catalog.set_alias("other", "live"),
+            Err(CatalogError::UnknownIndex { .. })
+        ));
+
+        let err = catalog.remove("green").err().unwrap();
+        assert_eq!(
+            err,
+            CatalogError::IndexAliased {
+                index: "green".to_owned(),
+                alias: "live".to_owned()
+            }
+        );
+        assert!(err.to_string().contains("alias"));
+        assert_eq!(catalog.remove("blue").unwrap().len(), 1);
+        assert_eq!(catalog.indexes().collect::<Vec<_>>(), ["green"]);
+        assert_eq!(catalog.aliases().collect::<Vec<_>>(), [("live", "green")]);
+
+        assert_eq!(catalog.remove_alias("live"), Some("green".to_owned()));
+        assert!(catalog.get("live").is_none());
+        assert_eq!(catalog.len(), 1);
+    }
+}
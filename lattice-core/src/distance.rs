@@ -0,0 +1,101 @@
+//! String distance functions.
+//!
+//! Used for spelling correction and fuzzy reranking. All distances operate
+//! on Unicode scalar values, so `"café"` and `"cafe"` differ by one edit.
+
+/// Computes the Levenshtein edit distance between two strings.
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::distance::levenshtein;
+///
+/// assert_eq!(levenshtein("kitten", "sitting"), 3);
+/// assert_eq!(levenshtein("", "abc"), 3);
+/// ```
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    levenshtein_bounded(a, b, usize::MAX).unwrap_or(usize::MAX)
+}
+
+/// Computes the Levenshtein distance, giving up once it exceeds `max`.
+///
+/// Returns `None` if the distance is greater than `max`. Bails out early
+/// when the length difference alone exceeds the bound, or when every cell
+/// of a DP row does.
+pub fn levenshtein_bounded(a: &str, b: &str, max: usize) -> Option<usize> {
+    if a == b {
+        return Some(0);
+    }
+
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    if a.is_empty() || b.is_empty() {
+        return Some(a.len().max(b.len()));
+    }
+
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut curr = vec![0usize; b.len() + 1];
+
+    for (i, &ca) in a.iter().enumerate() {
+        curr[0] = i + 1;
+        let mut row_min = curr[0];
+
+        for (j, &cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            curr[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(curr[j] + 1);
+            row_min = row_min.min(curr[j + 1]);
+        }
+
+        if row_min > max {
+            return None;
+        }
+        core::mem::swap(&mut prev, &mut curr);
+    }
+
+    let distance = prev[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identical_and_empty() {
+        assert_eq!(levenshtein("hello", "hello"), 0);
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn single_edits() {
+        assert_eq!(levenshtein("hello", "helo"), 1); // deletion
+        assert_eq!(levenshtein("helo", "hello"), 1); // insertion
+        assert_eq!(levenshtein("hello", "hallo"), 1); // substitution
+    }
+
+    #[test]
+    fn classic_examples() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("flaw", "lawn"), 2);
+    }
+
+    #[test]
+    fn counts_chars_not_bytes() {
+        assert_eq!(levenshtein("café", "cafe"), 1);
+        assert_eq!(levenshtein("日本語", "日本"), 1);
+    }
+
+    #[test]
+    fn bounded_gives_up() {
+        assert_eq!(levenshtein_bounded("kitten", "sitting", 3), Some(3));
+        assert_eq!(levenshtein_bounded("kitten", "sitting", 2), None);
+        assert_eq!(levenshtein_bounded("a", "abcdef", 2), None);
+        assert_eq!(levenshtein_bounded("same", "same", 0), Some(0));
+    }
+}
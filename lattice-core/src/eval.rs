@@ -0,0 +1,239 @@
+//! Relevance evaluation against a golden set.
+//!
+//! A golden set pairs queries with the documents a good engine should
+//! return for them. [`evaluate`] runs every query and averages the usual
+//! binary-relevance measures over the set, so the effect of a config change
+//! on result quality can be measured rather than eyeballed:
+//!
+//! | Measure        | Question answered                                         |
+//! |----------------|-----------------------------------------------------------|
+//! | precision@k    | What share of the top k results is relevant?              |
+//! | recall@k       | What share of the relevant documents is in the top k?     |
+//! | MRR            | How high does the first relevant result rank?             |
+//! | NDCG@k         | How close is the top-k order to the ideal one?            |
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::eval::{evaluate, GoldenQuery};
+//! use lattice_core::Lattice;
+//!
+//! let mut engine = Lattice::new();
+//! engine.add("rust programming language").unwrap();
+//! engine.add("python programming language").unwrap();
+//! engine.add("rusty old bicycle").unwrap();
+//!
+//! let golden = [
+//!     GoldenQuery::new("rust language", [0]),
+//!     GoldenQuery::new("python", [1]),
+//! ];
+//! let report = evaluate(&mut engine, &golden, 3);
+//! assert_eq!(report.mrr, 1.0);
+//! assert_eq!(report.recall_at_k, 1.0);
+//! ```
+
+use crate::index::Lattice;
+use core::fmt;
+use lattice_types::{DocId, SearchResult};
+
+/// A query with the documents relevant to it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GoldenQuery {
+    /// Query text.
+    pub query: String,
+    /// Documents a perfect engine would return, in any order.
+    pub relevant: Vec<DocId>,
+}
+
+impl GoldenQuery {
+    /// Creates a golden query.
+    pub fn new(query: impl Into<String>, relevant: impl IntoIterator<Item = DocId>) -> Self {
+        let mut relevant: Vec<DocId> = relevant.into_iter().collect();
+        relevant.sort_unstable();
+        relevant.dedup();
+        Self {
+            query: query.into(),
+            relevant,
+        }
+    }
+}
+
+/// Measures for one query at cutoff k.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueryEval {
+    /// Relevant results in the top k, divided by k.
+    pub precision: f64,
+    /// Relevant results in the top k, divided by the number of relevant
+    /// documents.
+    pub recall: f64,
+    /// 1 / rank of the first relevant result in the top k, 0 if none.
+    pub reciprocal_rank: f64,
+    /// Discounted cumulative gain of the top k over that of the ideal
+    /// ranking.
+    pub ndcg: f64,
+}
+
+impl QueryEval {
+    /// Scores `results` (best first) against the sorted `relevant` set.
+    pub fn compute(results: &[SearchResult], relevant: &[DocId], k: usize) -> Self {
+        if k == 0 || relevant.is_empty() {
+            return Self::default();
+        }
+
+        let mut hits = 0usize;
+        let mut first_hit = None;
+        let mut dcg = 0.0;
+        for (rank, result) in results.iter().take(k).enumerate() {
+            if relevant.binary_search(&result.doc_id).is_ok() {
+                hits += 1;
+                first_hit.get_or_insert(rank);
+                dcg += 1.0 / (rank as f64 + 2.0).log2();
+            }
+        }
+        let ideal: f64 = (0..relevant.len().min(k))
+            .map(|rank| 1.0 / (rank as f64 + 2.0).log2())
+            .sum();
+
+        Self {
+            precision: hits as f64 / k as f64,
+            recall: hits as f64 / relevant.len() as f64,
+            reciprocal_rank: first_hit.map_or(0.0, |rank| 1.0 / (rank as f64 + 1.0)),
+            ndcg: dcg / ideal,
+        }
+    }
+}
+
+/// Measures averaged over a golden set.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EvalReport {
+    /// Cutoff the measures were taken at.
+    pub k: usize,
+    /// Mean precision@k.
+    pub precision_at_k: f64,
+    /// Mean recall@k.
+    pub recall_at_k: f64,
+    /// Mean reciprocal rank.
+    pub mrr: f64,
+    /// Mean NDCG@k.
+    pub ndcg_at_k: f64,
+    /// Measures of each evaluated query, in golden-set order.
+    pub per_query: Vec<QueryEval>,
+    /// Golden queries skipped for having no relevant documents.
+    pub skipped: usize,
+}
+
+impl fmt::Display for EvalReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} queries: P@{k}={:.3} R@{k}={:.3} MRR={:.3} NDCG@{k}={:.3}",
+            self.per_query.len(),
+            self.precision_at_k,
+            self.recall_at_k,
+            self.mrr,
+            self.ndcg_at_k,
+            k = self.k
+        )
+    }
+}
+
+/// Evaluates [`Lattice::search`] on `golden` at cutoff `k`.
+pub fn evaluate(engine: &mut Lattice, golden: &[GoldenQuery], k: usize) -> EvalReport {
+    evaluate_with(golden, k, |query, k| engine.search(query, k))
+}
+
+/// Evaluates an arbitrary search function on `golden` at cutoff `k`.
+///
+/// `search` receives each query and `k` and returns results best first;
+/// use it to evaluate a [`SearchRequest`](crate::SearchRequest), a custom
+/// scorer or a [`SearchPipeline`](crate::pipeline::SearchPipeline).
+/// Queries without relevant documents are skipped.
+pub fn evaluate_with<F>(golden: &[GoldenQuery], k: usize, mut search: F) -> EvalReport
+where
+    F: FnMut(&str, usize) -> Vec<SearchResult>,
+{
+    let mut report = EvalReport {
+        k,
+        ..EvalReport::default()
+    };
+    for entry in golden {
+        if entry.relevant.is_empty() {
+            report.skipped += 1;
+            continue;
+        }
+        let results = search(&entry.query, k);
+        report
+            .per_query
+            .push(QueryEval::compute(&results, &entry.relevant, k));
+    }
+
+    let n = report.per_query.len();
+    if n > 0 {
+        let mean = |measure: fn(&QueryEval) -> f64| {
+            report.per_query.iter().map(measure).sum::<f64>() / n as f64
+        };
+        report.precision_at_k = mean(|q| q.precision);
+        report.recall_at_k = mean(|q| q.recall);
+        report.mrr = mean(|q| q.reciprocal_rank);
+        report.ndcg_at_k = mean(|q| q.ndcg);
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(ids: &[DocId]) -> Vec<SearchResult> {
+        ids.iter()
+            .enumerate()
+            .map(|(i, &id)| SearchResult::new(id, 10.0 - i as f32))
+            .collect()
+    }
+
+    #[test]
+    fn computes_textbook_values() {
+        // Relevant documents at ranks 2 and 3 of 4; a third is missed
+        let eval = QueryEval::compute(&results(&[9, 1, 2, 8]), &[1, 2, 3], 4);
+        assert_eq!(eval.precision, 0.5);
+        assert!((eval.recall - 2.0 / 3.0).abs() < 1e-12);
+        assert_eq!(eval.reciprocal_rank, 0.5);
+        let dcg = 1.0 / 3f64.log2() + 1.0 / 4f64.log2();
+        let ideal = 1.0 + 1.0 / 3f64.log2() + 1.0 / 4f64.log2();
+        assert!((eval.ndcg - dcg / ideal).abs() < 1e-12);
+
+        let perfect = QueryEval::compute(&results(&[1, 2]), &[1, 2], 5);
+        assert_eq!(perfect.ndcg, 1.0);
+        assert_eq!(perfect.precision, 0.4);
+        assert_eq!(
+            QueryEval::compute(&results(&[7]), &[1], 5).reciprocal_rank,
+            0.0
+        );
+    }
+
+    #[test]
+    fn averages_over_golden_set() {
+        let mut engine = Lattice::new();
+        for doc in ["apple pie", "apple tart", "cherry pie", "banana split"] {
+            engine.add(doc).unwrap();
+        }
+        let golden = [
+            GoldenQuery::new("apple", [1, 0, 1]),
+            GoldenQuery::new("banana", [3]),
+            GoldenQuery::new("durian", []),
+        ];
+        assert_eq!(golden[0].relevant, vec![0, 1]);
+
+        let report = evaluate(&mut engine, &golden, 2);
+        assert_eq!(report.per_query.len(), 2);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.recall_at_k, 1.0);
+        assert_eq!(report.mrr, 1.0);
+        assert_eq!(report.precision_at_k, 0.75);
+        assert!(report.to_string().starts_with("2 queries: P@2=0.750"));
+
+        let nothing = evaluate_with(&golden, 2, |_, _| Vec::new());
+        assert_eq!(nothing.mrr, 0.0);
+        assert_eq!(nothing.ndcg_at_k, 0.0);
+    }
+}
@@ -0,0 +1,102 @@
+//! Combining result lists into one ranking.
+//!
+//! Scores from different queries or engines (trigram matches, an external
+//! vector search, a second index) live on different scales and cannot be
+//! added up. [Reciprocal rank fusion][rrf] ignores the scores and uses
+//! only each document's rank in every list:
+//!
+//! ```text
+//! rrf(d) = Σ over lists containing d of 1 / (k + rank of d)
+//! ```
+//!
+//! with ranks counted from 1. `k` damps the weight of the very top ranks;
+//! [`DEFAULT_K`] is the value from the original paper.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::fusion::{rrf, DEFAULT_K};
+//! use lattice_core::Lattice;
+//! use lattice_types::SearchResult;
+//!
+//! let mut engine = Lattice::new();
+//! engine.add("rust web framework").unwrap();
+//! engine.add("rust game engine").unwrap();
+//! engine.add("python web framework").unwrap();
+//!
+//! let text = engine.search("rust web", 10);
+//! // Results of some other engine, best first
+//! let vectors = vec![SearchResult::new(2, 0.91), SearchResult::new(0, 0.88)];
+//!
+//! let fused = rrf(&[text, vectors], DEFAULT_K);
+//! assert_eq!(fused[0].doc_id, 0);
+//! ```
+//!
+//! [rrf]: https://plg.uwaterloo.ca/~gvcormac/cormacksigir09-rrf.pdf
+
+use lattice_types::{DocId, SearchResult};
+use rustc_hash::FxHashMap;
+
+/// The customary `k` for [`rrf`].
+pub const DEFAULT_K: f32 = 60.0;
+
+/// Merges result lists, each ordered best first, into one ranking by
+/// reciprocal rank fusion.
+///
+/// Each document's score is the sum of `1 / (k + rank)` over the lists
+/// it appears in, counting only its first appearance in each list. The
+/// fused list holds every document of every list, in
+/// [`SearchResult`] order: by descending score, ties by ascending doc ID.
+/// A negative or NaN `k` is treated as 0.
+pub fn rrf(lists: &[Vec<SearchResult>], k: f32) -> Vec<SearchResult> {
+    let k = k.max(0.0);
+    let mut scores: FxHashMap<DocId, f32> = FxHashMap::default();
+    let mut seen: Vec<DocId> = Vec::new();
+    for list in lists {
+        seen.clear();
+        for (rank, result) in list.iter().enumerate() {
+            if seen.contains(&result.doc_id) {
+                continue;
+            }
+            seen.push(result.doc_id);
+            *scores.entry(result.doc_id).or_insert(0.0) += 1.0 / (k + rank as f32 + 1.0);
+        }
+    }
+
+    let mut fused: Vec<SearchResult> = scores
+        .into_iter()
+        .map(|(doc_id, score)| SearchResult::new(doc_id, score))
+        .collect();
+    fused.sort_unstable_by(|a, b| b.score.total_cmp(&a.score).then(a.doc_id.cmp(&b.doc_id)));
+    fused
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(ids: &[DocId]) -> Vec<SearchResult> {
+        ids.iter()
+            .enumerate()
+            .map(|(i, &id)| SearchResult::new(id, 100.0 - i as f32))
+            .collect()
+    }
+
+    #[test]
+    fn sums_reciprocal_ranks() {
+        let fused = rrf(&[list(&[1, 2, 3]), list(&[3, 1]), list(&[])], 0.0);
+        let ids: Vec<DocId> = fused.iter().map(|r| r.doc_id).collect();
+        assert_eq!(ids, [1, 3, 2]);
+        assert_eq!(fused[0].score, 1.0 + 0.5);
+        assert_eq!(fused[1].score, 1.0 / 3.0 + 1.0);
+        assert_eq!(fused[2].score, 0.5);
+
+        // Equal fused scores fall back to ascending doc IDs; repeats within
+        // a list count once
+        let tied = rrf(&[list(&[7, 4, 7]), list(&[4, 7])], DEFAULT_K);
+        assert_eq!(tied[0].doc_id, 4);
+        assert_eq!(tied[0].score, tied[1].score);
+        assert_eq!(tied.len(), 2);
+        assert!(rrf(&[], DEFAULT_K).is_empty());
+    }
+}
@@ -1,11 +1,27 @@
 //! Public API for adding and retrieving documents.
 
-use crate::analyzer::trigram::extract_trigrams;
+use crate::analyzer::tokenizer::{Field, TokenStats, Tokenizer, TokenizerConfig};
+use crate::analyzer::trigram::extract_terms;
+use crate::arena::ArenaError;
+use crate::index::changefeed::IndexEvent;
+use crate::index::metrics::Metrics;
+use crate::index::stats::IndexStats;
 use crate::index::types::{Lattice, TempTrigramEntry, MAX_DOCUMENT_LENGTH};
-use lattice_types::{DocId, DocumentError};
+use lattice_types::{DocHandle, DocId, DocumentError, SearchEngine, SearchResult};
+use smallvec::SmallVec;
+use std::borrow::Cow;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Returns the current time in milliseconds since the Unix epoch (0 if the
+/// clock is set before it).
+pub(crate) fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
 
 /// Checks if input contains invalid control characters (other than whitespace).
-fn contains_invalid_controls(input: &str) -> bool {
+pub(crate) fn contains_invalid_controls(input: &str) -> bool {
     input
         .bytes()
         .any(|b| matches!(b, 0x00..=0x08 | 0x0B | 0x0C | 0x0E..=0x1F | 0x7F))
@@ -18,8 +34,94 @@ impl Lattice {
     ///
     /// Returns `DocumentError::TooLarge` if the document exceeds 64KB.
     /// Returns `DocumentError::InvalidInput` if the document contains control characters.
+    /// Returns `DocumentError::CapacityExceeded` if document storage or the
+    /// `DocId` space is exhausted.
     #[inline(never)]
     pub fn add(&mut self, content: &str) -> Result<DocId, DocumentError> {
+        self.add_parts(&[(Field::Body, content)], false)
+    }
+
+    /// Adds a document, taking ownership of its text.
+    ///
+    /// Behaves like [`add`](Self::add), but the buffer is reused instead
+    /// of copied: it becomes the stored display text when
+    /// [`IndexConfig::store_display_text`](crate::IndexConfig::store_display_text)
+    /// is set, and otherwise takes the place of the normalization buffer
+    /// whose contents are moved into the arena. That move avoids a copy
+    /// only when the arena adopts the buffer as a page of its own, which
+    /// takes a document of at least 4KB with little spare capacity (see
+    /// [`Arena::push_owned`](crate::arena::Arena::push_owned)); smaller
+    /// documents are copied as by [`add`](Self::add).
+    ///
+    /// # Errors
+    ///
+    /// As for [`add`](Self::add).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// let body = "log line ".repeat(1_000);
+    /// let doc_id = engine.add_owned(body).unwrap();
+    /// assert!(engine.get(doc_id).unwrap().starts_with("log line log line"));
+    /// ```
+    pub fn add_owned(&mut self, content: String) -> Result<DocId, DocumentError> {
+        self.add_document(&[], false, Some(content))
+    }
+
+    /// Adds a document from UTF-8 bytes, taking ownership of the buffer.
+    /// See [`add_owned`](Self::add_owned).
+    ///
+    /// # Errors
+    ///
+    /// Returns `DocumentError::InvalidInput` if `content` is not valid
+    /// UTF-8, and otherwise as for [`add`](Self::add).
+    pub fn add_bytes(&mut self, content: Vec<u8>) -> Result<DocId, DocumentError> {
+        let content = String::from_utf8(content).map_err(|_| DocumentError::InvalidInput {
+            reason: "document is not valid UTF-8",
+        })?;
+        self.add_owned(content)
+    }
+
+    /// Adds a document made of `parts`, recording their layout if
+    /// `with_fields` is set.
+    pub(crate) fn add_parts(
+        &mut self,
+        parts: &[(Field, &str)],
+        with_fields: bool,
+    ) -> Result<DocId, DocumentError> {
+        self.add_document(parts, with_fields, None)
+    }
+
+    /// Adds a document made of `parts`, or of the single body `owned` if
+    /// given, whose buffer is then reused for storage.
+    fn add_document(
+        &mut self,
+        parts: &[(Field, &str)],
+        with_fields: bool,
+        owned: Option<String>,
+    ) -> Result<DocId, DocumentError> {
+        let body: [(Field, &str); 1];
+        let parts = match &owned {
+            Some(text) => {
+                body = [(Field::Body, text.as_str())];
+                &body[..]
+            }
+            None => parts,
+        };
+        let content: Cow<'_, str> = match parts {
+            [(_, content)] => Cow::Borrowed(content),
+            _ => Cow::Owned(
+                parts
+                    .iter()
+                    .map(|(_, text)| *text)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+        };
+
         // Validate document length before processing
         if content.len() > MAX_DOCUMENT_LENGTH {
             return Err(DocumentError::TooLarge {
@@ -29,37 +131,169 @@ impl Lattice {
         }
 
         // Check for control characters (null bytes, bells, etc.)
-        if contains_invalid_controls(content) {
+        if contains_invalid_controls(&content) {
             return Err(DocumentError::InvalidInput {
                 reason: "control characters (0x00-0x1F excluding whitespace) are not allowed",
             });
         }
 
+        #[cfg(feature = "language-detection")]
+        let (normalizer, language) = self.document_normalizer(&content);
+        #[cfg(not(feature = "language-detection"))]
+        let normalizer = self.normalizer;
+
         self.norm_buf.clear();
-        self.normalizer.normalize_into(content, &mut self.norm_buf);
+        let mut layout: SmallVec<[(Field, usize, usize); 4]> = SmallVec::new();
+        let mut tokens = TokenStats::default();
+        if let [(field, text)] = parts {
+            let normalizer = self.field_normalizers[*field as usize].unwrap_or(normalizer);
+            normalizer.normalize_into(text, &mut self.norm_buf);
+            tokens = Self::apply_tokenizer(
+                self.tokenizer,
+                &mut self.norm_buf,
+                &mut self.scratch.token_buf,
+            );
+            layout.push((*field, 0, self.norm_buf.len()));
+        } else {
+            let (mut part, mut scratch) = (String::new(), String::new());
+            for &(field, text) in parts {
+                part.clear();
+                let normalizer = self.field_normalizers[field as usize].unwrap_or(normalizer);
+                normalizer.normalize_into(text, &mut part);
+                let stats = Self::apply_tokenizer(self.tokenizer, &mut part, &mut scratch);
+                tokens.emitted += stats.emitted;
+                tokens.skipped += stats.skipped;
+                tokens.truncated += stats.truncated;
+                if !self.norm_buf.is_empty() && !part.is_empty() {
+                    self.norm_buf.push(' ');
+                }
+                let start = self.norm_buf.len();
+                self.norm_buf.push_str(&part);
+                layout.push((field, start, self.norm_buf.len()));
+            }
+        }
 
         let doc_len = self.norm_buf.len() as u32;
         let doc_id = self
             .documents
-            .push(&self.norm_buf)
-            .ok_or(DocumentError::TooLarge {
-                size: self.norm_buf.len(),
-                max_size: MAX_DOCUMENT_LENGTH,
+            .check(self.norm_buf.len())
+            .map_err(|e| match e {
+                ArenaError::TooLarge { len } => DocumentError::TooLarge {
+                    size: len,
+                    max_size: MAX_DOCUMENT_LENGTH,
+                },
+                ArenaError::CapacityExceeded => DocumentError::CapacityExceeded,
             })?;
+        let spare = if self.index_config.store_display_text {
+            // `content` is within the span limit (checked above), and the
+            // DocId space was checked for `documents`
+            match owned {
+                Some(text) => self.display.push_owned(text),
+                None => self.display.push(&content),
+            }
+            .map_err(|_| DocumentError::CapacityExceeded)?;
+            None
+        } else {
+            owned
+        };
+        if with_fields || !self.fields.is_empty() {
+            // Offsets fit: the stored text is within the span limit
+            let spans: SmallVec<[(Field, u16, u16); 4]> = layout
+                .iter()
+                .filter(|_| with_fields)
+                .map(|&(field, start, end)| (field, start as u16, end as u16))
+                .collect();
+            self.fields.push(doc_id as usize, &spans);
+        }
         self.doc_lengths.push(doc_len);
-        self.documents_added += 1;
+        #[cfg(feature = "language-detection")]
+        self.record_language(doc_id, language);
+        Metrics::add(&self.counters.documents_indexed, 1);
+        Metrics::add(&self.counters.tokens_skipped, tokens.skipped as u64);
+        Metrics::add(&self.counters.tokens_truncated, tokens.truncated as u64);
+        self.index_tokens();
 
+        let mut distinct = 0u32;
         if self.norm_buf.len() >= 3 {
-            extract_trigrams(&self.norm_buf, |trigram| {
+            let start = self.temp_trigrams.len();
+            extract_terms(&self.norm_buf, self.tokenizer.cjk_bigrams, |trigram| {
                 self.temp_trigrams
                     .push(TempTrigramEntry { trigram, doc_id });
             });
+            distinct = Self::dedup_doc_trigrams(&mut self.temp_trigrams, start);
             self.needs_rebuild = true;
         }
+        self.doc_trigram_counts.push(distinct);
+        if self.index_config.store_positions {
+            self.positions
+                .push_document(&self.norm_buf, self.tokenizer.cjk_bigrams);
+        }
+        if self.index_config.store_term_frequencies {
+            self.frequencies
+                .push_document(&self.norm_buf, self.tokenizer.cjk_bigrams);
+        }
+        if !self.boosts.is_empty() {
+            self.boosts.push(1.0);
+        }
+        if self.index_config.store_indexed_at {
+            self.indexed_at.push(now_millis());
+        }
+
+        // Stored last so that an owned buffer can replace `norm_buf`
+        let stored = match spare {
+            Some(mut buffer) => {
+                buffer.clear();
+                let text = core::mem::replace(&mut self.norm_buf, buffer);
+                self.documents.push_owned(text)
+            }
+            None => self.documents.push(&self.norm_buf),
+        };
+        debug_assert_eq!(stored, Ok(doc_id));
+        Metrics::set(&self.counters.current_doc_count, self.len() as u64);
+        self.publish(IndexEvent::Added(doc_id));
+        self.report_stats_if_due();
 
         Ok(doc_id)
     }
 
+    /// Re-tokenizes normalized `text` in place with `config`, using `scratch`
+    /// as the output buffer. The default tokenizer leaves the text as it is
+    /// and reports zero counts, whether or not it sets `cjk_bigrams`, which
+    /// applies to trigram extraction only.
+    pub(crate) fn apply_tokenizer(
+        config: TokenizerConfig,
+        text: &mut String,
+        scratch: &mut String,
+    ) -> TokenStats {
+        let splits = TokenizerConfig {
+            cjk_bigrams: false,
+            ..config
+        };
+        if splits == TokenizerConfig::new() {
+            return TokenStats::default();
+        }
+        let stats = Tokenizer::with_config(Field::Body, config).rewrite_into(text, scratch);
+        core::mem::swap(text, scratch);
+        stats
+    }
+
+    /// Sorts and deduplicates the trigrams of the most recently added document,
+    /// which occupy `entries[start..]`. Returns the number of distinct trigrams.
+    fn dedup_doc_trigrams(entries: &mut Vec<TempTrigramEntry>, start: usize) -> u32 {
+        entries[start..].sort_unstable_by_key(|e| e.trigram.0);
+
+        let mut write = start;
+        for read in start..entries.len() {
+            if write == start || entries[read].trigram != entries[write - 1].trigram {
+                entries[write] = entries[read];
+                write += 1;
+            }
+        }
+        entries.truncate(write);
+
+        (write - start) as u32
+    }
+
     /// Adds multiple documents in batch for better performance.
     ///
     /// Returns a tuple of (success_count, error_count) and the last error encountered.
@@ -81,8 +315,204 @@ impl Lattice {
     }
 
     /// Retrieves a document by its ID.
+    ///
+    /// Returns the indexed text, after normalization; see
+    /// [`display_text`](Self::display_text) for the text as added.
     #[inline(always)]
     pub fn get(&self, doc_id: DocId) -> Option<&str> {
         self.documents.get(doc_id)
     }
+
+    /// Iterates over every document's indexed text with its ID, in
+    /// insertion order, for re-exporting, re-indexing or auditing the
+    /// corpus. Use [`display_text`](Self::display_text) for the original
+    /// text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("first").unwrap();
+    /// engine.add("second").unwrap();
+    ///
+    /// let mut copy = Lattice::new();
+    /// for (_, text) in engine.iter() {
+    ///     copy.add(text).unwrap();
+    /// }
+    /// assert!(copy.diff(&engine).is_empty());
+    /// assert_eq!(engine.iter().last(), Some((1, "second")));
+    /// ```
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (DocId, &str)> + DoubleEndedIterator + '_ {
+        self.documents.iter()
+    }
+
+    /// Returns the byte length of a document's indexed (normalized) text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// let id = engine.add("  Hello   World ").unwrap();
+    /// assert_eq!(engine.doc_len(id), Some("hello world".len()));
+    /// assert_eq!(engine.doc_trigram_count(id), Some(9));
+    /// assert_eq!(engine.doc_len(id + 1), None);
+    /// ```
+    #[inline]
+    pub fn doc_len(&self, doc_id: DocId) -> Option<usize> {
+        let len = self.doc_lengths.get(usize::try_from(doc_id).ok()?)?;
+        Some(*len as usize)
+    }
+
+    /// Returns the number of distinct trigrams a document was indexed
+    /// under.
+    #[inline]
+    pub fn doc_trigram_count(&self, doc_id: DocId) -> Option<u32> {
+        self.doc_trigram_counts
+            .get(usize::try_from(doc_id).ok()?)
+            .copied()
+    }
+
+    /// Returns when a document was added, if
+    /// [`IndexConfig::store_indexed_at`](crate::IndexConfig::store_indexed_at)
+    /// is set. Documents merged in from an index that did not record the
+    /// time are stamped with the time of the merge.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{IndexConfig, Lattice};
+    /// use std::time::{Duration, SystemTime};
+    ///
+    /// let mut engine = Lattice::with_index_config(IndexConfig {
+    ///     store_indexed_at: true,
+    ///     ..IndexConfig::new()
+    /// });
+    /// let id = engine.add("fresh news").unwrap();
+    ///
+    /// let age = engine.indexed_at(id).unwrap().elapsed().unwrap_or_default();
+    /// assert!(age < Duration::from_secs(60));
+    /// assert!(Lattice::new().indexed_at(0).is_none());
+    /// ```
+    pub fn indexed_at(&self, doc_id: DocId) -> Option<SystemTime> {
+        let millis = self.indexed_at.get(usize::try_from(doc_id).ok()?)?;
+        Some(UNIX_EPOCH + Duration::from_millis(*millis))
+    }
+
+    /// Retrieves a document's text as it was added, before normalization.
+    ///
+    /// Falls back to the indexed text unless
+    /// [`IndexConfig::store_display_text`](crate::IndexConfig::store_display_text)
+    /// is set.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, NormalizerConfig};
+    ///
+    /// let mut engine = Lattice::with_display_text(NormalizerConfig::strip_diacritics());
+    /// let id = engine.add("Café Müller").unwrap();
+    ///
+    /// assert_eq!(engine.search("cafe", 10)[0].doc_id, id);
+    /// assert_eq!(engine.search("CAFÉ", 10)[0].doc_id, id);
+    /// assert_eq!(engine.get(id), Some("cafe muller"));
+    /// assert_eq!(engine.display_text(id), Some("Café Müller"));
+    /// ```
+    pub fn display_text(&self, doc_id: DocId) -> Option<&str> {
+        if self.index_config.store_display_text {
+            self.display.get(doc_id)
+        } else {
+            self.documents.get(doc_id)
+        }
+    }
+
+    /// Returns a generation-qualified handle for a document.
+    ///
+    /// Returns `None` if the document does not exist.
+    #[inline]
+    pub fn handle(&self, doc_id: DocId) -> Option<DocHandle> {
+        ((doc_id as usize) < self.len()).then_some(DocHandle::new(self.generation, doc_id))
+    }
+
+    /// Resolves a handle to a DocId in the current generation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DocumentError::StaleHandle` if the handle was issued before the
+    /// index was last cleared, or `DocumentError::NotFound` if the document
+    /// does not exist.
+    pub fn resolve(&self, handle: DocHandle) -> Result<DocId, DocumentError> {
+        if handle.generation != self.generation {
+            return Err(DocumentError::StaleHandle {
+                handle,
+                current_generation: self.generation,
+            });
+        }
+        if handle.doc_id as usize >= self.len() {
+            return Err(DocumentError::NotFound {
+                doc_id: handle.doc_id,
+            });
+        }
+        Ok(handle.doc_id)
+    }
+
+    /// Sets a score multiplier for a document (default 1.0).
+    ///
+    /// The built-in scoring multiplies each document's relevance by its
+    /// boost, so values above 1.0 favor fresh or popular documents and values
+    /// below 1.0 demote them. Custom scorers receive it as
+    /// [`ScoreContext::boost`](crate::index::ScoreContext::boost).
+    ///
+    /// # Errors
+    ///
+    /// Returns `DocumentError::NotFound` if the document does not exist.
+    /// Returns `DocumentError::InvalidInput` if `boost` is negative or not finite.
+    pub fn set_boost(&mut self, doc_id: DocId, boost: f32) -> Result<(), DocumentError> {
+        if doc_id as usize >= self.len() {
+            return Err(DocumentError::NotFound { doc_id });
+        }
+        if !boost.is_finite() || boost < 0.0 {
+            return Err(DocumentError::InvalidInput {
+                reason: "boost must be a finite, non-negative number",
+            });
+        }
+
+        if self.boosts.is_empty() {
+            self.boosts.resize(self.len(), 1.0);
+        }
+        self.boosts[doc_id as usize] = boost;
+        Ok(())
+    }
+
+    /// Returns the score multiplier of a document, or `None` if it does not exist.
+    #[inline]
+    pub fn boost(&self, doc_id: DocId) -> Option<f32> {
+        if doc_id as usize >= self.len() {
+            return None;
+        }
+        Some(self.boosts.get(doc_id as usize).copied().unwrap_or(1.0))
+    }
+}
+
+impl SearchEngine for Lattice {
+    type Stats = IndexStats;
+
+    fn add(&mut self, content: &str) -> Result<DocId, DocumentError> {
+        Lattice::add(self, content)
+    }
+
+    fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+        Lattice::search(self, query, limit)
+    }
+
+    fn len(&self) -> usize {
+        Lattice::len(self)
+    }
+
+    fn stats(&self) -> IndexStats {
+        Lattice::stats(self)
+    }
 }
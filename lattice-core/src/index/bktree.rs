@@ -0,0 +1,114 @@
+//! BK-tree over dictionary tokens.
+//!
+//! A Burkhard-Keller tree indexes tokens by their Levenshtein distance to a
+//! parent node. By the triangle inequality, a lookup with tolerance `k` only
+//! needs to descend into children whose edge distance lies within `k` of the
+//! query's distance to the current node.
+//!
+//! Unlike trigram candidate generation, the tree finds every token within
+//! the tolerance, including very short tokens that share no trigrams with
+//! the query (e.g. "teh" → "the").
+
+use crate::distance::levenshtein;
+use smallvec::SmallVec;
+
+#[derive(Clone)]
+struct BkNode {
+    /// Dictionary token ID stored at this node
+    token: u32,
+    /// (edge distance, child node index) pairs
+    children: SmallVec<[(u32, u32); 4]>,
+}
+
+/// BK-tree keyed by dictionary token IDs.
+#[derive(Clone, Default)]
+pub(crate) struct BkTree {
+    nodes: Vec<BkNode>,
+}
+
+impl BkTree {
+    /// Inserts a token. `tokens` resolves token IDs to their text.
+    pub(crate) fn insert(&mut self, token: u32, tokens: &[Box<str>]) {
+        if self.nodes.is_empty() {
+            self.nodes.push(BkNode {
+                token,
+                children: SmallVec::new(),
+            });
+            return;
+        }
+
+        let text = &tokens[token as usize];
+        let mut node = 0usize;
+        loop {
+            let distance = levenshtein(text, &tokens[self.nodes[node].token as usize]) as u32;
+            if distance == 0 {
+                return;
+            }
+
+            match self.nodes[node]
+                .children
+                .iter()
+                .find(|&&(d, _)| d == distance)
+            {
+                Some(&(_, child)) => node = child as usize,
+                None => {
+                    let child = self.nodes.len() as u32;
+                    self.nodes.push(BkNode {
+                        token,
+                        children: SmallVec::new(),
+                    });
+                    self.nodes[node].children.push((distance, child));
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Returns the number of heap bytes held by the tree.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        let spilled: usize = self
+            .nodes
+            .iter()
+            .filter(|n| n.children.spilled())
+            .map(|n| n.children.capacity() * core::mem::size_of::<(u32, u32)>())
+            .sum();
+        self.nodes.capacity() * core::mem::size_of::<BkNode>() + spilled
+    }
+
+    /// Frees unused node capacity.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.nodes.shrink_to_fit();
+    }
+
+    /// Calls `f(token, distance)` for every token within `max_distance` of `term`.
+    pub(crate) fn find(
+        &self,
+        term: &str,
+        max_distance: u32,
+        tokens: &[Box<str>],
+        mut f: impl FnMut(u32, u32),
+    ) {
+        if self.nodes.is_empty() {
+            return;
+        }
+
+        let mut stack: SmallVec<[usize; 32]> = SmallVec::new();
+        stack.push(0);
+
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            let distance = levenshtein(term, &tokens[node.token as usize]) as u32;
+            if distance <= max_distance {
+                f(node.token, distance);
+            }
+
+            let low = distance.saturating_sub(max_distance);
+            let high = distance + max_distance;
+            for &(edge, child) in &node.children {
+                if (low..=high).contains(&edge) {
+                    stack.push(child as usize);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,309 @@
+//! Trigram dictionary: the sorted posting blocks plus a lookup structure.
+//!
+//! Every query trigram is looked up in the dictionary to find its posting
+//! block. A [`BlockTable`] keeps the blocks sorted by trigram and answers
+//! lookups with one of two structures, chosen by [`TrigramDictionary`]:
+//!
+//! - **Sorted** (default): binary search. Once the dictionary has
+//!   [`DIRECTORY_THRESHOLD`] blocks, a 65536-entry directory indexed by the
+//!   trigram's first two bytes narrows the search to the run of blocks
+//!   sharing that prefix, at most 256 and for text usually a few dozen:
+//!
+//!   ```text
+//!   prefix "ap" ──▶ directory[0x6170] .. directory[0x6171]
+//!                           │                  │
+//!   blocks:  ... [ "apa" "ape" "app" "apr" ] ...
+//!   ```
+//!
+//! - **Perfect hash**: a collision-free hash of the committed trigrams
+//!   (hash and displace). A lookup hashes the trigram to a bucket, reads
+//!   the bucket's displacement, and hashes again to the one slot that can
+//!   hold it: O(1) with two dependent reads, whatever the vocabulary size.
+//!   Building it costs a few hash evaluations per trigram at every commit,
+//!   and about 5.5 bytes per block on top of the block's own 12.
+//!
+//!   ```text
+//!   trigram ──hash──▶ bucket ──pilot──▶ slot ──▶ block index
+//!   ```
+
+use crate::index::types::{Lattice, PostingBlock};
+use core::mem::size_of;
+use core::ops::Deref;
+use lattice_types::Trigram;
+use std::sync::Arc;
+
+/// Blocks needed before the prefix directory is built.
+pub(crate) const DIRECTORY_THRESHOLD: usize = 4096;
+
+/// Number of two-byte prefixes.
+const PREFIXES: usize = 1 << 16;
+
+/// Trigrams per perfect-hash bucket, on average.
+const KEYS_PER_BUCKET: usize = 4;
+
+/// Displacements tried per bucket before the perfect hash is abandoned.
+const MAX_PILOT: u32 = 1 << 20;
+
+/// Marks an unused perfect-hash slot.
+const EMPTY_SLOT: u32 = u32::MAX;
+
+/// How the trigram dictionary finds a query trigram's posting block.
+///
+/// Selected with [`Lattice::set_trigram_dictionary`] or
+/// [`LatticeBuilder::trigram_dictionary`](crate::LatticeBuilder::trigram_dictionary),
+/// and built at every commit. Lookups give the same blocks either way.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TrigramDictionary {
+    /// Binary search over the sorted blocks, narrowed by a two-byte prefix
+    /// directory on large vocabularies. Cheap to build.
+    #[default]
+    Sorted,
+    /// A perfect hash of the committed trigrams: constant-time lookups for
+    /// a costlier commit and about half again the dictionary memory.
+    PerfectHash,
+}
+
+/// Posting blocks sorted by trigram, with a structure for lookups.
+///
+/// Dereferences to the block slice.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct BlockTable {
+    blocks: Vec<PostingBlock>,
+    kind: TrigramDictionary,
+    lookup: Lookup,
+}
+
+#[derive(Debug, Clone)]
+enum Lookup {
+    /// `directory[p]..directory[p + 1]` are the blocks whose trigram
+    /// starts with the two bytes `p`; empty below the threshold
+    Sorted {
+        directory: Box<[u32]>,
+    },
+    PerfectHash(PerfectHash),
+}
+
+impl Default for Lookup {
+    fn default() -> Self {
+        Lookup::Sorted {
+            directory: Box::default(),
+        }
+    }
+}
+
+impl BlockTable {
+    /// Wraps blocks sorted by trigram and builds the `kind` of lookup
+    /// over them.
+    ///
+    /// Unsorted or duplicate blocks (a corrupt index file) give wrong
+    /// lookups but no panic; [`Lattice::verify`] reports them.
+    pub(crate) fn new(blocks: Vec<PostingBlock>, kind: TrigramDictionary) -> Self {
+        let lookup = match kind {
+            TrigramDictionary::PerfectHash => match PerfectHash::build(&blocks) {
+                Some(hash) => Lookup::PerfectHash(hash),
+                // Only duplicate trigrams defeat the hash
+                None => Self::sorted_lookup(&blocks),
+            },
+            TrigramDictionary::Sorted => Self::sorted_lookup(&blocks),
+        };
+        Self {
+            blocks,
+            kind,
+            lookup,
+        }
+    }
+
+    fn sorted_lookup(blocks: &[PostingBlock]) -> Lookup {
+        if blocks.len() < DIRECTORY_THRESHOLD {
+            return Lookup::default();
+        }
+        let mut directory = vec![0u32; PREFIXES + 1];
+        let mut next = 0;
+        for (prefix, start) in directory.iter_mut().enumerate() {
+            while next < blocks.len() && ((blocks[next].trigram.0 >> 8) as usize) < prefix {
+                next += 1;
+            }
+            *start = next as u32;
+        }
+        Lookup::Sorted {
+            directory: directory.into_boxed_slice(),
+        }
+    }
+
+    /// Returns the kind of lookup requested.
+    pub(crate) fn kind(&self) -> TrigramDictionary {
+        self.kind
+    }
+
+    /// Returns the index of the block for `trigram`, if it has postings.
+    #[inline]
+    pub(crate) fn find(&self, trigram: Trigram) -> Option<usize> {
+        let (lo, hi) = match &self.lookup {
+            Lookup::PerfectHash(hash) => {
+                let block = hash.get(trigram.0)?;
+                return (self.blocks[block].trigram == trigram).then_some(block);
+            }
+            Lookup::Sorted { directory } if directory.is_empty() => (0, self.blocks.len()),
+            Lookup::Sorted { directory } => {
+                let prefix = (trigram.0 >> 8) as usize & (PREFIXES - 1);
+                (directory[prefix] as usize, directory[prefix + 1] as usize)
+            }
+        };
+        self.blocks[lo..hi]
+            .binary_search_by_key(&trigram.0, |b| b.trigram.0)
+            .ok()
+            .map(|i| lo + i)
+    }
+
+    /// Returns the heap bytes held by the blocks and the lookup structure.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        let lookup = match &self.lookup {
+            Lookup::Sorted { directory } => directory.len() * size_of::<u32>(),
+            Lookup::PerfectHash(hash) => hash.allocated_bytes(),
+        };
+        self.blocks.capacity() * size_of::<PostingBlock>() + lookup
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.blocks.shrink_to_fit();
+    }
+}
+
+impl Deref for BlockTable {
+    type Target = [PostingBlock];
+
+    fn deref(&self) -> &[PostingBlock] {
+        &self.blocks
+    }
+}
+
+/// Hash-and-displace perfect hash from trigram to block index.
+///
+/// Each trigram hashes to a bucket; each bucket stores the displacement
+/// ("pilot") that sends all of its trigrams to distinct free slots. Slots
+/// hold block indexes, so the hash needs no particular order of blocks.
+#[derive(Debug, Clone)]
+struct PerfectHash {
+    pilots: Box<[u32]>,
+    slots: Box<[u32]>,
+}
+
+impl PerfectHash {
+    /// Builds the hash over the blocks' trigrams, or returns `None` if a
+    /// trigram occurs twice.
+    fn build(blocks: &[PostingBlock]) -> Option<Self> {
+        let buckets = blocks.len().div_ceil(KEYS_PER_BUCKET).max(1);
+        // 10% spare slots keep the last, single-key buckets quick to place
+        let slots = (blocks.len() + blocks.len() / 10).max(1);
+
+        let mut members: Vec<(u32, u32)> = blocks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (reduce(mix(b.trigram.0 as u64), buckets) as u32, i as u32))
+            .collect();
+        members.sort_unstable();
+        let mut ranges: Vec<(usize, usize)> = members
+            .chunk_by(|a, b| a.0 == b.0)
+            .scan(0, |start, run| {
+                let range = (*start, *start + run.len());
+                *start = range.1;
+                Some(range)
+            })
+            .collect();
+        // Largest buckets first, while most slots are free
+        ranges.sort_unstable_by_key(|&(start, end)| core::cmp::Reverse(end - start));
+
+        let mut hash = Self {
+            pilots: vec![0; buckets].into_boxed_slice(),
+            slots: vec![EMPTY_SLOT; slots].into_boxed_slice(),
+        };
+        let mut taken = Vec::with_capacity(KEYS_PER_BUCKET * 4);
+        for (start, end) in ranges {
+            let bucket = &members[start..end];
+            let pilot = (0..MAX_PILOT).find(|&pilot| {
+                taken.clear();
+                bucket.iter().all(|&(_, block)| {
+                    let slot = hash.slot(blocks[block as usize].trigram.0, pilot);
+                    let free = hash.slots[slot] == EMPTY_SLOT && !taken.contains(&slot);
+                    taken.push(slot);
+                    free
+                })
+            })?;
+            hash.pilots[bucket[0].0 as usize] = pilot;
+            for (&slot, &(_, block)) in taken.iter().zip(bucket) {
+                hash.slots[slot] = block;
+            }
+        }
+        Some(hash)
+    }
+
+    #[inline]
+    fn slot(&self, key: u32, pilot: u32) -> usize {
+        let seed = (pilot as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        reduce(mix(key as u64 ^ seed), self.slots.len())
+    }
+
+    /// Returns the block index stored in `key`'s slot, which is `key`'s
+    /// block if `key` was among the hashed trigrams.
+    #[inline]
+    fn get(&self, key: u32) -> Option<usize> {
+        let pilot = self.pilots[reduce(mix(key as u64), self.pilots.len())];
+        let block = self.slots[self.slot(key, pilot)];
+        (block != EMPTY_SLOT).then_some(block as usize)
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        (self.pilots.len() + self.slots.len()) * size_of::<u32>()
+    }
+}
+
+/// SplitMix64 finalizer.
+#[inline]
+fn mix(mut x: u64) -> u64 {
+    x ^= x >> 30;
+    x = x.wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x ^= x >> 27;
+    x = x.wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Maps a hash onto `0..n` without a division.
+#[inline]
+fn reduce(hash: u64, n: usize) -> usize {
+    (((hash >> 32) * n as u64) >> 32) as usize
+}
+
+impl Lattice {
+    /// Chooses how query trigrams are looked up, rebuilding the lookup
+    /// structure of the committed index now and at every later commit.
+    ///
+    /// The choice is not saved with the index; set it again after
+    /// loading.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, TrigramDictionary};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.set_trigram_dictionary(TrigramDictionary::PerfectHash);
+    /// engine.add("hello world").unwrap();
+    /// engine.commit();
+    /// assert_eq!(engine.search("hello", 10).len(), 1);
+    /// ```
+    pub fn set_trigram_dictionary(&mut self, kind: TrigramDictionary) {
+        if self.blocks.kind() != kind {
+            self.blocks = Arc::new(BlockTable::new(self.blocks.to_vec(), kind));
+        }
+    }
+
+    /// Returns how query trigrams are looked up.
+    pub fn trigram_dictionary(&self) -> TrigramDictionary {
+        self.blocks.kind()
+    }
+
+    /// Installs freshly merged blocks, keeping the dictionary kind.
+    pub(crate) fn install_blocks(&mut self, blocks: Vec<PostingBlock>) {
+        self.blocks = Arc::new(BlockTable::new(blocks, self.blocks.kind()));
+    }
+}
@@ -1,7 +1,12 @@
 //! Index building logic.
 
-use crate::index::types::{Lattice, PostingBlock, TempTrigramEntry, RADIX_SORT_THRESHOLD};
+use crate::index::commit::CommitReport;
+use crate::index::types::{
+    Lattice, PostingBlock, PostingOffset, TempTrigramEntry, RADIX_SORT_THRESHOLD,
+};
 use lattice_types::{DocId, Trigram};
+use std::sync::Arc;
+use std::time::Instant;
 
 impl Lattice {
     /// Commits `temp_trigrams` into the main index.
@@ -34,31 +39,147 @@ impl Lattice {
         // Lazy rebuild: if under threshold, just sort and keep as delta
         // This avoids expensive merge for small incremental updates
         if self.temp_trigrams.len() < REBUILD_THRESHOLD && !self.blocks.is_empty() {
-            Self::sort_trigrams(&mut self.temp_trigrams);
-            self.needs_rebuild = false;
+            self.sort_delta();
             return;
         }
 
-        Self::sort_trigrams(&mut self.temp_trigrams);
+        self.merge_delta();
+    }
 
-        if self.blocks.is_empty() {
-            let (blocks, postings) = Self::build_blocks_from_sorted(&self.temp_trigrams);
-            self.blocks = blocks;
-            self.postings = postings;
-        } else {
-            let (delta_blocks, delta_postings) =
-                Self::build_blocks_from_sorted(&self.temp_trigrams);
-            let (merged_blocks, merged_postings) =
-                Self::merge_indexes(&self.blocks, &self.postings, &delta_blocks, &delta_postings);
-            self.blocks = merged_blocks;
-            self.postings = merged_postings;
+    /// Sorts `temp_trigrams` and merges them into the main index,
+    /// regardless of size.
+    pub(crate) fn merge_delta(&mut self) -> CommitReport {
+        // A pending background commit is superseded by this one
+        if !self.committing.is_empty() {
+            let frozen = std::mem::take(&mut self.committing);
+            self.temp_trigrams.extend_from_slice(&frozen);
         }
 
+        if self.temp_trigrams.is_empty() {
+            self.needs_rebuild = false;
+            return CommitReport::default();
+        }
+
+        let (blocks, postings, report) =
+            Self::commit_entries(&self.blocks, &self.postings, &mut self.temp_trigrams);
+        self.install_blocks(blocks);
+        self.postings = Arc::new(postings);
+
         self.temp_trigrams.clear();
         self.needs_rebuild = false;
+        self.record_commit(report);
+        report
+    }
+
+    /// Sorts `entries` and merges them into a copy of the given posting
+    /// lists, timing both phases.
+    pub(crate) fn commit_entries(
+        blocks: &[PostingBlock],
+        postings: &[DocId],
+        entries: &mut [TempTrigramEntry],
+    ) -> (Vec<PostingBlock>, Vec<DocId>, CommitReport) {
+        let started = Instant::now();
+        Self::sort_trigrams(entries);
+        let sorted = Instant::now();
+
+        let (delta_blocks, delta_postings) = Self::build_blocks_from_sorted(entries);
+        let (merged_blocks, merged_postings) = if blocks.is_empty() {
+            (delta_blocks, delta_postings)
+        } else {
+            Self::merge_indexes(blocks, postings, &delta_blocks, &delta_postings)
+        };
+
+        let report = CommitReport {
+            delta_entries: entries.len(),
+            sort_time: sorted - started,
+            merge_time: sorted.elapsed(),
+            new_blocks: merged_blocks.len() - blocks.len(),
+            total_blocks: merged_blocks.len(),
+            postings_added: merged_postings.len() - postings.len(),
+            total_postings: merged_postings.len(),
+        };
+        (merged_blocks, merged_postings, report)
+    }
+
+    /// Sorts `temp_trigrams` in place without merging it into the index.
+    ///
+    /// Only entries appended since the delta was last sorted are sorted; they
+    /// are then merged with the already sorted prefix in O(Δ).
+    pub(crate) fn sort_delta(&mut self) {
+        self.needs_rebuild = false;
+
+        let key = |e: &TempTrigramEntry| (e.trigram.0, e.doc_id);
+        let entries = &mut self.temp_trigrams;
+        let sorted = match entries.windows(2).position(|w| key(&w[0]) > key(&w[1])) {
+            Some(i) => i + 1,
+            None => return,
+        };
+
+        Self::sort_trigrams(&mut entries[sorted..]);
+        if key(&entries[sorted - 1]) <= key(&entries[sorted]) {
+            return;
+        }
+
+        let (head, tail) = entries.split_at(sorted);
+        let mut merged = Vec::with_capacity(entries.len());
+        let (mut i, mut j) = (0, 0);
+        while i < head.len() && j < tail.len() {
+            if key(&tail[j]) < key(&head[i]) {
+                merged.push(tail[j]);
+                j += 1;
+            } else {
+                merged.push(head[i]);
+                i += 1;
+            }
+        }
+        merged.extend_from_slice(&head[i..]);
+        merged.extend_from_slice(&tail[j..]);
+        *entries = merged;
     }
 
     pub(crate) fn sort_trigrams(entries: &mut [TempTrigramEntry]) {
+        #[cfg(feature = "rayon")]
+        if entries.len() >= crate::index::types::PARALLEL_SORT_THRESHOLD {
+            Self::par_sort_trigrams(entries);
+            return;
+        }
+
+        Self::sort_trigrams_sequential(entries);
+    }
+
+    /// Parallel sort: one counting pass scatters entries into 256 buckets by
+    /// the trigram's top byte, then the buckets (disjoint slices, already in
+    /// final order relative to each other) are radix-sorted concurrently.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn par_sort_trigrams(entries: &mut [TempTrigramEntry]) {
+        use rayon::prelude::*;
+
+        let top = |e: &TempTrigramEntry| (e.trigram.0 >> 16) as u8;
+        let aux = entries.to_vec();
+        Self::radix_pass(&aux, entries, top);
+        drop(aux);
+
+        let mut counts = [0usize; 256];
+        for entry in entries.iter() {
+            counts[top(entry) as usize] += 1;
+        }
+
+        let mut buckets: Vec<&mut [TempTrigramEntry]> = Vec::with_capacity(256);
+        let mut rest = entries;
+        for count in counts {
+            let (bucket, tail) = rest.split_at_mut(count);
+            if bucket.len() > 1 {
+                buckets.push(bucket);
+            }
+            rest = tail;
+        }
+
+        buckets
+            .into_par_iter()
+            .for_each(Self::sort_trigrams_sequential);
+    }
+
+    fn sort_trigrams_sequential(entries: &mut [TempTrigramEntry]) {
         if entries.len() < RADIX_SORT_THRESHOLD {
             entries.sort_unstable_by(|a, b| {
                 a.trigram
@@ -77,15 +198,31 @@ impl Lattice {
         };
         let mut aux = vec![dummy; len];
 
-        Self::radix_pass(entries, &mut aux, |e| e.doc_id as u8);
-        Self::radix_pass(&aux, entries, |e| (e.doc_id >> 8) as u8);
-        Self::radix_pass(entries, &mut aux, |e| (e.doc_id >> 16) as u8);
-        Self::radix_pass(&aux, entries, |e| (e.doc_id >> 24) as u8);
-        Self::radix_pass(entries, &mut aux, |e| e.trigram.0 as u8);
-        Self::radix_pass(&aux, entries, |e| (e.trigram.0 >> 8) as u8);
-        Self::radix_pass(entries, &mut aux, |e| (e.trigram.0 >> 16) as u8);
+        // LSD passes alternate between the two buffers: doc_id bytes first
+        // (4, or 8 with `large-corpus`), then the 3 trigram bytes.
+        let mut in_aux = false;
+        for shift in (0..DocId::BITS).step_by(8) {
+            let key = |e: &TempTrigramEntry| (e.doc_id >> shift) as u8;
+            if in_aux {
+                Self::radix_pass(&aux, entries, key);
+            } else {
+                Self::radix_pass(entries, &mut aux, key);
+            }
+            in_aux = !in_aux;
+        }
+        for shift in [0, 8, 16] {
+            let key = |e: &TempTrigramEntry| (e.trigram.0 >> shift) as u8;
+            if in_aux {
+                Self::radix_pass(&aux, entries, key);
+            } else {
+                Self::radix_pass(entries, &mut aux, key);
+            }
+            in_aux = !in_aux;
+        }
 
-        entries.copy_from_slice(&aux);
+        if in_aux {
+            entries.copy_from_slice(&aux);
+        }
     }
 
     #[inline(always)]
@@ -94,14 +231,14 @@ impl Lattice {
         dst: &mut [TempTrigramEntry],
         key_fn: impl Fn(&TempTrigramEntry) -> u8,
     ) {
-        let mut hist = [0u32; 256];
-        let mut offsets = [0u32; 256];
+        let mut hist: [PostingOffset; 256] = [0; 256];
+        let mut offsets: [PostingOffset; 256] = [0; 256];
 
         for entry in src {
             hist[key_fn(entry) as usize] += 1;
         }
 
-        let mut sum = 0u32;
+        let mut sum: PostingOffset = 0;
         for (h, off) in hist.iter().zip(offsets.iter_mut()) {
             *off = sum;
             sum += h;
@@ -125,8 +262,8 @@ impl Lattice {
         let mut postings: Vec<DocId> = Vec::with_capacity(entries.len());
 
         let mut current_trigram = entries[0].trigram.0;
-        let mut current_offset = 0u32;
-        let mut current_len = 0u32;
+        let mut current_offset: PostingOffset = 0;
+        let mut current_len: PostingOffset = 0;
         let mut last_doc_id: Option<DocId> = None;
 
         for entry in entries {
@@ -199,9 +336,9 @@ impl Lattice {
                 std::cmp::Ordering::Equal => {
                     let a_list = Self::block_postings(&a_blocks[ai], a_postings);
                     let b_list = Self::block_postings(&b_blocks[bi], b_postings);
-                    let merged_offset = out_postings.len() as u32;
+                    let merged_offset = out_postings.len() as PostingOffset;
                     Self::merge_sorted_dedup(a_list, b_list, &mut out_postings);
-                    let merged_len = out_postings.len() as u32 - merged_offset;
+                    let merged_len = out_postings.len() as PostingOffset - merged_offset;
                     out_blocks.push(PostingBlock {
                         trigram: a_blocks[ai].trigram,
                         offset: merged_offset,
@@ -242,7 +379,7 @@ impl Lattice {
         out_blocks: &mut Vec<PostingBlock>,
         out_postings: &mut Vec<DocId>,
     ) {
-        let new_offset = out_postings.len() as u32;
+        let new_offset = out_postings.len() as PostingOffset;
         out_postings.extend_from_slice(Self::block_postings(block, source_postings));
         out_blocks.push(PostingBlock {
             trigram: block.trigram,
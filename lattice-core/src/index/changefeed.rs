@@ -0,0 +1,83 @@
+//! Change notifications.
+//!
+//! [`Lattice::subscribe`] hands out a channel receiving an [`IndexEvent`]
+//! for every document added or removed and every merge of uncommitted
+//! documents into the posting lists, so caches and mirrors can follow the
+//! engine without polling it. Events are sent synchronously by the call
+//! that caused them, in order; receivers that have been dropped are
+//! forgotten on the next event.
+
+use crate::index::types::Lattice;
+use lattice_types::DocId;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// A change to an index, as delivered to [`Lattice::subscribe`]rs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndexEvent {
+    /// A document was added, by [`Lattice::add`] and its variants or by
+    /// [`Lattice::merge_from`].
+    Added(DocId),
+    /// A document was removed. [`Lattice::clear`] removes every document,
+    /// in ascending order, after which IDs restart at 0.
+    Removed(DocId),
+    /// Uncommitted documents were merged into the posting lists, by an
+    /// explicit or background commit or by a query.
+    Committed,
+}
+
+/// Senders of the open subscriptions.
+#[derive(Debug, Default)]
+pub(crate) struct Subscribers {
+    senders: Vec<Sender<IndexEvent>>,
+}
+
+impl Subscribers {
+    #[inline]
+    pub(crate) fn is_empty(&self) -> bool {
+        self.senders.is_empty()
+    }
+
+    fn publish(&mut self, event: IndexEvent) {
+        self.senders.retain(|sender| sender.send(event).is_ok());
+    }
+}
+
+impl Lattice {
+    /// Subscribes to changes of this index.
+    ///
+    /// Every event from now on is sent to the returned receiver, which
+    /// buffers them until read; drop it to unsubscribe. Clones and
+    /// snapshots of the engine start without subscribers.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{IndexEvent, Lattice};
+    ///
+    /// let mut engine = Lattice::new();
+    /// let events = engine.subscribe();
+    ///
+    /// engine.add("hello world").unwrap();
+    /// engine.commit();
+    /// engine.clear();
+    ///
+    /// let seen: Vec<IndexEvent> = events.try_iter().collect();
+    /// assert_eq!(
+    ///     seen,
+    ///     [IndexEvent::Added(0), IndexEvent::Committed, IndexEvent::Removed(0)]
+    /// );
+    /// ```
+    pub fn subscribe(&mut self) -> Receiver<IndexEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.senders.push(sender);
+        receiver
+    }
+
+    /// Sends `event` to every subscriber.
+    #[inline]
+    pub(crate) fn publish(&mut self, event: IndexEvent) {
+        if !self.subscribers.is_empty() {
+            self.subscribers.publish(event);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! Columnar ingestion from Apache Arrow and Parquet.
+//!
+//! [`Lattice::add_record_batch`] indexes one column of an Arrow
+//! [`RecordBatch`], reading the strings in place, and
+//! [`Lattice::add_parquet`] streams a Parquet file through it, decoding only
+//! the columns named in the [`ColumnMapping`]. Data-lake exports can be
+//! indexed directly, without converting them to CSV or JSON first.
+//!
+//! Documents get the next free IDs as usual. An optional integer ID column
+//! carries the pipeline's own row IDs; the [`ColumnarReport`] pairs each of
+//! them with the document ID it was given.
+
+use crate::index::types::Lattice;
+use arrow_array::cast::AsArray;
+use arrow_array::types::{
+    Int16Type, Int32Type, Int64Type, Int8Type, UInt16Type, UInt32Type, UInt64Type, UInt8Type,
+};
+use arrow_array::{Array, RecordBatch};
+use arrow_schema::{ArrowError, DataType};
+use core::fmt;
+use lattice_types::{DocId, DocumentError};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ProjectionMask;
+use parquet::errors::ParquetError;
+use parquet::file::reader::ChunkReader;
+
+/// The columns [`Lattice::add_record_batch`] and [`Lattice::add_parquet`]
+/// read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    text: String,
+    id: Option<String>,
+}
+
+impl ColumnMapping {
+    /// Reads documents from the string column `text`.
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_owned(),
+            id: None,
+        }
+    }
+
+    /// Reads each row's external ID from the integer column `id`.
+    /// Without one, a row's ID is its position in the input, counting
+    /// from 0.
+    pub fn id(mut self, id: &str) -> Self {
+        self.id = Some(id.to_owned());
+        self
+    }
+}
+
+/// Outcome of a columnar ingestion.
+#[derive(Debug, Default)]
+pub struct ColumnarReport {
+    /// External ID and document ID of every row added, in row order.
+    pub added: Vec<(i64, DocId)>,
+    /// Rows skipped because their text was null.
+    pub skipped: usize,
+    /// Rows whose document was rejected, or whose ID was null.
+    pub rejected: usize,
+    /// External ID of the first rejected row, or its position if the ID
+    /// was null, and why it was rejected.
+    pub first_error: Option<(i64, DocumentError)>,
+}
+
+impl ColumnarReport {
+    fn reject(&mut self, id: i64, error: DocumentError) {
+        self.rejected += 1;
+        if self.first_error.is_none() {
+            self.first_error = Some((id, error));
+        }
+    }
+}
+
+/// Errors returned by [`Lattice::add_record_batch`] and
+/// [`Lattice::add_parquet`].
+#[derive(Debug)]
+pub enum ColumnarError {
+    /// A mapped column is not in the input.
+    MissingColumn(String),
+    /// A mapped column is not of a supported type: a string type for the
+    /// text, an integer type for the ID.
+    UnsupportedType {
+        /// The column.
+        column: String,
+        /// Its type.
+        data_type: DataType,
+    },
+    /// Decoding the Arrow data failed.
+    Arrow(ArrowError),
+    /// Reading the Parquet file failed.
+    Parquet(ParquetError),
+}
+
+impl fmt::Display for ColumnarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColumnarError::MissingColumn(column) => write!(f, "no column named {:?}", column),
+            ColumnarError::UnsupportedType { column, data_type } => {
+                write!(f, "column {:?} has unsupported type {}", column, data_type)
+            }
+            ColumnarError::Arrow(e) => write!(f, "failed to decode Arrow data: {}", e),
+            ColumnarError::Parquet(e) => write!(f, "failed to read Parquet file: {}", e),
+        }
+    }
+}
+
+impl core::error::Error for ColumnarError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ColumnarError::Arrow(e) => Some(e),
+            ColumnarError::Parquet(e) => Some(e),
+            ColumnarError::MissingColumn(_) | ColumnarError::UnsupportedType { .. } => None,
+        }
+    }
+}
+
+impl From<ArrowError> for ColumnarError {
+    fn from(e: ArrowError) -> Self {
+        ColumnarError::Arrow(e)
+    }
+}
+
+impl From<ParquetError> for ColumnarError {
+    fn from(e: ParquetError) -> Self {
+        ColumnarError::Parquet(e)
+    }
+}
+
+/// Returns the value of row `row` of an integer column; `None` if null
+/// or beyond `i64`.
+fn integer_at(column: &dyn Array, row: usize) -> Option<i64> {
+    if column.is_null(row) {
+        return None;
+    }
+    Some(match column.data_type() {
+        DataType::Int8 => column.as_primitive::<Int8Type>().value(row).into(),
+        DataType::Int16 => column.as_primitive::<Int16Type>().value(row).into(),
+        DataType::Int32 => column.as_primitive::<Int32Type>().value(row).into(),
+        DataType::Int64 => column.as_primitive::<Int64Type>().value(row),
+        DataType::UInt8 => column.as_primitive::<UInt8Type>().value(row).into(),
+        DataType::UInt16 => column.as_primitive::<UInt16Type>().value(row).into(),
+        DataType::UInt32 => column.as_primitive::<UInt32Type>().value(row).into(),
+        DataType::UInt64 => column
+            .as_primitive::<UInt64Type>()
+            .value(row)
+            .try_into()
+            .ok()?,
+        _ => return None,
+    })
+}
+
+/// Returns the text of row `row` of a string column; `None` if null.
+fn text_at(column: &dyn Array, row: usize) -> Option<&str> {
+    if column.is_null(row) {
+        return None;
+    }
+    match column.data_type() {
+        DataType::Utf8 => Some(column.as_string::<i32>().value(row)),
+        DataType::LargeUtf8 => Some(column.as_string::<i64>().value(row)),
+        DataType::Utf8View => Some(column.as_string_view().value(row)),
+        _ => None,
+    }
+}
+
+impl Lattice {
+    /// Adds a document for every row of `batch`, taking its text from the
+    /// column named by `columns`.
+    ///
+    /// Rows with a null text are skipped. A rejected document, such as
+    /// one over the size limit, is counted in the report and does not stop
+    /// the others. The first row of `batch` is numbered `first_row` when no
+    /// ID column is mapped; pass the number of rows already read to
+    /// continue a stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ColumnarError::MissingColumn`] or
+    /// [`ColumnarError::UnsupportedType`] if a mapped column is missing or
+    /// of the wrong type, before adding anything.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use arrow_array::{Int64Array, RecordBatch, StringArray};
+    /// use lattice_core::{ColumnMapping, Lattice};
+    /// use std::sync::Arc;
+    ///
+    /// let batch = RecordBatch::try_from_iter([
+    ///     ("id", Arc::new(Int64Array::from(vec![10, 20, 30])) as _),
+    ///     ("body", Arc::new(StringArray::from(vec![Some("hello world"), None, Some("help")])) as _),
+    /// ])
+    /// .unwrap();
+    ///
+    /// let mut engine = Lattice::new();
+    /// let report = engine
+    ///     .add_record_batch(&batch, &ColumnMapping::new("body").id("id"), 0)
+    ///     .unwrap();
+    /// assert_eq!(report.added, [(10, 0), (30, 1)]);
+    /// assert_eq!(report.skipped, 1);
+    /// ```
+    pub fn add_record_batch(
+        &mut self,
+        batch: &RecordBatch,
+        columns: &ColumnMapping,
+        first_row: i64,
+    ) -> Result<ColumnarReport, ColumnarError> {
+        let mut report = ColumnarReport::default();
+        self.add_batch_rows(batch, columns, first_row, &mut report)?;
+        Ok(report)
+    }
+
+    /// Adds a document for every row of a Parquet file, reading only the
+    /// mapped columns. See [`add_record_batch`](Self::add_record_batch).
+    ///
+    /// `file` is typically a [`std::fs::File`]. Rows are decoded and added
+    /// a batch at a time, so memory use does not grow with the file.
+    ///
+    /// # Errors
+    ///
+    /// As for [`add_record_batch`](Self::add_record_batch), and
+    /// [`ColumnarError::Parquet`] or [`ColumnarError::Arrow`] if the file
+    /// cannot be read. Documents added before a read error stay added.
+    pub fn add_parquet<R: ChunkReader + 'static>(
+        &mut self,
+        file: R,
+        columns: &ColumnMapping,
+    ) -> Result<ColumnarReport, ColumnarError> {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)?;
+        let schema = builder.schema();
+        let mut roots = Vec::with_capacity(2);
+        for name in std::iter::once(&columns.text).chain(&columns.id) {
+            let index = schema
+                .index_of(name)
+                .map_err(|_| ColumnarError::MissingColumn(name.clone()))?;
+            roots.push(index);
+        }
+        let mask = ProjectionMask::roots(builder.parquet_schema(), roots);
+
+        let mut report = ColumnarReport::default();
+        let mut rows = 0;
+        for batch in builder.with_projection(mask).build()? {
+            let batch = batch?;
+            self.add_batch_rows(&batch, columns, rows, &mut report)?;
+            rows += batch.num_rows() as i64;
+        }
+        Ok(report)
+    }
+
+    fn add_batch_rows(
+        &mut self,
+        batch: &RecordBatch,
+        columns: &ColumnMapping,
+        first_row: i64,
+        report: &mut ColumnarReport,
+    ) -> Result<(), ColumnarError> {
+        let column = |name: &String| {
+            batch
+                .column_by_name(name)
+                .ok_or_else(|| ColumnarError::MissingColumn(name.clone()))
+        };
+        let text = column(&columns.text)?;
+        if !matches!(
+            text.data_type(),
+            DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+        ) {
+            return Err(ColumnarError::UnsupportedType {
+                column: columns.text.clone(),
+                data_type: text.data_type().clone(),
+            });
+        }
+        let ids = columns.id.as_ref().map(column).transpose()?;
+        if let Some(ids) = ids {
+            if !ids.data_type().is_integer() {
+                return Err(ColumnarError::UnsupportedType {
+                    column: columns.id.clone().unwrap_or_default(),
+                    data_type: ids.data_type().clone(),
+                });
+            }
+        }
+
+        for row in 0..batch.num_rows() {
+            let position = first_row + row as i64;
+            let Some(content) = text_at(text.as_ref(), row) else {
+                report.skipped += 1;
+                continue;
+            };
+            let id = match ids {
+                Some(ids) => match integer_at(ids.as_ref(), row) {
+                    Some(id) => id,
+                    None => {
+                        report.reject(
+                            position,
+                            DocumentError::InvalidInput {
+                                reason: "row ID is null or out of range",
+                            },
+                        );
+                        continue;
+                    }
+                },
+                None => position,
+            };
+            match self.add(content) {
+                Ok(doc_id) => report.added.push((id, doc_id)),
+                Err(error) => report.reject(id, error),
+            }
+        }
+        Ok(())
+    }
+}
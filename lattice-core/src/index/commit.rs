@@ -0,0 +1,279 @@
+//! Controlling when indexing cost is paid.
+//!
+//! Added documents first land in an uncommitted delta. Merging that delta
+//! into the posting lists (sort + merge) is the expensive part of indexing.
+//! By default it happens lazily inside the next query, so the first search
+//! after a bulk load absorbs the whole cost. With [`CommitPolicy::Manual`]
+//! queries never touch the delta and the application calls
+//! [`Lattice::commit`] when it suits it. [`CommitPolicy::RealTime`] sits in
+//! between: queries keep the delta sorted and search it alongside the
+//! posting lists, but never merge it.
+//!
+//! [`Lattice::commit_in_background`] moves the sort and merge to a worker
+//! thread. The delta is frozen and shared with the worker, which merges it
+//! into its own reference to the current posting lists; queries meanwhile
+//! use the old posting lists plus a linear scan of the frozen delta, until
+//! [`Lattice::finish_commit`] swaps the merged lists in.
+
+use crate::index::block_table::BlockTable;
+use crate::index::changefeed::IndexEvent;
+use crate::index::types::{Lattice, PostingBlock, TempTrigramEntry};
+use core::fmt;
+use lattice_types::DocId;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// A commit running on a background thread.
+///
+/// Returned by [`Lattice::commit_in_background`]; pass it back to
+/// [`Lattice::finish_commit`] to install the result. Dropping the handle
+/// abandons the result, and the delta is merged by the next commit instead.
+#[must_use = "the merged index is only installed by `Lattice::finish_commit`"]
+pub struct CommitHandle {
+    /// The frozen delta being merged
+    delta: Arc<Vec<TempTrigramEntry>>,
+    /// The committed blocks the merge started from
+    base: Arc<BlockTable>,
+    worker: JoinHandle<(Vec<PostingBlock>, Vec<DocId>, CommitReport)>,
+}
+
+impl CommitHandle {
+    /// Returns true once the merge has completed, so that
+    /// [`Lattice::finish_commit`] will not block.
+    pub fn is_finished(&self) -> bool {
+        self.worker.is_finished()
+    }
+}
+
+/// What a merge of uncommitted documents into the posting lists did.
+///
+/// Returned by [`Lattice::commit`] and [`Lattice::finish_commit`]; the
+/// latest one is also available from [`Lattice::last_commit_report`].
+/// A commit with nothing to merge reports all zeros.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommitReport {
+    /// (trigram, document) entries merged from the delta.
+    pub delta_entries: usize,
+    /// Time spent sorting the delta.
+    pub sort_time: Duration,
+    /// Time spent building and merging posting lists.
+    pub merge_time: Duration,
+    /// Trigrams that were not in the index before.
+    pub new_blocks: usize,
+    /// Trigrams in the index afterwards.
+    pub total_blocks: usize,
+    /// Postings added to the index.
+    pub postings_added: usize,
+    /// Postings in the index afterwards.
+    pub total_postings: usize,
+}
+
+impl fmt::Display for CommitReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "merged {} entries in {:?} (sort {:?}, merge {:?}): +{} trigrams ({} total), +{} postings ({} total)",
+            self.delta_entries,
+            self.sort_time + self.merge_time,
+            self.sort_time,
+            self.merge_time,
+            self.new_blocks,
+            self.total_blocks,
+            self.postings_added,
+            self.total_postings
+        )
+    }
+}
+
+/// When uncommitted documents are merged into the index.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CommitPolicy {
+    /// Queries merge (or, for small deltas, sort and scan) uncommitted
+    /// documents as needed, so new documents are always searchable.
+    #[default]
+    Auto,
+    /// Queries never do indexing work. Documents added since the last
+    /// [`Lattice::commit`] are not returned by searches; check
+    /// [`Lattice::has_uncommitted`] to detect that.
+    Manual,
+    /// New documents are searchable by the next query, which only sorts the
+    /// documents added since the previous query into the delta and scans it.
+    /// The delta is never merged by queries, so query cost grows with it;
+    /// call [`Lattice::commit`] (or [`Lattice::commit_in_background`])
+    /// periodically to fold it into the posting lists.
+    RealTime,
+}
+
+impl Lattice {
+    /// Merges all uncommitted documents into the index.
+    ///
+    /// Unlike the lazy merge performed by queries under
+    /// [`CommitPolicy::Auto`], this always folds the whole delta into the
+    /// posting lists, leaving nothing to be scanned at query time.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{CommitPolicy, Lattice};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.set_commit_policy(CommitPolicy::Manual);
+    /// engine.add("hello world").unwrap();
+    ///
+    /// assert!(engine.has_uncommitted());
+    /// assert!(engine.search("hello", 10).is_empty());
+    ///
+    /// let report = engine.commit();
+    /// assert_eq!(report.new_blocks, report.total_blocks);
+    /// assert!(!engine.has_uncommitted());
+    /// assert_eq!(engine.search("hello", 10).len(), 1);
+    /// ```
+    pub fn commit(&mut self) -> CommitReport {
+        self.merge_delta()
+    }
+
+    /// Starts merging the uncommitted documents on a background thread.
+    ///
+    /// Returns `None` if there is nothing to commit or a previous background
+    /// commit has not been finished yet. Until the returned handle is passed
+    /// to [`finish_commit`](Self::finish_commit), the handed-off documents
+    /// stay searchable through a linear scan; documents added meanwhile
+    /// form a new delta as usual.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// for i in 0..1000 {
+    ///     engine.add(&format!("document {}", i)).unwrap();
+    /// }
+    ///
+    /// let handle = engine.commit_in_background().unwrap();
+    /// // Searches keep working while the merge runs
+    /// assert_eq!(engine.search("document 7", 1)[0].doc_id, 7);
+    ///
+    /// assert!(engine.finish_commit(handle).is_some());
+    /// assert!(!engine.has_uncommitted());
+    /// ```
+    pub fn commit_in_background(&mut self) -> Option<CommitHandle> {
+        if self.temp_trigrams.is_empty() || !self.committing.is_empty() {
+            return None;
+        }
+
+        let delta = Arc::new(std::mem::take(&mut self.temp_trigrams));
+        self.committing = Arc::clone(&delta);
+        self.needs_rebuild = false;
+
+        let base = Arc::clone(&self.blocks);
+        let worker = {
+            let delta = Arc::clone(&delta);
+            let blocks = Arc::clone(&self.blocks);
+            let postings = Arc::clone(&self.postings);
+            thread::spawn(move || {
+                let mut entries = delta.to_vec();
+                drop(delta);
+                Self::commit_entries(&blocks, &postings, &mut entries)
+            })
+        };
+
+        Some(CommitHandle {
+            delta,
+            base,
+            worker,
+        })
+    }
+
+    /// Waits for a background commit and installs its result.
+    ///
+    /// Returns `None` if the result was discarded because the index changed
+    /// underneath it: after a [`clear`](Self::clear) or
+    /// [`commit`](Self::commit) the handed-off documents are already gone or
+    /// merged; after a [`merge_from`](Self::merge_from) they are returned to
+    /// the uncommitted delta.
+    pub fn finish_commit(&mut self, handle: CommitHandle) -> Option<CommitReport> {
+        let merged = handle.worker.join().expect("background commit panicked");
+
+        if !Arc::ptr_eq(&self.committing, &handle.delta) {
+            return None;
+        }
+        self.committing = Arc::default();
+
+        if !Arc::ptr_eq(&self.blocks, &handle.base) {
+            self.temp_trigrams.extend_from_slice(&handle.delta);
+            self.needs_rebuild = true;
+            return None;
+        }
+
+        let (blocks, postings, report) = merged;
+        self.install_blocks(blocks);
+        self.postings = Arc::new(postings);
+        self.record_commit(report);
+        Some(report)
+    }
+
+    /// Returns the report of the most recent merge into the posting lists,
+    /// whether it was run by [`commit`](Self::commit), a background commit
+    /// or a query under [`CommitPolicy::Auto`].
+    #[inline]
+    #[must_use]
+    pub fn last_commit_report(&self) -> Option<CommitReport> {
+        self.telemetry.last_commit
+    }
+
+    pub(crate) fn record_commit(&mut self, report: CommitReport) {
+        self.telemetry
+            .commit_latency
+            .record(report.sort_time + report.merge_time);
+        self.telemetry.last_commit = Some(report);
+        self.publish(IndexEvent::Committed);
+    }
+
+    /// Returns true if documents have been added since the last merge
+    /// (including documents a background commit is still merging).
+    #[inline]
+    #[must_use]
+    pub fn has_uncommitted(&self) -> bool {
+        !self.temp_trigrams.is_empty() || !self.committing.is_empty()
+    }
+
+    /// Sets when uncommitted documents are merged.
+    ///
+    /// The policy is not saved with the index: a loaded index starts with
+    /// [`CommitPolicy::Auto`], so set it again after loading.
+    pub fn set_commit_policy(&mut self, policy: CommitPolicy) {
+        self.commit_policy = policy;
+    }
+
+    /// Returns the current commit policy.
+    #[inline]
+    #[must_use]
+    pub fn commit_policy(&self) -> CommitPolicy {
+        self.commit_policy
+    }
+
+    /// Does whatever indexing work the commit policy allows before a query.
+    pub(crate) fn prepare_for_query(&mut self) {
+        if !self.needs_rebuild {
+            return;
+        }
+        match self.commit_policy {
+            CommitPolicy::Auto => self.rebuild_index(),
+            CommitPolicy::RealTime => self.sort_delta(),
+            CommitPolicy::Manual => {}
+        }
+    }
+
+    /// The part of the delta queries may consult: empty unless it is sorted
+    /// and the policy makes uncommitted documents visible.
+    #[inline]
+    pub(crate) fn searchable_delta(&self) -> &[TempTrigramEntry] {
+        if self.commit_policy != CommitPolicy::Manual && !self.needs_rebuild {
+            &self.temp_trigrams
+        } else {
+            &[]
+        }
+    }
+}
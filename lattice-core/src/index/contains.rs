@@ -0,0 +1,82 @@
+//! Exact substring search.
+//!
+//! Trigram overlap admits documents that contain every trigram of a query
+//! without containing the query itself ("bandana nan" has all trigrams of
+//! "banana"). Substring search intersects the posting lists of all the
+//! needle's trigrams, rarest first, and then confirms each candidate with
+//! `memmem` over its stored text.
+
+use crate::analyzer::trigram::extract_terms;
+use crate::index::types::Lattice;
+use lattice_types::{DocId, SearchResult, Trigram};
+use memchr::memmem;
+
+impl Lattice {
+    /// Returns the documents whose stored text contains the normalized
+    /// `substr`, ranked by number of (non-overlapping) occurrences times
+    /// the document boost.
+    ///
+    /// Needles shorter than a trigram cannot use the index and are looked
+    /// for in every document.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("bandana nan").unwrap();
+    /// engine.add("banana split").unwrap();
+    ///
+    /// // Both documents contain every trigram of "banana"
+    /// assert_eq!(engine.search("banana", 10).len(), 2);
+    ///
+    /// let hits = engine.search_contains("banana", 10);
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(hits[0].doc_id, 1);
+    /// ```
+    pub fn search_contains(&mut self, substr: &str, limit: usize) -> Vec<SearchResult> {
+        let needle = self.analyze_query(substr);
+        if needle.is_empty() || limit == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        self.prepare_for_query();
+
+        let candidates = if needle.len() < 3 {
+            (0..self.len() as DocId).collect()
+        } else {
+            self.containing_candidates(&needle)
+        };
+
+        let finder = memmem::Finder::new(needle.as_bytes());
+        let mut results: Vec<SearchResult> = candidates
+            .into_iter()
+            .filter_map(|doc_id| {
+                let count = finder.find_iter(self.get(doc_id)?.as_bytes()).count();
+                let boost = self.boost(doc_id).unwrap_or(1.0);
+                (count > 0).then(|| SearchResult::new(doc_id, count as f32 * boost))
+            })
+            .collect();
+        self.select_top_k(&mut results, limit);
+        results
+    }
+
+    /// Documents containing every trigram of `needle`.
+    fn containing_candidates(&self, needle: &str) -> Vec<DocId> {
+        let mut trigrams: Vec<Trigram> = Vec::with_capacity(needle.len() - 2);
+        extract_terms(needle, self.tokenizer.cjk_bigrams, |t| trigrams.push(t));
+        trigrams.sort_unstable_by_key(|t| t.0);
+        trigrams.dedup();
+        trigrams.sort_by_cached_key(|&t| self.doc_frequency(t));
+
+        let mut candidates = self.posting_docs(trigrams[0]);
+        for &trigram in &trigrams[1..] {
+            if candidates.is_empty() {
+                break;
+            }
+            let docs = self.posting_docs(trigram);
+            candidates.retain(|doc| docs.binary_search(doc).is_ok());
+        }
+        candidates
+    }
+}
@@ -0,0 +1,340 @@
+//! Token dictionary and spelling correction.
+//!
+//! Every token seen at index time is recorded with its corpus frequency.
+//! Tokens are themselves indexed by trigram (padded with a space on each
+//! side, as in `pg_trgm`) so that a misspelled query term can cheaply find
+//! dictionary entries that share trigrams with it before paying for an
+//! edit-distance comparison.
+//!
+//! An optional BK-tree can be maintained alongside; when present it is
+//! used for lookups instead, which also finds close tokens that share no
+//! trigrams with the query.
+
+use crate::analyzer::tokenizer::{Field, Tokenizer};
+use crate::analyzer::trigram::extract_trigrams;
+use crate::distance::levenshtein_bounded;
+use crate::index::bktree::BkTree;
+use crate::index::types::Lattice;
+use lattice_types::Trigram;
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// A proposed replacement for a query term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The suggested dictionary term.
+    pub term: String,
+    /// Edit distance from the original term.
+    pub distance: u32,
+    /// Number of times the term occurs in the indexed corpus.
+    pub frequency: u32,
+}
+
+/// Token → frequency dictionary with a trigram index over the tokens.
+#[derive(Clone, Default)]
+pub(crate) struct TokenDictionary {
+    tokens: Vec<Box<str>>,
+    frequencies: Vec<u32>,
+    ids: FxHashMap<Box<str>, u32>,
+    trigrams: FxHashMap<Trigram, Vec<u32>>,
+    bk_tree: Option<BkTree>,
+    pad_buf: String,
+}
+
+impl TokenDictionary {
+    /// Records one occurrence of `token`.
+    #[inline]
+    pub(crate) fn insert(&mut self, token: &str) {
+        self.insert_count(token, 1);
+    }
+
+    /// Records `count` occurrences of `token`.
+    pub(crate) fn insert_count(&mut self, token: &str, count: u32) {
+        if let Some(&id) = self.ids.get(token) {
+            let frequency = &mut self.frequencies[id as usize];
+            *frequency = frequency.saturating_add(count);
+            return;
+        }
+
+        let id = self.tokens.len() as u32;
+        self.tokens.push(token.into());
+        self.frequencies.push(count);
+        self.ids.insert(token.into(), id);
+        if let Some(tree) = &mut self.bk_tree {
+            tree.insert(id, &self.tokens);
+        }
+
+        Self::pad_into(token, &mut self.pad_buf);
+        let trigrams = &mut self.trigrams;
+        extract_trigrams(&self.pad_buf, |t| {
+            let ids = trigrams.entry(t).or_default();
+            // A token repeating a trigram ("aaaa") must only be listed once
+            if ids.last() != Some(&id) {
+                ids.push(id);
+            }
+        });
+    }
+
+    /// Returns how often `token` occurs in the corpus (0 if never seen).
+    pub(crate) fn frequency(&self, token: &str) -> u32 {
+        self.ids
+            .get(token)
+            .map_or(0, |&id| self.frequencies[id as usize])
+    }
+
+    /// Adds every token of `other` with its frequency.
+    pub(crate) fn merge(&mut self, other: &TokenDictionary) {
+        for (token, &count) in other.tokens.iter().zip(&other.frequencies) {
+            self.insert_count(token, count);
+        }
+    }
+
+    /// Returns the number of distinct tokens.
+    pub(crate) fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns dictionary terms within `max_distance` edits of `term`,
+    /// ranked by distance, then frequency (descending), then term.
+    pub(crate) fn suggest(&self, term: &str, max_distance: u32, limit: usize) -> Vec<Suggestion> {
+        let mut suggestions = match &self.bk_tree {
+            Some(tree) => {
+                let mut found = Vec::new();
+                tree.find(term, max_distance, &self.tokens, |id, distance| {
+                    found.push(Suggestion {
+                        term: self.tokens[id as usize].to_string(),
+                        distance,
+                        frequency: self.frequencies[id as usize],
+                    });
+                });
+                found
+            }
+            None => self.trigram_candidates(term, max_distance),
+        };
+
+        suggestions.sort_unstable_by(|a, b| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| b.frequency.cmp(&a.frequency))
+                .then_with(|| a.term.cmp(&b.term))
+        });
+        suggestions.truncate(limit);
+        suggestions
+    }
+
+    /// Finds close tokens among those sharing at least one padded trigram.
+    fn trigram_candidates(&self, term: &str, max_distance: u32) -> Vec<Suggestion> {
+        let mut padded = String::new();
+        Self::pad_into(term, &mut padded);
+
+        let mut shared: FxHashMap<u32, u32> = FxHashMap::default();
+        extract_trigrams(&padded, |t| {
+            if let Some(ids) = self.trigrams.get(&t) {
+                for &id in ids {
+                    *shared.entry(id).or_insert(0) += 1;
+                }
+            }
+        });
+
+        shared
+            .into_keys()
+            .filter_map(|id| {
+                let candidate = &self.tokens[id as usize];
+                let distance = levenshtein_bounded(term, candidate, max_distance as usize)?;
+                Some(Suggestion {
+                    term: candidate.to_string(),
+                    distance: distance as u32,
+                    frequency: self.frequencies[id as usize],
+                })
+            })
+            .collect()
+    }
+
+    /// Builds the BK-tree over all current tokens and keeps it up to date.
+    pub(crate) fn enable_bk_tree(&mut self) {
+        if self.bk_tree.is_some() {
+            return;
+        }
+        let mut tree = BkTree::default();
+        for id in 0..self.tokens.len() as u32 {
+            tree.insert(id, &self.tokens);
+        }
+        self.bk_tree = Some(tree);
+    }
+
+    /// Drops the BK-tree.
+    pub(crate) fn disable_bk_tree(&mut self) {
+        self.bk_tree = None;
+    }
+
+    /// Returns true if a BK-tree is being maintained.
+    pub(crate) fn has_bk_tree(&self) -> bool {
+        self.bk_tree.is_some()
+    }
+
+    /// Removes all tokens.
+    pub(crate) fn clear(&mut self) {
+        self.tokens.clear();
+        self.frequencies.clear();
+        self.ids.clear();
+        self.trigrams.clear();
+        if let Some(tree) = &mut self.bk_tree {
+            *tree = BkTree::default();
+        }
+    }
+
+    /// Returns an estimate of the heap bytes held by the dictionary.
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        use core::mem::size_of;
+
+        let text: usize = self.tokens.iter().map(|t| t.len()).sum();
+        let trigram_lists: usize = self
+            .trigrams
+            .values()
+            .map(|ids| ids.capacity() * size_of::<u32>())
+            .sum();
+
+        // Token text is stored twice: in `tokens` and as the `ids` keys
+        self.tokens.capacity() * size_of::<Box<str>>()
+            + self.frequencies.capacity() * size_of::<u32>()
+            + self.ids.capacity() * size_of::<(Box<str>, u32)>()
+            + self.trigrams.capacity() * size_of::<(Trigram, Vec<u32>)>()
+            + 2 * text
+            + trigram_lists
+            + self.pad_buf.capacity()
+            + self.bk_tree.as_ref().map_or(0, BkTree::allocated_bytes)
+    }
+
+    /// Frees unused capacity in every table.
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.tokens.shrink_to_fit();
+        self.frequencies.shrink_to_fit();
+        self.ids.shrink_to_fit();
+        self.trigrams.shrink_to_fit();
+        for ids in self.trigrams.values_mut() {
+            ids.shrink_to_fit();
+        }
+        self.pad_buf.shrink_to_fit();
+        if let Some(tree) = &mut self.bk_tree {
+            tree.shrink_to_fit();
+        }
+    }
+
+    /// Empties a dictionary that may be shared with index clones, keeping
+    /// the BK-tree setting. Shared dictionaries are replaced, not copied.
+    pub(crate) fn clear_shared(this: &mut Arc<Self>) {
+        if let Some(dictionary) = Arc::get_mut(this) {
+            dictionary.clear();
+            return;
+        }
+        let mut fresh = Self::default();
+        if this.has_bk_tree() {
+            fresh.enable_bk_tree();
+        }
+        *this = Arc::new(fresh);
+    }
+
+    fn pad_into(token: &str, out: &mut String) {
+        out.clear();
+        out.push(' ');
+        out.push_str(token);
+        out.push(' ');
+    }
+}
+
+impl Lattice {
+    /// Records the tokens of the normalized document in the dictionary.
+    pub(crate) fn index_tokens(&mut self) {
+        let dictionary = Arc::make_mut(&mut self.dictionary);
+        Tokenizer::new(Field::Body).tokenize(&self.norm_buf, |token, _, _| {
+            dictionary.insert(token);
+        });
+    }
+
+    /// Proposes a corrected version of `query`.
+    ///
+    /// Each query term that does not occur in the corpus is replaced by its
+    /// best dictionary suggestion (closest edit distance, then most frequent)
+    /// within `SearchConfig::max_edit_distance`. Returns `None` if every term
+    /// is already known or no correction was found.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// assert_eq!(engine.correct("helo wrld").as_deref(), Some("hello world"));
+    /// ```
+    pub fn correct(&self, query: &str) -> Option<String> {
+        let max_distance = self.config.max_edit_distance as u32;
+        if max_distance == 0 {
+            return None;
+        }
+
+        let normalized = self.analyze_query(query);
+        let mut corrected = String::with_capacity(normalized.len());
+        let mut changed = false;
+
+        Tokenizer::new(Field::Body).tokenize(&normalized, |token, _, pos| {
+            if pos > 0 {
+                corrected.push(' ');
+            }
+
+            if self.dictionary.frequency(token) > 0 {
+                corrected.push_str(token);
+                return;
+            }
+
+            match self.dictionary.suggest(token, max_distance, 1).pop() {
+                Some(suggestion) => {
+                    corrected.push_str(&suggestion.term);
+                    changed = true;
+                }
+                None => corrected.push_str(token),
+            }
+        });
+
+        changed.then_some(corrected)
+    }
+
+    /// Returns up to `limit` dictionary terms close to `term`.
+    ///
+    /// The term is normalized first. Suggestions are limited to
+    /// `SearchConfig::max_edit_distance` edits and ranked by distance, then
+    /// corpus frequency.
+    pub fn suggest(&self, term: &str, limit: usize) -> Vec<Suggestion> {
+        let normalized = self.analyze_query(term);
+        self.dictionary
+            .suggest(&normalized, self.config.max_edit_distance as u32, limit)
+    }
+
+    /// Maintains a BK-tree over the token dictionary.
+    ///
+    /// The tree is built from the current vocabulary and then updated as new
+    /// tokens are indexed. While enabled, [`suggest`](Self::suggest) and
+    /// [`correct`](Self::correct) find every term within the edit distance,
+    /// and a [`search`](Self::search) that finds nothing is retried once with
+    /// the corrected query. This rescues short typos such as "teh" whose
+    /// trigrams never occur in the index.
+    pub fn enable_bk_tree(&mut self) {
+        if !self.dictionary.has_bk_tree() {
+            Arc::make_mut(&mut self.dictionary).enable_bk_tree();
+        }
+    }
+
+    /// Stops maintaining the BK-tree and frees it.
+    pub fn disable_bk_tree(&mut self) {
+        if self.dictionary.has_bk_tree() {
+            Arc::make_mut(&mut self.dictionary).disable_bk_tree();
+        }
+    }
+
+    /// Returns the number of distinct tokens in the spelling dictionary.
+    #[inline(always)]
+    #[must_use]
+    pub fn vocabulary_size(&self) -> usize {
+        self.dictionary.len()
+    }
+}
@@ -0,0 +1,181 @@
+//! Index comparison.
+//!
+//! [`Lattice::diff`] compares two indexes document by document and posting
+//! by posting, for checking that a replica or an incrementally maintained
+//! index matches its source. Uncommitted documents count as indexed on both
+//! sides, so the result does not depend on when either index committed.
+
+use crate::index::types::{Lattice, PostingBlock};
+use core::cmp::Ordering;
+use core::fmt;
+use lattice_types::{DocId, Trigram};
+
+/// Differences between two indexes, as returned by [`Lattice::diff`].
+///
+/// "Left" is the index `diff` was called on, "right" the argument. All
+/// lists are ascending.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IndexDiff {
+    /// Documents only the left index has.
+    pub docs_only_in_left: Vec<DocId>,
+    /// Documents only the right index has.
+    pub docs_only_in_right: Vec<DocId>,
+    /// Documents both indexes have, with different text.
+    pub docs_changed: Vec<DocId>,
+    /// Trigrams with postings only in the left index.
+    pub trigrams_only_in_left: Vec<Trigram>,
+    /// Trigrams with postings only in the right index.
+    pub trigrams_only_in_right: Vec<Trigram>,
+    /// Postings only the left index has, by trigram then document.
+    pub postings_only_in_left: Vec<(Trigram, DocId)>,
+    /// Postings only the right index has, by trigram then document.
+    pub postings_only_in_right: Vec<(Trigram, DocId)>,
+}
+
+impl IndexDiff {
+    /// Returns true if the indexes hold the same documents and postings.
+    pub fn is_empty(&self) -> bool {
+        self.docs_only_in_left.is_empty()
+            && self.docs_only_in_right.is_empty()
+            && self.docs_changed.is_empty()
+            && self.postings_only_in_left.is_empty()
+            && self.postings_only_in_right.is_empty()
+    }
+}
+
+impl fmt::Display for IndexDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("indexes are identical");
+        }
+        write!(
+            f,
+            "documents: {} left only, {} right only, {} changed; \
+             trigrams: {} left only, {} right only; \
+             postings: {} left only, {} right only",
+            self.docs_only_in_left.len(),
+            self.docs_only_in_right.len(),
+            self.docs_changed.len(),
+            self.trigrams_only_in_left.len(),
+            self.trigrams_only_in_right.len(),
+            self.postings_only_in_left.len(),
+            self.postings_only_in_right.len()
+        )
+    }
+}
+
+impl Lattice {
+    /// Compares this index (left) with `other` (right).
+    ///
+    /// Documents are matched by ID and compared by indexed text; postings
+    /// are compared per trigram, including uncommitted documents. Scoring
+    /// settings, boosts and metrics are not compared; use
+    /// [`build_fingerprint`](Self::build_fingerprint) for a byte-level check.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut primary = Lattice::new();
+    /// let mut replica = Lattice::new();
+    /// primary.add("hello world").unwrap();
+    /// replica.add("hello world").unwrap();
+    /// assert!(primary.diff(&replica).is_empty());
+    ///
+    /// primary.add("missed update").unwrap();
+    /// let diff = primary.diff(&replica);
+    /// assert_eq!(diff.docs_only_in_left, vec![1]);
+    /// assert!(diff.postings_only_in_right.is_empty());
+    /// ```
+    pub fn diff(&self, other: &Lattice) -> IndexDiff {
+        let mut diff = IndexDiff::default();
+
+        let shared = self.len().min(other.len()) as DocId;
+        diff.docs_changed = (0..shared)
+            .filter(|&doc_id| self.get(doc_id) != other.get(doc_id))
+            .collect();
+        diff.docs_only_in_left = (shared..self.len() as DocId).collect();
+        diff.docs_only_in_right = (shared..other.len() as DocId).collect();
+
+        let left = self.merged_postings();
+        let right = other.merged_postings();
+        let (left_blocks, left_postings) = match &left {
+            Some((blocks, postings)) => (blocks.as_slice(), postings.as_slice()),
+            None => (&self.blocks[..], self.postings.as_slice()),
+        };
+        let (right_blocks, right_postings) = match &right {
+            Some((blocks, postings)) => (blocks.as_slice(), postings.as_slice()),
+            None => (&other.blocks[..], other.postings.as_slice()),
+        };
+
+        let (mut li, mut ri) = (0usize, 0usize);
+        loop {
+            let order = match (left_blocks.get(li), right_blocks.get(ri)) {
+                (None, None) => break,
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(l), Some(r)) => l.trigram.0.cmp(&r.trigram.0),
+            };
+            match order {
+                Ordering::Less => {
+                    let block = &left_blocks[li];
+                    diff.trigrams_only_in_left.push(block.trigram);
+                    diff.postings_only_in_left.extend(
+                        postings_of(block, left_postings)
+                            .iter()
+                            .map(|&doc_id| (block.trigram, doc_id)),
+                    );
+                    li += 1;
+                }
+                Ordering::Greater => {
+                    let block = &right_blocks[ri];
+                    diff.trigrams_only_in_right.push(block.trigram);
+                    diff.postings_only_in_right.extend(
+                        postings_of(block, right_postings)
+                            .iter()
+                            .map(|&doc_id| (block.trigram, doc_id)),
+                    );
+                    ri += 1;
+                }
+                Ordering::Equal => {
+                    let trigram = left_blocks[li].trigram;
+                    diff_sorted(
+                        trigram,
+                        postings_of(&left_blocks[li], left_postings),
+                        postings_of(&right_blocks[ri], right_postings),
+                        &mut diff,
+                    );
+                    li += 1;
+                    ri += 1;
+                }
+            }
+        }
+        diff
+    }
+}
+
+fn postings_of<'a>(block: &PostingBlock, postings: &'a [DocId]) -> &'a [DocId] {
+    &postings[block.offset as usize..(block.offset + block.len) as usize]
+}
+
+/// Records the postings of `trigram` found in only one of two sorted lists.
+fn diff_sorted(trigram: Trigram, mut left: &[DocId], mut right: &[DocId], diff: &mut IndexDiff) {
+    while let (Some(&l), Some(&r)) = (left.first(), right.first()) {
+        if l < r {
+            diff.postings_only_in_left.push((trigram, l));
+        } else if r < l {
+            diff.postings_only_in_right.push((trigram, r));
+        }
+        if l <= r {
+            left = &left[1..];
+        }
+        if r <= l {
+            right = &right[1..];
+        }
+    }
+    diff.postings_only_in_left
+        .extend(left.iter().map(|&doc_id| (trigram, doc_id)));
+    diff.postings_only_in_right
+        .extend(right.iter().map(|&doc_id| (trigram, doc_id)));
+}
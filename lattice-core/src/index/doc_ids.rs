@@ -0,0 +1,142 @@
+//! Unscored document ID retrieval.
+//!
+//! [`Lattice::matching_doc_ids`] runs candidate selection exactly as
+//! [`Lattice::search`] does but skips scoring and top-k selection, handing
+//! back a sorted [`DocIdSet`] that callers can join against their own data.
+
+use crate::index::types::Lattice;
+use lattice_types::DocId;
+
+/// Sorted, deduplicated set of document IDs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DocIdSet {
+    ids: Vec<DocId>,
+}
+
+impl DocIdSet {
+    /// Creates an empty set.
+    pub const fn new() -> Self {
+        Self { ids: Vec::new() }
+    }
+
+    /// Returns the number of IDs in the set.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns true if the set is empty.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns true if `doc_id` is in the set.
+    #[inline]
+    pub fn contains(&self, doc_id: DocId) -> bool {
+        self.ids.binary_search(&doc_id).is_ok()
+    }
+
+    /// Returns the IDs, ascending.
+    #[inline]
+    pub fn as_slice(&self) -> &[DocId] {
+        &self.ids
+    }
+
+    /// Iterates over the IDs, ascending.
+    pub fn iter(&self) -> impl Iterator<Item = DocId> + '_ {
+        self.ids.iter().copied()
+    }
+
+    /// Returns the IDs present in both sets.
+    pub fn intersection(&self, other: &DocIdSet) -> DocIdSet {
+        let (mut a, mut b) = (self.ids.as_slice(), other.ids.as_slice());
+        let mut ids = Vec::with_capacity(a.len().min(b.len()));
+        while let (Some(&x), Some(&y)) = (a.first(), b.first()) {
+            if x <= y {
+                a = &a[1..];
+            }
+            if y <= x {
+                b = &b[1..];
+            }
+            if x == y {
+                ids.push(x);
+            }
+        }
+        DocIdSet { ids }
+    }
+
+    /// Returns the IDs present in either set.
+    pub fn union(&self, other: &DocIdSet) -> DocIdSet {
+        let mut ids = Vec::with_capacity(self.len() + other.len());
+        ids.extend_from_slice(&self.ids);
+        ids.extend_from_slice(&other.ids);
+        ids.sort_unstable();
+        ids.dedup();
+        DocIdSet { ids }
+    }
+
+    /// Returns the IDs as a sorted vector.
+    pub fn into_vec(self) -> Vec<DocId> {
+        self.ids
+    }
+}
+
+impl FromIterator<DocId> for DocIdSet {
+    fn from_iter<I: IntoIterator<Item = DocId>>(iter: I) -> Self {
+        let mut ids: Vec<DocId> = iter.into_iter().collect();
+        ids.sort_unstable();
+        ids.dedup();
+        DocIdSet { ids }
+    }
+}
+
+impl IntoIterator for DocIdSet {
+    type Item = DocId;
+    type IntoIter = std::vec::IntoIter<DocId>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ids.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a DocIdSet {
+    type Item = DocId;
+    type IntoIter = core::iter::Copied<core::slice::Iter<'a, DocId>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.ids.iter().copied()
+    }
+}
+
+impl Lattice {
+    /// Returns the IDs of every document [`search`](Self::search) would
+    /// consider a match for `query`, without scoring or ranking them.
+    ///
+    /// Cheaper than a search with a large limit when only membership
+    /// matters, e.g. to join hits against an external table. Spelling
+    /// correction is not attempted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("red apple").unwrap();
+    /// engine.add("green pear").unwrap();
+    /// engine.add("apple pie").unwrap();
+    ///
+    /// let ids = engine.matching_doc_ids("apple");
+    /// assert_eq!(ids.as_slice(), &[0, 2]);
+    /// assert!(!ids.contains(1));
+    /// ```
+    pub fn matching_doc_ids(&mut self, query: &str) -> DocIdSet {
+        self.with_scratch(|engine, scratch| {
+            if !matches!(engine.collect_candidates(query, None, scratch), Ok(Some(_))) {
+                return DocIdSet::new();
+            }
+            scratch.candidates.iter().map(|c| c.doc_id).collect()
+        })
+    }
+}
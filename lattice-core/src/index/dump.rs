@@ -0,0 +1,246 @@
+//! Human-readable index dumps.
+//!
+//! [`Lattice::dump`] writes the committed posting blocks, the posting-length
+//! distribution and the first few documents, as plain text for reading or
+//! as JSON for tools. Trigrams are shown with non-ASCII and control bytes
+//! escaped (`\xc3\xa9`), since a trigram may split a UTF-8 character.
+//!
+//! [`Lattice::export_jsonl`] writes every document instead, one JSON object
+//! per line, for backups and for moving a corpus into another index.
+
+use crate::index::stats::IndexStats;
+use crate::index::types::Lattice;
+use lattice_types::{DocId, Trigram};
+use std::io::{self, Write};
+
+/// Documents included in a dump.
+const SAMPLE_DOCUMENTS: usize = 10;
+
+/// Output format of [`Lattice::dump`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DumpFormat {
+    /// Indented plain text.
+    #[default]
+    Text,
+    /// A single JSON object.
+    Json,
+}
+
+/// Writes `s` as a JSON string literal.
+pub(crate) fn write_json_str<W: Write>(w: &mut W, s: &str) -> io::Result<()> {
+    w.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escaped = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            c if (c as u32) < 0x20 => "",
+            _ => continue,
+        };
+        w.write_all(&s.as_bytes()[start..i])?;
+        if escaped.is_empty() {
+            write!(w, "\\u{:04x}", c as u32)?;
+        } else {
+            w.write_all(escaped.as_bytes())?;
+        }
+        start = i + c.len_utf8();
+    }
+    w.write_all(&s.as_bytes()[start..])?;
+    w.write_all(b"\"")
+}
+
+/// Formats a float as a JSON number (`null` if not finite).
+pub(crate) fn json_f32(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+pub(crate) fn trigram_label(trigram: Trigram) -> String {
+    trigram.to_bytes().escape_ascii().to_string()
+}
+
+impl Lattice {
+    /// Writes a human-readable view of the index to `writer`: document,
+    /// trigram and posting counts, the posting-length distribution, every
+    /// committed posting block (trigram, offset, length) and the first ten
+    /// documents.
+    ///
+    /// Uncommitted documents have no blocks yet; the dump reports whether
+    /// any exist. Commit first for a complete view.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `writer`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{DumpFormat, Lattice};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.commit();
+    ///
+    /// let mut out = Vec::new();
+    /// engine.dump(&mut out, DumpFormat::Text).unwrap();
+    /// let text = String::from_utf8(out).unwrap();
+    /// assert!(text.contains("documents: 1"));
+    /// assert!(text.contains("\"hel\""));
+    /// ```
+    pub fn dump<W: Write>(&self, mut writer: W, format: DumpFormat) -> io::Result<()> {
+        let stats = self.stats();
+        let samples = (0..self.len().min(SAMPLE_DOCUMENTS) as DocId)
+            .filter_map(|doc_id| Some((doc_id, self.get(doc_id)?)));
+        match format {
+            DumpFormat::Text => self.dump_text(&mut writer, &stats, samples),
+            DumpFormat::Json => self.dump_json(&mut writer, &stats, samples),
+        }?;
+        writer.flush()
+    }
+
+    /// Writes every document to `writer` as JSON Lines, in ID order:
+    ///
+    /// ```text
+    /// {"doc_id":0,"text":"hello world","length":11}
+    /// ```
+    ///
+    /// `text` is the stored, normalized text and `length` its size in
+    /// bytes. Adding the texts in order to an empty index with the same
+    /// configuration reproduces the document IDs. Uncommitted documents
+    /// are included.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `writer`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.add("say \"hi\"").unwrap();
+    ///
+    /// let mut out = Vec::new();
+    /// engine.export_jsonl(&mut out).unwrap();
+    /// assert_eq!(
+    ///     String::from_utf8(out).unwrap(),
+    ///     "{\"doc_id\":0,\"text\":\"hello world\",\"length\":11}\n\
+    ///      {\"doc_id\":1,\"text\":\"say \\\"hi\\\"\",\"length\":8}\n"
+    /// );
+    /// ```
+    pub fn export_jsonl<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut w = io::BufWriter::new(writer);
+        for doc_id in 0..self.len() as DocId {
+            let Some(text) = self.get(doc_id) else {
+                continue;
+            };
+            write!(w, "{{\"doc_id\":{},\"text\":", doc_id)?;
+            write_json_str(&mut w, text)?;
+            writeln!(w, ",\"length\":{}}}", text.len())?;
+        }
+        w.flush()
+    }
+
+    fn dump_text<'a, W: Write>(
+        &self,
+        w: &mut W,
+        stats: &IndexStats,
+        samples: impl Iterator<Item = (DocId, &'a str)>,
+    ) -> io::Result<()> {
+        let lengths = &stats.posting_lengths;
+        writeln!(w, "documents: {}", stats.num_documents)?;
+        writeln!(w, "trigrams: {}", stats.num_trigrams)?;
+        writeln!(w, "postings: {}", stats.total_postings)?;
+        writeln!(w, "uncommitted: {}", self.has_uncommitted())?;
+        writeln!(
+            w,
+            "posting lengths: p50={} p90={} p99={} max={} singletons={} avg_postings_per_doc={:.2}",
+            lengths.p50,
+            lengths.p90,
+            lengths.p99,
+            lengths.max,
+            lengths.singletons,
+            lengths.avg_postings_per_doc
+        )?;
+
+        writeln!(w)?;
+        writeln!(w, "blocks (trigram offset len):")?;
+        for block in self.blocks.iter() {
+            writeln!(
+                w,
+                "  \"{}\" {} {}",
+                trigram_label(block.trigram),
+                block.offset,
+                block.len
+            )?;
+        }
+
+        writeln!(w)?;
+        writeln!(w, "sample documents:")?;
+        for (doc_id, text) in samples {
+            writeln!(w, "  {} ({} bytes): {:?}", doc_id, text.len(), text)?;
+        }
+        Ok(())
+    }
+
+    fn dump_json<'a, W: Write>(
+        &self,
+        w: &mut W,
+        stats: &IndexStats,
+        samples: impl Iterator<Item = (DocId, &'a str)>,
+    ) -> io::Result<()> {
+        let lengths = &stats.posting_lengths;
+        write!(
+            w,
+            "{{\"num_documents\":{},\"num_trigrams\":{},\"total_postings\":{},\"uncommitted\":{}",
+            stats.num_documents,
+            stats.num_trigrams,
+            stats.total_postings,
+            self.has_uncommitted()
+        )?;
+        write!(
+            w,
+            ",\"posting_lengths\":{{\"p50\":{},\"p90\":{},\"p99\":{},\"max\":{},\"singletons\":{},\"avg_postings_per_doc\":{}}}",
+            lengths.p50,
+            lengths.p90,
+            lengths.p99,
+            lengths.max,
+            lengths.singletons,
+            json_f32(lengths.avg_postings_per_doc)
+        )?;
+
+        w.write_all(b",\"blocks\":[")?;
+        for (i, block) in self.blocks.iter().enumerate() {
+            if i > 0 {
+                w.write_all(b",")?;
+            }
+            w.write_all(b"{\"trigram\":")?;
+            write_json_str(w, &trigram_label(block.trigram))?;
+            write!(w, ",\"offset\":{},\"len\":{}}}", block.offset, block.len)?;
+        }
+
+        w.write_all(b"],\"sample_documents\":[")?;
+        for (i, (doc_id, text)) in samples.enumerate() {
+            if i > 0 {
+                w.write_all(b",")?;
+            }
+            write!(
+                w,
+                "{{\"doc_id\":{},\"len\":{},\"text\":",
+                doc_id,
+                text.len()
+            )?;
+            write_json_str(w, text)?;
+            w.write_all(b"}")?;
+        }
+        w.write_all(b"]}\n")
+    }
+}
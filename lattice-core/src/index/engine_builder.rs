@@ -0,0 +1,242 @@
+//! Validated engine construction.
+//!
+//! [`Lattice::builder`] gathers every construction-time option in one
+//! place — analysis, scoring, search limits and initial capacities — and
+//! checks them all before an engine exists. Sizing the document arena,
+//! per-document arrays and uncommitted delta for a known workload avoids
+//! the reallocations a default-sized engine goes through while ingesting.
+
+use crate::analyzer::normalizer::{NormalizerConfig, TextNormalizer};
+use crate::analyzer::tokenizer::{Field, TokenizerConfig};
+use crate::arena::Arena;
+use crate::index::block_table::TrigramDictionary;
+use crate::index::commit::CommitPolicy;
+use crate::index::scoring::{LengthNorm, ScoringConfig};
+use crate::index::types::{IndexConfig, Lattice};
+use lattice_types::{ConfigError, DocId, SearchConfig};
+
+/// Document arena bytes reserved by default.
+const DEFAULT_ARENA_BYTES: usize = 1024 * 1024;
+
+/// Documents reserved for by default.
+const DEFAULT_DOCUMENTS: usize = 1024;
+
+/// Options for a new [`Lattice`], created by [`Lattice::builder`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LatticeBuilder {
+    search: SearchConfig,
+    normalizer: NormalizerConfig,
+    field_normalizers: [Option<NormalizerConfig>; Field::COUNT],
+    tokenizer: TokenizerConfig,
+    index: IndexConfig,
+    scoring: ScoringConfig,
+    commit_policy: CommitPolicy,
+    dictionary: TrigramDictionary,
+    arena_bytes: usize,
+    documents: usize,
+    trigrams: usize,
+}
+
+impl Default for LatticeBuilder {
+    fn default() -> Self {
+        Self {
+            search: SearchConfig::default(),
+            normalizer: NormalizerConfig::default(),
+            field_normalizers: [None; Field::COUNT],
+            tokenizer: TokenizerConfig::new(),
+            index: IndexConfig::new(),
+            scoring: ScoringConfig::new(),
+            commit_policy: CommitPolicy::Auto,
+            dictionary: TrigramDictionary::Sorted,
+            arena_bytes: DEFAULT_ARENA_BYTES,
+            documents: DEFAULT_DOCUMENTS,
+            trigrams: 0,
+        }
+    }
+}
+
+impl LatticeBuilder {
+    /// Sets the search configuration (overlap ratio, fuzziness, score
+    /// floor).
+    pub fn search_config(mut self, config: SearchConfig) -> Self {
+        self.search = config;
+        self
+    }
+
+    /// Sets how documents and queries are normalized.
+    pub fn normalizer(mut self, config: NormalizerConfig) -> Self {
+        self.normalizer = config;
+        self
+    }
+
+    /// Normalizes the text of `field` with `config` instead of the
+    /// engine's normalizer (or the one language detection picks), e.g. to
+    /// fold diacritics in the body while tags keep theirs.
+    ///
+    /// Queries always go through the engine's normalizer, also when
+    /// [`SearchRequest::fields`](crate::SearchRequest::fields) weighs
+    /// fields, so they match a field configured differently only on the
+    /// trigrams the two forms share: a tag keeping "é" is matched by a
+    /// query folded to "e" on the trigrams around it, and a code field with
+    /// [`keep_case`](NormalizerConfig::keep_case) set matches a lowercased
+    /// query only where the code is lowercase. To search such a field
+    /// case-sensitively, keep case in the engine's normalizer too and
+    /// lowercase the other fields with theirs. Plain [`Lattice::add`]
+    /// documents are body text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::analyzer::Field;
+    /// use lattice_core::{Lattice, NormalizerConfig};
+    ///
+    /// let mut engine = Lattice::builder()
+    ///     .field_normalizer(Field::Body, NormalizerConfig::strip_diacritics())
+    ///     .build()
+    ///     .unwrap();
+    /// engine
+    ///     .add_fields(&[(Field::Title, "Café Society"), (Field::Body, "Crème brûlée")])
+    ///     .unwrap();
+    /// assert_eq!(engine.get(0), Some("café society creme brulee"));
+    /// ```
+    pub fn field_normalizer(mut self, field: Field, config: NormalizerConfig) -> Self {
+        self.field_normalizers[field as usize] = Some(config);
+        self
+    }
+
+    /// Sets how normalized text is tokenized.
+    pub fn tokenizer(mut self, config: TokenizerConfig) -> Self {
+        self.tokenizer = config;
+        self
+    }
+
+    /// Sets what is stored per document.
+    pub fn index_config(mut self, config: IndexConfig) -> Self {
+        self.index = config;
+        self
+    }
+
+    /// Sets the query-time scoring options.
+    pub fn scoring(mut self, config: ScoringConfig) -> Self {
+        self.scoring = config;
+        self
+    }
+
+    /// Sets when uncommitted documents are merged.
+    pub fn commit_policy(mut self, policy: CommitPolicy) -> Self {
+        self.commit_policy = policy;
+        self
+    }
+
+    /// Sets how query trigrams are looked up in the dictionary.
+    pub fn trigram_dictionary(mut self, kind: TrigramDictionary) -> Self {
+        self.dictionary = kind;
+        self
+    }
+
+    /// Reserves document text storage. Capped at one arena page; further
+    /// pages are allocated as they fill.
+    pub fn arena_bytes(mut self, bytes: usize) -> Self {
+        self.arena_bytes = bytes;
+        self
+    }
+
+    /// Reserves the per-document arrays for `documents` documents.
+    pub fn expected_documents(mut self, documents: usize) -> Self {
+        self.documents = documents;
+        self
+    }
+
+    /// Reserves the uncommitted delta for `trigrams` (trigram, document)
+    /// entries, about the number of distinct trigrams per document times
+    /// the documents added before the first commit.
+    pub fn expected_trigrams(mut self, trigrams: usize) -> Self {
+        self.trigrams = trigrams;
+        self
+    }
+
+    /// Validates the options and creates the engine.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first invalid option: anything
+    /// [`SearchConfig::validate`] rejects, a negative or non-finite
+    /// proximity weight, a pivoted length normalization slope outside
+    /// 0.0-1.0, or more expected documents than `DocId` can number.
+    pub fn build(self) -> Result<Lattice, ConfigError> {
+        self.search.validate()?;
+        if !(self.scoring.proximity_weight.is_finite() && self.scoring.proximity_weight >= 0.0) {
+            return Err(ConfigError::InvalidOption {
+                option: "proximity_weight",
+                reason: "must be finite and not negative",
+            });
+        }
+        if let LengthNorm::Pivoted { b } = self.scoring.length_norm {
+            if !(0.0..=1.0).contains(&b) {
+                return Err(ConfigError::InvalidOption {
+                    option: "length_norm",
+                    reason: "pivoted slope must be within 0.0-1.0",
+                });
+            }
+        }
+        if DocId::try_from(self.documents).is_err() {
+            return Err(ConfigError::InvalidOption {
+                option: "expected_documents",
+                reason: "exceeds the DocId range",
+            });
+        }
+
+        let mut engine = Lattice {
+            config: self.search,
+            normalizer: TextNormalizer::with_config(self.normalizer),
+            field_normalizers: self
+                .field_normalizers
+                .map(|c| c.map(TextNormalizer::with_config)),
+            tokenizer: self.tokenizer,
+            index_config: self.index,
+            scoring: self.scoring,
+            commit_policy: self.commit_policy,
+            documents: Arena::with_capacity(self.arena_bytes, self.documents),
+            ..Lattice::new()
+        };
+        if self.index.store_display_text {
+            engine.display = Arena::with_capacity(self.arena_bytes, self.documents);
+        }
+        engine.doc_lengths.reserve(self.documents);
+        engine.doc_trigram_counts.reserve(self.documents);
+        engine.temp_trigrams.reserve(self.trigrams);
+        engine.set_trigram_dictionary(self.dictionary);
+        Ok(engine)
+    }
+}
+
+impl Lattice {
+    /// Starts building an engine with custom options and capacities.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{IndexConfig, Lattice, NormalizerConfig};
+    /// use lattice_types::SearchConfig;
+    ///
+    /// let mut engine = Lattice::builder()
+    ///     .search_config(SearchConfig::exact())
+    ///     .normalizer(NormalizerConfig::strip_diacritics())
+    ///     .index_config(IndexConfig::positional())
+    ///     .expected_documents(100_000)
+    ///     .expected_trigrams(5_000_000)
+    ///     .build()
+    ///     .unwrap();
+    /// engine.add("Crème brûlée").unwrap();
+    /// assert_eq!(engine.search("creme", 1).len(), 1);
+    ///
+    /// let invalid = Lattice::builder().search_config(SearchConfig {
+    ///     min_overlap_ratio: 2.0,
+    ///     ..SearchConfig::default()
+    /// });
+    /// assert!(invalid.build().is_err());
+    /// ```
+    pub fn builder() -> LatticeBuilder {
+        LatticeBuilder::default()
+    }
+}
@@ -0,0 +1,187 @@
+//! Multi-field documents.
+//!
+//! [`Lattice::add_fields`] normalizes each field on its own and stores them
+//! as one text, separated by spaces, so trigram matching is unchanged. The
+//! byte range of every field is recorded per document; documents added with
+//! [`Lattice::add`] have no layout and count as a single
+//! [`Field::Body`].
+//!
+//! [`SearchRequest::fields`](crate::SearchRequest::fields) uses the layout
+//! at query time to weigh matches by the field they fall in, so fields can
+//! be re-weighted per query without reindexing.
+
+use crate::analyzer::tokenizer::Field;
+use crate::analyzer::trigram::extract_terms;
+use crate::index::types::Lattice;
+use lattice_types::{DocId, DocumentError, Trigram};
+
+/// Field ranges of every document, in `DocId` order.
+#[derive(Clone, Default)]
+pub(crate) struct FieldIndex {
+    /// Start of each document's run in `spans`, plus an end marker (empty
+    /// until the first multi-field document)
+    doc_starts: Vec<u32>,
+    /// (field, start, end) byte ranges in the stored text
+    spans: Vec<(Field, u16, u16)>,
+}
+
+impl FieldIndex {
+    /// Number of documents recorded.
+    pub(crate) fn len(&self) -> usize {
+        self.doc_starts.len().saturating_sub(1)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.doc_starts.is_empty()
+    }
+
+    /// Records the layout of document `doc`, padding earlier documents
+    /// with empty layouts.
+    pub(crate) fn push(&mut self, doc: usize, spans: &[(Field, u16, u16)]) {
+        if self.doc_starts.is_empty() {
+            self.doc_starts.push(0);
+        }
+        self.pad(doc);
+        self.spans.extend_from_slice(spans);
+        self.doc_starts.push(self.spans.len() as u32);
+    }
+
+    /// Pads with empty layouts up to (not including) document `doc`.
+    fn pad(&mut self, doc: usize) {
+        let end = self.spans.len() as u32;
+        while self.len() < doc {
+            self.doc_starts.push(end);
+        }
+    }
+
+    /// Returns the field ranges of document `doc`; empty if it has none.
+    pub(crate) fn get(&self, doc: usize) -> &[(Field, u16, u16)] {
+        match (self.doc_starts.get(doc), self.doc_starts.get(doc + 1)) {
+            (Some(&lo), Some(&hi)) => &self.spans[lo as usize..hi as usize],
+            _ => &[],
+        }
+    }
+
+    /// Appends the layouts of `other`'s `count` documents after this
+    /// index's first `first`.
+    pub(crate) fn append(&mut self, other: &FieldIndex, first: usize, count: usize) {
+        if self.is_empty() && other.is_empty() {
+            return;
+        }
+        for doc in 0..count {
+            self.push(first + doc, other.get(doc));
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.doc_starts.clear();
+        self.spans.clear();
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.doc_starts.shrink_to_fit();
+        self.spans.shrink_to_fit();
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.doc_starts.capacity() * size_of::<u32>()
+            + self.spans.capacity() * size_of::<(Field, u16, u16)>()
+    }
+}
+
+impl Lattice {
+    /// Adds a document made of several fields.
+    ///
+    /// Each field is normalized separately; the stored text is the fields
+    /// joined by spaces, in the given order. A field may appear more than
+    /// once. Plain searches treat the document like any other; field
+    /// weights apply through [`SearchRequest::fields`](crate::SearchRequest::fields).
+    ///
+    /// # Errors
+    ///
+    /// As for [`add`](Self::add), with the size limit applying to the
+    /// joined text.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Field, Lattice, SearchRequest};
+    ///
+    /// let mut engine = Lattice::new();
+    /// let id = engine
+    ///     .add_fields(&[(Field::Title, "Rust"), (Field::Body, "A systems language")])
+    ///     .unwrap();
+    ///
+    /// assert_eq!(engine.get(id), Some("rust a systems language"));
+    /// assert_eq!(engine.field_text(id, Field::Title), Some("rust"));
+    ///
+    /// let title_only = SearchRequest::new("systems", 10).fields(&[(Field::Title, 1.0)]);
+    /// assert!(engine.search_request(&title_only).is_empty());
+    /// ```
+    pub fn add_fields(&mut self, fields: &[(Field, &str)]) -> Result<DocId, DocumentError> {
+        self.add_parts(fields, true)
+    }
+
+    /// Returns the text of the first `field` of `doc_id`, as indexed.
+    ///
+    /// Documents added with [`add`](Self::add) consist of a single
+    /// [`Field::Body`]. Returns `None` if the document does not exist or
+    /// lacks the field.
+    pub fn field_text(&self, doc_id: DocId, field: Field) -> Option<&str> {
+        let text = self.get(doc_id)?;
+        let layout = self.fields.get(doc_id as usize);
+        if layout.is_empty() {
+            return (field == Field::Body).then_some(text);
+        }
+        layout
+            .iter()
+            .find(|span| span.0 == field)
+            .map(|&(_, start, end)| &text[start as usize..end as usize])
+    }
+
+    /// Weighted mean of `weights` over the query trigrams matched in each
+    /// field of `doc_id`; fields without a weight count as 0.
+    ///
+    /// `query` holds the distinct query trigrams, sorted.
+    pub(crate) fn field_factor(
+        &self,
+        doc_id: DocId,
+        query: &[Trigram],
+        weights: &[(Field, f32)],
+    ) -> f32 {
+        let Some(text) = self.get(doc_id) else {
+            return 0.0;
+        };
+        let body = [(Field::Body, 0, text.len() as u16)];
+        let layout = match self.fields.get(doc_id as usize) {
+            [] => &body[..],
+            layout => layout,
+        };
+
+        let mut seen = vec![false; query.len()];
+        let (mut weighted, mut matched) = (0.0f32, 0u32);
+        for &(field, start, end) in layout {
+            seen.fill(false);
+            let mut count = 0u32;
+            let part = &text[start as usize..end as usize];
+            extract_terms(part, self.tokenizer.cjk_bigrams, |trigram| {
+                if let Ok(i) = query.binary_search_by_key(&trigram.0, |t| t.0) {
+                    count += !seen[i] as u32;
+                    seen[i] = true;
+                }
+            });
+            let weight = weights
+                .iter()
+                .find(|(f, _)| *f == field)
+                .map_or(0.0, |&(_, w)| w);
+            weighted += weight * count as f32;
+            matched += count;
+        }
+
+        if matched == 0 {
+            0.0
+        } else {
+            weighted / matched as f32
+        }
+    }
+}
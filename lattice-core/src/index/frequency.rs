@@ -0,0 +1,118 @@
+//! Term frequencies.
+//!
+//! Postings hold one entry per (trigram, document) pair, so they say
+//! whether a trigram occurs in a document but not how often. With
+//! [`IndexConfig::store_term_frequencies`](crate::IndexConfig::store_term_frequencies)
+//! every document also records a saturating one-byte count per distinct
+//! trigram, which the TF-IDF and BM25 [`ScoringMode`](crate::ScoringMode)s
+//! use.
+//!
+//! Like positions, the counts are derived from the stored text and rebuilt
+//! on load rather than saved.
+
+use crate::analyzer::trigram::extract_terms;
+use crate::index::types::{Lattice, PostingOffset};
+use lattice_types::{DocId, Trigram};
+
+/// Trigram counts of every document, in `DocId` order.
+#[derive(Clone, Default)]
+pub(crate) struct FrequencyIndex {
+    /// Start of each document's range in `entries`, plus an end marker
+    doc_starts: Vec<PostingOffset>,
+    /// Distinct trigrams of each document, sorted, with their count
+    entries: Vec<(Trigram, u8)>,
+    /// Trigram occurrences across all documents (for average lengths)
+    total_trigrams: u64,
+}
+
+impl FrequencyIndex {
+    /// Number of documents recorded.
+    pub(crate) fn len(&self) -> usize {
+        self.doc_starts.len().saturating_sub(1)
+    }
+
+    /// Records the trigram counts of the next document.
+    pub(crate) fn push_document(&mut self, text: &str, cjk_bigrams: bool) {
+        if self.doc_starts.is_empty() {
+            self.doc_starts.push(0);
+        }
+
+        let mut trigrams: Vec<u32> = Vec::with_capacity(text.len());
+        extract_terms(text, cjk_bigrams, |trigram| trigrams.push(trigram.0));
+        trigrams.sort_unstable();
+        self.total_trigrams += trigrams.len() as u64;
+
+        for chunk in trigrams.chunk_by(|a, b| a == b) {
+            let count = chunk.len().min(u8::MAX as usize) as u8;
+            self.entries.push((Trigram(chunk[0]), count));
+        }
+        self.doc_starts.push(self.entries.len() as PostingOffset);
+    }
+
+    /// Returns how often `trigram` occurs in document `doc` (saturating at
+    /// 255), 0 if it does not.
+    pub(crate) fn get(&self, doc: usize, trigram: Trigram) -> u8 {
+        let (Some(&lo), Some(&hi)) = (self.doc_starts.get(doc), self.doc_starts.get(doc + 1))
+        else {
+            return 0;
+        };
+        let entries = &self.entries[lo as usize..hi as usize];
+        entries
+            .binary_search_by_key(&trigram.0, |e| e.0 .0)
+            .map_or(0, |k| entries[k].1)
+    }
+
+    /// Average number of trigram occurrences per document.
+    pub(crate) fn average_len(&self) -> f32 {
+        if self.len() == 0 {
+            return 0.0;
+        }
+        (self.total_trigrams as f64 / self.len() as f64) as f32
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.doc_starts.clear();
+        self.entries.clear();
+        self.total_trigrams = 0;
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.doc_starts.shrink_to_fit();
+        self.entries.shrink_to_fit();
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.doc_starts.capacity() * size_of::<PostingOffset>()
+            + self.entries.capacity() * size_of::<(Trigram, u8)>()
+    }
+}
+
+impl Lattice {
+    /// Returns how often `trigram` occurs in the stored text of `doc_id`,
+    /// saturating at 255.
+    ///
+    /// Returns `None` if term frequencies are not stored or the document
+    /// does not exist.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{IndexConfig, Lattice};
+    /// use lattice_types::Trigram;
+    ///
+    /// let mut engine = Lattice::with_index_config(IndexConfig {
+    ///     store_term_frequencies: true,
+    ///     ..IndexConfig::new()
+    /// });
+    /// engine.add("banana bandana").unwrap();
+    ///
+    /// assert_eq!(engine.term_frequency(0, Trigram::from_str("ana")), Some(3));
+    /// assert_eq!(engine.term_frequency(0, Trigram::from_str("xyz")), Some(0));
+    /// ```
+    pub fn term_frequency(&self, doc_id: DocId, trigram: Trigram) -> Option<u8> {
+        if !self.index_config.store_term_frequencies || doc_id as usize >= self.len() {
+            return None;
+        }
+        Some(self.frequencies.get(doc_id as usize, trigram))
+    }
+}
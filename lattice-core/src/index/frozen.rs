@@ -0,0 +1,140 @@
+//! Read-only engines for concurrent querying.
+//!
+//! [`Lattice::freeze`] turns a finished index into a [`FrozenLattice`]:
+//! every pending document is committed, spare capacity and per-query
+//! buffers are released, and no method can modify the index afterwards.
+//! Its searches take `&self` and use a per-thread scratch, so a frozen
+//! engine goes straight into an `Arc` and serves a whole thread pool:
+//!
+//! ```text
+//!  build (&mut Lattice) ──freeze──▶ Arc<FrozenLattice> ◀── worker threads
+//! ```
+//!
+//! [`thaw`](FrozenLattice::thaw) gives the engine back for further
+//! indexing.
+
+use crate::index::request::SearchRequest;
+use crate::index::scoring::ScoreContext;
+use crate::index::scratch::SearchScratch;
+use crate::index::stats::IndexStats;
+use crate::index::types::Lattice;
+use core::ops::Deref;
+use lattice_types::{DocId, DocumentError, SearchEngine, SearchResult};
+
+/// An immutable, fully committed [`Lattice`], created by
+/// [`Lattice::freeze`].
+///
+/// `Send` and `Sync`. Dereferences to the engine for read-only methods
+/// such as [`get`](Lattice::get), [`len`](Lattice::len) and
+/// [`search_with_scratch`](Lattice::search_with_scratch).
+#[derive(Clone)]
+pub struct FrozenLattice {
+    engine: Lattice,
+}
+
+impl Lattice {
+    /// Commits every pending document, releases scratch state and spare
+    /// capacity, and returns the engine in read-only form.
+    ///
+    /// The stats reporter and change subscriptions are dropped, since a
+    /// frozen engine never changes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use std::sync::Arc;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.add("hello there").unwrap();
+    ///
+    /// let frozen = Arc::new(engine.freeze());
+    /// let workers: Vec<_> = (0..4)
+    ///     .map(|_| {
+    ///         let frozen = Arc::clone(&frozen);
+    ///         std::thread::spawn(move || frozen.search("hello", 10).len())
+    ///     })
+    ///     .collect();
+    /// for worker in workers {
+    ///     assert_eq!(worker.join().unwrap(), 2);
+    /// }
+    /// ```
+    pub fn freeze(mut self) -> FrozenLattice {
+        self.commit();
+        self.stats_reporter = None;
+        self.subscribers = Default::default();
+        self.scratch = SearchScratch::new();
+        self.norm_buf = String::new();
+        self.shrink_to_fit();
+        FrozenLattice { engine: self }
+    }
+}
+
+impl FrozenLattice {
+    /// Wraps an engine that has nothing left to commit, as is.
+    pub(crate) fn from_committed(engine: Lattice) -> Self {
+        debug_assert!(!engine.has_uncommitted());
+        Self { engine }
+    }
+
+    /// Searches the index. See [`Lattice::search`].
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        self.search_request(&SearchRequest::new(query, limit))
+    }
+
+    /// Searches with a custom scorer. See [`Lattice::search_with_scorer`].
+    pub fn search_with_scorer<F>(&self, query: &str, limit: usize, scorer: F) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        self.engine
+            .search_thread_local(&SearchRequest::new(query, limit), &scorer)
+    }
+
+    /// Searches with per-query options. See [`Lattice::search_request`].
+    pub fn search_request(&self, request: &SearchRequest<'_>) -> Vec<SearchResult> {
+        self.engine
+            .search_thread_local(request, &|ctx| ctx.default_score())
+    }
+
+    /// Returns index statistics. See [`Lattice::stats`].
+    pub fn stats(&self) -> IndexStats {
+        self.engine.stats()
+    }
+
+    /// Turns the frozen engine back into a writable one.
+    pub fn thaw(self) -> Lattice {
+        self.engine
+    }
+}
+
+/// Adding to a frozen engine fails with [`DocumentError::ReadOnly`];
+/// [`thaw`](FrozenLattice::thaw) it first.
+impl SearchEngine for FrozenLattice {
+    type Stats = IndexStats;
+
+    fn add(&mut self, _content: &str) -> Result<DocId, DocumentError> {
+        Err(DocumentError::ReadOnly)
+    }
+
+    fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+        FrozenLattice::search(self, query, limit)
+    }
+
+    fn len(&self) -> usize {
+        self.engine.len()
+    }
+
+    fn stats(&self) -> IndexStats {
+        FrozenLattice::stats(self)
+    }
+}
+
+impl Deref for FrozenLattice {
+    type Target = Lattice;
+
+    fn deref(&self) -> &Lattice {
+        &self.engine
+    }
+}
@@ -0,0 +1,283 @@
+//! Impact-ordered postings for early-terminating top-k search.
+//!
+//! [`Lattice::impact_index`] builds an [`ImpactIndex`]: a second copy of
+//! every committed posting list in which each posting carries a quantized,
+//! precomputed score contribution (its *impact*) and the list is sorted by
+//! impact, highest first. A document's score is the sum of its impacts
+//! over the query trigrams, where the impact of trigram `t` in document `d`
+//! is `idf(t) × length factor(d) × boost(d)`, quantized to 8 bits.
+//!
+//! [`Lattice::search_impacts`] reads the lists highest impacts first and
+//! stops once no document outside the current top `limit` can overtake
+//! it (the NRA, "no random access", threshold test). Lengths and boosts
+//! were folded in at build time, so ranking never looks up per-document
+//! data for the candidates it discards:
+//!
+//! ```text
+//! "apple"  ─▶ (d7, 212) (d2, 180) (d9, 64) ┆ (d1, 12) ...
+//! "pple"   ─▶ (d2, 201) (d7, 150) (d4, 40) ┆ (d8, 9) ...
+//!                                          ┆ stop: d2 and d7 cannot be
+//!                                          ┆ overtaken by anything below
+//! ```
+//!
+//! Scores approximate [`ScoringMode::TfIdf`](crate::ScoringMode::TfIdf)
+//! without term frequencies rather than reproducing
+//! [`Lattice::search`]; use the index where top-k latency over large
+//! posting lists matters more than exact parity. It is a snapshot: rebuild
+//! it after adding documents.
+
+use crate::analyzer::trigram::extract_terms;
+use crate::index::block_table::BlockTable;
+use crate::index::metrics::Metrics;
+use crate::index::types::{Lattice, MAX_QUERY_TRIGRAMS};
+use lattice_types::{DocId, SearchResult, Trigram};
+use rustc_hash::FxHashMap;
+use std::sync::Arc;
+
+/// Postings read between checks of the stopping condition.
+const CHECK_INTERVAL: usize = 64;
+
+/// A posting with its quantized score contribution.
+#[derive(Debug, Clone, Copy)]
+struct Impact {
+    doc_id: DocId,
+    impact: u8,
+}
+
+/// Impact-ordered copy of an engine's committed posting lists, built by
+/// [`Lattice::impact_index`].
+#[derive(Debug, Clone)]
+pub struct ImpactIndex {
+    /// The engine's document-ordered lists at build time (shared), for
+    /// completing the scores of the final results
+    blocks: Arc<BlockTable>,
+    postings: Arc<Vec<DocId>>,
+    /// Inverse document frequency per block
+    pub(crate) idfs: Vec<f32>,
+    /// Each block's postings at the same offsets as in `postings`, by
+    /// descending impact
+    impacts: Vec<Impact>,
+    /// Length factor times boost, per document
+    pub(crate) doc_factors: Vec<f32>,
+    /// Score of one quantization step
+    pub(crate) scale: f32,
+}
+
+impl ImpactIndex {
+    /// Returns the number of documents covered.
+    pub fn num_documents(&self) -> usize {
+        self.doc_factors.len()
+    }
+
+    /// Returns the number of postings.
+    pub fn num_postings(&self) -> usize {
+        self.impacts.len()
+    }
+
+    /// Returns the heap bytes held by the impact lists and per-document
+    /// factors; the document-ordered lists are shared with the engine.
+    pub fn allocated_bytes(&self) -> usize {
+        self.idfs.capacity() * size_of::<f32>()
+            + self.impacts.capacity() * size_of::<Impact>()
+            + self.doc_factors.capacity() * size_of::<f32>()
+    }
+
+    #[inline]
+    pub(crate) fn quantize(&self, idf: f32, doc_factor: f32) -> u8 {
+        (idf * doc_factor / self.scale).ceil().clamp(0.0, 255.0) as u8
+    }
+
+    fn block_impacts(&self, block: usize) -> &[Impact] {
+        let b = &self.blocks[block];
+        &self.impacts[b.offset as usize..(b.offset + b.len) as usize]
+    }
+
+    /// Returns whether `doc_id` appears in block `block`.
+    fn contains(&self, block: usize, doc_id: DocId) -> bool {
+        Lattice::block_postings(&self.blocks[block], &self.postings)
+            .binary_search(&doc_id)
+            .is_ok()
+    }
+}
+
+impl Lattice {
+    /// Builds impact-ordered posting lists over the committed documents.
+    ///
+    /// Takes O(postings · log) time and about 8 bytes per posting. Uses the
+    /// engine's current length normalization and boosts; documents added,
+    /// boosted or committed afterwards are not reflected.
+    pub fn impact_index(&self) -> ImpactIndex {
+        let docs = self.documents.len();
+        let avg_doc_len = self.average_doc_len();
+        let doc_factors: Vec<f32> = (0..docs)
+            .map(|doc| {
+                let doc_len = self.doc_lengths.get(doc).copied().unwrap_or(0);
+                let boost = self.boosts.get(doc).copied().unwrap_or(1.0);
+                self.scoring.length_norm.factor(doc_len, avg_doc_len) / 100.0 * boost.max(0.0)
+            })
+            .collect();
+        let idfs: Vec<f32> = self
+            .blocks
+            .iter()
+            .map(|b| (1.0 + docs as f32 / (b.len as f32).max(1.0)).ln())
+            .collect();
+
+        let max_idf = idfs.iter().copied().fold(0.0, f32::max);
+        let max_factor = doc_factors.iter().copied().fold(0.0, f32::max);
+        let mut index = ImpactIndex {
+            blocks: Arc::clone(&self.blocks),
+            postings: Arc::clone(&self.postings),
+            idfs,
+            impacts: Vec::with_capacity(self.postings.len()),
+            doc_factors,
+            scale: (max_idf * max_factor / 255.0).max(f32::MIN_POSITIVE),
+        };
+
+        for (block, idf) in self.blocks.iter().zip(&index.idfs) {
+            let start = index.impacts.len();
+            for &doc_id in Self::block_postings(block, &self.postings) {
+                let impact = index.quantize(*idf, index.doc_factors[doc_id as usize]);
+                index.impacts.push(Impact { doc_id, impact });
+            }
+            index.impacts[start..]
+                .sort_unstable_by(|a, b| b.impact.cmp(&a.impact).then(a.doc_id.cmp(&b.doc_id)));
+        }
+        index
+    }
+
+    /// Returns the top `limit` documents for `query` under the impact
+    /// scores of `index`, best first, reading posting lists only as far as
+    /// needed to be sure of them.
+    ///
+    /// The query is analyzed the way [`search`](Self::search) analyzes it;
+    /// there is no overlap threshold or spelling correction, so any
+    /// document sharing a trigram with the query can be returned.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("apple pie recipe").unwrap();
+    /// engine.add("apple").unwrap();
+    /// engine.add("banana bread").unwrap();
+    /// engine.commit();
+    ///
+    /// let index = engine.impact_index();
+    /// let results = engine.search_impacts(&index, "apple", 10);
+    /// assert_eq!(results.len(), 2);
+    /// assert_eq!(results[0].doc_id, 1); // shorter, so each trigram weighs more
+    /// ```
+    pub fn search_impacts(
+        &self,
+        index: &ImpactIndex,
+        query: &str,
+        limit: usize,
+    ) -> Vec<SearchResult> {
+        Metrics::add(&self.counters.queries_executed, 1);
+        let normalized = self.analyze_query(query);
+        let mut trigrams: Vec<Trigram> = Vec::new();
+        extract_terms(&normalized, self.tokenizer.cjk_bigrams, |t| {
+            trigrams.push(t)
+        });
+        trigrams.sort_unstable_by_key(|t| t.0);
+        trigrams.dedup();
+        trigrams.truncate(MAX_QUERY_TRIGRAMS);
+
+        let lists: Vec<usize> = trigrams
+            .iter()
+            .filter_map(|&t| index.blocks.find(t))
+            .collect();
+        if limit == 0 || lists.is_empty() {
+            return Vec::new();
+        }
+
+        let mut cursors = vec![0usize; lists.len()];
+        let frontier = |cursors: &[usize], i: usize| {
+            index
+                .block_impacts(lists[i])
+                .get(cursors[i])
+                .map_or(0, |p| p.impact as u32)
+        };
+        // Impact total so far and the lists it came from, per document
+        let mut seen: FxHashMap<DocId, (u32, u32)> = FxHashMap::default();
+        let mut read = 0usize;
+
+        loop {
+            // Read from the list whose next impact is largest
+            let next = (0..lists.len())
+                .filter(|&i| cursors[i] < index.block_impacts(lists[i]).len())
+                .max_by_key(|&i| frontier(&cursors, i));
+            let Some(i) = next else { break };
+            let posting = index.block_impacts(lists[i])[cursors[i]];
+            cursors[i] += 1;
+            read += 1;
+            let entry = seen.entry(posting.doc_id).or_insert((0, 0));
+            entry.0 += posting.impact as u32;
+            entry.1 |= 1 << i;
+
+            if read.is_multiple_of(CHECK_INTERVAL) && seen.len() > limit {
+                let frontiers: Vec<u32> = (0..lists.len()).map(|i| frontier(&cursors, i)).collect();
+                if Self::top_k_settled(&seen, &frontiers, limit) {
+                    break;
+                }
+            }
+        }
+        Metrics::add(&self.counters.candidates_examined, read as u64);
+
+        let mut top: Vec<(DocId, u32, u32)> = seen
+            .into_iter()
+            .map(|(doc_id, (score, lists))| (doc_id, score, lists))
+            .collect();
+        let by_rank =
+            |a: &(DocId, u32, u32), b: &(DocId, u32, u32)| b.1.cmp(&a.1).then(a.0.cmp(&b.0));
+        if top.len() > limit {
+            top.select_nth_unstable_by(limit - 1, by_rank);
+            top.truncate(limit);
+        }
+
+        // Add the impacts the early stop left unread
+        for (doc_id, score, read_from) in &mut top {
+            let factor = index.doc_factors[*doc_id as usize];
+            for (i, &block) in lists.iter().enumerate() {
+                let unread = cursors[i] < index.block_impacts(block).len();
+                if *read_from & (1 << i) == 0 && unread && index.contains(block, *doc_id) {
+                    *score += index.quantize(index.idfs[block], factor) as u32;
+                }
+            }
+        }
+        top.sort_unstable_by(by_rank);
+        top.into_iter()
+            .map(|(doc_id, score, _)| SearchResult::new(doc_id, score as f32 * index.scale))
+            .collect()
+    }
+
+    /// Returns true once the `limit` best partial totals in `seen` cannot
+    /// be overtaken: neither by an unseen document (worth at most
+    /// every frontier impact) nor by a seen one outside them (worth at most
+    /// its total plus the frontiers of the lists it was not read from).
+    fn top_k_settled(seen: &FxHashMap<DocId, (u32, u32)>, frontiers: &[u32], limit: usize) -> bool {
+        let unseen_bound: u32 = frontiers.iter().sum();
+        let mut totals: Vec<(DocId, u32, u32)> = seen
+            .iter()
+            .map(|(&doc_id, &(score, lists))| (doc_id, score, lists))
+            .collect();
+        totals.select_nth_unstable_by(limit - 1, |a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let (kth_doc, kth, _) = totals[limit - 1];
+        if kth <= unseen_bound {
+            return false;
+        }
+
+        // Ties go to the lower doc ID, as in the final ranking
+        totals[limit..].iter().all(|&(doc_id, score, lists)| {
+            let missing: u32 = frontiers
+                .iter()
+                .enumerate()
+                .filter(|&(i, _)| lists & (1 << i) == 0)
+                .map(|(_, f)| f)
+                .sum();
+            score + missing < kth || (score + missing == kth && doc_id > kth_doc)
+        })
+    }
+}
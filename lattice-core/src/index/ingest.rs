@@ -0,0 +1,138 @@
+//! Streaming document ingestion.
+//!
+//! [`Lattice::add_from_reader`] reads a document straight from an
+//! [`io::Read`] source such as a file, so callers do not have to load it
+//! into a `String` first. Bytes are validated as UTF-8 chunk by chunk as
+//! they arrive, and reading stops at the first invalid sequence or as soon
+//! as the document outgrows its limit. The finished buffer is handed to
+//! [`Lattice::add_owned`], which normalizes it and moves it into storage
+//! without another copy; normalization runs once the whole document is in,
+//! since whitespace collapsing and language detection look across chunk
+//! boundaries.
+
+use crate::index::types::{Lattice, MAX_DOCUMENT_LENGTH};
+use core::fmt;
+use lattice_types::{DocId, DocumentError};
+use std::io::{self, Read};
+
+/// Bytes requested from the reader per call.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// Errors returned by [`Lattice::add_from_reader`].
+#[derive(Debug)]
+pub enum IngestError {
+    /// The reader failed.
+    Io(io::Error),
+    /// The document was rejected.
+    Document(DocumentError),
+}
+
+impl fmt::Display for IngestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IngestError::Io(e) => write!(f, "failed to read document: {}", e),
+            IngestError::Document(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for IngestError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            IngestError::Io(e) => Some(e),
+            IngestError::Document(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for IngestError {
+    fn from(e: io::Error) -> Self {
+        IngestError::Io(e)
+    }
+}
+
+impl From<DocumentError> for IngestError {
+    fn from(e: DocumentError) -> Self {
+        IngestError::Document(e)
+    }
+}
+
+impl Lattice {
+    /// Reads one document from `reader` until end of input and adds it.
+    ///
+    /// At most `max_len` bytes are accepted (and never more than the 64KB
+    /// document limit); reading stops as soon as the input is longer.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IngestError::Io`] if the reader fails, and
+    /// [`IngestError::Document`] with `DocumentError::TooLarge` if the input
+    /// exceeds the limit (`size` is the number of bytes read before
+    /// stopping), `DocumentError::InvalidInput` if it is not valid UTF-8,
+    /// or anything [`add`](Self::add) rejects. Nothing is added on error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// let file: &[u8] = b"contents of a text file";
+    /// let doc_id = engine.add_from_reader(file, 4096).unwrap();
+    /// assert_eq!(engine.get(doc_id), Some("contents of a text file"));
+    ///
+    /// assert!(engine.add_from_reader(file, 8).is_err());
+    /// ```
+    pub fn add_from_reader<R: Read>(
+        &mut self,
+        mut reader: R,
+        max_len: usize,
+    ) -> Result<DocId, IngestError> {
+        let limit = max_len.min(MAX_DOCUMENT_LENGTH);
+        let mut buffer: Vec<u8> = Vec::with_capacity(limit.min(CHUNK_SIZE));
+        // Bytes known to be complete, valid UTF-8
+        let mut valid = 0;
+
+        loop {
+            let filled = buffer.len();
+            // One byte past the limit tells an exact fit from an overflow
+            let room = (limit + 1 - filled).min(CHUNK_SIZE);
+            buffer.resize(filled + room, 0);
+            let read = reader.read(&mut buffer[filled..]);
+            buffer.truncate(filled + *read.as_ref().unwrap_or(&0));
+            match read {
+                Ok(0) => break,
+                Ok(_) => {}
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            }
+            if buffer.len() > limit {
+                return Err(DocumentError::TooLarge {
+                    size: buffer.len(),
+                    max_size: limit,
+                }
+                .into());
+            }
+
+            // A multi-byte sequence split across reads is revisited next time
+            match core::str::from_utf8(&buffer[valid..]) {
+                Ok(_) => valid = buffer.len(),
+                Err(e) if e.error_len().is_none() => valid += e.valid_up_to(),
+                Err(_) => return Err(not_utf8()),
+            }
+        }
+        if valid != buffer.len() {
+            return Err(not_utf8());
+        }
+
+        // SAFETY: every byte of `buffer` was validated above
+        let content = unsafe { String::from_utf8_unchecked(buffer) };
+        Ok(self.add_owned(content)?)
+    }
+}
+
+fn not_utf8() -> IngestError {
+    IngestError::Document(DocumentError::InvalidInput {
+        reason: "document is not valid UTF-8",
+    })
+}
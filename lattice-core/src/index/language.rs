@@ -0,0 +1,81 @@
+//! Per-language document normalization.
+//!
+//! With detection enabled, each added document is tagged with its detected
+//! language and normalized with that language's configuration. Queries are
+//! detected the same way; since short queries are rarely detected
+//! reliably, the default configuration should be the one that suits mixed
+//! queries best (usually diacritic folding).
+//!
+//! Detected languages are not saved by [`Lattice::save`]; documents are
+//! stored already normalized, so a loaded index searches the same way once
+//! detection is enabled again.
+
+use crate::analyzer::language::{Language, LanguageNormalizers};
+use crate::analyzer::normalizer::TextNormalizer;
+use crate::index::types::Lattice;
+use lattice_types::DocId;
+
+impl Lattice {
+    /// Detects the language of each document added from now on and
+    /// normalizes it with the configuration `normalizers` gives for it.
+    ///
+    /// Documents already in the index keep their normalization.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Language, LanguageNormalizers, Lattice, NormalizerConfig};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.enable_language_detection(
+    ///     LanguageNormalizers::new(NormalizerConfig::strip_diacritics())
+    ///         .with(Language::GERMAN, NormalizerConfig::default()),
+    /// );
+    ///
+    /// let fr = engine
+    ///     .add("Le garçon est allé à l'école avec sa mère, puis ils ont mangé une crème brûlée.")
+    ///     .unwrap();
+    /// let de = engine
+    ///     .add("Der Junge ist mit seiner Mutter zur Schule gegangen und war sehr glücklich.")
+    ///     .unwrap();
+    ///
+    /// assert_eq!(engine.doc_language(fr), Some(Language::FRENCH));
+    /// assert!(engine.get(fr).unwrap().contains("creme brulee"));
+    /// assert!(engine.get(de).unwrap().contains("glücklich"));
+    /// ```
+    pub fn enable_language_detection(&mut self, normalizers: LanguageNormalizers) {
+        self.languages = Some(Box::new(normalizers));
+    }
+
+    /// Stops detecting languages; new documents and queries use the
+    /// engine's own normalizer again. Existing tags are kept.
+    pub fn disable_language_detection(&mut self) {
+        self.languages = None;
+    }
+
+    /// Returns the language detected for `doc_id`, if detection was enabled
+    /// when it was added and the detector was confident.
+    pub fn doc_language(&self, doc_id: DocId) -> Option<Language> {
+        self.doc_languages.get(doc_id as usize).copied().flatten()
+    }
+
+    /// Returns the normalizer for a new document and its detected language.
+    pub(crate) fn document_normalizer(&self, content: &str) -> (TextNormalizer, Option<Language>) {
+        match &self.languages {
+            Some(normalizers) => {
+                let language = Language::detect(content);
+                let config = normalizers.config_for(language);
+                (TextNormalizer::with_config(config), language)
+            }
+            None => (self.normalizer, None),
+        }
+    }
+
+    /// Records the language of a newly added document.
+    pub(crate) fn record_language(&mut self, doc_id: DocId, language: Option<Language>) {
+        if self.languages.is_some() {
+            self.doc_languages.resize(doc_id as usize, None);
+            self.doc_languages.push(language);
+        }
+    }
+}
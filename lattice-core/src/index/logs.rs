@@ -0,0 +1,273 @@
+//! Log-line ingestion.
+//!
+//! [`Lattice::add_log_line`] splits a leading timestamp off a log line,
+//! indexes the message after it and stores the time under the sort key
+//! [`TIMESTAMP_KEY`], as seconds since the Unix epoch. Fuzzy searches can
+//! then be limited to a time window with
+//! [`SearchRequest::range`](crate::SearchRequest::range), or ordered by
+//! time with [`SortBy`](crate::SortBy).
+//!
+//! Recognized timestamps, optionally in square brackets:
+//!
+//! ```text
+//! 2024-03-01T12:30:05Z            RFC 3339 / ISO 8601, `T` or space
+//! 2024-03-01 12:30:05,123         fractions after `.` or `,`
+//! 2024-03-01T12:30:05.5+02:00     offsets as `±HH:MM` or `±HHMM`; none is UTC
+//! 1709296205                      Unix seconds (10 digits), or
+//! 1709296205123                   milliseconds (13 digits)
+//! ```
+//!
+//! [`Lattice::add_log`] reads a whole log. A line without a timestamp
+//! continues the entry above it, so a stack trace stays with the message
+//! that logged it.
+
+use crate::index::types::{Lattice, MAX_DOCUMENT_LENGTH};
+use lattice_types::{DocId, DocumentError};
+use std::io::{self, BufRead};
+
+/// Sort key holding the timestamp of log entries, in seconds since the
+/// Unix epoch.
+pub const TIMESTAMP_KEY: &str = "timestamp";
+
+/// Reads `n` ASCII digits at `*pos`, advancing past them.
+fn digits(bytes: &[u8], pos: &mut usize, n: usize) -> Option<u32> {
+    let field = bytes.get(*pos..*pos + n)?;
+    if !field.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    *pos += n;
+    Some(
+        field
+            .iter()
+            .fold(0, |acc, &b| acc * 10 + u32::from(b - b'0')),
+    )
+}
+
+/// Consumes `byte` at `*pos` if present.
+fn accept(bytes: &[u8], pos: &mut usize, byte: impl Fn(u8) -> bool) -> bool {
+    let found = bytes.get(*pos).is_some_and(|&b| byte(b));
+    if found {
+        *pos += 1;
+    }
+    found
+}
+
+/// Days from 1970-01-01 to the given civil date (proleptic Gregorian).
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Parses `YYYY-MM-DD[T ]HH:MM:SS[.fraction][zone]`.
+fn parse_iso(bytes: &[u8]) -> Option<(f64, usize)> {
+    let mut pos = 0;
+    let year = digits(bytes, &mut pos, 4)?;
+    accept(bytes, &mut pos, |b| b == b'-').then_some(())?;
+    let month = digits(bytes, &mut pos, 2)?;
+    accept(bytes, &mut pos, |b| b == b'-').then_some(())?;
+    let day = digits(bytes, &mut pos, 2)?;
+    accept(bytes, &mut pos, |b| b == b'T' || b == b' ').then_some(())?;
+    let hour = digits(bytes, &mut pos, 2)?;
+    accept(bytes, &mut pos, |b| b == b':').then_some(())?;
+    let minute = digits(bytes, &mut pos, 2)?;
+    accept(bytes, &mut pos, |b| b == b':').then_some(())?;
+    let second = digits(bytes, &mut pos, 2)?;
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 60
+    {
+        return None;
+    }
+
+    let mut fraction = 0.0;
+    if accept(bytes, &mut pos, |b| b == b'.' || b == b',') {
+        let mut scale = 0.1;
+        let start = pos;
+        while let Some(digit) = digits(bytes, &mut pos, 1) {
+            fraction += f64::from(digit) * scale;
+            scale /= 10.0;
+        }
+        if pos == start {
+            return None;
+        }
+    }
+
+    let offset = match bytes.get(pos) {
+        Some(b'Z') => {
+            pos += 1;
+            0
+        }
+        Some(&sign @ (b'+' | b'-')) => {
+            pos += 1;
+            let hours = digits(bytes, &mut pos, 2)?;
+            accept(bytes, &mut pos, |b| b == b':');
+            let minutes = digits(bytes, &mut pos, 2)?;
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+            let seconds = i64::from(hours * 3600 + minutes * 60);
+            if sign == b'+' {
+                seconds
+            } else {
+                -seconds
+            }
+        }
+        _ => 0,
+    };
+
+    let days = days_from_civil(i64::from(year), month, day);
+    let seconds = days * 86_400 + i64::from(hour * 3600 + minute * 60 + second) - offset;
+    Some((seconds as f64 + fraction, pos))
+}
+
+/// Parses Unix time: 10 digits of seconds or 13 of milliseconds.
+fn parse_epoch(bytes: &[u8]) -> Option<(f64, usize)> {
+    let len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    let value: u64 = core::str::from_utf8(&bytes[..len]).ok()?.parse().ok()?;
+    match len {
+        10 => Some((value as f64, len)),
+        13 => Some((value as f64 / 1000.0, len)),
+        _ => None,
+    }
+}
+
+/// Parses the timestamp at the start of `line`, returning it in seconds
+/// since the Unix epoch and the length of the text it takes up. The
+/// timestamp must be followed by whitespace or the end of the line.
+pub(crate) fn parse_timestamp(line: &str) -> Option<(f64, usize)> {
+    let bytes = line.as_bytes();
+    let bracketed = bytes.first() == Some(&b'[');
+    let start = usize::from(bracketed);
+    let (seconds, len) = parse_iso(&bytes[start..]).or_else(|| parse_epoch(&bytes[start..]))?;
+    let mut end = start + len;
+    if bracketed && !accept(bytes, &mut end, |b| b == b']') {
+        return None;
+    }
+    match bytes.get(end) {
+        Some(b) if !b.is_ascii_whitespace() => None,
+        _ => Some((seconds, end)),
+    }
+}
+
+impl Lattice {
+    /// Adds a log line, indexing the message after its leading timestamp
+    /// and storing the time under [`TIMESTAMP_KEY`]. A line without a
+    /// recognized timestamp is indexed whole, without a time.
+    ///
+    /// # Errors
+    ///
+    /// As for [`add`](Self::add).
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, SearchRequest, TIMESTAMP_KEY};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add_log_line("2024-03-01T12:00:00Z ERROR disk full on /var").unwrap();
+    /// engine.add_log_line("2024-03-02T12:00:00Z ERROR disk full on /home").unwrap();
+    ///
+    /// let march_2 = 1_709_337_600.0;
+    /// let request = SearchRequest::new("disk ful", 10).range(TIMESTAMP_KEY, march_2, f64::MAX);
+    /// let hits = engine.search_request(&request);
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(engine.get(hits[0].doc_id), Some("error disk full on /home"));
+    /// ```
+    pub fn add_log_line(&mut self, line: &str) -> Result<DocId, DocumentError> {
+        match parse_timestamp(line) {
+            Some((seconds, end)) => self.add_log_entry(line[end..].trim_start(), Some(seconds)),
+            None => self.add_log_entry(line, None),
+        }
+    }
+
+    fn add_log_entry(&mut self, message: &str, time: Option<f64>) -> Result<DocId, DocumentError> {
+        let doc_id = self.add(message)?;
+        if let Some(seconds) = time {
+            self.set_sort_value(doc_id, TIMESTAMP_KEY, seconds)?;
+        }
+        Ok(doc_id)
+    }
+
+    /// Adds every entry of a log, as [`add_log_line`](Self::add_log_line)
+    /// does for single lines.
+    ///
+    /// An entry starts at a line with a timestamp and takes in the lines
+    /// after it that have none. An entry outgrowing the document size
+    /// limit is split, each part keeping the entry's time. Invalid UTF-8
+    /// is replaced with U+FFFD.
+    ///
+    /// Returns the number of entries added and rejected, and the last
+    /// rejection, as [`add_batch`](Self::add_batch) does.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `reader`; entries read before it stay added.
+    pub fn add_log<R: BufRead>(
+        &mut self,
+        mut reader: R,
+    ) -> io::Result<(usize, usize, Option<DocumentError>)> {
+        let mut outcome = (0, 0, None);
+        let mut record =
+            |engine: &mut Self, entry: &str, time| match engine.add_log_entry(entry, time) {
+                Ok(_) => outcome.0 += 1,
+                Err(e) => {
+                    outcome.1 += 1;
+                    outcome.2 = Some(e);
+                }
+            };
+
+        let mut entry = String::new();
+        let mut time = None;
+        let mut started = false;
+        let mut raw = Vec::new();
+        loop {
+            raw.clear();
+            if reader.read_until(b'\n', &mut raw)? == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&raw);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if let Some((seconds, end)) = parse_timestamp(line) {
+                if started {
+                    record(self, &entry, time);
+                }
+                entry.clear();
+                entry.push_str(line[end..].trim_start());
+                time = Some(seconds);
+                started = true;
+                continue;
+            }
+            if started && entry.len() + 1 + line.len() > MAX_DOCUMENT_LENGTH {
+                record(self, &entry, time);
+                entry.clear();
+            } else if started {
+                entry.push('\n');
+            }
+            entry.push_str(line);
+            started = true;
+        }
+        if started {
+            record(self, &entry, time);
+        }
+        Ok(outcome)
+    }
+}
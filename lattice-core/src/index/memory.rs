@@ -0,0 +1,275 @@
+//! Returning unused memory to the allocator.
+//!
+//! [`Lattice::clear`] and the query path keep their buffers' capacity so
+//! that refilling the index or running the next query does not reallocate.
+//! For long-running processes that build a large transient index and then
+//! stay small, that capacity is dead weight; the methods here release it.
+//! Conversely, [`Lattice::reserve_documents`] sizes everything up front
+//! for a bulk load of known size.
+
+use crate::index::types::{Lattice, TempTrigramEntry};
+use core::mem::size_of;
+use lattice_types::DocId;
+use std::sync::Arc;
+
+impl Lattice {
+    /// Releases all unused capacity held by the index.
+    ///
+    /// Covers the document arena, posting blocks, postings, per-document
+    /// arrays, the uncommitted delta, the spelling dictionary, and the
+    /// per-query scratch buffers. Indexed data is untouched; subsequent adds
+    /// and searches simply allocate again as needed.
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_scratch();
+        self.documents.shrink_to_fit();
+        self.display.shrink_to_fit();
+        self.shrink_postings();
+        self.doc_lengths.shrink_to_fit();
+        self.doc_trigram_counts.shrink_to_fit();
+        self.positions.shrink_to_fit();
+        self.frequencies.shrink_to_fit();
+        self.fields.shrink_to_fit();
+        self.namespaces.shrink_to_fit();
+        self.sort_values.shrink_to_fit();
+        self.vectors.shrink_to_fit();
+        self.boosts.shrink_to_fit();
+        self.indexed_at.shrink_to_fit();
+        #[cfg(feature = "language-detection")]
+        self.doc_languages.shrink_to_fit();
+        if let Some(dictionary) = Arc::get_mut(&mut self.dictionary) {
+            dictionary.shrink_to_fit();
+        }
+    }
+
+    /// Reserves room for `n` more documents averaging `avg_doc_len` bytes,
+    /// so bulk ingestion does not reallocate as it goes.
+    ///
+    /// Sizes the document arena (and display text, if stored), the
+    /// per-document arrays, and the uncommitted delta for up to one trigram
+    /// per byte. Arena pages beyond the current one are allocated now
+    /// rather than as each fills; [`reclaim`](Self::reclaim) returns any
+    /// that end up unused.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.reserve_documents(10_000, 40);
+    ///
+    /// let before = engine.memory_breakdown();
+    /// for i in 0..10_000 {
+    ///     engine.add(&format!("bulk ingested record {:05}", i)).unwrap();
+    /// }
+    /// assert_eq!(engine.memory_breakdown().arena_text, before.arena_text);
+    /// ```
+    pub fn reserve_documents(&mut self, n: usize, avg_doc_len: usize) {
+        let bytes = n.saturating_mul(avg_doc_len);
+        self.documents.reserve(n, bytes);
+        if self.index_config.store_display_text {
+            self.display.reserve(n, bytes);
+        }
+        self.doc_lengths.reserve(n);
+        self.doc_trigram_counts.reserve(n);
+        if !self.boosts.is_empty() {
+            self.boosts.reserve(n);
+        }
+        if self.index_config.store_indexed_at {
+            self.indexed_at.reserve(n);
+        }
+        // A document of `len` bytes has at most `len - 2` distinct trigrams
+        self.temp_trigrams
+            .reserve(n.saturating_mul(avg_doc_len.saturating_sub(2)));
+    }
+
+    /// Releases unused capacity until the index holds at most
+    /// `target_bytes` of heap memory, or as little as it can without
+    /// dropping indexed data. Returns the number of bytes released.
+    ///
+    /// Cheapest-to-regrow buffers go first: per-query scratch space, then
+    /// arena pages kept by [`clear`](Self::clear), then spare posting
+    /// capacity, and finally everything else.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// for i in 0..1000 {
+    ///     engine.add(&format!("transient document {}", i)).unwrap();
+    /// }
+    /// engine.search("document", 10);
+    /// engine.clear();
+    ///
+    /// assert!(engine.reclaim(0) > 0);
+    /// ```
+    pub fn reclaim(&mut self, target_bytes: usize) -> usize {
+        let before = self.allocated_bytes();
+        let steps: [fn(&mut Self); 4] = [
+            Self::shrink_scratch,
+            |engine| {
+                engine.documents.release_unused_pages();
+                engine.display.release_unused_pages();
+            },
+            Self::shrink_postings,
+            Self::shrink_to_fit,
+        ];
+
+        for step in steps {
+            if self.allocated_bytes() <= target_bytes {
+                break;
+            }
+            step(self);
+        }
+
+        before.saturating_sub(self.allocated_bytes())
+    }
+
+    /// Returns the heap memory held by each component of the index.
+    ///
+    /// Counts allocated capacity rather than live data, since that is what
+    /// the process actually holds; use [`reclaim`](Self::reclaim) to bring
+    /// the two closer together. Data shared with clones or snapshots is
+    /// counted in full by each of them.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.search("hello", 10);
+    ///
+    /// let memory = engine.memory_breakdown();
+    /// assert!(memory.arena_text >= "hello world".len());
+    /// assert!(memory.total() > memory.postings);
+    /// ```
+    pub fn memory_breakdown(&self) -> MemoryBreakdown {
+        #[cfg(feature = "language-detection")]
+        let languages = self.doc_languages.capacity()
+            * size_of::<Option<crate::analyzer::language::Language>>();
+        #[cfg(not(feature = "language-detection"))]
+        let languages = 0;
+
+        MemoryBreakdown {
+            arena_text: self.documents.page_capacity_bytes(),
+            arena_spans: self.documents.span_capacity_bytes(),
+            display_text: self.display.page_capacity_bytes() + self.display.span_capacity_bytes(),
+            blocks: self.blocks.allocated_bytes(),
+            postings: self.postings.capacity() * size_of::<DocId>(),
+            doc_metadata: (self.doc_lengths.capacity() + self.doc_trigram_counts.capacity())
+                * size_of::<u32>()
+                + self.boosts.capacity() * size_of::<f32>()
+                + self.indexed_at.capacity() * size_of::<u64>()
+                + self.fields.allocated_bytes()
+                + self.namespaces.allocated_bytes()
+                + self.sort_values.allocated_bytes()
+                + self.vectors.allocated_bytes()
+                + languages,
+            positions: self.positions.allocated_bytes(),
+            term_frequencies: self.frequencies.allocated_bytes(),
+            uncommitted: (self.temp_trigrams.capacity() + self.committing.capacity())
+                * size_of::<TempTrigramEntry>(),
+            dictionary: self.dictionary.allocated_bytes(),
+            scratch: self.norm_buf.capacity()
+                + self.scratch.allocated_bytes()
+                + self.telemetry.allocated_bytes(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.memory_breakdown().total()
+    }
+
+    /// Frees the uncommitted delta's spare capacity and per-query buffers.
+    fn shrink_scratch(&mut self) {
+        self.temp_trigrams.shrink_to_fit();
+        self.scratch.shrink_to_fit();
+        self.norm_buf.shrink_to_fit();
+    }
+
+    fn shrink_postings(&mut self) {
+        // Lists shared with a clone are left alone
+        if let Some(blocks) = Arc::get_mut(&mut self.blocks) {
+            blocks.shrink_to_fit();
+        }
+        if let Some(postings) = Arc::get_mut(&mut self.postings) {
+            postings.shrink_to_fit();
+        }
+    }
+}
+
+/// Heap bytes held by each component of a [`Lattice`].
+///
+/// Returned by [`Lattice::memory_breakdown`]. Every field is allocated
+/// capacity in bytes; fixed-size struct fields are not included.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryBreakdown {
+    /// Document text pages.
+    pub arena_text: usize,
+    /// Per-document (page, offset, length) spans.
+    pub arena_spans: usize,
+    /// Original document text and its spans (see
+    /// [`IndexConfig::store_display_text`](crate::IndexConfig::store_display_text)).
+    pub display_text: usize,
+    /// Trigram → posting list directory.
+    pub blocks: usize,
+    /// Committed posting lists.
+    pub postings: usize,
+    /// Document lengths, trigram counts, boosts, indexing times, field
+    /// layouts, namespaces, sort values, vectors and detected languages.
+    pub doc_metadata: usize,
+    /// Positional index (see [`IndexConfig`](crate::IndexConfig)).
+    pub positions: usize,
+    /// Per-document trigram counts.
+    pub term_frequencies: usize,
+    /// Trigrams added since the last rebuild.
+    pub uncommitted: usize,
+    /// Spelling dictionary and BK-tree (estimated).
+    pub dictionary: usize,
+    /// Normalization and per-query buffers, and latency histograms.
+    pub scratch: usize,
+}
+
+impl MemoryBreakdown {
+    /// Returns the sum of all components.
+    pub fn total(&self) -> usize {
+        self.arena_text
+            + self.arena_spans
+            + self.display_text
+            + self.blocks
+            + self.postings
+            + self.doc_metadata
+            + self.positions
+            + self.term_frequencies
+            + self.uncommitted
+            + self.dictionary
+            + self.scratch
+    }
+}
+
+impl core::fmt::Display for MemoryBreakdown {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} bytes (text {}, spans {}, display text {}, blocks {}, postings {}, doc metadata {}, \
+             positions {}, term frequencies {}, uncommitted {}, dictionary {}, scratch {})",
+            self.total(),
+            self.arena_text,
+            self.arena_spans,
+            self.display_text,
+            self.blocks,
+            self.postings,
+            self.doc_metadata,
+            self.positions,
+            self.term_frequencies,
+            self.uncommitted,
+            self.dictionary,
+            self.scratch
+        )
+    }
+}
@@ -0,0 +1,180 @@
+//! Combining indexes.
+//!
+//! Supports map-reduce style indexing: shards are built independently
+//! (e.g. one per thread) and then folded into a single engine. The other
+//! engine's DocIds are shifted past this engine's, so every posting list of
+//! the other index sorts entirely after the matching list here and the
+//! merge is a linear pass through `merge_indexes`.
+
+use crate::index::api::now_millis;
+use crate::index::changefeed::IndexEvent;
+use crate::index::metrics::Metrics;
+use crate::index::types::{Lattice, TempTrigramEntry};
+use lattice_types::{DocId, DocumentError};
+use std::sync::Arc;
+
+impl Lattice {
+    /// Appends all documents of `other` to this index.
+    ///
+    /// Document `i` of `other` becomes document `first + i` here, where
+    /// `first` is the returned DocId (this engine's length before the
+    /// merge). Postings, document text, boosts, sort values, vectors and
+    /// spelling-dictionary frequencies are all carried over; `other` is left
+    /// untouched and this engine's configuration is kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DocumentError::CapacityExceeded` if the combined document
+    /// count does not fit in a `DocId` or the document storage, and
+    /// `DocumentError::InvalidInput` if both indexes hold vectors of
+    /// different dimensions. The index is left unchanged on error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let shards: Vec<Lattice> = std::thread::scope(|scope| {
+    ///     let handles: Vec<_> = [["apple pie", "banana split"], ["cherry tart", "apple crumble"]]
+    ///         .into_iter()
+    ///         .map(|docs| {
+    ///             scope.spawn(move || {
+    ///                 let mut shard = Lattice::new();
+    ///                 for doc in docs {
+    ///                     shard.add(doc).unwrap();
+    ///                 }
+    ///                 shard
+    ///             })
+    ///         })
+    ///         .collect();
+    ///     handles.into_iter().map(|h| h.join().unwrap()).collect()
+    /// });
+    ///
+    /// let mut engine = Lattice::new();
+    /// for shard in &shards {
+    ///     engine.merge_from(shard).unwrap();
+    /// }
+    ///
+    /// let ids: Vec<_> = engine.search("apple", 10).iter().map(|r| r.doc_id).collect();
+    /// assert_eq!(ids.len(), 2);
+    /// assert!(ids.contains(&0) && ids.contains(&3));
+    /// ```
+    pub fn merge_from(&mut self, other: &Lattice) -> Result<DocId, DocumentError> {
+        let first = self.len();
+        let base = DocId::try_from(first).map_err(|_| DocumentError::CapacityExceeded)?;
+        let fits = first
+            .checked_add(other.len())
+            .is_some_and(|total| total == 0 || DocId::try_from(total - 1).is_ok());
+        if !fits {
+            return Err(DocumentError::CapacityExceeded);
+        }
+        if !self.vectors.compatible(&other.vectors) {
+            return Err(DocumentError::InvalidInput {
+                reason: "vector dimension differs from the index's",
+            });
+        }
+        // Every later step is infallible once the text is known to fit, so
+        // a failure never leaves the columns out of step
+        self.documents
+            .check_batch(other.len())
+            .map_err(|_| DocumentError::CapacityExceeded)?;
+        if self.index_config.store_display_text {
+            self.display
+                .check_batch(other.len())
+                .map_err(|_| DocumentError::CapacityExceeded)?;
+        }
+
+        for doc_id in 0..other.len() {
+            let text = other.documents.get(doc_id as DocId).unwrap_or("");
+            self.documents
+                .push(text)
+                .map_err(|_| DocumentError::CapacityExceeded)?;
+        }
+        if self.index_config.store_display_text {
+            for doc_id in 0..other.len() {
+                let text = other.display_text(doc_id as DocId).unwrap_or("");
+                self.display
+                    .push(text)
+                    .map_err(|_| DocumentError::CapacityExceeded)?;
+            }
+        }
+        self.doc_lengths.extend_from_slice(&other.doc_lengths);
+        self.doc_trigram_counts
+            .extend_from_slice(&other.doc_trigram_counts);
+        self.merge_boosts(other, first);
+        if self.index_config.store_indexed_at {
+            if other.index_config.store_indexed_at {
+                self.indexed_at.extend_from_slice(&other.indexed_at);
+            } else {
+                self.indexed_at.resize(first + other.len(), now_millis());
+            }
+        }
+        self.fields.append(&other.fields, first, other.len());
+        self.namespaces
+            .append(&other.namespaces, first, other.len());
+        self.sort_values
+            .append(&other.sort_values, first, other.len());
+        self.vectors.append(&other.vectors, first, other.len());
+        let config = self.index_config;
+        if config.store_positions || config.store_term_frequencies {
+            for doc_id in 0..other.len() {
+                let text = other.documents.get(doc_id as DocId).unwrap_or("");
+                if config.store_positions {
+                    self.positions
+                        .push_document(text, self.tokenizer.cjk_bigrams);
+                }
+                if config.store_term_frequencies {
+                    self.frequencies
+                        .push_document(text, self.tokenizer.cjk_bigrams);
+                }
+            }
+        }
+        #[cfg(feature = "language-detection")]
+        if !other.doc_languages.is_empty() {
+            self.doc_languages.resize(first, None);
+            self.doc_languages.extend_from_slice(&other.doc_languages);
+        }
+        Metrics::add(&self.counters.documents_indexed, other.len() as u64);
+        Metrics::set(&self.counters.current_doc_count, self.len() as u64);
+
+        if other.dictionary.len() > 0 {
+            Arc::make_mut(&mut self.dictionary).merge(&other.dictionary);
+        }
+
+        if !other.blocks.is_empty() {
+            let shifted: Vec<DocId> = other.postings.iter().map(|&d| d + base).collect();
+            let (blocks, postings) =
+                Self::merge_indexes(&self.blocks, &self.postings, &other.blocks, &shifted);
+            self.install_blocks(blocks);
+            self.postings = Arc::new(postings);
+        }
+
+        if other.has_uncommitted() {
+            let delta = other.temp_trigrams.iter().chain(other.committing.iter());
+            self.temp_trigrams.extend(delta.map(|e| TempTrigramEntry {
+                trigram: e.trigram,
+                doc_id: e.doc_id + base,
+            }));
+            self.needs_rebuild = true;
+        }
+
+        for doc_id in 0..other.len() as DocId {
+            self.publish(IndexEvent::Added(base + doc_id));
+        }
+        self.report_stats_if_due();
+        Ok(base)
+    }
+
+    /// Boosts are only materialized once set, so either side may lack them.
+    fn merge_boosts(&mut self, other: &Lattice, first: usize) {
+        if self.boosts.is_empty() && other.boosts.is_empty() {
+            return;
+        }
+        self.boosts.resize(first, 1.0);
+        if other.boosts.is_empty() {
+            self.boosts.resize(first + other.len(), 1.0);
+        } else {
+            self.boosts.extend_from_slice(&other.boosts);
+        }
+    }
+}
@@ -0,0 +1,387 @@
+//! Latency and work metrics.
+//!
+//! Query and commit latencies are recorded into [`LatencyHistogram`]s with
+//! HDR-style log-linear buckets: 16 sub-buckets per power of two, so every
+//! recorded value is reported within ~6% while the whole range of `u64`
+//! nanoseconds fits in under a thousand counters.
+//!
+//! Counters live in a shared [`Metrics`] of atomics, so monitoring threads
+//! can read them through [`Lattice::shared_metrics`] while the engine keeps
+//! running. [`Lattice::metrics_snapshot`] bundles the counters and
+//! histograms; its `Display` output is the Prometheus text exposition
+//! format.
+
+use crate::index::commit::CommitReport;
+use crate::index::types::{EngineMetrics, Lattice};
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Sub-bucket precision in bits (16 sub-buckets per power of two)
+const SUB_BITS: u32 = 4;
+pub(crate) const SUB_COUNT: usize = 1 << SUB_BITS;
+/// Buckets needed to cover every `u64` value
+pub(crate) const BUCKETS: usize = (64 - SUB_BITS as usize) * SUB_COUNT + SUB_COUNT;
+
+/// Histogram of durations with bounded relative error.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    /// Bucket counts, allocated on the first recorded value
+    counts: Vec<u64>,
+    total: u64,
+    sum_nanos: u128,
+    max_nanos: u64,
+}
+
+impl LatencyHistogram {
+    /// Creates an empty histogram.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records one duration.
+    pub fn record(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        if self.counts.is_empty() {
+            self.counts = vec![0; BUCKETS];
+        }
+        self.counts[Self::bucket(nanos)] += 1;
+        self.total += 1;
+        self.sum_nanos += nanos as u128;
+        self.max_nanos = self.max_nanos.max(nanos);
+    }
+
+    /// Returns the number of recorded values.
+    #[inline]
+    pub fn len(&self) -> u64 {
+        self.total
+    }
+
+    /// Returns true if nothing has been recorded.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.total == 0
+    }
+
+    /// Returns the largest recorded duration.
+    pub fn max(&self) -> Duration {
+        Duration::from_nanos(self.max_nanos)
+    }
+
+    /// Returns the sum of all recorded durations.
+    pub fn sum(&self) -> Duration {
+        Duration::from_nanos(u64::try_from(self.sum_nanos).unwrap_or(u64::MAX))
+    }
+
+    /// Returns the mean recorded duration.
+    pub fn mean(&self) -> Duration {
+        if self.total == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.sum_nanos / self.total as u128) as u64)
+    }
+
+    /// Returns the duration at quantile `q` (0.0-1.0).
+    ///
+    /// The result is the upper bound of the bucket holding the value of
+    /// that rank, capped at [`max`](Self::max).
+    pub fn quantile(&self, q: f64) -> Duration {
+        if self.total == 0 {
+            return Duration::ZERO;
+        }
+
+        let rank = ((q.clamp(0.0, 1.0) * self.total as f64).ceil() as u64).max(1);
+        let mut seen = 0u64;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let upper = Self::bucket_upper(bucket).min(self.max_nanos);
+                return Duration::from_nanos(upper);
+            }
+        }
+        self.max()
+    }
+
+    /// Adds every value recorded in `other`.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if other.total == 0 {
+            return;
+        }
+        if self.counts.is_empty() {
+            self.counts = vec![0; BUCKETS];
+        }
+        for (a, b) in self.counts.iter_mut().zip(&other.counts) {
+            *a += b;
+        }
+        self.total += other.total;
+        self.sum_nanos += other.sum_nanos;
+        self.max_nanos = self.max_nanos.max(other.max_nanos);
+    }
+
+    /// Forgets all recorded values, keeping the bucket allocation.
+    pub fn reset(&mut self) {
+        self.counts.fill(0);
+        self.total = 0;
+        self.sum_nanos = 0;
+        self.max_nanos = 0;
+    }
+
+    fn allocated_bytes(&self) -> usize {
+        self.counts.capacity() * core::mem::size_of::<u64>()
+    }
+
+    #[inline]
+    pub(crate) fn bucket(value: u64) -> usize {
+        let magnitude = 63 - (value | 1).leading_zeros();
+        let shift = magnitude.saturating_sub(SUB_BITS);
+        ((shift as usize) << SUB_BITS) + (value >> shift) as usize
+    }
+
+    /// Largest value mapped to `bucket`.
+    pub(crate) fn bucket_upper(bucket: usize) -> u64 {
+        if bucket < 2 * SUB_COUNT {
+            return bucket as u64;
+        }
+        let shift = (bucket >> SUB_BITS) as u32 - 1;
+        let mantissa = (bucket - ((shift as usize) << SUB_BITS)) as u64;
+        (mantissa << shift) + ((1u64 << shift) - 1)
+    }
+}
+
+/// The engine's counters, readable from any thread.
+///
+/// Obtained with [`Lattice::shared_metrics`]; the engine updates them as it
+/// runs and readers see the new values without locking. Each counter is
+/// read individually, so a [`snapshot`](Self::snapshot) taken while the
+/// engine is busy may mix values from just before and just after an
+/// operation.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    pub(crate) documents_indexed: AtomicU64,
+    pub(crate) queries_executed: AtomicU64,
+    pub(crate) current_doc_count: AtomicU64,
+    pub(crate) candidates_examined: AtomicU64,
+    pub(crate) tokens_skipped: AtomicU64,
+    pub(crate) tokens_truncated: AtomicU64,
+}
+
+impl Metrics {
+    /// Returns the current value of every counter.
+    pub fn snapshot(&self) -> EngineMetrics {
+        EngineMetrics {
+            documents_indexed: self.documents_indexed.load(Ordering::Relaxed),
+            queries_executed: self.queries_executed.load(Ordering::Relaxed),
+            current_doc_count: self.current_doc_count.load(Ordering::Relaxed),
+            candidates_examined: self.candidates_examined.load(Ordering::Relaxed),
+            tokens_skipped: self.tokens_skipped.load(Ordering::Relaxed),
+            tokens_truncated: self.tokens_truncated.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns independent counters starting from this one's values.
+    pub(crate) fn detached(&self) -> Self {
+        let metrics = self.snapshot();
+        Self {
+            documents_indexed: AtomicU64::new(metrics.documents_indexed),
+            queries_executed: AtomicU64::new(metrics.queries_executed),
+            current_doc_count: AtomicU64::new(metrics.current_doc_count),
+            candidates_examined: AtomicU64::new(metrics.candidates_examined),
+            tokens_skipped: AtomicU64::new(metrics.tokens_skipped),
+            tokens_truncated: AtomicU64::new(metrics.tokens_truncated),
+        }
+    }
+
+    /// Adds `n` to `counter`.
+    #[inline]
+    pub(crate) fn add(counter: &AtomicU64, n: u64) {
+        counter.fetch_add(n, Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub(crate) fn set(counter: &AtomicU64, value: u64) {
+        counter.store(value, Ordering::Relaxed);
+    }
+}
+
+/// Histograms recorded by the engine as it runs.
+#[derive(Clone, Default)]
+pub(crate) struct Telemetry {
+    /// Wall time of each `search` call
+    pub(crate) query_latency: LatencyHistogram,
+    /// Time spent merging the delta into the posting lists
+    pub(crate) commit_latency: LatencyHistogram,
+    /// Report of the most recent merge
+    pub(crate) last_commit: Option<CommitReport>,
+}
+
+impl Telemetry {
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.query_latency.allocated_bytes() + self.commit_latency.allocated_bytes()
+    }
+}
+
+/// Point-in-time copy of the engine's metrics.
+///
+/// Returned by [`Lattice::metrics_snapshot`]. The `Display` output is in
+/// the Prometheus text exposition format, ready to serve from a `/metrics`
+/// endpoint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Counters, as returned by [`Lattice::metrics`].
+    pub metrics: EngineMetrics,
+    /// Latency of `search` calls.
+    pub query_latency: LatencyHistogram,
+    /// Duration of merges of the delta into the posting lists, whether
+    /// triggered by a query, [`Lattice::commit`] or a background commit.
+    pub commit_latency: LatencyHistogram,
+}
+
+impl Lattice {
+    /// Returns the engine's counters, shared: the returned handle keeps
+    /// seeing updates and can be moved to a monitoring thread.
+    ///
+    /// Clones and snapshots of the engine count separately from the
+    /// original.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use std::thread;
+    ///
+    /// let mut engine = Lattice::new();
+    /// let metrics = engine.shared_metrics();
+    ///
+    /// engine.add("hello world").unwrap();
+    /// engine.search("hello", 10);
+    ///
+    /// let seen = thread::spawn(move || metrics.snapshot()).join().unwrap();
+    /// assert_eq!(seen.documents_indexed, 1);
+    /// assert_eq!(seen.queries_executed, 1);
+    /// ```
+    pub fn shared_metrics(&self) -> Arc<Metrics> {
+        Arc::clone(&self.counters)
+    }
+
+    /// Returns the counters together with the latency histograms.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.search("hello", 10);
+    ///
+    /// let snapshot = engine.metrics_snapshot();
+    /// assert_eq!(snapshot.query_latency.len(), 1);
+    /// assert!(snapshot.to_string().contains("lattice_queries_total 1"));
+    /// ```
+    pub fn metrics_snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            metrics: self.metrics(),
+            query_latency: self.telemetry.query_latency.clone(),
+            commit_latency: self.telemetry.commit_latency.clone(),
+        }
+    }
+
+    /// Clears the latency histograms, the candidate and token counters and
+    /// the last commit report.
+    ///
+    /// Document and query totals are kept; they are reset only by
+    /// [`clear`](Self::clear).
+    pub fn reset_metrics(&mut self) {
+        self.telemetry.query_latency.reset();
+        self.telemetry.commit_latency.reset();
+        Metrics::set(&self.counters.candidates_examined, 0);
+        Metrics::set(&self.counters.tokens_skipped, 0);
+        Metrics::set(&self.counters.tokens_truncated, 0);
+        self.telemetry.last_commit = None;
+    }
+}
+
+const QUANTILES: [(f64, &str); 4] = [(0.5, "0.5"), (0.9, "0.9"), (0.99, "0.99"), (1.0, "1")];
+
+fn write_summary(
+    f: &mut fmt::Formatter<'_>,
+    name: &str,
+    help: &str,
+    histogram: &LatencyHistogram,
+) -> fmt::Result {
+    writeln!(f, "# HELP {} {}", name, help)?;
+    writeln!(f, "# TYPE {} summary", name)?;
+    for (q, label) in QUANTILES {
+        writeln!(
+            f,
+            "{}{{quantile=\"{}\"}} {}",
+            name,
+            label,
+            histogram.quantile(q).as_secs_f64()
+        )?;
+    }
+    writeln!(f, "{}_sum {}", name, histogram.sum().as_secs_f64())?;
+    writeln!(f, "{}_count {}", name, histogram.len())
+}
+
+impl fmt::Display for MetricsSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counters = [
+            (
+                "lattice_documents_indexed_total",
+                "Documents added since the index was created or cleared.",
+                "counter",
+                self.metrics.documents_indexed,
+            ),
+            (
+                "lattice_queries_total",
+                "Search queries executed.",
+                "counter",
+                self.metrics.queries_executed,
+            ),
+            (
+                "lattice_candidates_examined_total",
+                "Candidate documents scored by search queries.",
+                "counter",
+                self.metrics.candidates_examined,
+            ),
+            (
+                "lattice_tokens_skipped_total",
+                "Over-long tokens dropped while indexing.",
+                "counter",
+                self.metrics.tokens_skipped,
+            ),
+            (
+                "lattice_tokens_truncated_total",
+                "Over-long tokens truncated while indexing.",
+                "counter",
+                self.metrics.tokens_truncated,
+            ),
+            (
+                "lattice_documents",
+                "Documents currently in the index.",
+                "gauge",
+                self.metrics.current_doc_count,
+            ),
+        ];
+        for (name, help, kind, value) in counters {
+            writeln!(f, "# HELP {} {}", name, help)?;
+            writeln!(f, "# TYPE {} {}", name, kind)?;
+            writeln!(f, "{} {}", name, value)?;
+        }
+
+        write_summary(
+            f,
+            "lattice_query_duration_seconds",
+            "Search query latency.",
+            &self.query_latency,
+        )?;
+        write_summary(
+            f,
+            "lattice_commit_duration_seconds",
+            "Time spent merging uncommitted documents into the index.",
+            &self.commit_latency,
+        )
+    }
+}
@@ -9,18 +9,88 @@
 //! - Eliminates HashMap overhead and SmallVec heap allocations
 //!
 //! Threading:
-//! - [`Lattice`] is intentionally not `Send`/`Sync`. It uses reusable mutable
-//!   buffers that are not safe to share across threads.
+//! - [`Lattice`] is `Send` and `Sync`. Indexing and the `search` family take
+//!   `&mut self`; [`Lattice::search_with_scratch`] and [`Lattice::search_ref`]
+//!   take `&self` and can run from many threads at once.
+//! - [`Lattice::freeze`] gives a read-only [`FrozenLattice`] whose searches
+//!   all take `&self`, ready to be shared through an `Arc`.
 
 mod api;
+mod bktree;
+mod block_table;
 mod builder;
+mod changefeed;
+#[cfg(feature = "arrow")]
+mod columnar;
+mod commit;
+mod contains;
+mod dictionary;
+mod diff;
+mod doc_ids;
+mod dump;
+mod engine_builder;
+mod fields;
+mod frequency;
+mod frozen;
+mod impact;
+mod ingest;
+#[cfg(feature = "language-detection")]
+mod language;
+mod logs;
+mod memory;
+mod merge;
+mod metrics;
+mod namespace;
+mod parallel;
+#[cfg(feature = "regex")]
+mod pattern;
+mod persist;
+mod positions;
+mod request;
+mod sample;
 mod scoring;
+mod scratch;
 mod search;
+mod similarity;
+mod snapshot;
+mod sort;
 mod stats;
 mod types;
+mod vectors;
+mod verify;
 
-pub use stats::IndexStats;
-pub use types::{EngineMetrics, Lattice};
+#[cfg(feature = "testing")]
+pub(crate) use api::contains_invalid_controls;
+pub use block_table::TrigramDictionary;
+pub use changefeed::IndexEvent;
+#[cfg(feature = "arrow")]
+pub use columnar::{ColumnMapping, ColumnarError, ColumnarReport};
+pub use commit::{CommitHandle, CommitPolicy, CommitReport};
+pub use dictionary::Suggestion;
+pub use diff::IndexDiff;
+pub use doc_ids::DocIdSet;
+pub use dump::DumpFormat;
+pub use engine_builder::LatticeBuilder;
+pub use frozen::FrozenLattice;
+pub use impact::ImpactIndex;
+pub use ingest::IngestError;
+pub use logs::TIMESTAMP_KEY;
+pub use memory::MemoryBreakdown;
+pub use metrics::{LatencyHistogram, Metrics, MetricsSnapshot};
+pub use namespace::Namespace;
+pub use persist::IndexLoadError;
+pub use request::{MinimumShouldMatch, MinimumShouldMatchError, SearchRequest, SearchResponse};
+#[cfg(feature = "testing")]
+pub(crate) use sample::SplitMix64;
+pub use scoring::{LengthNorm, ScoreContext, ScoringConfig, ScoringMode, TieBreak};
+pub use scratch::SearchScratch;
+pub use snapshot::Snapshot;
+pub use sort::SortBy;
+pub use stats::{IndexStats, PostingLengths, TrigramStat};
+#[cfg(feature = "testing")]
+pub(crate) use types::MAX_DOCUMENT_LENGTH;
+pub use types::{EngineMetrics, IndexConfig, Lattice};
+pub use verify::{IntegrityIssue, VerifyReport};
 
 #[cfg(test)]
 mod tests {
@@ -99,7 +169,7 @@ mod tests {
         let mut engine = Lattice::new();
         engine.add("hello").expect("should add doc");
         engine.add("hello world").expect("should add doc");
-        assert_eq!(engine.doc_lengths.get(0).copied(), Some(5));
+        assert_eq!(engine.doc_lengths.first().copied(), Some(5));
         assert_eq!(engine.doc_lengths.get(1).copied(), Some(11));
     }
 
@@ -159,7 +229,7 @@ mod tests {
         }
         let _ = engine.search("test", 1);
 
-        for block in &engine.blocks {
+        for block in engine.blocks.iter() {
             let postings = Lattice::block_postings(block, &engine.postings);
             for w in postings.windows(2) {
                 assert!(w[0] < w[1], "Posting list must be strictly sorted");
@@ -248,8 +318,8 @@ mod tests {
         let n = RADIX_SORT_THRESHOLD * 4;
         let mut entries: Vec<TempTrigramEntry> = (0..n as u32)
             .map(|i| TempTrigramEntry {
-                trigram: Trigram(((i.wrapping_mul(7919)) % 0x00FF_FFFF) as u32),
-                doc_id: n as u32 - 1 - i,
+                trigram: Trigram((i.wrapping_mul(7919)) % 0x00FF_FFFF),
+                doc_id: (n as u32 - 1 - i) as DocId,
             })
             .collect();
 
@@ -279,7 +349,7 @@ mod tests {
         let mut entries: Vec<TempTrigramEntry> = (0..10u32)
             .map(|i| TempTrigramEntry {
                 trigram: Trigram(10 - i),
-                doc_id: i,
+                doc_id: i as DocId,
             })
             .collect();
 
@@ -294,6 +364,30 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_sort_matches_sequential() {
+        use crate::index::types::TempTrigramEntry;
+        use lattice_types::Trigram;
+
+        // Skewed top bytes, like real text, plus duplicate trigrams per doc
+        let mut entries: Vec<TempTrigramEntry> = (0..200_000u32)
+            .map(|i| TempTrigramEntry {
+                trigram: Trigram(0x61_0000 + (i.wrapping_mul(7919) % 0x1_FFFF)),
+                doc_id: (i.wrapping_mul(31) % 50_000) as DocId,
+            })
+            .collect();
+
+        let mut reference = entries.clone();
+        reference.sort_unstable_by_key(|e| (e.trigram.0, e.doc_id));
+
+        Lattice::par_sort_trigrams(&mut entries);
+        assert!(entries
+            .iter()
+            .zip(&reference)
+            .all(|(a, b)| (a.trigram, a.doc_id) == (b.trigram, b.doc_id)));
+    }
+
     #[test]
     fn compression_saves_space() {
         let mut engine = Lattice::new();
@@ -335,7 +429,8 @@ mod tests {
         let _ = engine.search("doc", 1);
 
         let stats = engine.stats();
-        let expected = engine.blocks.len() * 12 + engine.postings.len() * 4;
+        let expected = engine.blocks.len() * std::mem::size_of::<types::PostingBlock>()
+            + engine.postings.len() * std::mem::size_of::<DocId>();
         assert_eq!(stats.memory_usage_bytes(), expected);
     }
 
@@ -430,4 +525,3431 @@ mod tests {
         assert_eq!(metrics.queries_executed, 0);
         assert_eq!(metrics.current_doc_count, 0);
     }
+
+    #[test]
+    fn similarity_is_jaccard_over_trigrams() {
+        let mut engine = Lattice::new();
+        let a = engine.add("hello world").unwrap();
+        let b = engine.add("hello world").unwrap();
+        let c = engine.add("hello there").unwrap();
+        let d = engine.add("xyz").unwrap();
+
+        assert_eq!(engine.similarity(a, b), Some(1.0));
+        assert_eq!(engine.similarity(a, d), Some(0.0));
+        let partial = engine.similarity(a, c).unwrap();
+        assert!(partial > 0.0 && partial < 1.0);
+        assert_eq!(engine.similarity(a, 99), None);
+    }
+
+    #[test]
+    fn find_similar_ranks_near_duplicates_first() {
+        let mut engine = Lattice::new();
+        engine.add("the quick brown fox jumps").unwrap();
+        engine.add("the quick brown fox jumped").unwrap();
+        engine.add("the quick red fox").unwrap();
+        engine.add("completely unrelated text").unwrap();
+
+        let results = engine.find_similar(0, 10);
+        assert_eq!(results[0].doc_id, 1);
+        assert!(results.iter().all(|r| r.doc_id != 0));
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+
+        // Uncommitted documents are visible too
+        engine.add("the quick brown fox jumps!").unwrap();
+        let results = engine.find_similar(0, 1);
+        assert_eq!(results[0].doc_id, 4);
+
+        assert!(engine.find_similar(42, 10).is_empty());
+    }
+
+    #[test]
+    fn more_like_this_prefers_shared_rare_trigrams() {
+        let mut engine = Lattice::new();
+        engine.add("rust borrow checker lifetimes").unwrap();
+        engine
+            .add("the borrow checker and lifetimes in rust")
+            .unwrap();
+        engine.add("the weather today").unwrap();
+        engine.add("the weather tomorrow").unwrap();
+
+        let results = engine.more_like_this(0, 10);
+        assert_eq!(results[0].doc_id, 1);
+        assert!(results.iter().all(|r| r.doc_id != 0));
+        assert!(results.iter().all(|r| r.score > 0.0 && r.score <= 1.0));
+
+        assert!(engine.more_like_this(0, 0).is_empty());
+        assert!(engine.more_like_this(42, 10).is_empty());
+    }
+
+    #[test]
+    fn correct_replaces_unknown_terms() {
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("hello there").unwrap();
+        engine.add("help wanted").unwrap();
+
+        assert_eq!(engine.correct("helo wrld").as_deref(), Some("hello world"));
+        assert_eq!(engine.correct("HELLO World"), None); // already correct
+        assert_eq!(engine.correct("zzzzzzzz"), None); // nothing close enough
+        assert_eq!(engine.vocabulary_size(), 5);
+
+        let exact = Lattice::with_config(lattice_types::SearchConfig::exact());
+        assert_eq!(exact.correct("helo"), None);
+    }
+
+    #[test]
+    fn suggest_ranks_by_distance_then_frequency() {
+        let mut engine = Lattice::new();
+        engine.add("cart cart cart").unwrap();
+        engine.add("card").unwrap();
+        engine.add("cat").unwrap();
+
+        let suggestions = engine.suggest("carx", 10);
+        let terms: Vec<&str> = suggestions.iter().map(|s| s.term.as_str()).collect();
+        assert_eq!(terms, ["cart", "card", "cat"]);
+        assert_eq!(suggestions[0].frequency, 3);
+        assert_eq!(suggestions[2].distance, 2);
+
+        engine.clear();
+        assert!(engine.suggest("carx", 10).is_empty());
+    }
+
+    #[test]
+    fn bk_tree_finds_short_typos() {
+        let mut engine = Lattice::new();
+        engine.add("the cat sat").unwrap();
+        engine.add("on the mat").unwrap();
+
+        // "teh" shares no trigram with "the", so plain search finds nothing
+        assert!(engine.search("teh", 10).is_empty());
+        assert!(engine.suggest("teh", 5).iter().all(|s| s.term != "the"));
+
+        engine.enable_bk_tree();
+        assert_eq!(engine.suggest("teh", 1)[0].term, "the");
+        let results = engine.search("teh", 10);
+        assert_eq!(results.len(), 2);
+
+        // Tokens indexed after enabling are added to the tree
+        engine.add("xylophone music").unwrap();
+        assert_eq!(engine.suggest("xylofone", 1)[0].term, "xylophone");
+
+        engine.disable_bk_tree();
+        assert!(engine.search("teh", 10).is_empty());
+    }
+
+    #[test]
+    fn bk_tree_matches_trigram_suggestions() {
+        let mut plain = Lattice::new();
+        let mut tree = Lattice::new();
+        tree.enable_bk_tree();
+        for doc in ["apple apply ample", "maple staple", "apples applied"] {
+            plain.add(doc).unwrap();
+            tree.add(doc).unwrap();
+        }
+
+        // The BK-tree finds a superset of the trigram candidates
+        let from_plain = plain.suggest("appel", 10);
+        let from_tree = tree.suggest("appel", 10);
+        assert!(from_plain.iter().all(|s| from_tree.contains(s)));
+        assert!(from_tree.iter().all(|s| s.distance <= 2));
+    }
+
+    #[test]
+    fn custom_scorer_controls_ranking() {
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("hello world and more words").unwrap();
+
+        // Default scoring favors the shorter document
+        assert_eq!(engine.search("hello", 10)[0].doc_id, 0);
+
+        let results = engine.search_with_scorer("hello", 10, |ctx| ctx.doc_len as f32);
+        assert_eq!(results[0].doc_id, 1);
+        assert_eq!(results[0].score, 26.0);
+
+        let default = engine.search("hello world", 10);
+        let explicit = engine.search_with_scorer("hello world", 10, |ctx| ctx.default_score());
+        assert_eq!(default, explicit);
+
+        engine.search_with_scorer("hello", 10, |ctx| {
+            assert_eq!(ctx.query_trigrams, 3);
+            assert!(ctx.matches > 0);
+            0.0
+        });
+    }
+
+    #[test]
+    fn boost_multiplies_score() {
+        use lattice_types::DocumentError;
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("hello world").unwrap();
+
+        let before = engine.search("hello", 10);
+        assert_eq!(before[0].score, before[1].score);
+        assert_eq!(engine.boost(1), Some(1.0));
+
+        engine.set_boost(1, 2.0).unwrap();
+        let after = engine.search("hello", 10);
+        assert_eq!(after[0].doc_id, 1);
+        assert_eq!(after[0].score, before[0].score * 2.0);
+
+        // Documents added after boosting default to 1.0
+        let id = engine.add("hello again").unwrap();
+        assert_eq!(engine.boost(id), Some(1.0));
+
+        assert_eq!(
+            engine.set_boost(99, 1.0),
+            Err(DocumentError::NotFound { doc_id: 99 })
+        );
+        assert!(engine.set_boost(0, f32::NAN).is_err());
+        assert!(engine.set_boost(0, -1.0).is_err());
+        assert_eq!(engine.boost(99), None);
+    }
+
+    #[test]
+    fn handles_detect_reuse_after_clear() {
+        use lattice_types::DocumentError;
+        let mut engine = Lattice::new();
+        let id = engine.add("first generation").unwrap();
+        let handle = engine.handle(id).unwrap();
+        assert_eq!(handle.generation, 0);
+        assert_eq!(engine.resolve(handle), Ok(id));
+        assert!(engine.handle(5).is_none());
+
+        engine.clear();
+        engine.add("second generation").unwrap();
+        assert_eq!(engine.generation(), 1);
+
+        // Same DocId, different document: the old handle must not resolve
+        assert!(matches!(
+            engine.resolve(handle),
+            Err(DocumentError::StaleHandle {
+                current_generation: 1,
+                ..
+            })
+        ));
+        let fresh = engine.handle(id).unwrap();
+        assert_ne!(fresh, handle);
+        assert_eq!(engine.resolve(fresh), Ok(id));
+        assert!(matches!(
+            engine.resolve(lattice_types::DocHandle::new(1, 7)),
+            Err(DocumentError::NotFound { doc_id: 7 })
+        ));
+    }
+
+    #[test]
+    fn shrink_and_reclaim_release_memory() {
+        let mut engine = Lattice::new();
+        engine.enable_bk_tree();
+        for i in 0..2000 {
+            engine
+                .add(&format!("transient document number {}", i))
+                .unwrap();
+        }
+        engine.search("document", 10);
+
+        engine.clear();
+        let retained = engine.allocated_bytes();
+        assert_eq!(engine.reclaim(usize::MAX), 0);
+
+        let released = engine.reclaim(0);
+        assert!(released > 0);
+        assert_eq!(engine.allocated_bytes(), retained - released);
+        assert_eq!(engine.reclaim(0), 0);
+
+        // The index is still fully usable afterwards
+        engine.add("hello world").unwrap();
+        engine.shrink_to_fit();
+        assert_eq!(engine.search("hello", 10)[0].doc_id, 0);
+        assert_eq!(engine.get(0), Some("hello world"));
+    }
+
+    #[test]
+    fn reclaim_stops_at_target() {
+        let mut engine = Lattice::new();
+        for i in 0..500 {
+            engine.add(&format!("document {}", i)).unwrap();
+        }
+        engine.search("document", 10);
+        let before = engine.allocated_bytes();
+
+        // A target just below the current size needs only the cheap steps
+        let released = engine.reclaim(before - 1);
+        assert!(released > 0);
+        assert!(engine.allocated_bytes() < before);
+        assert_eq!(engine.search("document 42", 1)[0].doc_id, 42);
+    }
+
+    #[test]
+    fn memory_breakdown_covers_components() {
+        let mut engine = Lattice::new();
+        let empty = engine.memory_breakdown();
+        assert_eq!(empty.postings, 0);
+        assert_eq!(empty.uncommitted, 0);
+
+        for i in 0..100 {
+            engine.add(&format!("capacity planning {}", i)).unwrap();
+        }
+        let pending = engine.memory_breakdown();
+        assert!(pending.uncommitted > 0);
+        assert!(pending.doc_metadata >= 100 * 2 * std::mem::size_of::<u32>());
+        assert!(pending.dictionary > 0);
+
+        engine.search("planning", 10);
+        let built = engine.memory_breakdown();
+        assert!(built.blocks > 0);
+        assert!(built.postings >= engine.stats().total_postings * std::mem::size_of::<DocId>());
+        assert!(built.arena_spans > 0);
+        assert_eq!(built.total(), engine.allocated_bytes());
+        assert!(built.to_string().starts_with(&built.total().to_string()));
+    }
+
+    #[test]
+    fn verify_accepts_valid_index() {
+        let mut engine = Lattice::new();
+        assert!(engine.verify().is_ok());
+
+        for i in 0..200 {
+            engine.add(&format!("verified document {}", i)).unwrap();
+        }
+        assert!(engine.verify().is_ok(), "pending delta");
+        engine.search("verified", 10);
+        engine.add("one more uncommitted").unwrap();
+        engine.set_boost(3, 2.0).unwrap();
+
+        let report = engine.verify();
+        assert!(report.is_ok(), "{}", report);
+        assert_eq!(report.documents_checked, 201);
+        assert_eq!(report.postings_checked, engine.postings.len());
+    }
+
+    #[test]
+    fn verify_reports_corruption() {
+        use super::verify::IntegrityIssue;
+
+        let mut engine = Lattice::new();
+        for doc in ["alpha beta", "beta gamma", "gamma delta"] {
+            engine.add(doc).unwrap();
+        }
+        engine.search("beta", 10);
+
+        let multi = engine.blocks.iter().position(|b| b.len > 1).unwrap();
+        let offset = engine.blocks[multi].offset as usize;
+        let postings = std::sync::Arc::make_mut(&mut engine.postings);
+        postings.swap(offset, offset + 1);
+        postings[0] = 99;
+        let mut blocks = engine.blocks.to_vec();
+        blocks.swap(0, 1);
+        let last = blocks.len() - 1;
+        blocks[last].len += 10;
+        engine.install_blocks(blocks);
+        engine.doc_lengths[1] += 1;
+        engine.doc_trigram_counts.pop();
+
+        let report = engine.verify();
+        assert!(!report.is_ok());
+        let has = |f: &dyn Fn(&IntegrityIssue) -> bool| report.issues.iter().any(f);
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::BlocksUnsorted { block: 1 }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::PostingsUnsorted { .. }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::BlockOutOfBounds { .. }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::DanglingPosting { doc_id: 99, .. }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::LengthMismatch {
+                array: "doc_trigram_counts",
+                ..
+            }
+        )));
+        assert!(has(&|i| matches!(
+            i,
+            IntegrityIssue::DocLengthMismatch { doc_id: 1, .. }
+        )));
+        assert!(report.to_string().contains("issues"));
+    }
+
+    fn saved(engine: &Lattice) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        engine.save(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn save_load_roundtrip() {
+        let mut engine = Lattice::with_config(lattice_types::SearchConfig::fuzzy());
+        for i in 0..300 {
+            engine
+                .add(&format!("persisted document {} about trigrams", i))
+                .unwrap();
+        }
+        engine.search("persisted", 10);
+        engine.add("uncommitted tail document").unwrap();
+        engine.set_boost(7, 3.0).unwrap();
+
+        let mut loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.len(), engine.len());
+        assert_eq!(loaded.config, engine.config);
+        assert_eq!(loaded.get(300), Some("uncommitted tail document"));
+        assert_eq!(loaded.boost(7), Some(3.0));
+        assert_eq!(loaded.vocabulary_size(), engine.vocabulary_size());
+        // The tail is saved merged into the posting lists
+        assert!(!loaded.has_uncommitted());
+        engine.commit();
+        for query in ["document 42", "tail", "trigrams"] {
+            assert_eq!(
+                loaded.search(query, 5),
+                engine.search(query, 5),
+                "{}",
+                query
+            );
+        }
+
+        let empty = Lattice::load(saved(&Lattice::new()).as_slice()).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn identical_builds_save_identical_bytes() {
+        let docs: Vec<String> = (0..200)
+            .map(|i| format!("replicated record {} in shard {}", i, i % 5))
+            .collect();
+
+        // One replica queries and commits along the way, the other never does
+        let mut busy = Lattice::new();
+        let mut quiet = Lattice::new();
+        for (i, doc) in docs.iter().enumerate() {
+            busy.add(doc).unwrap();
+            quiet.add(doc).unwrap();
+            if i % 37 == 0 {
+                busy.search("shard 3", 5);
+            }
+            if i % 90 == 0 {
+                busy.commit();
+            }
+        }
+        busy.search("record", 1);
+        busy.add("tail").unwrap();
+        quiet.add("tail").unwrap();
+
+        assert!(busy.has_uncommitted());
+        assert_eq!(saved(&busy), saved(&quiet));
+        assert_eq!(busy.build_fingerprint(), quiet.build_fingerprint());
+
+        let loaded = Lattice::load(saved(&busy).as_slice()).unwrap();
+        assert!(!loaded.has_uncommitted());
+        assert_eq!(loaded.build_fingerprint(), quiet.build_fingerprint());
+
+        quiet.set_boost(3, 2.0).unwrap();
+        assert_ne!(busy.build_fingerprint(), quiet.build_fingerprint());
+        busy.add("extra").unwrap();
+        assert_ne!(busy.build_fingerprint(), loaded.build_fingerprint());
+    }
+
+    #[test]
+    fn load_rejects_damaged_files() {
+        use super::persist::FORMAT_VERSION;
+
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("checksummed sections").unwrap();
+        engine.search("hello", 1);
+        let bytes = saved(&engine);
+
+        for cut in [0, 5, 17, 40, bytes.len() - 1] {
+            assert!(
+                matches!(
+                    Lattice::load(&bytes[..cut]),
+                    Err(IndexLoadError::Truncated { .. })
+                ),
+                "cut at {}",
+                cut
+            );
+        }
+
+        let mut flipped = bytes.clone();
+        let last = flipped.len() - 1;
+        flipped[last] ^= 0x01;
+        assert!(matches!(
+            Lattice::load(flipped.as_slice()),
+            Err(IndexLoadError::ChecksumMismatch {
+                section: "uncommitted",
+                ..
+            }) | Err(IndexLoadError::ChecksumMismatch {
+                section: "postings",
+                ..
+            }) | Err(IndexLoadError::ChecksumMismatch {
+                section: "namespaces",
+                ..
+            }) | Err(IndexLoadError::ChecksumMismatch {
+                section: "vectors",
+                ..
+            })
+        ));
+
+        let mut foreign = bytes.clone();
+        foreign[0] = b'X';
+        assert!(matches!(
+            Lattice::load(foreign.as_slice()),
+            Err(IndexLoadError::BadMagic)
+        ));
+
+        // Rewrite the version and re-seal the header checksum
+        let mut newer = bytes.clone();
+        newer[8..12].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        let crc = lattice_types::checksum::crc32(&newer[..13]);
+        newer[13..17].copy_from_slice(&crc.to_le_bytes());
+        let err = Lattice::load(newer.as_slice())
+            .err()
+            .expect("newer format must be rejected");
+        assert!(matches!(err, IndexLoadError::UnsupportedVersion { .. }));
+        assert!(err.to_string().contains("version"));
+    }
+
+    #[test]
+    fn snapshot_is_isolated_from_writes() {
+        let mut engine = Lattice::new();
+        for i in 0..100 {
+            engine.add(&format!("snapshot document {}", i)).unwrap();
+        }
+        engine.search("snapshot", 1);
+
+        let mut snapshot = engine.snapshot();
+        assert!(std::sync::Arc::ptr_eq(&engine.postings, &snapshot.postings));
+        let before = snapshot.search("document", 200);
+
+        // Bulk ingestion past the lazy-rebuild threshold, then a clear
+        for i in 0..10_000 {
+            engine.add(&format!("ingested document {}", i)).unwrap();
+        }
+        assert_eq!(engine.search("document", 20_000).len(), 10_100);
+        assert_eq!(snapshot.search("document", 200), before);
+        assert_eq!(snapshot.len(), 100);
+
+        engine.clear();
+        engine.add("fresh").unwrap();
+        assert_eq!(snapshot.get(99), Some("snapshot document 99"));
+        assert_eq!(snapshot.vocabulary_size(), 102);
+        assert!(snapshot.verify().is_ok());
+
+        // Snapshots can be searched from other threads
+        std::thread::scope(|scope| {
+            let mut copy = snapshot.clone();
+            scope.spawn(move || assert_eq!(copy.search("document 7", 1)[0].doc_id, 7));
+        });
+
+        let mut fork = snapshot.into_lattice();
+        fork.add("forked document").unwrap();
+        assert_eq!(fork.search("forked", 1)[0].doc_id, 100);
+    }
+
+    #[test]
+    fn merge_from_remaps_doc_ids() {
+        let mut left = Lattice::new();
+        left.add("alpha document").unwrap();
+        left.add("shared words here").unwrap();
+        left.search("alpha", 1); // commit left
+
+        let mut right = Lattice::new();
+        for i in 0..50 {
+            right.add(&format!("shared words {}", i)).unwrap();
+        }
+        right.search("shared", 1); // commit right
+        right.add("uncommitted omega").unwrap();
+        right.set_boost(1, 4.0).unwrap();
+
+        assert_eq!(left.merge_from(&right), Ok(2));
+        assert_eq!(left.len(), 53);
+        assert_eq!(left.get(52), Some("uncommitted omega"));
+        assert_eq!(left.boost(0), Some(1.0));
+        assert_eq!(left.boost(3), Some(4.0));
+        assert!(left.verify().is_ok(), "{}", left.verify());
+
+        assert_eq!(left.search("omega", 1)[0].doc_id, 52);
+        assert_eq!(left.search("alpha", 1)[0].doc_id, 0);
+
+        // Same results as indexing every document into one engine, with
+        // the same document left uncommitted
+        let mut sequential = Lattice::new();
+        for doc_id in 0..52 {
+            sequential.add(left.get(doc_id).unwrap()).unwrap();
+        }
+        sequential.search("commit", 1);
+        sequential.add("uncommitted omega").unwrap();
+        sequential.set_boost(3, 4.0).unwrap();
+        for query in ["shared words", "words 12", "omega"] {
+            assert_eq!(left.search(query, 100), sequential.search(query, 100));
+        }
+        assert_eq!(left.suggest("sharde", 1)[0].frequency, 51);
+
+        // Merging into an empty engine reproduces the source
+        let mut empty = Lattice::new();
+        assert_eq!(empty.merge_from(&right), Ok(0));
+        assert_eq!(empty.search("shared 7", 3), right.search("shared 7", 3));
+        assert_eq!(empty.merge_from(&Lattice::new()), Ok(51));
+    }
+
+    #[test]
+    fn build_parallel_matches_sequential() {
+        let docs: Vec<String> = (0..3000)
+            .map(|i| format!("parallel document {} with tail {}", i, i % 17))
+            .collect();
+
+        let mut sequential = Lattice::new();
+        for doc in &docs {
+            sequential.add(doc).unwrap();
+        }
+
+        for threads in [0, 1, 3, 8] {
+            let mut parallel = Lattice::build_parallel(&docs, threads).unwrap();
+            assert_eq!(parallel.len(), docs.len());
+            assert!(parallel.temp_trigrams.is_empty());
+            assert!(parallel.verify().is_ok());
+            assert_eq!(parallel.get(2999), sequential.get(2999));
+            assert_eq!(parallel.vocabulary_size(), sequential.vocabulary_size());
+            for query in ["document 1234", "tail 5", "paralel"] {
+                assert_eq!(
+                    parallel.search(query, 20),
+                    sequential.search(query, 20),
+                    "{} threads, {}",
+                    threads,
+                    query
+                );
+            }
+        }
+
+        let empty: [&str; 0] = [];
+        assert!(Lattice::build_parallel(&empty, 4).unwrap().is_empty());
+        assert!(matches!(
+            Lattice::build_parallel(&["ok", "bad\u{0}doc", "fine"], 2),
+            Err(lattice_types::DocumentError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn manual_commit_policy_defers_indexing() {
+        use super::commit::CommitPolicy;
+
+        let mut engine = Lattice::new();
+        assert_eq!(engine.commit_policy(), CommitPolicy::Auto);
+        engine.add("committed document").unwrap();
+        engine.commit();
+        assert!(engine.temp_trigrams.is_empty());
+
+        engine.set_commit_policy(CommitPolicy::Manual);
+        engine.add("pending document").unwrap();
+        assert!(engine.has_uncommitted());
+
+        // Queries neither rebuild nor see the pending document
+        let ids: Vec<_> = engine
+            .search("document", 10)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        assert_eq!(ids, vec![0]);
+        assert!(engine.needs_rebuild);
+        assert_eq!(engine.more_like_this(0, 10), Vec::new());
+
+        engine.commit();
+        assert!(!engine.has_uncommitted());
+        assert_eq!(engine.search("pending", 10)[0].doc_id, 1);
+        assert_eq!(engine.more_like_this(0, 10)[0].doc_id, 1);
+
+        // Small deltas are fully merged too, not kept for scanning
+        engine.set_commit_policy(CommitPolicy::Auto);
+        engine.add("third document").unwrap();
+        engine.commit();
+        assert!(engine.temp_trigrams.is_empty());
+        assert!(engine.verify().is_ok());
+    }
+
+    #[test]
+    fn background_commit_swaps_in_merged_index() {
+        let mut engine = Lattice::new();
+        engine.add("alpha document").unwrap();
+        engine.commit();
+        engine.add("bravo document").unwrap();
+        engine.add("charlie document").unwrap();
+
+        let handle = engine.commit_in_background().unwrap();
+        assert!(engine.commit_in_background().is_none());
+        assert!(engine.has_uncommitted());
+
+        // In-flight documents stay searchable, and new adds form a fresh delta
+        assert_eq!(engine.search("bravo", 10)[0].doc_id, 1);
+        engine.add("delta document").unwrap();
+        assert_eq!(engine.search("document", 10).len(), 4);
+        assert!(engine.verify().is_ok());
+
+        assert!(engine.finish_commit(handle).is_some());
+        assert!(engine.committing.is_empty());
+        assert_eq!(engine.search("charlie", 10)[0].doc_id, 2);
+        assert_eq!(engine.search("delta", 10)[0].doc_id, 3);
+
+        engine.commit();
+        assert!(!engine.has_uncommitted());
+        assert_eq!(engine.search("document", 10).len(), 4);
+        assert!(engine.verify().is_ok());
+    }
+
+    #[test]
+    fn background_commit_is_discarded_when_superseded() {
+        let mut engine = Lattice::new();
+        engine.add("alpha document").unwrap();
+
+        // A foreground commit absorbs the frozen delta
+        let handle = engine.commit_in_background().unwrap();
+        engine.commit();
+        assert!(engine.finish_commit(handle).is_none());
+        assert_eq!(engine.search("alpha", 10)[0].doc_id, 0);
+        assert!(engine.verify().is_ok());
+
+        // Clearing drops it
+        engine.add("bravo document").unwrap();
+        let handle = engine.commit_in_background().unwrap();
+        engine.clear();
+        assert!(engine.finish_commit(handle).is_none());
+        assert!(engine.is_empty());
+        assert!(engine.search("bravo", 10).is_empty());
+
+        // Merging another index returns it to the delta
+        engine.add("charlie document").unwrap();
+        let handle = engine.commit_in_background().unwrap();
+        let mut other = Lattice::new();
+        other.add("echo document").unwrap();
+        other.commit();
+        engine.merge_from(&other).unwrap();
+        assert!(engine.finish_commit(handle).is_none());
+        assert!(engine.has_uncommitted());
+        assert_eq!(engine.search("charlie", 10)[0].doc_id, 0);
+        assert_eq!(engine.search("echo", 10)[0].doc_id, 1);
+        assert!(engine.verify().is_ok());
+    }
+
+    #[test]
+    fn real_time_policy_searches_delta_without_merging() {
+        use super::commit::CommitPolicy;
+
+        let mut engine = Lattice::new();
+        engine.set_commit_policy(CommitPolicy::RealTime);
+        let mut reference = Lattice::new();
+
+        // Interleave adds and queries so the delta is sorted incrementally
+        for round in 0..5 {
+            for i in 0..20 {
+                let doc = format!("round {} item {} {}", round, i, i * 7919 % 101);
+                engine.add(&doc).unwrap();
+                reference.add(&doc).unwrap();
+            }
+            assert!(!engine.search("item 1", 5).is_empty());
+            assert!(engine.blocks.is_empty());
+            assert!(engine
+                .temp_trigrams
+                .windows(2)
+                .all(|w| { (w[0].trigram.0, w[0].doc_id) <= (w[1].trigram.0, w[1].doc_id) }));
+        }
+        assert!(engine.has_uncommitted());
+
+        // The delta applies the overlap ratio as a plain count, so it may
+        // keep a few more weak matches than the posting-list intersection
+        reference.commit();
+        for query in ["round 3 item 17", "item 4", "round"] {
+            let got = engine.search(query, 200);
+            let want = reference.search(query, 200);
+            assert_eq!(got[0].score, want[0].score, "query {:?}", query);
+            assert!(want.iter().all(|r| got.contains(r)), "query {:?}", query);
+        }
+
+        engine.commit();
+        assert!(!engine.has_uncommitted());
+        assert_eq!(engine.search("round 4 item 19", 1)[0].doc_id, 99);
+        assert!(engine.verify().is_ok());
+    }
+
+    #[test]
+    fn delta_only_query_trigrams_do_not_break_intersection() {
+        let mut engine = Lattice::new();
+        for i in 0..50 {
+            engine.add(&format!("zzz filler {}", i)).unwrap();
+        }
+        engine.add("abc").unwrap();
+        engine.commit();
+
+        // Most of the query's trigrams exist only in the uncommitted delta
+        engine.add("abcdefgh").unwrap();
+        assert_eq!(engine.search("abcdefgh", 5)[0].doc_id, 51);
+    }
+
+    #[test]
+    fn trigram_stats_cover_committed_and_pending() {
+        use lattice_types::Trigram;
+
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("hello there").unwrap();
+        engine.add("help").unwrap();
+        engine.commit();
+        engine.add("hello zebra").unwrap();
+
+        let hel = Trigram::from_str("hel");
+        let zeb = Trigram::from_str("zeb");
+        assert_eq!(engine.trigram_df(hel), 4);
+        assert_eq!(engine.trigram_df(zeb), 1);
+        assert_eq!(engine.trigram_df(Trigram::from_str("qqq")), 0);
+
+        let stats = engine.trigram_stats();
+        assert!(stats.windows(2).all(|w| w[0].trigram.0 < w[1].trigram.0));
+        let stat = |t: Trigram| *stats.iter().find(|s| s.trigram == t).unwrap();
+        assert_eq!(stat(hel).posting_len, 3);
+        assert_eq!(stat(hel).doc_frequency, 4);
+        assert_eq!(stat(zeb).posting_len, 0);
+        for s in &stats {
+            assert_eq!(s.doc_frequency, engine.trigram_df(s.trigram));
+        }
+
+        let heaviest = engine.heaviest_trigrams(2);
+        assert_eq!(heaviest.len(), 2);
+        assert_eq!(heaviest[0].trigram, hel);
+        assert!(heaviest[0].posting_len >= heaviest[1].posting_len);
+        assert_eq!(heaviest[0].to_string(), "\"hel\" df=4 postings=3");
+        assert_eq!(engine.heaviest_trigrams(1000).len(), stats.len());
+    }
+
+    #[test]
+    fn stats_report_posting_length_distribution() {
+        let empty = Lattice::new().stats();
+        assert_eq!(empty.posting_lengths, stats::PostingLengths::default());
+        assert_eq!(empty.to_string(), "0 docs, 0 trigrams, 0 postings");
+
+        let mut engine = Lattice::new();
+        for i in 0..100 {
+            engine.add(&format!("common {:03}", i)).unwrap();
+        }
+        engine.commit();
+
+        let stats = engine.stats();
+        let lengths = stats.posting_lengths;
+        let mut lens: Vec<usize> = engine.blocks.iter().map(|b| b.len as usize).collect();
+        lens.sort_unstable();
+        assert_eq!(lengths.max, 100);
+        assert_eq!(lengths.max, *lens.last().unwrap());
+        assert!(lengths.p50 <= lengths.p90 && lengths.p90 <= lengths.p99);
+        assert!(lengths.p99 <= lengths.max);
+        assert_eq!(lengths.singletons, lens.iter().filter(|&&l| l == 1).count());
+        assert!(lengths.singletons > 0);
+        assert_eq!(
+            lengths.avg_postings_per_doc,
+            stats.total_postings as f32 / 100.0
+        );
+
+        let text = stats.to_string();
+        assert!(text.contains("max=100"), "{}", text);
+        assert!(text.contains("singleton trigrams"), "{}", text);
+    }
+
+    #[test]
+    fn metrics_snapshot_tracks_latency_and_candidates() {
+        let mut engine = Lattice::new();
+        for i in 0..50 {
+            engine.add(&format!("metric document {}", i)).unwrap();
+        }
+
+        engine.search("document", 10);
+        engine.search("zzzz", 10);
+        let snapshot = engine.metrics_snapshot();
+        assert_eq!(snapshot.query_latency.len(), 2);
+        assert_eq!(snapshot.commit_latency.len(), 1);
+        assert_eq!(snapshot.metrics.candidates_examined, 50);
+        assert!(snapshot.query_latency.quantile(0.99) <= snapshot.query_latency.max());
+
+        // Background commits are timed on the worker and recorded on install
+        engine.add("late document").unwrap();
+        let handle = engine.commit_in_background().unwrap();
+        assert!(engine.finish_commit(handle).is_some());
+        assert_eq!(engine.metrics_snapshot().commit_latency.len(), 2);
+
+        let text = engine.metrics_snapshot().to_string();
+        assert!(text.contains("# TYPE lattice_queries_total counter\nlattice_queries_total 2\n"));
+        assert!(text.contains("lattice_candidates_examined_total 50\n"));
+        assert!(text.contains("lattice_query_duration_seconds{quantile=\"0.99\"}"));
+        assert!(text.contains("lattice_commit_duration_seconds_count 2\n"));
+
+        engine.reset_metrics();
+        let snapshot = engine.metrics_snapshot();
+        assert!(snapshot.query_latency.is_empty());
+        assert_eq!(snapshot.metrics.candidates_examined, 0);
+        assert_eq!(snapshot.metrics.queries_executed, 2);
+    }
+
+    #[test]
+    fn commit_reports_describe_the_merge() {
+        let mut engine = Lattice::new();
+        assert_eq!(engine.commit(), Default::default());
+        assert!(engine.last_commit_report().is_none());
+
+        engine.add("hello world").unwrap();
+        let first = engine.commit();
+        assert!(first.delta_entries > 0);
+        assert_eq!(first.new_blocks, first.total_blocks);
+        assert_eq!(first.postings_added, first.delta_entries);
+        assert_eq!(first.total_postings, engine.postings.len());
+        assert_eq!(engine.last_commit_report(), Some(first));
+
+        engine.add("hello there").unwrap();
+        let handle = engine.commit_in_background().unwrap();
+        let second = engine.finish_commit(handle).unwrap();
+        assert_eq!(second.postings_added, second.delta_entries);
+        assert_eq!(
+            second.total_postings,
+            first.total_postings + second.postings_added
+        );
+        assert!(second.new_blocks < second.delta_entries);
+        assert_eq!(second.total_blocks, engine.blocks.len());
+        assert_eq!(engine.last_commit_report(), Some(second));
+        assert!(second
+            .to_string()
+            .starts_with(&format!("merged {} entries", second.delta_entries)));
+
+        // Query-triggered merges are reported too
+        let mut lazy = Lattice::new();
+        lazy.add("goodbye").unwrap();
+        lazy.search("goodbye", 1);
+        let report = lazy.last_commit_report().unwrap();
+        assert_eq!(report.total_blocks, lazy.blocks.len());
+        assert_eq!(lazy.metrics_snapshot().commit_latency.len(), 1);
+    }
+
+    #[test]
+    fn diacritic_folding_applies_to_documents_queries_and_persistence() {
+        use crate::analyzer::normalizer::NormalizerConfig;
+
+        let mut engine = Lattice::with_normalizer(NormalizerConfig::strip_diacritics());
+        engine.add("Déjà vu at the café").unwrap();
+        engine.add("cafe racer").unwrap();
+        assert_eq!(engine.get(0), Some("deja vu at the cafe"));
+
+        let ids = |e: &mut Lattice, q: &str| {
+            let mut ids: Vec<_> = e.search(q, 10).iter().map(|r| r.doc_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(ids(&mut engine, "CAFÉ"), vec![0, 1]);
+        assert_eq!(ids(&mut engine, "deja"), vec![0]);
+
+        let mut bytes = Vec::new();
+        engine.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(
+            loaded.normalizer_config(),
+            NormalizerConfig::strip_diacritics()
+        );
+        assert_eq!(ids(&mut loaded, "déjà"), vec![0]);
+
+        let config = NormalizerConfig {
+            remove_format_chars: true,
+            ..NormalizerConfig::new()
+        };
+        let mut clean = Lattice::with_normalizer(config);
+        clean.add("zero\u{200B}width").unwrap();
+        let mut bytes = Vec::new();
+        clean.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.normalizer_config(), config);
+        assert_eq!(ids(&mut loaded, "zero\u{FEFF}width"), vec![0]);
+
+        // Without folding, accents are part of the text
+        let mut plain = Lattice::new();
+        plain.add("Déjà vu").unwrap();
+        assert_eq!(plain.get(0), Some("déjà vu"));
+    }
+
+    #[cfg(feature = "language-detection")]
+    #[test]
+    fn language_detection_picks_normalizer_per_document() {
+        use crate::analyzer::language::{Language, LanguageNormalizers};
+        use crate::analyzer::normalizer::NormalizerConfig;
+
+        let mut engine = Lattice::new();
+        engine.add("Übung before detection").unwrap();
+        engine.enable_language_detection(
+            LanguageNormalizers::new(NormalizerConfig::strip_diacritics())
+                .with(Language::GERMAN, NormalizerConfig::default()),
+        );
+
+        let fr = engine
+            .add("Les élèves sont allés à la bibliothèque pour étudier l'histoire de la région.")
+            .unwrap();
+        let de = engine
+            .add("Die Schüler sind in die Bibliothek gegangen, um die Geschichte zu lernen.")
+            .unwrap();
+        let short = engine.add("Ärger").unwrap();
+
+        assert_eq!(engine.doc_language(0), None);
+        assert_eq!(engine.doc_language(fr), Some(Language::FRENCH));
+        assert_eq!(engine.doc_language(de), Some(Language::GERMAN));
+        assert_eq!(engine.doc_language(short), None);
+        assert!(engine.get(fr).unwrap().starts_with("les eleves sont alles"));
+        assert!(engine.get(de).unwrap().contains("schüler"));
+        assert_eq!(engine.get(short), Some("arger"));
+        assert_eq!(engine.get(0), Some("Übung before detection"));
+
+        // Undetected (short) queries use the default, folding configuration
+        assert_eq!(engine.search("élèves", 1)[0].doc_id, fr);
+
+        let mut other = Lattice::new();
+        other.add("plain").unwrap();
+        let base = engine.merge_from(&other).unwrap();
+        assert_eq!(engine.doc_language(base), None);
+        assert_eq!(engine.doc_language(de), Some(Language::GERMAN));
+
+        engine.disable_language_detection();
+        engine
+            .add("Ça va très bien aujourd'hui, merci beaucoup pour votre question.")
+            .unwrap();
+        assert_eq!(engine.doc_language(base + 1), None);
+        assert!(engine.get(base + 1).unwrap().contains("très"));
+    }
+
+    #[cfg(feature = "unicode-normalization")]
+    #[test]
+    fn unicode_form_survives_save_and_load() {
+        use crate::analyzer::normalizer::{NormalizerConfig, UnicodeForm};
+
+        let config = NormalizerConfig {
+            unicode_form: UnicodeForm::Nfkc,
+            ..NormalizerConfig::new()
+        };
+        let mut engine = Lattice::with_normalizer(config);
+        engine.add("\u{fb01}nal caf\u{e9}").unwrap();
+        assert_eq!(engine.search("final cafe\u{301}", 1).len(), 1);
+
+        let mut bytes = Vec::new();
+        engine.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.normalizer_config(), config);
+        assert_eq!(loaded.search("\u{fb01}nal", 1).len(), 1);
+    }
+
+    #[test]
+    fn tokenizer_limits_long_tokens_during_indexing() {
+        use crate::analyzer::delimiter::DelimiterSet;
+        use crate::analyzer::tokenizer::{LongTokenPolicy, TokenizerConfig};
+
+        let blob = "QUJDREVGR0hJSktMTU5PUFFSU1RVVldYWVo0123456789abcdef";
+        let config = TokenizerConfig::new()
+            .split_on(DelimiterSet::SEPARATORS)
+            .limit_token_len(24, LongTokenPolicy::Skip);
+        let mut engine = Lattice::with_tokenizer(config);
+        let doc = engine
+            .add(&format!("upload,payload={} status:done", blob))
+            .unwrap();
+        assert_eq!(engine.get(doc), Some("upload status done"));
+        assert_eq!(engine.metrics().tokens_skipped, 1);
+        assert!(engine.search(&blob[..20], 10).is_empty());
+
+        // Queries go through the same delimiters
+        let hits = engine.search("upload;status", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].doc_id, doc);
+
+        let truncating = TokenizerConfig {
+            long_token_policy: LongTokenPolicy::Truncate,
+            ..config
+        };
+        let mut engine = Lattice::with_tokenizer(truncating);
+        engine.add(&format!("blob {}", blob)).unwrap();
+        let expected = format!("blob {}", blob[..24].to_lowercase());
+        assert_eq!(engine.get(0), Some(expected.as_str()));
+        assert_eq!(engine.metrics().tokens_truncated, 1);
+        assert!(engine
+            .metrics_snapshot()
+            .to_string()
+            .contains("lattice_tokens_truncated_total 1"));
+
+        let mut bytes = Vec::new();
+        engine.save(&mut bytes).unwrap();
+        let loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.tokenizer_config(), truncating);
+        assert_eq!(
+            Lattice::new().tokenizer_config(),
+            TokenizerConfig::default()
+        );
+    }
+
+    #[test]
+    fn positional_index_answers_phrase_queries() {
+        use lattice_types::Trigram;
+
+        let docs = [
+            "new york city guide",
+            "york is far from new hampshire",
+            "a new york new york song",
+            "newyork without a space",
+        ];
+        let mut positional = Lattice::with_index_config(IndexConfig::positional());
+        let mut plain = Lattice::new();
+        for doc in docs {
+            positional.add(doc).unwrap();
+            plain.add(doc).unwrap();
+        }
+
+        assert_eq!(
+            positional.trigram_positions(2, Trigram::from_str("new")),
+            Some(&[2u16, 11][..])
+        );
+        assert_eq!(
+            positional.trigram_positions(2, Trigram::from_str("xyz")),
+            Some(&[][..])
+        );
+        assert_eq!(plain.trigram_positions(2, Trigram::from_str("new")), None);
+        assert_eq!(positional.token_position(2, 11), Some(3));
+        assert_eq!(positional.token_position(2, 1), Some(1));
+
+        // Positions and text scanning agree; more occurrences rank first
+        for engine in [&mut positional, &mut plain] {
+            let hits = engine.search_phrase("New  York", 10);
+            let ids: Vec<DocId> = hits.iter().map(|r| r.doc_id).collect();
+            assert_eq!(ids, vec![2, 0]);
+            assert_eq!(hits[0].score, 2.0);
+            assert_eq!(engine.phrase_offsets(2, "new york"), vec![2, 11]);
+            assert!(engine.search_phrase("ny", 10).is_empty());
+        }
+
+        // Overlapping occurrences are all reported
+        positional.add("aaaa").unwrap();
+        assert_eq!(positional.phrase_offsets(4, "aaa"), vec![0, 1]);
+
+        // Positions survive merges and reloads, and are counted in memory
+        let mut merged = Lattice::with_index_config(IndexConfig::positional());
+        merged.merge_from(&plain).unwrap();
+        assert_eq!(merged.phrase_offsets(2, "new york"), vec![2, 11]);
+        assert!(merged.verify().is_ok());
+        assert!(merged.memory_breakdown().positions > 0);
+
+        let mut bytes = Vec::new();
+        positional.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.index_config(), IndexConfig::positional());
+        assert_eq!(loaded.search_phrase("york song", 10)[0].doc_id, 2);
+        assert_eq!(
+            loaded.trigram_positions(0, Trigram::from_str("cit")),
+            Some(&[9u16][..])
+        );
+    }
+
+    #[test]
+    fn proximity_weight_favors_adjacent_terms() {
+        let mut engine = Lattice::with_index_config(IndexConfig::positional());
+        let far = engine.add("new yolk and york new").unwrap();
+        let near = engine
+            .add("visit the new york office today please")
+            .unwrap();
+
+        let base = engine.search("new york", 2);
+        assert_eq!(base[0].doc_id, far);
+
+        engine.set_scoring(ScoringConfig {
+            proximity_weight: 2.0,
+            ..ScoringConfig::new()
+        });
+        let boosted = engine.search("new york", 2);
+        assert_eq!(boosted[0].doc_id, near);
+        let score = |results: &[lattice_types::SearchResult], doc| {
+            results.iter().find(|r| r.doc_id == doc).unwrap().score
+        };
+        // Exact layout triples the score; terms far apart earn less
+        assert!((score(&boosted, near) - 3.0 * score(&base, near)).abs() < 1e-4);
+        assert!(score(&boosted, far) < 2.0 * score(&base, far));
+        assert!(score(&boosted, far) > score(&base, far));
+
+        // Single-term queries and engines without positions are unaffected
+        assert_eq!(engine.search("york", 3), {
+            let mut plain = engine.clone();
+            plain.set_scoring(ScoringConfig::default());
+            plain.search("york", 3)
+        });
+        let mut plain = Lattice::new();
+        plain.add("new yolk and york new").unwrap();
+        plain.add("visit the new york office today please").unwrap();
+        plain.set_scoring(ScoringConfig {
+            proximity_weight: 2.0,
+            ..ScoringConfig::new()
+        });
+        assert_eq!(plain.search("new york", 2)[0].doc_id, far);
+        assert_eq!(plain.scoring_config().proximity_weight, 2.0);
+    }
+
+    #[test]
+    fn term_frequencies_drive_bm25_ranking() {
+        let config = IndexConfig {
+            store_term_frequencies: true,
+            ..IndexConfig::new()
+        };
+        let mut engine = Lattice::with_index_config(config);
+        engine.add("banana split with one banana").unwrap();
+        engine.add("banana bread and cherry split").unwrap();
+        engine.add("apple pie").unwrap();
+        let banana = lattice_types::Trigram::from_str("ban");
+        assert_eq!(engine.term_frequency(0, banana), Some(2));
+        assert_eq!(engine.term_frequency(1, banana), Some(1));
+        assert_eq!(engine.term_frequency(3, banana), None);
+
+        for mode in [ScoringMode::TfIdf, ScoringMode::BM25] {
+            engine.set_scoring(ScoringConfig {
+                mode,
+                ..ScoringConfig::new()
+            });
+            let results = engine.search("banana", 10);
+            assert_eq!(results.len(), 2, "{:?}", mode);
+            assert_eq!(results[0].doc_id, 0, "{:?}", mode);
+            assert!(results[0].score > results[1].score);
+        }
+
+        let restored = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert!(restored.index_config().store_term_frequencies);
+        assert_eq!(restored.term_frequency(0, banana), Some(2));
+        assert!(restored.verify().is_ok());
+        assert!(restored.memory_breakdown().term_frequencies > 0);
+
+        let mut merged = Lattice::with_index_config(config);
+        merged.merge_from(&engine).unwrap();
+        assert_eq!(
+            merged.term_frequency(2, lattice_types::Trigram::from_str("app")),
+            Some(1)
+        );
+        assert!(merged.verify().is_ok());
+
+        // Without stored frequencies the statistical modes fall back to overlap
+        let mut plain = Lattice::new();
+        plain.add("banana").unwrap();
+        plain.set_scoring(ScoringConfig {
+            mode: ScoringMode::BM25,
+            ..ScoringConfig::new()
+        });
+        assert_eq!(plain.term_frequency(0, banana), None);
+        assert_eq!(plain.search("banana", 10).len(), 1);
+    }
+
+    #[test]
+    fn search_request_overrides_overlap_ratio() {
+        let mut engine = Lattice::new();
+        for doc in ["sandwich", "sandwick", "rich", "which"] {
+            engine.add(doc).unwrap();
+        }
+        engine.search("warm up", 10);
+        let ids = |results: Vec<lattice_types::SearchResult>| {
+            let mut ids: Vec<DocId> = results.iter().map(|r| r.doc_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        // Doc 1 has 5 of the 6 trigrams of "sandwich"
+        let strict =
+            SearchRequest::new("sandwich", 10).minimum_should_match("100%".parse().unwrap());
+        assert_eq!(ids(engine.search_request(&strict)), vec![0]);
+        let lenient =
+            SearchRequest::new("sandwich", 10).minimum_should_match("-1".parse().unwrap());
+        assert_eq!(ids(engine.search_request(&lenient)), vec![0, 1]);
+
+        // Short queries must match fully, longer ones may miss a trigram
+        let tiered: MinimumShouldMatch = "3<-1".parse().unwrap();
+        let short = SearchRequest::new("wich", 10).minimum_should_match(tiered.clone());
+        assert_eq!(ids(engine.search_request(&short)), vec![0]);
+        let long = SearchRequest::new("sandwich", 10).minimum_should_match(tiered);
+        assert_eq!(ids(engine.search_request(&long)), vec![0, 1]);
+
+        // Uncommitted documents honor the request as well
+        engine.add("sandwiches").unwrap();
+        assert_eq!(ids(engine.search_request(&lenient)), vec![0, 1, 4]);
+        assert_eq!(ids(engine.snapshot().search_request(&strict)), vec![0, 4]);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_search_confirms_trigram_candidates() {
+        let mut engine = Lattice::new();
+        engine.add("let total = count + 1;").unwrap();
+        engine.add("let total = count - 1;").unwrap();
+        engine.add("counter totals").unwrap();
+        engine.search("warm up", 10);
+        engine.add("let total = count * count;").unwrap();
+
+        let ids = |results: Vec<lattice_types::SearchResult>| {
+            let mut ids: Vec<DocId> = results.iter().map(|r| r.doc_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        let search =
+            |engine: &mut Lattice, pattern: &str| ids(engine.search_regex(pattern, 10).unwrap());
+
+        assert_eq!(search(&mut engine, r"total = count [+-] 1"), vec![0, 1]);
+        assert_eq!(search(&mut engine, r"count \* count"), vec![3]);
+        assert_eq!(search(&mut engine, r"^counter"), vec![2]);
+        // No required trigram: every document is scanned
+        assert_eq!(search(&mut engine, r"a.s"), vec![2]);
+        assert!(search(&mut engine, "count / count").is_empty());
+
+        let ranked = engine.search_regex("count", 10).unwrap();
+        assert_eq!(ranked[0].doc_id, 3);
+        assert_eq!(ranked[0].score, 2.0);
+        assert!(engine.search_regex("(unclosed", 10).is_err());
+    }
+
+    #[test]
+    fn search_contains_verifies_substrings() {
+        let mut engine = Lattice::new();
+        engine.add("bandana nan").unwrap();
+        engine.add("banana split").unwrap();
+        engine.add("a banana and a banana").unwrap();
+        engine.search("warm up", 10);
+        engine.add("Banana-Bread").unwrap();
+
+        let results = engine.search_contains("BANANA", 10);
+        let ids: Vec<DocId> = results.iter().map(|r| r.doc_id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        assert_eq!(results[0].score, 2.0);
+
+        // Too short for the index: every document is checked
+        let ids: Vec<DocId> = engine
+            .search_contains("sp", 10)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        assert_eq!(ids, vec![1]);
+
+        assert!(engine.search_contains("bananas", 10).is_empty());
+        assert!(engine.search_contains("banana", 0).is_empty());
+    }
+
+    #[test]
+    fn display_text_survives_folding() {
+        let mut engine = Lattice::with_display_text(crate::NormalizerConfig::strip_diacritics());
+        engine.add("Café de Flore").unwrap();
+        engine.add("Cafe Society").unwrap();
+        engine.search("warm up", 10);
+        engine.add("CAFÉ NOIR").unwrap();
+
+        let ids = |results: Vec<lattice_types::SearchResult>| {
+            let mut ids: Vec<DocId> = results.iter().map(|r| r.doc_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(ids(engine.search("café", 10)), vec![0, 1, 2]);
+        assert_eq!(
+            ids(engine.search("café", 10)),
+            ids(engine.search("cafe", 10))
+        );
+        assert_eq!(engine.get(0), Some("cafe de flore"));
+        assert_eq!(engine.display_text(0), Some("Café de Flore"));
+        assert_eq!(engine.display_text(2), Some("CAFÉ NOIR"));
+        assert!(engine.memory_breakdown().display_text > 0);
+
+        let restored = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert!(restored.index_config().store_display_text);
+        assert_eq!(restored.display_text(2), Some("CAFÉ NOIR"));
+
+        // Documents merged from an engine without display text show their
+        // indexed text
+        let mut plain = Lattice::new();
+        plain.add("Café Crème").unwrap();
+        engine.merge_from(&plain).unwrap();
+        engine.merge_from(&restored).unwrap();
+        assert_eq!(engine.display_text(3), Some("café crème"));
+        assert_eq!(engine.display_text(4), Some("Café de Flore"));
+        assert!(engine.verify().is_ok());
+
+        assert_eq!(plain.display_text(0), plain.get(0));
+        engine.clear();
+        assert_eq!(engine.display_text(0), None);
+    }
+
+    #[test]
+    fn field_weights_rerank_without_reindexing() {
+        use crate::Field;
+
+        let mut engine = Lattice::new();
+        engine.add("a guide to rust macros").unwrap();
+        engine
+            .add_fields(&[(Field::Title, "Gardening"), (Field::Body, "rust on tools")])
+            .unwrap();
+        engine
+            .add_fields(&[
+                (Field::Title, "Rust Tools"),
+                (Field::Body, "a gardening log"),
+            ])
+            .unwrap();
+        assert_eq!(engine.field_text(1, Field::Body), Some("rust on tools"));
+        assert_eq!(engine.field_text(2, Field::Title), Some("rust tools"));
+        assert_eq!(engine.field_text(0, Field::Body), engine.get(0));
+        assert_eq!(engine.field_text(0, Field::Title), None);
+
+        let ids = |results: Vec<lattice_types::SearchResult>| -> Vec<DocId> {
+            results.iter().map(|r| r.doc_id).collect()
+        };
+        let title_only = SearchRequest::new("rust", 10).fields(&[(Field::Title, 1.0)]);
+        assert_eq!(ids(engine.search_request(&title_only)), vec![2]);
+
+        let boosted =
+            SearchRequest::new("rust", 10).fields(&[(Field::Title, 5.0), (Field::Body, 1.0)]);
+        let results = engine.search_request(&boosted);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].doc_id, 2);
+        let plain = engine.search_request(&SearchRequest::new("rust", 10));
+        let score = |results: &[lattice_types::SearchResult], id| {
+            results.iter().find(|r| r.doc_id == id).unwrap().score
+        };
+        assert_eq!(score(&results, 1), score(&plain, 1));
+        assert!(score(&results, 2) > score(&plain, 2));
+        assert!(engine.verify().is_ok());
+
+        let restored = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(restored.field_text(2, Field::Title), Some("rust tools"));
+
+        let mut merged = Lattice::new();
+        merged.add("rust belt").unwrap();
+        merged.merge_from(&restored).unwrap();
+        assert_eq!(merged.field_text(3, Field::Title), Some("rust tools"));
+        assert_eq!(ids(merged.search_request(&title_only)), vec![3]);
+        assert!(merged.verify().is_ok());
+    }
+
+    #[test]
+    fn length_norm_trades_matches_against_length() {
+        let mut engine = Lattice::new();
+        engine.add("quick brown fox").unwrap();
+        let long = engine
+            .add("the quick brown fox jumps over the lazy dog by the river bank today")
+            .unwrap();
+        let partial = engine.add("quick brown").unwrap();
+        engine.add("brown fox").unwrap();
+
+        let mut ranked = |length_norm| {
+            engine.set_scoring(ScoringConfig {
+                length_norm,
+                ..ScoringConfig::new()
+            });
+            engine.search("quick brown fox", 10)
+        };
+        let position = |results: &[lattice_types::SearchResult], doc| {
+            results.iter().position(|r| r.doc_id == doc).unwrap()
+        };
+
+        // Without a penalty the full match wins regardless of length
+        let none = ranked(LengthNorm::None);
+        assert_eq!(none[0].score, none[1].score);
+        assert!(position(&none, long) < position(&none, partial));
+        // The logarithm barely separates long documents from short ones
+        let log = ranked(LengthNorm::Log);
+        assert_eq!(log[0].doc_id, 0);
+        assert!(position(&log, long) < position(&log, partial));
+        // The square root and full pivoting favor the short partial match
+        for norm in [LengthNorm::Sqrt, LengthNorm::PIVOTED] {
+            let results = ranked(norm);
+            assert_eq!(results[0].doc_id, 0, "{:?}", norm);
+            assert!(position(&results, partial) < position(&results, long));
+        }
+        assert_eq!(ranked(LengthNorm::default()), ranked(LengthNorm::Sqrt));
+
+        // b = 0 disables pivoting
+        assert_eq!(
+            ranked(LengthNorm::Pivoted { b: 0.0 }),
+            ranked(LengthNorm::None)
+        );
+        assert_eq!(LengthNorm::PIVOTED.factor(20, 20.0), 100.0);
+        assert!(LengthNorm::PIVOTED.factor(40, 20.0) < LengthNorm::PIVOTED.factor(10, 20.0));
+    }
+
+    #[test]
+    fn normalized_scores_are_comparable_across_queries() {
+        let mut engine = Lattice::with_index_config(IndexConfig {
+            store_term_frequencies: true,
+            ..IndexConfig::positional()
+        });
+        engine.add("rust").unwrap();
+        engine.add("rust programming language").unwrap();
+        engine.add("the rust programming language book").unwrap();
+
+        // Exact matches reach 1.0 whatever the query length, though their
+        // raw scores differ widely
+        let short = engine.search_response(&SearchRequest::new("rust", 10));
+        let long = engine.search_response(&SearchRequest::new("rust programming language", 10));
+        assert_ne!(short.results[0].score, long.results[0].score);
+        assert_eq!(short.normalized(&short.results[0]), 1.0);
+        assert_eq!(long.normalized(&long.results[0]), 1.0);
+        let scores: Vec<f32> = long.normalized_results().map(|(_, s)| s).collect();
+        assert!(scores[1] > 0.0 && scores[1] < 1.0);
+
+        for scoring in [
+            ScoringConfig {
+                mode: ScoringMode::BM25,
+                ..ScoringConfig::new()
+            },
+            ScoringConfig {
+                mode: ScoringMode::TfIdf,
+                ..ScoringConfig::new()
+            },
+            ScoringConfig {
+                proximity_weight: 2.0,
+                length_norm: LengthNorm::PIVOTED,
+                ..ScoringConfig::new()
+            },
+        ] {
+            engine.set_scoring(scoring);
+            let response = engine.search_response(&SearchRequest::new("rust language", 10));
+            assert!(!response.results.is_empty());
+            for (result, normalized) in response.normalized_results() {
+                assert!(result.score <= response.max_score, "{:?}", scoring);
+                assert!(normalized > 0.0 && normalized <= 1.0);
+            }
+        }
+
+        let none = engine.search_response(&SearchRequest::new("zz", 10));
+        assert!(none.results.is_empty());
+        assert_eq!(none.max_score, 0.0);
+    }
+
+    #[test]
+    fn search_threshold_returns_every_match_above_cutoff() {
+        let mut engine = Lattice::new();
+        for i in 0..40 {
+            engine.add(&format!("invoice {:03}", i)).unwrap();
+        }
+        engine
+            .add("invoice reminder sent to the accounts department")
+            .unwrap();
+
+        let all = engine.search("invoice", 100);
+        assert_eq!(all.len(), 41);
+        let cutoff = all[39].score;
+        assert!(all[40].score < cutoff);
+
+        let above = engine.search_threshold("invoice", cutoff);
+        assert_eq!(above, all[..40]);
+        assert!(engine.search_threshold("invoice", f32::MAX).is_empty());
+        assert_eq!(engine.search_threshold("invoice", 0.0).len(), 41);
+
+        // The floor applies before the limit
+        let request = SearchRequest::new("invoice", 50).min_score(cutoff);
+        assert_eq!(engine.search_request(&request).len(), 40);
+    }
+
+    #[test]
+    fn matching_doc_ids_agree_with_search() {
+        let mut engine = Lattice::new();
+        for doc in ["red apple", "green pear", "apple pie", "pineapple"] {
+            engine.add(doc).unwrap();
+        }
+        engine.search("warm up", 1);
+        engine.add("apple cider").unwrap();
+
+        let ids = engine.matching_doc_ids("apple");
+        let mut searched: Vec<DocId> = engine
+            .search("apple", 100)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        searched.sort_unstable();
+        assert_eq!(ids.as_slice(), searched.as_slice());
+        assert_eq!(ids.as_slice(), &[0, 2, 3, 4]);
+
+        let pies = engine.matching_doc_ids("pie");
+        assert_eq!(ids.intersection(&pies).into_vec(), vec![2]);
+        assert_eq!(
+            ids.union(&engine.matching_doc_ids("pear")).len(),
+            ids.len() + 1
+        );
+        assert_eq!(
+            [3, 1, 3].into_iter().collect::<DocIdSet>().as_slice(),
+            &[1, 3]
+        );
+        assert!(engine.matching_doc_ids("xyz").is_empty());
+        assert!(engine.matching_doc_ids("ap").is_empty());
+    }
+
+    #[test]
+    fn trigram_introspection_explains_matches() {
+        use lattice_types::Trigram;
+
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("help wanted").unwrap();
+        engine.search("warm up", 1);
+        engine.add("yellow hello").unwrap();
+
+        for doc_id in 0..3 {
+            let trigrams = engine.doc_trigrams(doc_id).unwrap();
+            assert_eq!(
+                trigrams.len() as u32,
+                engine.doc_trigram_counts[doc_id as usize]
+            );
+            for trigram in trigrams {
+                assert!(engine.docs_for_trigram(trigram).contains(doc_id));
+            }
+        }
+        assert_eq!(engine.doc_trigrams(3), None);
+
+        // "help" lacks "llo", so only the other documents match "hello"
+        let llo = Trigram::from_str("llo");
+        assert_eq!(engine.docs_for_trigram(llo).as_slice(), &[0, 2]);
+        assert!(!engine.doc_trigrams(1).unwrap().contains(&llo));
+        assert!(engine.docs_for_trigram(Trigram::from_str("xyz")).is_empty());
+    }
+
+    #[test]
+    fn dump_lists_blocks_and_sample_documents() {
+        let mut engine = Lattice::new();
+        engine.add("Say hi").unwrap();
+        engine.add("café").unwrap();
+        engine.commit();
+        engine.add("pending").unwrap();
+
+        let dump = |format| {
+            let mut out = Vec::new();
+            engine.dump(&mut out, format).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let text = dump(DumpFormat::Text);
+        assert!(text.contains("documents: 3"));
+        assert!(text.contains("uncommitted: true"));
+        assert!(text.contains("  \"caf\" "));
+        assert!(text.contains("  \"af\\xc3\" "));
+        assert!(text.contains("  1 (5 bytes): \"café\""));
+        assert_eq!(
+            text.lines().filter(|l| l.starts_with("  \"")).count(),
+            engine.blocks.len()
+        );
+
+        let json = dump(DumpFormat::Json);
+        assert!(json.starts_with("{\"num_documents\":3,"));
+        assert!(json.contains("{\"trigram\":\"af\\\\xc3\",\"offset\":"));
+        assert!(json.contains("{\"doc_id\":1,\"len\":5,\"text\":\"café\"}"));
+        assert!(json.ends_with("]}\n"));
+    }
+
+    #[test]
+    fn export_jsonl_writes_every_document() {
+        let mut engine = Lattice::new();
+        engine.add("café au lait").unwrap();
+        engine.commit();
+        engine.add("back\\slash").unwrap();
+
+        let mut out = Vec::new();
+        engine.export_jsonl(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "{\"doc_id\":0,\"text\":\"café au lait\",\"length\":13}",
+                "{\"doc_id\":1,\"text\":\"back\\\\slash\",\"length\":10}",
+            ]
+        );
+
+        let mut empty = Vec::new();
+        Lattice::new().export_jsonl(&mut empty).unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn diff_reports_replication_gaps() {
+        let mut primary = Lattice::new();
+        let mut replica = Lattice::new();
+        for doc in ["alpha beta", "gamma delta"] {
+            primary.add(doc).unwrap();
+            replica.add(doc).unwrap();
+        }
+        // Commit state does not matter
+        primary.commit();
+        assert!(primary.diff(&replica).is_empty());
+        assert_eq!(primary.diff(&replica).to_string(), "indexes are identical");
+
+        primary.add("alpha omega").unwrap();
+        replica.add("alpha omicron").unwrap();
+        replica.add("zeta").unwrap();
+
+        let diff = primary.diff(&replica);
+        assert!(!diff.is_empty());
+        assert_eq!(diff.docs_changed, vec![2]);
+        assert!(diff.docs_only_in_left.is_empty());
+        assert_eq!(diff.docs_only_in_right, vec![3]);
+
+        let ome = lattice_types::Trigram::from_bytes(b'o', b'm', b'e');
+        let zet = lattice_types::Trigram::from_bytes(b'z', b'e', b't');
+        let alp = lattice_types::Trigram::from_bytes(b'a', b'l', b'p');
+        assert!(diff.trigrams_only_in_left.contains(&ome));
+        assert!(diff.trigrams_only_in_right.contains(&zet));
+        assert!(diff.postings_only_in_left.contains(&(ome, 2)));
+        assert!(diff.postings_only_in_right.contains(&(zet, 3)));
+        // Shared postings are not reported
+        assert!(!diff.postings_only_in_left.iter().any(|&(t, _)| t == alp));
+        assert!(diff
+            .postings_only_in_left
+            .windows(2)
+            .all(|w| (w[0].0 .0, w[0].1) < (w[1].0 .0, w[1].1)));
+
+        let reverse = replica.diff(&primary);
+        assert_eq!(reverse.docs_only_in_left, diff.docs_only_in_right);
+        assert_eq!(reverse.postings_only_in_right, diff.postings_only_in_left);
+    }
+
+    #[test]
+    fn subscribers_follow_adds_commits_and_clears() {
+        let mut engine = Lattice::new();
+        engine.add("before subscribing").unwrap();
+        let events = engine.subscribe();
+        let dropped = engine.subscribe();
+        drop(dropped);
+
+        engine.add("hello world").unwrap();
+        assert!(engine.add("bad\u{0}").is_err());
+        let mut shard = Lattice::new();
+        shard.add("merged one").unwrap();
+        shard.add("merged two").unwrap();
+        assert_eq!(engine.merge_from(&shard), Ok(2));
+        let handle = engine.commit_in_background().unwrap();
+        engine.finish_commit(handle).unwrap();
+
+        // Snapshots do not notify the original subscribers
+        let mut snapshot = engine.snapshot();
+        snapshot.search("hello", 1);
+
+        let seen: Vec<IndexEvent> = events.try_iter().collect();
+        assert_eq!(
+            seen,
+            [
+                IndexEvent::Added(1),
+                IndexEvent::Added(2),
+                IndexEvent::Added(3),
+                IndexEvent::Committed,
+            ]
+        );
+
+        engine.clear();
+        let removed: Vec<IndexEvent> = events.try_iter().collect();
+        assert_eq!(removed.len(), 4);
+        assert_eq!(removed[3], IndexEvent::Removed(3));
+
+        drop(events);
+        engine.add("nobody listening").unwrap();
+        assert!(engine.subscribers.is_empty());
+    }
+
+    #[test]
+    fn namespaces_isolate_tenants() {
+        let mut engine = Lattice::new();
+        engine.add("shared invoice template").unwrap();
+        let a = engine
+            .namespace("tenant-a")
+            .add("invoice for acme")
+            .unwrap();
+        let b = engine
+            .namespace("tenant-b")
+            .add("invoice for globex")
+            .unwrap();
+        engine.namespace("tenant-a").add("acme contract").unwrap();
+
+        let mut tenant_a = engine.namespace("tenant-a");
+        assert_eq!(tenant_a.name(), "tenant-a");
+        assert_eq!(tenant_a.len(), 2);
+        let hits: Vec<DocId> = tenant_a
+            .search("invoice", 10)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        assert_eq!(hits, vec![a]);
+        assert!(tenant_a.contains(a) && !tenant_a.contains(b));
+        assert_eq!(tenant_a.get(b), None);
+        assert!(engine.namespace("tenant-b").search("acme", 10).is_empty());
+        assert!(engine.namespace("nobody").is_empty());
+        assert_eq!(engine.search("invoice", 10).len(), 3);
+        assert_eq!(engine.namespace_of(0), None);
+        assert_eq!(engine.namespace_of(b), Some("tenant-b"));
+
+        // Namespaces survive saving and are matched by name when merging
+        let mut loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.namespace_of(b), Some("tenant-b"));
+        assert_eq!(loaded.namespace("tenant-b").search("invoice", 10).len(), 1);
+
+        let mut other = Lattice::new();
+        other
+            .namespace("tenant-b")
+            .add("globex invoice copy")
+            .unwrap();
+        let base = loaded.merge_from(&other).unwrap();
+        assert_eq!(loaded.namespace_of(base), Some("tenant-b"));
+        assert_eq!(loaded.namespace("tenant-b").search("invoice", 10).len(), 2);
+        assert_eq!(
+            loaded.namespace_names().collect::<Vec<_>>(),
+            ["tenant-a", "tenant-b", "nobody"]
+        );
+        assert!(loaded.verify().is_ok());
+    }
+
+    #[test]
+    fn config_min_score_drops_weak_matches() {
+        let config = lattice_types::SearchConfig {
+            min_overlap_ratio: 0.1,
+            ..Default::default()
+        };
+        let mut engine = Lattice::with_config(config);
+        for doc in [
+            "warehouse",
+            "warehouse inventory",
+            "warehouse inventory list for the northern region",
+            "old warehouse inventory records kept for the auditors of every region",
+        ] {
+            engine.add(doc).unwrap();
+        }
+        let all = engine.search("warehouse", 10);
+        assert_eq!(all.len(), 4);
+        let floor = all[1].score;
+
+        engine.set_search_config(lattice_types::SearchConfig {
+            min_score: Some(floor),
+            ..config
+        });
+        let strong = engine.search("warehouse", 10);
+        assert_eq!(strong, all[..2]);
+        assert!(strong.iter().all(|r| r.score >= floor));
+
+        // A request-level floor replaces the configured one
+        let request = SearchRequest::new("warehouse", 10).min_score(0.0);
+        assert_eq!(engine.search_request(&request).len(), 4);
+
+        let loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.search_config().min_score, Some(floor));
+    }
+
+    #[test]
+    fn invalid_search_configs_are_rejected() {
+        use lattice_types::{ConfigError, SearchConfig};
+
+        let cases = [
+            (
+                SearchConfig {
+                    min_overlap_ratio: -0.1,
+                    ..SearchConfig::default()
+                },
+                ConfigError::OverlapRatioOutOfRange { ratio: -0.1 },
+            ),
+            (
+                SearchConfig {
+                    max_edit_distance: 9,
+                    ..SearchConfig::default()
+                },
+                ConfigError::EditDistanceTooLarge {
+                    distance: 9,
+                    max: 4,
+                },
+            ),
+            (
+                SearchConfig {
+                    min_score: Some(f32::INFINITY),
+                    ..SearchConfig::default()
+                },
+                ConfigError::InvalidMinScore {
+                    score: f32::INFINITY,
+                },
+            ),
+        ];
+        for (config, expected) in cases {
+            assert_eq!(config.validate(), Err(expected));
+            assert_eq!(Lattice::try_with_config(config).err(), Some(expected));
+        }
+        let nan = SearchConfig {
+            min_overlap_ratio: f32::NAN,
+            ..SearchConfig::default()
+        };
+        assert!(nan.validate().is_err());
+        assert!(SearchConfig::exact().validate().is_ok());
+        assert!(SearchConfig::fuzzy().validate().is_ok());
+
+        let result = std::panic::catch_unwind(|| Lattice::with_config(nan));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn builder_sizes_and_validates() {
+        let mut engine = Lattice::builder()
+            .index_config(IndexConfig {
+                store_display_text: true,
+                ..IndexConfig::new()
+            })
+            .commit_policy(CommitPolicy::Manual)
+            .scoring(ScoringConfig {
+                length_norm: LengthNorm::PIVOTED,
+                ..ScoringConfig::new()
+            })
+            .arena_bytes(4096)
+            .expected_documents(500)
+            .expected_trigrams(20_000)
+            .build()
+            .unwrap();
+        assert_eq!(engine.commit_policy(), CommitPolicy::Manual);
+        assert_eq!(engine.scoring_config().length_norm, LengthNorm::PIVOTED);
+        assert!(engine.doc_lengths.capacity() >= 500);
+        assert!(engine.temp_trigrams.capacity() >= 20_000);
+
+        // Reserving up front means no reallocation while within the estimate
+        let before = engine.temp_trigrams.as_ptr();
+        for i in 0..100 {
+            engine.add(&format!("Preallocated Document {}", i)).unwrap();
+        }
+        assert_eq!(engine.temp_trigrams.as_ptr(), before);
+        assert_eq!(engine.display_text(3), Some("Preallocated Document 3"));
+        engine.commit();
+        assert_eq!(engine.search("document 42", 1)[0].doc_id, 42);
+
+        let err = Lattice::builder()
+            .scoring(ScoringConfig {
+                proximity_weight: -1.0,
+                ..ScoringConfig::new()
+            })
+            .build()
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("proximity_weight"));
+        assert!(Lattice::builder()
+            .scoring(ScoringConfig {
+                length_norm: LengthNorm::Pivoted { b: 1.5 },
+                ..ScoringConfig::new()
+            })
+            .build()
+            .is_err());
+    }
+
+    #[test]
+    fn reserve_documents_presizes_bulk_ingestion() {
+        let mut engine = Lattice::new();
+        engine.reserve_documents(2_000, 32);
+        let reserved = engine.memory_breakdown();
+        assert!(reserved.arena_spans >= 2_000 * 12);
+        assert!(reserved.doc_metadata >= 2_000 * 2 * 4);
+        assert!(reserved.uncommitted >= 2_000 * 30 * 8);
+
+        engine.set_commit_policy(CommitPolicy::Manual);
+        for i in 0..2_000 {
+            engine
+                .add(&format!("reserved document number {:06}", i))
+                .unwrap();
+        }
+        let filled = engine.memory_breakdown();
+        assert_eq!(filled.arena_text, reserved.arena_text);
+        assert_eq!(filled.arena_spans, reserved.arena_spans);
+        assert_eq!(filled.doc_metadata, reserved.doc_metadata);
+        assert_eq!(filled.uncommitted, reserved.uncommitted);
+        assert_eq!(engine.get(1234), Some("reserved document number 001234"));
+        engine.commit();
+        assert_eq!(engine.search("reserved document", 5).len(), 5);
+    }
+
+    #[test]
+    fn owned_adds_match_borrowed_adds() {
+        let long = "Owned Buffer Contents ".repeat(600);
+        let texts = [
+            "short owned text".to_owned(),
+            long.clone(),
+            "another".to_owned(),
+        ];
+
+        let mut borrowed = Lattice::new();
+        let mut owned = Lattice::new();
+        for text in &texts {
+            borrowed.add(text).unwrap();
+        }
+        owned.add_owned(texts[0].clone()).unwrap();
+        owned.add_owned(texts[1].clone()).unwrap();
+        owned.add_bytes(texts[2].clone().into_bytes()).unwrap();
+        assert!(owned.diff(&borrowed).is_empty());
+        assert_eq!(
+            owned.search("buffer contents", 5),
+            borrowed.search("buffer contents", 5)
+        );
+        owned.add("after").unwrap();
+        assert_eq!(owned.get(3), Some("after"));
+
+        assert!(matches!(
+            owned.add_bytes(vec![0x66, 0xff, 0x66]),
+            Err(lattice_types::DocumentError::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            owned.add_owned("x".repeat(70_000)),
+            Err(lattice_types::DocumentError::TooLarge { .. })
+        ));
+        assert_eq!(owned.len(), 4);
+
+        let mut display = Lattice::with_index_config(IndexConfig {
+            store_display_text: true,
+            ..IndexConfig::new()
+        });
+        display.add_owned(long.clone()).unwrap();
+        assert_eq!(display.display_text(0), Some(long.as_str()));
+        assert_eq!(display.get(0), borrowed.get(1));
+    }
+
+    #[test]
+    fn add_from_reader_streams_and_validates() {
+        /// Hands out one byte per read, splitting every UTF-8 sequence.
+        struct Trickle<'a>(&'a [u8]);
+
+        impl std::io::Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let Some((&first, rest)) = self.0.split_first() else {
+                    return Ok(0);
+                };
+                buf[0] = first;
+                self.0 = rest;
+                Ok(1)
+            }
+        }
+
+        let mut engine = Lattice::new();
+        let text = "naïve café ☕ übersicht";
+        let id = engine
+            .add_from_reader(Trickle(text.as_bytes()), 1024)
+            .unwrap();
+        let mut reference = Lattice::new();
+        reference.add(text).unwrap();
+        assert_eq!(engine.get(id), reference.get(0));
+
+        let large = "streamed chunk ".repeat(3_000);
+        let id = engine
+            .add_from_reader(large.as_bytes(), usize::MAX)
+            .unwrap();
+        assert_eq!(engine.get(id), Some(large.trim_end()));
+
+        // Exactly at the limit is accepted, one byte over is not
+        assert!(engine.add_from_reader(&b"abcdef"[..], 6).is_ok());
+        assert!(matches!(
+            engine.add_from_reader(&b"abcdefg"[..], 6),
+            Err(IngestError::Document(
+                lattice_types::DocumentError::TooLarge { max_size: 6, .. }
+            ))
+        ));
+        for invalid in [&b"bad \xff byte"[..], &b"cut \xe2\x98"[..]] {
+            assert!(matches!(
+                engine.add_from_reader(Trickle(invalid), 64),
+                Err(IngestError::Document(
+                    lattice_types::DocumentError::InvalidInput { .. }
+                ))
+            ));
+        }
+
+        struct Failing;
+
+        impl std::io::Read for Failing {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("disk gone"))
+            }
+        }
+
+        let err = engine
+            .add_from_reader(std::io::Read::chain(&b"partial"[..], Failing), 64)
+            .unwrap_err();
+        assert!(matches!(err, IngestError::Io(_)));
+        assert!(err.to_string().contains("disk gone"));
+        assert_eq!(engine.len(), 3);
+    }
+
+    #[test]
+    fn iter_walks_documents_in_order() {
+        let mut engine = Lattice::new();
+        assert_eq!(engine.iter().next(), None);
+
+        engine.add("Alpha").unwrap();
+        engine.add_owned("beta ".repeat(1_000)).unwrap();
+        engine.add("gamma").unwrap();
+        engine.commit();
+
+        let docs: Vec<(DocId, &str)> = engine.iter().collect();
+        assert_eq!(docs.len(), 3);
+        assert_eq!(docs[0], (0, "alpha"));
+        assert_eq!(docs[2], (2, "gamma"));
+        assert!(docs
+            .iter()
+            .all(|&(doc_id, text)| engine.get(doc_id) == Some(text)));
+        assert_eq!(engine.iter().len(), engine.len());
+        assert_eq!(engine.iter().next_back(), Some((2, "gamma")));
+    }
+
+    #[test]
+    fn sample_is_uniform_and_reproducible() {
+        let mut engine = Lattice::new();
+        assert!(engine.sample(3, 1).is_empty());
+        for i in 0..50 {
+            engine.add(&format!("sampled document {:02}", i)).unwrap();
+        }
+
+        let sample = engine.sample(10, 7);
+        assert_eq!(sample.len(), 10);
+        assert!(sample.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(sample, engine.sample(10, 7));
+        assert_ne!(sample, engine.sample(10, 8));
+        assert_eq!(engine.sample(50, 7).len(), 50);
+        assert_eq!(engine.sample(500, 7), engine.iter().collect::<Vec<_>>());
+
+        // Every document turns up across seeds, none far more than others
+        let mut hits = [0u32; 50];
+        for seed in 0..1_000 {
+            for (doc_id, _) in engine.sample(5, seed) {
+                hits[doc_id as usize] += 1;
+            }
+        }
+        assert!(hits.iter().all(|&count| (50..=150).contains(&count)));
+    }
+
+    #[test]
+    fn document_metadata_accessors() {
+        let mut engine = Lattice::with_index_config(IndexConfig {
+            store_indexed_at: true,
+            ..IndexConfig::new()
+        });
+        let before = std::time::SystemTime::now() - std::time::Duration::from_secs(1);
+        let short = engine.add("Ab").unwrap();
+        let long = engine.add("abcabc xyz").unwrap();
+
+        assert_eq!(engine.doc_len(short), Some(2));
+        assert_eq!(engine.doc_trigram_count(short), Some(0));
+        assert_eq!(engine.doc_len(long), Some(10));
+        assert_eq!(engine.doc_trigram_count(long), Some(7));
+        assert_eq!(engine.doc_len(9), None);
+        assert_eq!(engine.doc_trigram_count(9), None);
+        let stamped = engine.indexed_at(long).unwrap();
+        assert!(stamped >= before);
+        assert!(engine.indexed_at(9).is_none());
+
+        // Times survive saving, and merges stamp documents that had none
+        let loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.indexed_at(long), Some(stamped));
+        let mut plain = Lattice::new();
+        plain.add("unstamped").unwrap();
+        assert!(plain.indexed_at(0).is_none());
+        let merged_id = engine.merge_from(&plain).unwrap();
+        assert!(engine.indexed_at(merged_id).unwrap() >= stamped);
+        assert!(engine.verify().is_ok());
+
+        engine.clear();
+        assert!(engine.indexed_at(0).is_none());
+    }
+
+    #[test]
+    fn stats_render_as_json_and_report_periodically() {
+        let mut engine = Lattice::new();
+        engine.add("hello \"quoted\" world").unwrap();
+        engine.add("hello there").unwrap();
+        engine.commit();
+
+        let stats = engine.stats_with_compression();
+        assert_eq!(stats.allocated_bytes, engine.memory_breakdown().total());
+        let json = stats.to_json();
+        assert!(json.starts_with('{') && json.ends_with('}'));
+        assert!(json.contains(&format!("\"num_trigrams\":{},", stats.num_trigrams)));
+        assert!(json.contains(&format!(
+            "\"compressed_postings_bytes\":{},",
+            stats.compressed_postings_bytes.unwrap()
+        )));
+        assert!(json.contains("\"posting_lengths\":{\"p50\":"));
+        assert_eq!(json.matches('{').count(), json.matches('}').count());
+        assert!(Lattice::new()
+            .stats()
+            .to_json()
+            .contains("\"avg_postings_per_doc\":0}"));
+
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = std::sync::Arc::clone(&reports);
+        engine.set_stats_reporter(std::time::Duration::from_secs(3600), move |stats| {
+            sink.lock().unwrap().push(stats.num_documents);
+        });
+        engine.add("first report").unwrap();
+        engine.add("too soon").unwrap();
+        engine.search("hello", 5);
+        assert_eq!(*reports.lock().unwrap(), [3]);
+
+        engine.set_stats_reporter(std::time::Duration::ZERO, {
+            let sink = std::sync::Arc::clone(&reports);
+            move |stats| sink.lock().unwrap().push(stats.num_documents)
+        });
+        engine.search("hello", 5);
+        engine.clone().add("clones do not report").unwrap();
+        engine.clear_stats_reporter();
+        engine.add("stopped").unwrap();
+        assert_eq!(*reports.lock().unwrap(), [3, 4]);
+    }
+
+    #[test]
+    fn shared_metrics_follow_the_engine() {
+        let mut engine = Lattice::new();
+        let metrics = engine.shared_metrics();
+        let monitor = std::thread::spawn({
+            let metrics = std::sync::Arc::clone(&metrics);
+            move || {
+                while metrics.snapshot().queries_executed < 2 {
+                    std::thread::yield_now();
+                }
+                metrics.snapshot()
+            }
+        });
+
+        engine.add("hello world").unwrap();
+        engine.add("hello there").unwrap();
+        engine.search("hello", 10);
+        let mut copy = engine.clone();
+        engine.search("world", 10);
+        let seen = monitor.join().unwrap();
+        assert_eq!(seen.documents_indexed, 2);
+        assert_eq!(seen.current_doc_count, 2);
+        // The monitor may look before the second search records its
+        // candidates, so the full comparison waits for the search to end
+        assert_eq!(seen.queries_executed, 2);
+        assert_eq!(metrics.snapshot(), engine.metrics());
+
+        // Clones count on their own
+        copy.add("only in the copy").unwrap();
+        assert_eq!(copy.metrics().documents_indexed, 3);
+        assert_eq!(copy.metrics().queries_executed, 1);
+        assert_eq!(metrics.snapshot().documents_indexed, 2);
+
+        let loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.metrics().documents_indexed, 2);
+        assert_eq!(loaded.metrics().current_doc_count, 2);
+
+        engine.clear();
+        assert_eq!(metrics.snapshot(), engine.metrics());
+        assert_eq!(metrics.snapshot().current_doc_count, 0);
+        assert_eq!(metrics.snapshot().queries_executed, 0);
+    }
+
+    #[test]
+    fn shared_reference_search_matches_mutable_search() {
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<Lattice>();
+
+        let mut engine = Lattice::new();
+        for i in 0..50 {
+            engine
+                .add(&format!("shared search document {}", i))
+                .unwrap();
+        }
+        engine.add("another kind of text").unwrap();
+        engine.commit();
+        let expected = engine.search("search document", 10);
+        let queries = engine.metrics().queries_executed;
+
+        let engine = &engine;
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut scratch = SearchScratch::new();
+                    let request = SearchRequest::new("search document", 10);
+                    for _ in 0..10 {
+                        assert_eq!(engine.search_with_scratch(&request, &mut scratch), expected);
+                    }
+                    assert_eq!(engine.search_ref("search document", 10), expected);
+                    assert_eq!(engine.search_ref("kind of text", 10)[0].doc_id, 50);
+                });
+            }
+        });
+        assert_eq!(engine.metrics().queries_executed, queries + 4 * 12);
+    }
+
+    #[test]
+    fn frozen_engine_commits_and_searches_from_threads() {
+        let mut engine = Lattice::new();
+        engine.set_commit_policy(CommitPolicy::Manual);
+        for i in 0..20 {
+            engine.add(&format!("frozen document {}", i)).unwrap();
+        }
+        engine.search("frozen", 1);
+        assert!(engine.has_uncommitted());
+
+        let frozen = std::sync::Arc::new(engine.freeze());
+        assert!(!frozen.has_uncommitted());
+        assert_eq!(frozen.scratch.allocated_bytes(), 0);
+        assert_eq!(frozen.norm_buf.capacity(), 0);
+        let expected = frozen.search("frozen document 7", 3);
+        assert_eq!(expected[0].doc_id, 7);
+
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let frozen = std::sync::Arc::clone(&frozen);
+                std::thread::spawn(move || {
+                    let boosted = frozen.search_with_scorer("frozen", 20, |ctx| ctx.doc_id as f32);
+                    (frozen.search("frozen document 7", 3), boosted[0].doc_id)
+                })
+            })
+            .collect();
+        for worker in workers {
+            assert_eq!(worker.join().unwrap(), (expected.clone(), 19));
+        }
+
+        let mut engine = std::sync::Arc::try_unwrap(frozen).ok().unwrap().thaw();
+        engine.add("frozen no more").unwrap();
+        engine.commit();
+        assert_eq!(engine.search("no more", 1)[0].doc_id, 20);
+    }
+
+    #[test]
+    fn impact_search_matches_exhaustive_scoring() {
+        let mut engine = Lattice::new();
+        for i in 0..2_000u32 {
+            let filler = "x".repeat((i % 37) as usize);
+            engine
+                .add(&format!("common words {} item{} {}", filler, i % 50, i))
+                .unwrap();
+        }
+        engine.add("rare sentinel phrase").unwrap();
+        engine.set_boost(7, 3.0).unwrap();
+        engine.commit();
+
+        let index = engine.impact_index();
+        assert_eq!(index.num_documents(), engine.len());
+        assert_eq!(index.num_postings(), engine.postings.len());
+
+        for query in ["common item7", "sentinel", "words item12 zz", "nothing"] {
+            // Exhaustive: every document's quantized total over the query
+            let normalized = engine.analyze_query(query);
+            let mut trigrams = Vec::new();
+            crate::analyzer::trigram::extract_trigrams(&normalized, |t| trigrams.push(t));
+            trigrams.sort_unstable_by_key(|t| t.0);
+            trigrams.dedup();
+            let mut totals = vec![0u32; engine.len()];
+            for t in &trigrams {
+                let Some(block) = engine.blocks.iter().position(|b| b.trigram == *t) else {
+                    continue;
+                };
+                for &doc in Lattice::block_postings(&engine.blocks[block], &engine.postings) {
+                    totals[doc as usize] +=
+                        index.quantize(index.idfs[block], index.doc_factors[doc as usize]) as u32;
+                }
+            }
+            let mut expected: Vec<(u32, DocId)> = totals
+                .iter()
+                .enumerate()
+                .filter(|&(_, &t)| t > 0)
+                .map(|(doc, &t)| (t, doc as DocId))
+                .collect();
+            expected.sort_unstable_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+            expected.truncate(10);
+
+            let results = engine.search_impacts(&index, query, 10);
+            let got: Vec<DocId> = results.iter().map(|r| r.doc_id).collect();
+            let want: Vec<DocId> = expected.iter().map(|&(_, doc)| doc).collect();
+            assert_eq!(got, want, "query {:?}", query);
+            for (result, (total, _)) in results.iter().zip(&expected) {
+                assert_eq!(result.score, *total as f32 * index.scale);
+            }
+        }
+
+        // A selective query stops long before reading every posting
+        let before = engine.metrics().candidates_examined;
+        engine.search_impacts(&index, "common words", 5);
+        let read = engine.metrics().candidates_examined - before;
+        assert!(read < 4_000, "read {} of 20000 postings", read);
+        assert!(engine.search_impacts(&index, "common", 0).is_empty());
+    }
+
+    #[test]
+    fn trigram_directory_finds_every_block() {
+        let mut engine = Lattice::new();
+        let word = |mut n: u32| {
+            let mut w = String::new();
+            for _ in 0..5 {
+                w.push((b'a' + (n % 26) as u8) as char);
+                n /= 26;
+            }
+            w
+        };
+        for i in 0..3_000u32 {
+            let doc = format!("{} {} {}", word(i * 7919), word(i * 104_729), i);
+            engine.add(&doc).unwrap();
+        }
+        engine.commit();
+        assert!(engine.blocks.len() >= block_table::DIRECTORY_THRESHOLD);
+
+        for (i, block) in engine.blocks.iter().enumerate() {
+            assert_eq!(engine.find_block(block.trigram), Some(i));
+        }
+        for missing in ["zzq", "\u{0}\u{0}\u{0}", "\u{7f}\u{7f}\u{7f}"] {
+            let trigram = lattice_types::Trigram::from_str(missing);
+            let linear = engine.blocks.iter().position(|b| b.trigram == trigram);
+            assert_eq!(engine.find_block(trigram), linear);
+        }
+        let doc = engine.get(1234).unwrap().to_string();
+        assert_eq!(engine.search(&doc, 1)[0].doc_id, 1234);
+    }
+
+    #[test]
+    fn perfect_hash_dictionary_matches_sorted_lookups() {
+        let mut engine = Lattice::builder()
+            .trigram_dictionary(TrigramDictionary::PerfectHash)
+            .build()
+            .unwrap();
+        let mut sorted = Lattice::new();
+        for i in 0..2_000u32 {
+            let doc = format!(
+                "entry {} code {:x} tag{}",
+                i,
+                i.wrapping_mul(2_654_435_761),
+                i % 97
+            );
+            engine.add(&doc).unwrap();
+            sorted.add(&doc).unwrap();
+        }
+        engine.commit();
+        sorted.commit();
+        assert_eq!(engine.trigram_dictionary(), TrigramDictionary::PerfectHash);
+
+        for (i, block) in sorted.blocks.iter().enumerate() {
+            assert_eq!(engine.find_block(block.trigram), Some(i));
+        }
+        for missing in ["zzq", "qqq", "\u{0}\u{0}\u{0}"] {
+            let trigram = lattice_types::Trigram::from_str(missing);
+            assert_eq!(engine.find_block(trigram), sorted.find_block(trigram));
+        }
+        for query in ["entry 15", "tag42", "code"] {
+            assert_eq!(engine.search(query, 10), sorted.search(query, 10));
+        }
+
+        // Later commits and clears keep the choice
+        engine.add("fresh words").unwrap();
+        engine.commit();
+        assert_eq!(engine.search("fresh", 1)[0].doc_id, 2_000);
+        engine.clear();
+        assert_eq!(engine.trigram_dictionary(), TrigramDictionary::PerfectHash);
+
+        let before = sorted.search("tag42", 30);
+        sorted.set_trigram_dictionary(TrigramDictionary::PerfectHash);
+        assert_eq!(sorted.search("tag42", 30), before);
+    }
+
+    #[test]
+    fn galloping_intersection_matches_brute_force() {
+        let mut engine = Lattice::with_config(lattice_types::SearchConfig {
+            min_overlap_ratio: 1.0,
+            enable_fuzzy: false,
+            ..Default::default()
+        });
+        for i in 0..6_000u32 {
+            let doc = match i % 500 {
+                0 => format!("zebra crossing common text {}", i),
+                7 => format!("zebra only {}", i),
+                _ => format!("common text number {}", i),
+            };
+            engine.add(&doc).unwrap();
+        }
+        engine.commit();
+
+        for query in ["zebra common text", "zebra crossing", "common text"] {
+            let mut got: Vec<DocId> = engine
+                .search(query, 10_000)
+                .iter()
+                .map(|r| r.doc_id)
+                .collect();
+            got.sort_unstable();
+            // Every query trigram occurs in the document
+            let want: Vec<DocId> = (0..engine.len() as DocId)
+                .filter(|&d| {
+                    let doc = engine.get(d).unwrap();
+                    query
+                        .as_bytes()
+                        .windows(3)
+                        .all(|t| doc.contains(core::str::from_utf8(t).unwrap()))
+                })
+                .collect();
+            assert_eq!(got, want, "query {:?}", query);
+        }
+
+        // A rare seed keeps its candidates through the common lists
+        let mut lenient = Lattice::new();
+        for i in 0..6_000u32 {
+            lenient.add(&format!("common text number {}", i)).unwrap();
+        }
+        lenient.add("zebra text").unwrap();
+        lenient.commit();
+        assert_eq!(lenient.search("zebra text", 1)[0].doc_id, 6_000);
+    }
+
+    #[test]
+    fn repeated_query_trigrams_are_collapsed() {
+        use super::commit::CommitPolicy;
+
+        let docs = ["aaaa banana", "aaa", "banana bread", "abab abab"];
+        let mut committed = Lattice::new();
+        let mut delta = Lattice::new();
+        delta.set_commit_policy(CommitPolicy::RealTime);
+        for doc in docs {
+            committed.add(doc).unwrap();
+            delta.add(doc).unwrap();
+        }
+        committed.commit();
+
+        for query in ["aaaaaaaa", "abababab", "ananana"] {
+            let from_postings = committed.search(query, 10);
+            assert!(!from_postings.is_empty(), "query {:?}", query);
+            assert_eq!(from_postings, delta.search(query, 10), "query {:?}", query);
+        }
+        let ids = |results: Vec<lattice_types::SearchResult>| {
+            results.iter().map(|r| r.doc_id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(committed.search("aaaaaaaa", 10)), [1, 0]);
+        assert_eq!(ids(committed.search("abababab", 10)), [3]);
+    }
+
+    #[test]
+    fn long_queries_match_on_their_rarest_trigrams() {
+        let prefix = "the quick brown fox jumps over the lazy dog and then";
+        let mut engine = Lattice::new();
+        for ending in ["walks home", "sleeps", "rides a zeppelin", "eats lunch"] {
+            engine.add(&format!("{} {}", prefix, ending)).unwrap();
+        }
+        for i in 0..20 {
+            engine.add(&format!("{} note {}", prefix, i)).unwrap();
+        }
+        engine.commit();
+
+        // The distinguishing word lies past the first 30 trigrams
+        let query = format!("{} zeppelin", prefix);
+        assert!(query.len() - 2 > lattice_types::SearchConfig::DEFAULT_MAX_QUERY_TRIGRAMS);
+        assert_eq!(engine.search(&query, 1)[0].doc_id, 2);
+
+        let mut narrow = engine.search_config();
+        narrow.max_query_trigrams = 8;
+        engine.set_search_config(narrow);
+        assert_eq!(engine.search(&query, 1)[0].doc_id, 2);
+
+        let mut saved = Vec::new();
+        engine.save(&mut saved).unwrap();
+        let loaded = Lattice::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.search_config().max_query_trigrams, 8);
+
+        narrow.max_query_trigrams = 0;
+        assert!(narrow.validate().is_err());
+    }
+
+    #[test]
+    fn query_length_limit_is_configurable_and_reported() {
+        use lattice_types::{SearchConfig, SearchError};
+
+        let mut engine = Lattice::new();
+        engine.add("a document about limits").unwrap();
+        let long = format!("{} limits", "x".repeat(1_200));
+        assert!(engine.search(&long, 10).is_empty());
+        assert_eq!(
+            engine.try_search(&long, 10),
+            Err(SearchError::QueryTooLong {
+                length: 1_207,
+                max_length: SearchConfig::DEFAULT_MAX_QUERY_LENGTH,
+            })
+        );
+
+        engine.set_search_config(SearchConfig {
+            max_query_length: 4_096,
+            min_overlap_ratio: 0.0,
+            ..SearchConfig::default()
+        });
+        assert_eq!(engine.try_search(&long, 10).unwrap().len(), 1);
+        engine.set_search_config(SearchConfig {
+            max_query_length: 5,
+            ..SearchConfig::default()
+        });
+        assert!(engine.try_search("limits", 10).is_err());
+        assert_eq!(engine.try_search("limit", 10).unwrap()[0].doc_id, 0);
+
+        let mut saved = Vec::new();
+        engine.save(&mut saved).unwrap();
+        let loaded = Lattice::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.search_config().max_query_length, 5);
+
+        // The longest supported query keeps its match counts in range
+        engine.set_search_config(SearchConfig {
+            max_query_length: SearchConfig::MAX_QUERY_LENGTH,
+            ..SearchConfig::default()
+        });
+        let repeated = "a".repeat(SearchConfig::MAX_QUERY_LENGTH);
+        assert!(engine.try_search(&repeated, 10).is_ok());
+        assert!(SearchConfig {
+            max_query_length: SearchConfig::MAX_QUERY_LENGTH + 1,
+            ..SearchConfig::default()
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn try_search_reports_why_a_query_cannot_run() {
+        use lattice_types::SearchError;
+
+        let mut engine = Lattice::new();
+        assert_eq!(engine.try_search("apple", 10), Err(SearchError::IndexEmpty));
+
+        let docs = vec!["apple pie"; types::MAX_CANDIDATES + 1];
+        engine.add_batch(&docs);
+        engine.add("pear tart").unwrap();
+        engine.commit();
+
+        assert_eq!(
+            engine.try_search("  ab ", 10),
+            Err(SearchError::QueryTooShort {
+                length: 2,
+                min_length: 3,
+            })
+        );
+        assert_eq!(
+            engine.try_search("apple", 10),
+            Err(SearchError::TooManyCandidates {
+                candidates: types::MAX_CANDIDATES + 1,
+                max: types::MAX_CANDIDATES,
+            })
+        );
+        assert!(engine.search("apple", 10).is_empty());
+        assert_eq!(engine.try_search("zebra", 10), Ok(Vec::new()));
+        assert_eq!(
+            engine.try_search("pear", 10).unwrap()[0].doc_id,
+            types::MAX_CANDIDATES as DocId + 1
+        );
+    }
+
+    #[test]
+    fn engines_share_the_search_engine_interface() {
+        use crate::SharedLattice;
+        use lattice_types::{DocumentError, SearchEngine};
+
+        fn index<E: SearchEngine<Stats = IndexStats>>(engine: &mut E) -> Vec<DocId> {
+            engine.add("hello world").unwrap();
+            engine.add("hello there").unwrap();
+            engine
+                .search("hello", 10)
+                .iter()
+                .map(|r| r.doc_id)
+                .collect()
+        }
+
+        let mut engine = Lattice::new();
+        assert_eq!(index(&mut engine), vec![0, 1]);
+        assert_eq!(SearchEngine::len(&engine), 2);
+
+        let mut frozen = engine.freeze();
+        assert_eq!(
+            SearchEngine::add(&mut frozen, "more"),
+            Err(DocumentError::ReadOnly)
+        );
+        assert_eq!(SearchEngine::search(&mut frozen, "there", 10)[0].doc_id, 1);
+        assert_eq!(SearchEngine::stats(&frozen).num_documents, 2);
+
+        let mut shared = SharedLattice::with_batch_size(Lattice::new(), 2);
+        assert!(SearchEngine::is_empty(&shared));
+        assert_eq!(index(&mut shared), vec![0, 1]);
+        assert_eq!(SearchEngine::stats(&shared).num_documents, 2);
+    }
+
+    #[test]
+    fn equal_scores_follow_the_tie_break() {
+        let mut engine = Lattice::new();
+        for _ in 0..5 {
+            engine.add("same old text").unwrap();
+        }
+        engine.commit();
+        engine.add("same old text").unwrap();
+
+        let ids = |results: Vec<lattice_types::SearchResult>| -> Vec<DocId> {
+            results.iter().map(|r| r.doc_id).collect()
+        };
+        assert_eq!(ids(engine.search("same old", 10)), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(ids(engine.search("same old", 3)), vec![0, 1, 2]);
+        assert_eq!(ids(engine.search_contains("old", 2)), vec![0, 1]);
+
+        engine.set_scoring(ScoringConfig {
+            tie_break: TieBreak::NewestFirst,
+            ..ScoringConfig::new()
+        });
+        assert_eq!(ids(engine.search("same old", 3)), vec![5, 4, 3]);
+        assert_eq!(ids(engine.search_contains("old", 2)), vec![5, 4]);
+        assert_eq!(ids(engine.more_like_this(0, 2)), vec![5, 4]);
+    }
+
+    #[test]
+    fn requests_sort_matches_by_field_or_doc_id() {
+        let mut engine = Lattice::new();
+        for (text, price) in [
+            ("red shoe", Some(30.0)),
+            ("blue shoe laces", None),
+            ("shoe", Some(10.0)),
+            ("green shoe", Some(20.0)),
+            ("hat", Some(1.0)),
+        ] {
+            let id = engine.add(text).unwrap();
+            if let Some(price) = price {
+                engine.set_sort_value(id, "price", price).unwrap();
+            }
+        }
+        assert_eq!(engine.sort_value(2, "price"), Some(10.0));
+        assert_eq!(engine.sort_value(1, "price"), None);
+        assert_eq!(
+            engine.set_sort_value(9, "price", 1.0),
+            Err(lattice_types::DocumentError::NotFound { doc_id: 9 })
+        );
+        assert!(engine.set_sort_value(0, "price", f64::NAN).is_err());
+
+        let ids = |engine: &mut Lattice, sort: SortBy, limit: usize| -> Vec<DocId> {
+            let request = SearchRequest::new("shoe", limit).sort(sort);
+            engine
+                .search_request(&request)
+                .iter()
+                .map(|r| r.doc_id)
+                .collect()
+        };
+        assert_eq!(
+            ids(&mut engine, SortBy::Field("price".into()), 10),
+            [2, 3, 0, 1]
+        );
+        assert_eq!(
+            ids(&mut engine, SortBy::FieldDesc("price".into()), 2),
+            [0, 3]
+        );
+        assert_eq!(
+            ids(&mut engine, SortBy::FieldDesc("price".into()), 10),
+            [0, 3, 2, 1]
+        );
+        assert_eq!(ids(&mut engine, SortBy::DocId, 3), [0, 1, 2]);
+        // An unknown key leaves every match unvalued, ordered by score
+        assert_eq!(
+            ids(&mut engine, SortBy::Field("stock".into()), 10),
+            ids(&mut engine, SortBy::Score, 10)
+        );
+
+        let mut saved = Vec::new();
+        engine.save(&mut saved).unwrap();
+        let mut loaded = Lattice::load(saved.as_slice()).unwrap();
+        assert_eq!(
+            ids(&mut loaded, SortBy::Field("price".into()), 10),
+            [2, 3, 0, 1]
+        );
+
+        let mut merged = Lattice::new();
+        merged.add("plain shoe").unwrap();
+        merged.merge_from(&engine).unwrap();
+        assert_eq!(merged.sort_value(3, "price"), Some(10.0));
+        assert_eq!(ids(&mut merged, SortBy::Field("price".into()), 1), [3]);
+    }
+
+    #[test]
+    fn collapse_keeps_the_first_match_per_group() {
+        let mut engine = Lattice::new();
+        for (text, source, version) in [
+            ("release notes", Some(1.0), 1.0),
+            ("release notes", Some(1.0), 2.0),
+            ("release notes draft", Some(2.0), 1.0),
+            ("release notes", None, 1.0),
+            ("release notes", Some(-0.0), 1.0),
+            ("release notes", Some(0.0), 3.0),
+        ] {
+            let id = engine.add(text).unwrap();
+            if let Some(source) = source {
+                engine.set_sort_value(id, "source_id", source).unwrap();
+            }
+            engine.set_sort_value(id, "version", version).unwrap();
+        }
+
+        let ids = |engine: &mut Lattice, request: SearchRequest<'_>| -> Vec<DocId> {
+            engine
+                .search_request(&request)
+                .iter()
+                .map(|r| r.doc_id)
+                .collect()
+        };
+        let request = SearchRequest::new("release notes", 10).collapse_by("source_id");
+        assert_eq!(ids(&mut engine, request.clone()), [0, 3, 4, 2]);
+        let first_two = SearchRequest::new("release notes", 2).collapse_by("source_id");
+        assert_eq!(ids(&mut engine, first_two), [0, 3]);
+        // The newest version of each source
+        let newest = request.sort(SortBy::FieldDesc("version".into()));
+        assert_eq!(ids(&mut engine, newest), [5, 1, 3, 2]);
+        // Collapsing on an unknown key keeps everything
+        let unknown = SearchRequest::new("release notes", 10).collapse_by("missing");
+        assert_eq!(ids(&mut engine, unknown).len(), 6);
+    }
+
+    #[test]
+    fn hybrid_requests_blend_text_and_vector_similarity() {
+        use lattice_types::DocumentError;
+
+        let mut engine = Lattice::new();
+        engine.add("rust compiler").unwrap();
+        engine.add("rust compiler errors explained").unwrap();
+        engine.add("rust compiler internals").unwrap();
+        engine.set_vector(0, &[1.0, 0.0, 0.0]).unwrap();
+        engine.set_vector(1, &[0.0, 2.0, 0.0]).unwrap();
+        assert_eq!(engine.vector_dimension(), 3);
+        assert_eq!(engine.vector(1), Some(&[0.0, 2.0, 0.0][..]));
+        assert_eq!(engine.vector(2), None);
+        assert_eq!(
+            engine.set_vector(0, &[1.0, 0.0]),
+            Err(DocumentError::InvalidInput {
+                reason: "vector dimension differs from the index's",
+            })
+        );
+        assert!(engine.set_vector(0, &[f32::NAN, 0.0, 0.0]).is_err());
+        assert_eq!(
+            engine.set_vector(7, &[1.0, 0.0, 0.0]),
+            Err(DocumentError::NotFound { doc_id: 7 })
+        );
+
+        let ids = |engine: &mut Lattice, alpha: f32| -> Vec<DocId> {
+            let request = SearchRequest::new("rust compiler", 10).hybrid(alpha, &[0.0, 1.0, 0.0]);
+            engine
+                .search_request(&request)
+                .iter()
+                .map(|r| r.doc_id)
+                .collect()
+        };
+        let text_order: Vec<DocId> = engine
+            .search("rust compiler", 10)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        assert_eq!(ids(&mut engine, 0.0), text_order);
+        assert_eq!(ids(&mut engine, 1.0)[0], 1);
+        let request = SearchRequest::new("rust compiler", 10).hybrid(1.0, &[0.0, 1.0, 0.0]);
+        let scores = engine.search_request(&request);
+        assert!((scores[0].score - 1.0).abs() < 1e-6);
+        assert!(scores[1..].iter().all(|r| r.score == 0.0));
+
+        // Unusable query vectors fall back to the text ranking, and huge
+        // finite values do not overflow the similarity
+        for bad in [&[0.0, f32::NAN, 0.0][..], &[0.0, 1.0][..]] {
+            let request = SearchRequest::new("rust compiler", 10).hybrid(0.5, bad);
+            let hits = engine.search_request(&request);
+            assert_eq!(
+                hits.iter().map(|r| r.doc_id).collect::<Vec<_>>(),
+                text_order
+            );
+            assert!(hits.iter().all(|r| r.score.is_finite()));
+        }
+        engine.set_vector(2, &[1e30, 1e30, 0.0]).unwrap();
+        let request = SearchRequest::new("rust compiler", 10).hybrid(1.0, &[1e30, 1e30, 0.0]);
+        let hits = engine.search_request(&request);
+        assert_eq!(hits[0].doc_id, 2);
+        assert!((hits[0].score - 1.0).abs() < 1e-6);
+        engine.set_vector(2, &[0.0, 0.0, 0.0]).unwrap();
+
+        let mut saved = Vec::new();
+        engine.save(&mut saved).unwrap();
+        let mut loaded = Lattice::load(saved.as_slice()).unwrap();
+        assert_eq!(loaded.vector(1), engine.vector(1));
+        assert_eq!(ids(&mut loaded, 1.0)[0], 1);
+
+        let mut other = Lattice::new();
+        other.add("rust compiler plugins").unwrap();
+        other.set_vector(0, &[1.0, 1.0]).unwrap();
+        assert!(engine.merge_from(&other).is_err());
+        let mut plain = Lattice::new();
+        plain.add("rust compiler plugins").unwrap();
+        plain.merge_from(&engine).unwrap();
+        assert_eq!(plain.vector(0), None);
+        assert_eq!(plain.vector(2), engine.vector(1));
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn parquet_files_are_ingested_column_by_column() {
+        use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, UInt32Array};
+        use parquet::arrow::ArrowWriter;
+        use std::sync::Arc;
+
+        let batch = RecordBatch::try_from_iter([
+            (
+                "row",
+                Arc::new(UInt32Array::from(vec![Some(7), Some(8), None, Some(9)])) as ArrayRef,
+            ),
+            (
+                "body",
+                Arc::new(StringArray::from(vec![
+                    Some("parquet reader"),
+                    None,
+                    Some("orphan"),
+                    Some("arrow batches"),
+                ])) as ArrayRef,
+            ),
+            (
+                "price",
+                Arc::new(Float64Array::from(vec![1.0, 2.0, 3.0, 4.0])) as ArrayRef,
+            ),
+        ])
+        .unwrap();
+
+        let path = std::env::temp_dir().join(format!("lattice-{}.parquet", std::process::id()));
+        let mut writer =
+            ArrowWriter::try_new(std::fs::File::create(&path).unwrap(), batch.schema(), None)
+                .unwrap();
+        // Two writes make two batches, numbered on from each other
+        writer.write(&batch).unwrap();
+        writer.write(&batch.slice(0, 1)).unwrap();
+        writer.close().unwrap();
+
+        let mut engine = Lattice::new();
+        let file = || std::fs::File::open(&path).unwrap();
+        let report = engine
+            .add_parquet(file(), &ColumnMapping::new("body").id("row"))
+            .unwrap();
+        assert_eq!(report.added, [(7, 0), (9, 1), (7, 2)]);
+        assert_eq!((report.skipped, report.rejected), (1, 1));
+        assert_eq!(report.first_error.as_ref().map(|(row, _)| *row), Some(2));
+
+        let report = engine
+            .add_parquet(file(), &ColumnMapping::new("body"))
+            .unwrap();
+        assert_eq!(report.added, [(0, 3), (2, 4), (3, 5), (4, 6)]);
+        assert_eq!(engine.get(4), Some("orphan"));
+        engine.commit();
+        assert_eq!(engine.search("arrow", 10).len(), 2);
+
+        assert!(matches!(
+            engine.add_parquet(file(), &ColumnMapping::new("title")),
+            Err(ColumnarError::MissingColumn(column)) if column == "title"
+        ));
+        assert!(matches!(
+            engine.add_parquet(file(), &ColumnMapping::new("price")),
+            Err(ColumnarError::UnsupportedType { .. })
+        ));
+        assert!(matches!(
+            engine.add_record_batch(&batch, &ColumnMapping::new("body").id("price"), 0),
+            Err(ColumnarError::UnsupportedType { .. })
+        ));
+        assert_eq!(engine.len(), 7);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn log_entries_are_searched_by_time_range() {
+        let parse = |line| logs::parse_timestamp(line).map(|(seconds, _)| seconds);
+        assert_eq!(parse("1970-01-01T00:00:00Z x"), Some(0.0));
+        assert_eq!(parse("2024-02-29 23:59:59"), Some(1_709_251_199.0));
+        assert_eq!(
+            parse("[2024-03-01T01:30:00+01:30] x"),
+            Some(1_709_251_200.0)
+        );
+        assert_eq!(
+            parse("2024-03-01T00:00:00.25-0100 x"),
+            Some(1_709_254_800.25)
+        );
+        assert_eq!(parse("2024-03-01 00:00:00,5 x"), Some(1_709_251_200.5));
+        assert_eq!(parse("1709251200123 x"), Some(1_709_251_200.123));
+        assert_eq!(parse("1709251200 x"), Some(1_709_251_200.0));
+        for bad in [
+            "2023-02-29 00:00:00",
+            "2024-03-01T24:00:00",
+            "2024-03-01T00:00:00x",
+            "[2024-03-01T00:00:00 x",
+            "170925120 x",
+            "2024-03-01",
+            "",
+        ] {
+            assert_eq!(parse(bad), None, "{bad}");
+        }
+
+        let log = "\
+2024-03-01T10:00:00Z ERROR connection refused
+    at db::connect
+    at main
+1709290800 WARN connection slow
+2024-03-01T12:00:00Z ERROR connection refused again
+";
+        let mut engine = Lattice::new();
+        assert_eq!(engine.add_log(log.as_bytes()).unwrap(), (3, 0, None));
+        assert_eq!(
+            engine.get(0),
+            Some("error connection refused at db::connect at main")
+        );
+        assert_eq!(engine.sort_value(1, TIMESTAMP_KEY), Some(1_709_290_800.0));
+        engine
+            .add_log_line("no timestamp: connection refused")
+            .unwrap();
+        engine.commit();
+
+        let mut window = |from: f64, to: f64| {
+            let request =
+                SearchRequest::new("conection refused", 10).range(TIMESTAMP_KEY, from, to);
+            let mut ids: Vec<DocId> = engine
+                .search_request(&request)
+                .iter()
+                .map(|r| r.doc_id)
+                .collect();
+            ids.sort_unstable();
+            ids
+        };
+        assert_eq!(window(f64::MIN, f64::MAX), [0, 2]);
+        assert_eq!(window(1_709_287_200.0, 1_709_290_800.0), [0]);
+        assert_eq!(window(1_709_290_801.0, f64::INFINITY), [2]);
+        assert!(window(f64::NAN, f64::MAX).is_empty());
+        assert_eq!(engine.search("conection refused", 10).len(), 3);
+    }
+
+    #[test]
+    fn compound_tokens_match_whole() {
+        use crate::analyzer::delimiter::DelimiterSet;
+        use crate::analyzer::tokenizer::TokenizerConfig;
+
+        let config = TokenizerConfig::new()
+            .split_on(DelimiterSet::PUNCTUATION)
+            .keep_compounds();
+        let mut engine = Lattice::with_tokenizer(config);
+        engine.add("items v1 2 3 in stock").unwrap();
+        engine.add("released v1.2.3 today").unwrap();
+        engine.add("mail alice@example.com").unwrap();
+        engine.add("mail alice at example com").unwrap();
+        engine.commit();
+
+        assert_eq!(engine.search("v1.2.3", 10)[0].doc_id, 1);
+        assert_eq!(engine.search("alice@example.com", 10)[0].doc_id, 2);
+        let components = engine.search("example", 10);
+        assert_eq!(components.len(), 2);
+
+        let mut bytes = Vec::new();
+        engine.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.tokenizer_config(), config);
+        assert_eq!(loaded.search("v1.2.3", 10)[0].doc_id, 1);
+    }
+
+    #[test]
+    fn digit_policies_shape_indexing_search_and_persistence() {
+        use crate::analyzer::normalizer::{DigitPolicy, NormalizerConfig};
+
+        let config = |digits| NormalizerConfig {
+            digits,
+            ..NormalizerConfig::new()
+        };
+        let ids = |engine: &mut Lattice, query: &str| -> Vec<DocId> {
+            let mut ids: Vec<DocId> = engine.search(query, 10).iter().map(|r| r.doc_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        let mut collapsed = Lattice::with_normalizer(config(DigitPolicy::Collapse));
+        collapsed.add("order 48213 shipped").unwrap();
+        collapsed.add("order 90177 shipped").unwrap();
+        assert_eq!(collapsed.get(0), Some("order 0 shipped"));
+        // Numbers no longer tell documents apart
+        assert_eq!(ids(&mut collapsed, "order 48213"), vec![0, 1]);
+
+        let mut both = Lattice::with_normalizer(config(DigitPolicy::Both));
+        both.add("order 48213 shipped").unwrap();
+        both.add("order 90177 shipped").unwrap();
+        assert_eq!(both.get(0), Some("order 48213 0 shipped"));
+        assert_eq!(both.search("48213", 10)[0].doc_id, 0);
+
+        let mut bytes = Vec::new();
+        both.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.normalizer_config().digits, DigitPolicy::Both);
+        assert_eq!(loaded.search("48213", 10)[0].doc_id, 0);
+        loaded.add("order 7 pending").unwrap();
+        assert_eq!(loaded.get(2), Some("order 7 0 pending"));
+    }
+
+    #[test]
+    fn cjk_bigrams_index_characters_rather_than_bytes() {
+        use crate::analyzer::tokenizer::TokenizerConfig;
+
+        // 亞 (E4 BA 9E) shares two bytes with 京 (E4 BA AC)
+        let docs = [
+            "東京タワーの夜景",
+            "東亞の歴史",
+            "京都の寺",
+            "tokyo tower 東京",
+        ];
+        let build = |tokenizer| {
+            let mut engine = Lattice::builder()
+                .tokenizer(tokenizer)
+                .search_config(lattice_types::SearchConfig::exact())
+                .index_config(IndexConfig::positional())
+                .build()
+                .unwrap();
+            for doc in docs {
+                engine.add(doc).unwrap();
+            }
+            engine
+        };
+        let ids = |engine: &mut Lattice, query: &str| -> Vec<DocId> {
+            let mut ids: Vec<DocId> = engine.search(query, 10).iter().map(|r| r.doc_id).collect();
+            ids.sort_unstable();
+            ids
+        };
+
+        // Byte trigrams spanning 東 and the first bytes of 京 match 東亞
+        let mut bytes = build(TokenizerConfig::new());
+        assert_eq!(ids(&mut bytes, "東京"), vec![0, 1, 3]);
+
+        let mut chars = build(TokenizerConfig::new().cjk_bigrams());
+        assert_eq!(ids(&mut chars, "東京"), vec![0, 3]);
+        assert_eq!(ids(&mut chars, "京都"), vec![2]);
+        assert_eq!(ids(&mut chars, "tokyo 東京"), vec![3]);
+        chars.commit();
+        assert_eq!(ids(&mut chars, "タワー"), vec![0]);
+        assert_eq!(chars.phrase_offsets(0, "タワー"), vec![6]);
+        let phrase: Vec<DocId> = chars
+            .search_phrase("京タ", 10)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        assert_eq!(phrase, vec![0]);
+
+        let mut file = Vec::new();
+        chars.save(&mut file).unwrap();
+        let mut loaded = Lattice::load(file.as_slice()).unwrap();
+        assert!(loaded.tokenizer_config().cjk_bigrams);
+        assert_eq!(ids(&mut loaded, "東京"), vec![0, 3]);
+        assert_eq!(loaded.phrase_offsets(3, "東京"), vec![12]);
+    }
+
+    #[test]
+    fn field_normalizers_apply_per_field_and_persist() {
+        use crate::analyzer::normalizer::NormalizerConfig;
+        use crate::analyzer::tokenizer::Field;
+
+        let folded = NormalizerConfig::strip_diacritics();
+        let mut engine = Lattice::builder()
+            .field_normalizer(Field::Body, folded)
+            .build()
+            .unwrap();
+        assert_eq!(engine.field_normalizer_config(Field::Body), folded);
+        assert_eq!(
+            engine.field_normalizer_config(Field::Tag),
+            NormalizerConfig::new()
+        );
+
+        engine
+            .add_fields(&[(Field::Tag, "Crème"), (Field::Body, "Crème brûlée")])
+            .unwrap();
+        engine.add("Déjà vu").unwrap();
+        assert_eq!(engine.get(0), Some("crème creme brulee"));
+        // Plain documents are body text
+        assert_eq!(engine.get(1), Some("deja vu"));
+        let hits = engine.search("brulee", 10);
+        assert_eq!(hits[0].doc_id, 0);
+
+        let mut file = Vec::new();
+        engine.save(&mut file).unwrap();
+        let mut loaded = Lattice::load(file.as_slice()).unwrap();
+        assert_eq!(loaded.field_normalizer_config(Field::Body), folded);
+        assert_eq!(
+            loaded.field_normalizer_config(Field::Title),
+            NormalizerConfig::new()
+        );
+        loaded.add("Noël").unwrap();
+        assert_eq!(loaded.get(2), Some("noel"));
+
+        // A case-sensitive code field under a case-sensitive engine, with
+        // prose lowercased
+        let keep_case = NormalizerConfig {
+            keep_case: true,
+            ..NormalizerConfig::new()
+        };
+        let mut code = Lattice::builder()
+            .normalizer(keep_case)
+            .field_normalizer(Field::Body, NormalizerConfig::new())
+            .build()
+            .unwrap();
+        code.add_fields(&[
+            (Field::Title, "HashMap::new"),
+            (Field::Body, "Creates a Map"),
+        ])
+        .unwrap();
+        code.add_fields(&[
+            (Field::Title, "hashmap_new"),
+            (Field::Body, "Creates a map"),
+        ])
+        .unwrap();
+        assert_eq!(code.get(0), Some("HashMap::new creates a map"));
+        let request = SearchRequest::new("HashMap", 10).fields(&[(Field::Title, 1.0)]);
+        let hits = code.search_request(&request);
+        assert_eq!(hits[0].doc_id, 0);
+        assert!(hits.len() == 1 || hits[1].score < hits[0].score);
+
+        let mut file = Vec::new();
+        code.save(&mut file).unwrap();
+        let loaded = Lattice::load(file.as_slice()).unwrap();
+        assert_eq!(loaded.normalizer_config(), keep_case);
+    }
+
+    #[test]
+    fn emoji_policies_shape_indexing_search_and_persistence() {
+        use crate::analyzer::normalizer::{EmojiPolicy, NormalizerConfig};
+
+        let config = |emoji| NormalizerConfig {
+            emoji,
+            ..NormalizerConfig::new()
+        };
+
+        let mut dropped = Lattice::with_normalizer(config(EmojiPolicy::Drop));
+        dropped.add("launch day🚀🚀🚀").unwrap();
+        assert_eq!(dropped.get(0), Some("launch day"));
+        assert_eq!(dropped.search("launch day", 10)[0].doc_id, 0);
+
+        let mut named = Lattice::with_normalizer(config(EmojiPolicy::Shortcode));
+        named.add("launch day🚀").unwrap();
+        named.add("pizza party 🍕🎉").unwrap();
+        assert_eq!(named.get(0), Some("launch day rocket"));
+        assert_eq!(named.search("rocket", 10)[0].doc_id, 0);
+        // Queries map emoji the same way
+        assert_eq!(named.search("🍕", 10)[0].doc_id, 1);
+
+        let mut bytes = Vec::new();
+        named.save(&mut bytes).unwrap();
+        let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+        assert_eq!(loaded.normalizer_config().emoji, EmojiPolicy::Shortcode);
+        assert_eq!(loaded.search("tada", 10)[0].doc_id, 1);
+        loaded.add("so tired😴").unwrap();
+        assert_eq!(loaded.get(2), Some("so tired sleeping"));
+    }
+
+    #[test]
+    fn positional_cjk_phrases_start_before_their_first_term() {
+        use crate::analyzer::tokenizer::TokenizerConfig;
+
+        let build = |index_config| {
+            let mut engine = Lattice::builder()
+                .tokenizer(TokenizerConfig::new().cjk_bigrams())
+                .index_config(index_config)
+                .build()
+                .unwrap();
+            engine.add("xa日本 東京").unwrap();
+            engine.add("b日本").unwrap();
+            engine
+        };
+        let mut positional = build(IndexConfig::positional());
+        let mut scanning = build(IndexConfig::default());
+
+        for engine in [&mut positional, &mut scanning] {
+            // "a" and "xa" are too short for a trigram of their own
+            let hits = engine.search_phrase("a日本", 10);
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].doc_id, 0);
+            assert_eq!(engine.phrase_offsets(0, "xa日"), vec![0]);
+            assert_eq!(engine.phrase_offsets(0, "日本 東"), vec![2]);
+            assert_eq!(engine.search_phrase("日本", 10).len(), 2);
+        }
+    }
+
+    #[test]
+    fn minimum_should_match_parses_and_applies_specs() {
+        let cases: [(&str, [usize; 5]); 7] = [
+            // Trigram totals: 1, 2, 4, 5, 10
+            ("3", [1, 2, 3, 3, 3]),
+            ("-2", [1, 1, 2, 3, 8]),
+            ("75%", [1, 1, 3, 3, 7]),
+            ("-25%", [1, 2, 3, 4, 8]),
+            ("2<75%", [1, 2, 3, 3, 7]),
+            ("2<-25% 9<-3", [1, 2, 3, 4, 7]),
+            ("100%", [1, 2, 4, 5, 10]),
+        ];
+        for (spec, expected) in cases {
+            let msm: MinimumShouldMatch = spec.parse().unwrap();
+            let got = [1, 2, 4, 5, 10].map(|total| msm.required(total));
+            assert_eq!(got, expected, "{}", spec);
+            assert_eq!(msm.to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn minimum_should_match_rejects_malformed_specs() {
+        let parse = |s: &str| s.parse::<MinimumShouldMatch>().unwrap_err();
+        assert_eq!(parse("  "), MinimumShouldMatchError::Empty);
+        assert_eq!(
+            parse("lots"),
+            MinimumShouldMatchError::Invalid {
+                clause: "lots".to_string()
+            }
+        );
+        assert_eq!(
+            parse("50% 3"),
+            MinimumShouldMatchError::Invalid {
+                clause: "50%".to_string()
+            }
+        );
+        assert_eq!(
+            parse("150%"),
+            MinimumShouldMatchError::PercentOutOfRange { percent: 150 }
+        );
+        assert_eq!(
+            parse("5<50% 3<1"),
+            MinimumShouldMatchError::UnorderedThresholds
+        );
+    }
+
+    #[cfg(feature = "regex")]
+    fn regex_query(pattern: &str) -> pattern::TrigramQuery {
+        pattern::Info::analyze(&regex_syntax::parse(pattern).unwrap()).into_query()
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_patterns_derive_trigram_queries() {
+        use pattern::TrigramQuery;
+
+        let query = regex_query;
+        let t = |s: &str| TrigramQuery::Trigram(lattice_types::Trigram::from_str(s));
+        assert_eq!(query("abcd"), t("abc").and(t("bcd")));
+        assert_eq!(query("ab"), TrigramQuery::All);
+        assert_eq!(query("a.*b"), TrigramQuery::All);
+        assert_eq!(query("(abc|xyz)"), t("abc").or(t("xyz")));
+        assert_eq!(query("ab[cd]"), t("abc").or(t("abd")));
+        // Both ends of an unbounded gap still contribute
+        assert_eq!(query("abc.*xyz"), t("abc").and(t("xyz")));
+        assert_eq!(query("(abc)+"), t("abc"));
+        assert_eq!(query("x(abc)?"), TrigramQuery::All);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_ascii_only_keeps_the_trigrams_outside_cjk_text() {
+        use pattern::TrigramQuery;
+
+        let query = regex_query;
+        assert_eq!(query("東京 tower").ascii_only(), query(" tower"));
+        assert_eq!(query("(東京|abc)").ascii_only(), TrigramQuery::All);
+        assert_eq!(query("東京").ascii_only(), TrigramQuery::All);
+    }
+
+    #[test]
+    fn latency_buckets_are_contiguous_and_bounded() {
+        use super::metrics::{BUCKETS, SUB_COUNT};
+
+        let mut previous = 0;
+        for value in (0..10_000u64).chain([u32::MAX as u64, u64::MAX - 1, u64::MAX]) {
+            let bucket = LatencyHistogram::bucket(value);
+            assert!(bucket < BUCKETS);
+            assert!(bucket >= previous || value > 10_000);
+            previous = bucket;
+
+            let upper = LatencyHistogram::bucket_upper(bucket);
+            assert!(upper >= value);
+            assert!((upper - value) as f64 <= value as f64 / SUB_COUNT as f64);
+            assert_eq!(LatencyHistogram::bucket(upper), bucket);
+        }
+    }
+
+    #[test]
+    fn latency_quantiles_are_within_relative_error() {
+        use std::time::Duration;
+
+        let mut histogram = LatencyHistogram::new();
+        assert_eq!(histogram.quantile(0.5), Duration::ZERO);
+
+        for micros in 1..=1000 {
+            histogram.record(Duration::from_micros(micros));
+        }
+        assert_eq!(histogram.len(), 1000);
+        assert_eq!(histogram.max(), Duration::from_micros(1000));
+        assert_eq!(histogram.quantile(1.0), Duration::from_micros(1000));
+
+        for (q, expected) in [(0.5, 500.0), (0.9, 900.0), (0.99, 990.0)] {
+            let got = histogram.quantile(q).as_secs_f64() * 1e6;
+            assert!(
+                got >= expected && got <= expected * 1.07,
+                "q={} got={}",
+                q,
+                got
+            );
+        }
+        let mean = histogram.mean().as_secs_f64() * 1e6;
+        assert!((mean - 500.5).abs() < 0.01);
+
+        let mut merged = LatencyHistogram::new();
+        merged.merge(&histogram);
+        merged.merge(&histogram);
+        assert_eq!(merged.len(), 2000);
+        assert_eq!(merged.quantile(0.5), histogram.quantile(0.5));
+
+        merged.reset();
+        assert!(merged.is_empty());
+        assert_eq!(merged.max(), Duration::ZERO);
+    }
+
+    #[test]
+    fn dump_escapes_json_strings() {
+        use super::dump::{json_f32, trigram_label, write_json_str};
+        use lattice_types::Trigram;
+
+        let mut out = Vec::new();
+        write_json_str(&mut out, "a \"b\" \\ c\nd\u{1}é").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#""a \"b\" \\ c\nd\u0001é""#
+        );
+        assert_eq!(json_f32(f32::NAN), "null");
+        assert_eq!(
+            trigram_label(Trigram::from_bytes(b'c', 0xc3, 0xa9)),
+            "c\\xc3\\xa9"
+        );
+    }
+
+    #[test]
+    fn scoring_options_survive_save_and_load() {
+        let mut engine = Lattice::with_index_config(IndexConfig {
+            store_term_frequencies: true,
+            ..IndexConfig::new()
+        });
+        engine.add("short rust note").unwrap();
+        engine
+            .add("a much longer rust document about many things")
+            .unwrap();
+        let scoring = ScoringConfig {
+            mode: ScoringMode::Bm25 { k1: 1.5, b: 0.25 },
+            length_norm: LengthNorm::Pivoted { b: 0.5 },
+            ..ScoringConfig::new()
+        };
+        engine.set_scoring(scoring);
+        let before = engine.search("rust", 10);
+
+        let mut loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.scoring_config(), scoring);
+        assert_eq!(loaded.search("rust", 10), before);
+    }
+
+    #[test]
+    fn proximity_weight_survives_save_and_load() {
+        let mut engine = Lattice::with_index_config(IndexConfig::positional());
+        engine.add("new yolk and york new").unwrap();
+        engine
+            .add("visit the new york office today please")
+            .unwrap();
+        engine.set_scoring(ScoringConfig {
+            proximity_weight: 2.0,
+            ..ScoringConfig::new()
+        });
+        assert_eq!(engine.search("new york", 2)[0].doc_id, 1);
+
+        let mut loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.scoring_config().proximity_weight, 2.0);
+        assert_eq!(loaded.search("new york", 2), engine.search("new york", 2));
+    }
+
+    #[test]
+    fn tie_break_survives_save_and_load() {
+        let mut engine = Lattice::new();
+        engine.add("rust").unwrap();
+        engine.add("rust").unwrap();
+        engine.set_scoring(ScoringConfig {
+            tie_break: TieBreak::NewestFirst,
+            ..ScoringConfig::new()
+        });
+        assert_eq!(engine.search("rust", 2)[0].doc_id, 1);
+
+        let mut loaded = Lattice::load(saved(&engine).as_slice()).unwrap();
+        assert_eq!(loaded.scoring_config().tie_break, TieBreak::NewestFirst);
+        assert_eq!(loaded.search("rust", 2)[0].doc_id, 1);
+    }
 }
@@ -0,0 +1,228 @@
+//! Tenant namespaces.
+//!
+//! [`Lattice::namespace`] returns a [`Namespace`] handle whose documents
+//! and queries are isolated from other tenants: its searches return only
+//! its own documents. All namespaces share one set of posting lists, one
+//! document arena and one spelling dictionary, so hundreds of small tenants
+//! cost about as much as one index of the same total size.
+//!
+//! Documents added to the engine directly belong to no namespace. Searches
+//! on the engine itself still see every document, which suits
+//! administrative tools.
+
+use crate::analyzer::tokenizer::Field;
+use crate::index::request::SearchRequest;
+use crate::index::types::Lattice;
+use lattice_types::{DocId, DocumentError, SearchResult};
+
+/// Namespace of every document (empty until the first namespaced add).
+#[derive(Clone, Default)]
+pub(crate) struct NamespaceIndex {
+    /// Registered names; namespace `n` is `names[n - 1]`
+    names: Vec<String>,
+    /// Namespace of each document, 0 for none; documents past the end
+    /// have none
+    doc_namespaces: Vec<u32>,
+}
+
+impl NamespaceIndex {
+    /// Returns the ID of `name`, registering it if new.
+    fn intern(&mut self, name: &str) -> u32 {
+        match self.id(name) {
+            Some(id) => id,
+            None => {
+                self.names.push(name.to_owned());
+                self.names.len() as u32
+            }
+        }
+    }
+
+    fn id(&self, name: &str) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|n| n == name)
+            .map(|i| i as u32 + 1)
+    }
+
+    pub(crate) fn name(&self, id: u32) -> Option<&str> {
+        let index = (id as usize).checked_sub(1)?;
+        self.names.get(index).map(String::as_str)
+    }
+
+    pub(crate) fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub(crate) fn doc_namespaces(&self) -> &[u32] {
+        &self.doc_namespaces
+    }
+
+    /// Returns the namespace of document `doc`, 0 for none.
+    #[inline]
+    pub(crate) fn of(&self, doc: usize) -> u32 {
+        self.doc_namespaces.get(doc).copied().unwrap_or(0)
+    }
+
+    /// Places document `doc` in namespace `id`.
+    fn assign(&mut self, doc: usize, id: u32) {
+        if self.doc_namespaces.len() <= doc {
+            self.doc_namespaces.resize(doc + 1, 0);
+        }
+        self.doc_namespaces[doc] = id;
+    }
+
+    /// Restores a saved index's namespaces.
+    pub(crate) fn restore(&mut self, names: Vec<String>, doc_namespaces: Vec<u32>) {
+        self.names = names;
+        self.doc_namespaces = doc_namespaces;
+    }
+
+    /// Appends the namespaces of `other`'s `count` documents after this
+    /// index's first `first`, matching namespaces by name.
+    pub(crate) fn append(&mut self, other: &NamespaceIndex, first: usize, count: usize) {
+        let ids: Vec<u32> = other.names.iter().map(|name| self.intern(name)).collect();
+        for doc in 0..count {
+            let id = other.of(doc);
+            if id != 0 {
+                self.assign(first + doc, ids[id as usize - 1]);
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.names.clear();
+        self.doc_namespaces.clear();
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.doc_namespaces.shrink_to_fit();
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.doc_namespaces.capacity() * size_of::<u32>()
+            + self.names.iter().map(String::capacity).sum::<usize>()
+            + self.names.capacity() * size_of::<String>()
+    }
+}
+
+/// A tenant's view of a [`Lattice`], returned by [`Lattice::namespace`].
+///
+/// Documents added through the handle belong to the namespace, and its
+/// searches return only those documents. DocIds are shared with the
+/// engine, so [`Lattice::get`] and friends work on them as usual.
+pub struct Namespace<'a> {
+    engine: &'a mut Lattice,
+    id: u32,
+}
+
+impl Lattice {
+    /// Returns the namespace called `name`, creating it if needed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.namespace("tenant-a").add("quarterly report").unwrap();
+    /// engine.namespace("tenant-b").add("quarterly budget").unwrap();
+    ///
+    /// let results = engine.namespace("tenant-a").search("quarterly", 10);
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(engine.namespace_of(results[0].doc_id), Some("tenant-a"));
+    ///
+    /// // The engine itself sees every tenant
+    /// assert_eq!(engine.search("quarterly", 10).len(), 2);
+    /// ```
+    pub fn namespace(&mut self, name: &str) -> Namespace<'_> {
+        let id = self.namespaces.intern(name);
+        Namespace { engine: self, id }
+    }
+
+    /// Returns the namespace `doc_id` was added to, or `None` if it was
+    /// added to the engine directly or does not exist.
+    pub fn namespace_of(&self, doc_id: DocId) -> Option<&str> {
+        self.namespaces.name(self.namespaces.of(doc_id as usize))
+    }
+
+    /// Returns the names of all namespaces, in creation order.
+    pub fn namespace_names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.namespaces.names().iter().map(String::as_str)
+    }
+}
+
+impl Namespace<'_> {
+    /// Returns the namespace's name.
+    pub fn name(&self) -> &str {
+        self.engine.namespaces.name(self.id).unwrap_or_default()
+    }
+
+    /// Adds a document to the namespace. See [`Lattice::add`].
+    ///
+    /// # Errors
+    ///
+    /// As for [`Lattice::add`].
+    pub fn add(&mut self, content: &str) -> Result<DocId, DocumentError> {
+        let doc_id = self.engine.add(content)?;
+        self.engine.namespaces.assign(doc_id as usize, self.id);
+        Ok(doc_id)
+    }
+
+    /// Adds a multi-field document to the namespace. See
+    /// [`Lattice::add_fields`].
+    ///
+    /// # Errors
+    ///
+    /// As for [`Lattice::add_fields`].
+    pub fn add_fields(&mut self, fields: &[(Field, &str)]) -> Result<DocId, DocumentError> {
+        let doc_id = self.engine.add_fields(fields)?;
+        self.engine.namespaces.assign(doc_id as usize, self.id);
+        Ok(doc_id)
+    }
+
+    /// Searches the namespace's documents. See [`Lattice::search`].
+    pub fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+        self.search_request(&SearchRequest::new(query, limit))
+    }
+
+    /// Searches the namespace's documents with per-query options. See
+    /// [`Lattice::search_request`].
+    pub fn search_request(&mut self, request: &SearchRequest<'_>) -> Vec<SearchResult> {
+        let mut request = request.clone();
+        request.namespace = Some(self.id);
+        self.engine.search_request(&request)
+    }
+
+    /// Returns true if `doc_id` belongs to the namespace.
+    #[inline]
+    pub fn contains(&self, doc_id: DocId) -> bool {
+        (doc_id as usize) < self.engine.len()
+            && self.engine.namespaces.of(doc_id as usize) == self.id
+    }
+
+    /// Returns a document of the namespace; `None` for other tenants'
+    /// documents.
+    pub fn get(&self, doc_id: DocId) -> Option<&str> {
+        self.contains(doc_id)
+            .then(|| self.engine.get(doc_id))
+            .flatten()
+    }
+
+    /// Returns the number of documents in the namespace.
+    ///
+    /// Counts them, so this is O(documents in the engine).
+    pub fn len(&self) -> usize {
+        let id = self.id;
+        self.engine
+            .namespaces
+            .doc_namespaces()
+            .iter()
+            .filter(|&&ns| ns == id)
+            .count()
+    }
+
+    /// Returns true if the namespace has no documents.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
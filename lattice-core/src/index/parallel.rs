@@ -0,0 +1,112 @@
+//! Multi-threaded bulk indexing.
+//!
+//! Documents are split into contiguous shards, one per thread. Each thread
+//! normalizes its documents, extracts and sorts their trigrams and builds a
+//! complete shard index. Neighbouring shards are then combined pairwise
+//! (also in parallel) with [`Lattice::merge_from`], so DocIds follow the
+//! input order exactly as if the documents had been added one by one.
+//!
+//! ```text
+//! docs ─▶ [shard 0] [shard 1] [shard 2] [shard 3]   build + sort per thread
+//!              └──┬───┘          └──┬───┘           merge pairs in parallel
+//!              [0 + 1]           [2 + 3]
+//!                  └──────┬──────────┘
+//!                     [0 + 1 + 2 + 3]
+//! ```
+
+use crate::index::types::Lattice;
+use lattice_types::{DocumentError, SearchConfig};
+use std::num::NonZeroUsize;
+use std::thread;
+
+impl Lattice {
+    /// Builds an index from `docs` using up to `num_threads` threads.
+    ///
+    /// `num_threads == 0` uses the available parallelism. Document `i` of
+    /// the input gets DocId `i`, and the returned index is fully committed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the first document (in input order) that
+    /// [`add`](Self::add) would reject; no index is returned in that case.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let docs: Vec<String> = (0..1000).map(|i| format!("document number {}", i)).collect();
+    /// let mut engine = Lattice::build_parallel(&docs, 4).unwrap();
+    ///
+    /// assert_eq!(engine.len(), 1000);
+    /// assert_eq!(engine.search("number 417", 1)[0].doc_id, 417);
+    /// ```
+    pub fn build_parallel<S>(docs: &[S], num_threads: usize) -> Result<Lattice, DocumentError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        Self::build_parallel_with_config(SearchConfig::default(), docs, num_threads)
+    }
+
+    /// Like [`build_parallel`](Self::build_parallel), with a custom search
+    /// configuration.
+    pub fn build_parallel_with_config<S>(
+        config: SearchConfig,
+        docs: &[S],
+        num_threads: usize,
+    ) -> Result<Lattice, DocumentError>
+    where
+        S: AsRef<str> + Sync,
+    {
+        let threads = match num_threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        };
+        let shard_len = docs.len().div_ceil(threads).max(1);
+
+        let mut shards = thread::scope(|scope| {
+            let handles: Vec<_> = docs
+                .chunks(shard_len)
+                .map(|chunk| scope.spawn(move || Self::build_shard(config, chunk)))
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("shard build panicked"))
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+
+        while shards.len() > 1 {
+            shards = thread::scope(|scope| {
+                let mut handles = Vec::with_capacity(shards.len().div_ceil(2));
+                let mut remaining = shards.into_iter();
+                while let Some(mut left) = remaining.next() {
+                    let right = remaining.next();
+                    handles.push(scope.spawn(move || {
+                        if let Some(right) = right {
+                            left.merge_from(&right)?;
+                        }
+                        Ok(left)
+                    }));
+                }
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().expect("shard merge panicked"))
+                    .collect::<Result<Vec<_>, DocumentError>>()
+            })?;
+        }
+
+        Ok(shards.pop().unwrap_or_else(|| Lattice::with_config(config)))
+    }
+
+    fn build_shard<S: AsRef<str>>(
+        config: SearchConfig,
+        docs: &[S],
+    ) -> Result<Lattice, DocumentError> {
+        let mut shard = Lattice::with_config(config);
+        for doc in docs {
+            shard.add(doc.as_ref())?;
+        }
+        shard.merge_delta();
+        Ok(shard)
+    }
+}
@@ -0,0 +1,437 @@
+//! Trigram-accelerated regular expression search.
+//!
+//! A regex is turned into a boolean query over trigrams that every match
+//! must satisfy, following Russ Cox's "Regular Expression Matching with a
+//! Trigram Index". Each sub-expression is summarized by:
+//!
+//! - `exact`: the full set of strings it matches, while small enough
+//! - `prefix` / `suffix`: sets that every match starts / ends with
+//! - `query`: trigrams required inside any match
+//!
+//! Concatenation joins the suffixes of the left side with the prefixes of
+//! the right one, which is where most trigrams come from. The query's
+//! posting lists yield candidates, and the real regex engine confirms each
+//! one against the stored text.
+//!
+//! ```text
+//!  /hello (world|there)/  →  hel ∧ ell ∧ llo ∧ ... ∧ ((o w ∧ wor ∧ ...) ∨ (o t ∧ the ∧ ...))
+//! ```
+
+use crate::index::types::Lattice;
+use lattice_types::{DocId, SearchResult, Trigram};
+use regex::Regex;
+use regex_syntax::hir::{Class, Hir, HirKind};
+use std::collections::BTreeSet;
+
+/// Largest string set kept exactly before it is summarized
+const MAX_SET: usize = 16;
+/// Largest character class expanded into its members
+const MAX_CLASS: usize = 8;
+
+/// Boolean query over trigram posting lists.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum TrigramQuery {
+    /// Matches every document
+    All,
+    Trigram(Trigram),
+    And(Vec<TrigramQuery>),
+    Or(Vec<TrigramQuery>),
+}
+
+impl TrigramQuery {
+    pub(crate) fn and(self, other: TrigramQuery) -> TrigramQuery {
+        match (self, other) {
+            (TrigramQuery::All, q) | (q, TrigramQuery::All) => q,
+            (a, b) if a == b => a,
+            (TrigramQuery::And(mut a), TrigramQuery::And(b)) => {
+                for q in b {
+                    push_unique(&mut a, q);
+                }
+                TrigramQuery::And(a)
+            }
+            (TrigramQuery::And(mut a), q) | (q, TrigramQuery::And(mut a)) => {
+                push_unique(&mut a, q);
+                TrigramQuery::And(a)
+            }
+            (a, b) => TrigramQuery::And(vec![a, b]),
+        }
+    }
+
+    pub(crate) fn or(self, other: TrigramQuery) -> TrigramQuery {
+        match (self, other) {
+            (TrigramQuery::All, _) | (_, TrigramQuery::All) => TrigramQuery::All,
+            (a, b) if a == b => a,
+            (TrigramQuery::Or(mut a), TrigramQuery::Or(b)) => {
+                for q in b {
+                    push_unique(&mut a, q);
+                }
+                TrigramQuery::Or(a)
+            }
+            (TrigramQuery::Or(mut a), q) | (q, TrigramQuery::Or(mut a)) => {
+                push_unique(&mut a, q);
+                TrigramQuery::Or(a)
+            }
+            (a, b) => TrigramQuery::Or(vec![a, b]),
+        }
+    }
+
+    /// Drops the trigrams with non-ASCII bytes, which may belong to CJK
+    /// characters that [`TokenizerConfig::cjk_bigrams`] indexes by
+    /// character instead.
+    ///
+    /// [`TokenizerConfig::cjk_bigrams`]: crate::analyzer::TokenizerConfig::cjk_bigrams
+    pub(crate) fn ascii_only(self) -> TrigramQuery {
+        match self {
+            TrigramQuery::Trigram(t) if !t.to_bytes().is_ascii() => TrigramQuery::All,
+            TrigramQuery::And(subs) => subs
+                .into_iter()
+                .map(Self::ascii_only)
+                .fold(TrigramQuery::All, TrigramQuery::and),
+            TrigramQuery::Or(subs) => subs
+                .into_iter()
+                .map(Self::ascii_only)
+                .reduce(TrigramQuery::or)
+                .unwrap_or(TrigramQuery::All),
+            q => q,
+        }
+    }
+
+    /// Requires all trigrams of `s`.
+    fn of_string(s: &[u8]) -> TrigramQuery {
+        s.windows(3)
+            .map(|w| TrigramQuery::Trigram(Trigram::from_bytes(w[0], w[1], w[2])))
+            .fold(TrigramQuery::All, TrigramQuery::and)
+    }
+
+    /// Requires the trigrams of at least one string of `set`.
+    fn of_set(set: &BTreeSet<Vec<u8>>) -> TrigramQuery {
+        let mut strings = set.iter();
+        let Some(first) = strings.next() else {
+            return TrigramQuery::All;
+        };
+        strings.fold(Self::of_string(first), |q, s| q.or(Self::of_string(s)))
+    }
+}
+
+/// What is known about the strings matched by a sub-expression.
+#[derive(Debug, Clone)]
+pub(crate) struct Info {
+    exact: Option<BTreeSet<Vec<u8>>>,
+    prefix: BTreeSet<Vec<u8>>,
+    suffix: BTreeSet<Vec<u8>>,
+    query: TrigramQuery,
+}
+
+impl Info {
+    fn exact(set: BTreeSet<Vec<u8>>) -> Self {
+        Info {
+            prefix: set.clone(),
+            suffix: set.clone(),
+            exact: Some(set),
+            query: TrigramQuery::All,
+        }
+    }
+
+    fn empty_string() -> Self {
+        Self::exact(BTreeSet::from([Vec::new()]))
+    }
+
+    /// Matches arbitrary text, possibly empty.
+    fn anything() -> Self {
+        Info {
+            exact: None,
+            prefix: BTreeSet::from([Vec::new()]),
+            suffix: BTreeSet::from([Vec::new()]),
+            query: TrigramQuery::All,
+        }
+    }
+
+    pub(crate) fn analyze(hir: &Hir) -> Self {
+        let info = match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => Self::empty_string(),
+            HirKind::Literal(literal) => Self::exact(BTreeSet::from([literal.0.to_vec()])),
+            HirKind::Class(class) => match class_members(class) {
+                Some(set) => Self::exact(set),
+                None => Self::anything(),
+            },
+            HirKind::Capture(capture) => Self::analyze(&capture.sub),
+            HirKind::Repetition(rep) => {
+                let sub = Self::analyze(&rep.sub);
+                match (rep.min, rep.max) {
+                    (0, Some(1)) => sub.alternate(Self::empty_string()),
+                    (0, _) => Self::anything(),
+                    // e{n,} and e{n,m} with n > 0 both behave like e+: the
+                    // ends and inner trigrams of e are required
+                    _ => {
+                        let query = sub.query_with_exact();
+                        Info {
+                            exact: None,
+                            prefix: sub.prefix,
+                            suffix: sub.suffix,
+                            query,
+                        }
+                    }
+                }
+            }
+            HirKind::Concat(subs) => subs
+                .iter()
+                .map(Self::analyze)
+                .reduce(|info, next| info.concat(next).simplify())
+                .unwrap_or_else(Self::empty_string),
+            HirKind::Alternation(subs) => subs
+                .iter()
+                .map(Self::analyze)
+                .reduce(Self::alternate)
+                .unwrap_or_else(Self::empty_string),
+        };
+        info.simplify()
+    }
+
+    fn concat(self, next: Info) -> Info {
+        let exact = match (&self.exact, &next.exact) {
+            (Some(a), Some(b)) if a.len() * b.len() <= MAX_SET => Some(cross(a, b)),
+            _ => None,
+        };
+        let prefix = match &self.exact {
+            Some(a) => cross(a, &next.prefix),
+            None => self.prefix.clone(),
+        };
+        let suffix = match &next.exact {
+            Some(b) => cross(&self.suffix, b),
+            None => next.suffix.clone(),
+        };
+
+        let mut query = self.query.and(next.query);
+        if exact.is_none() {
+            // Trigrams spanning the boundary
+            let joined = cross(&self.suffix, &next.prefix);
+            if joined.len() <= MAX_SET {
+                query = query.and(TrigramQuery::of_set(&joined));
+            }
+        }
+        Info {
+            exact,
+            prefix,
+            suffix,
+            query,
+        }
+    }
+
+    fn alternate(self, other: Info) -> Info {
+        let exact = match (self.exact.as_ref(), other.exact.as_ref()) {
+            (Some(a), Some(b)) => Some(a.union(b).cloned().collect()),
+            _ => None,
+        };
+        let query = if exact.is_some() {
+            TrigramQuery::All
+        } else {
+            self.query_with_exact().or(other.query_with_exact())
+        };
+        Info {
+            exact,
+            prefix: self.prefix.union(&other.prefix).cloned().collect(),
+            suffix: self.suffix.union(&other.suffix).cloned().collect(),
+            query,
+        }
+    }
+
+    /// The query including the trigrams implied by `exact`.
+    fn query_with_exact(&self) -> TrigramQuery {
+        match &self.exact {
+            Some(set) => self.query.clone().and(TrigramQuery::of_set(set)),
+            None => self.query.clone(),
+        }
+    }
+
+    /// Keeps the string sets small, moving what they imply into `query`.
+    fn simplify(mut self) -> Info {
+        if self.exact.as_ref().is_some_and(|set| set.len() > MAX_SET) {
+            self.query = self.query_with_exact();
+            self.exact = None;
+        }
+        if self.exact.is_none() {
+            self.prefix = shrink(&mut self.query, self.prefix, |s| &s[..2]);
+            self.suffix = shrink(&mut self.query, self.suffix, |s| &s[s.len() - 2..]);
+        }
+        self
+    }
+
+    /// The query every match of the whole pattern satisfies.
+    pub(crate) fn into_query(self) -> TrigramQuery {
+        match &self.exact {
+            Some(_) => self.query_with_exact(),
+            None => self
+                .query
+                .and(TrigramQuery::of_set(&self.prefix))
+                .and(TrigramQuery::of_set(&self.suffix)),
+        }
+    }
+}
+
+/// Caps an oversized prefix or suffix set by requiring its trigrams and
+/// cutting its strings down to two bytes (with `cut`).
+fn shrink(
+    query: &mut TrigramQuery,
+    set: BTreeSet<Vec<u8>>,
+    cut: impl Fn(&[u8]) -> &[u8],
+) -> BTreeSet<Vec<u8>> {
+    if set.len() <= MAX_SET {
+        return set;
+    }
+    *query = query.clone().and(TrigramQuery::of_set(&set));
+    let cut: BTreeSet<Vec<u8>> = set
+        .iter()
+        .map(|s| {
+            if s.len() > 2 {
+                cut(s).to_vec()
+            } else {
+                s.clone()
+            }
+        })
+        .collect();
+    if cut.len() <= MAX_SET {
+        cut
+    } else {
+        BTreeSet::from([Vec::new()])
+    }
+}
+
+fn push_unique(queries: &mut Vec<TrigramQuery>, query: TrigramQuery) {
+    if !queries.contains(&query) {
+        queries.push(query);
+    }
+}
+
+fn cross(a: &BTreeSet<Vec<u8>>, b: &BTreeSet<Vec<u8>>) -> BTreeSet<Vec<u8>> {
+    a.iter()
+        .flat_map(|x| b.iter().map(move |y| [x.as_slice(), y].concat()))
+        .collect()
+}
+
+/// The UTF-8 encodings of the members of a small class.
+fn class_members(class: &Class) -> Option<BTreeSet<Vec<u8>>> {
+    let mut set = BTreeSet::new();
+    match class {
+        Class::Unicode(class) => {
+            for range in class.ranges() {
+                for c in range.start()..=range.end() {
+                    if set.len() == MAX_CLASS {
+                        return None;
+                    }
+                    set.insert(c.encode_utf8(&mut [0; 4]).as_bytes().to_vec());
+                }
+            }
+        }
+        Class::Bytes(class) => {
+            for range in class.ranges() {
+                for b in range.start()..=range.end() {
+                    if set.len() == MAX_CLASS {
+                        return None;
+                    }
+                    set.insert(vec![b]);
+                }
+            }
+        }
+    }
+    Some(set)
+}
+
+impl Lattice {
+    /// Returns the documents whose stored text matches the regular
+    /// expression `pattern`, ranked by number of matches (times the
+    /// document boost).
+    ///
+    /// The pattern runs against the stored, normalized text, so write it
+    /// in normalized form (lowercase with the default normalizer). Trigrams
+    /// every match must contain are derived from the pattern to select
+    /// candidates from the index; only those are scanned. Patterns that
+    /// imply no trigram, such as `a.b`, scan every document.
+    ///
+    /// # Errors
+    ///
+    /// Returns the regex compilation error for an invalid pattern.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("fn parse_config() -> config").unwrap();
+    /// engine.add("fn parse_args() -> args").unwrap();
+    /// engine.add("struct config").unwrap();
+    ///
+    /// let hits = engine.search_regex(r"fn parse_\w+\(\) -> config", 10).unwrap();
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(hits[0].doc_id, 0);
+    /// ```
+    pub fn search_regex(
+        &mut self,
+        pattern: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, regex::Error> {
+        let regex = Regex::new(pattern)?;
+        if limit == 0 || self.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.prepare_for_query();
+
+        // Regex::new accepted the pattern, so the parser does as well
+        let mut query = regex_syntax::parse(pattern)
+            .map_or(TrigramQuery::All, |hir| Info::analyze(&hir).into_query());
+        if self.tokenizer.cjk_bigrams {
+            query = query.ascii_only();
+        }
+        let candidates = match self.evaluate(&query) {
+            Some(candidates) => candidates,
+            None => (0..self.len() as DocId).collect(),
+        };
+
+        let mut results: Vec<SearchResult> = candidates
+            .into_iter()
+            .filter_map(|doc_id| {
+                let count = regex.find_iter(self.get(doc_id)?).count();
+                let boost = self.boost(doc_id).unwrap_or(1.0);
+                (count > 0).then(|| SearchResult::new(doc_id, count as f32 * boost))
+            })
+            .collect();
+        self.select_top_k(&mut results, limit);
+        Ok(results)
+    }
+
+    /// Sorted documents satisfying `query`, or `None` for every document.
+    fn evaluate(&self, query: &TrigramQuery) -> Option<Vec<DocId>> {
+        match query {
+            TrigramQuery::All => None,
+            TrigramQuery::Trigram(trigram) => Some(self.posting_docs(*trigram)),
+            TrigramQuery::And(subs) => {
+                let mut result: Option<Vec<DocId>> = None;
+                for sub in subs {
+                    let Some(docs) = self.evaluate(sub) else {
+                        continue;
+                    };
+                    result = Some(match result {
+                        Some(mut acc) => {
+                            acc.retain(|doc| docs.binary_search(doc).is_ok());
+                            acc
+                        }
+                        None => docs,
+                    });
+                    if result.as_ref().is_some_and(Vec::is_empty) {
+                        break;
+                    }
+                }
+                result
+            }
+            TrigramQuery::Or(subs) => {
+                let mut union = Vec::new();
+                for sub in subs {
+                    union.extend(self.evaluate(sub)?);
+                }
+                union.sort_unstable();
+                union.dedup();
+                Some(union)
+            }
+        }
+    }
+}
@@ -0,0 +1,1061 @@
+//! Saving and loading indexes.
+//!
+//! ## File Layout
+//!
+//! ```text
+//! header:  magic "LATTICE\0" | version u32 | DocId width u8 | header CRC-32
+//! section: tag [u8; 4] | payload length u64 | payload CRC-32 | payload
+//! ```
+//!
+//! Sections appear in a fixed order: `CONF` (configuration and counters),
+//! `DOCS` (normalized document text, each followed by its original text
+//! when display text is stored, by its field ranges and by its indexing
+//! time when that is stored), `DTCT` (per-document trigram counts),
+//! `BOST` (boosts), `BLKS` (posting blocks), `POST` (postings), `TEMP`
+//! (the uncommitted delta), `NSPC` (namespace names and the namespace of
+//! each document), `SORT` (each sort key with its values) and `VECT` (the
+//! vector dimension and the vectors, flat). All integers are
+//! little-endian; document IDs and posting offsets use this build's width,
+//! recorded in the header.
+//!
+//! Files are deterministic: the same documents added in the same order
+//! (with the same configuration and boosts) save to identical bytes,
+//! whatever queries ran and whenever commits happened, unless
+//! [`IndexConfig::store_indexed_at`] records when each was added. Uncommitted
+//! documents are written merged into `BLKS` and `POST`, so `TEMP` is
+//! always empty since version 6; it is still read from older files.
+//! [`Lattice::build_fingerprint`] hashes these bytes.
+//!
+//! Every section is checksummed separately, so a truncated or damaged file
+//! is rejected with the name of the section that failed rather than loading
+//! into an index that returns wrong results. A loaded index is additionally
+//! checked with [`Lattice::verify`]. The spelling dictionary is not stored;
+//! it is rebuilt from the document text.
+
+use crate::analyzer::delimiter::DelimiterSet;
+use crate::analyzer::normalizer::{
+    DigitPolicy, EmojiPolicy, NormalizerConfig, TextNormalizer, UnicodeForm,
+};
+use crate::analyzer::tokenizer::{Field, LongTokenPolicy, TokenizerConfig};
+use crate::index::metrics::Metrics;
+use crate::index::scoring::{LengthNorm, ScoringConfig, ScoringMode, TieBreak};
+use crate::index::types::{IndexConfig, Lattice, PostingBlock, PostingOffset, TempTrigramEntry};
+use crate::index::verify::VerifyReport;
+use core::mem::size_of;
+use lattice_types::checksum::crc32;
+use lattice_types::{DocId, SearchConfig, Trigram};
+use std::io::{self, Read, Write};
+use std::sync::Arc;
+
+/// File signature.
+const MAGIC: [u8; 8] = *b"LATTICE\0";
+
+/// Current format version. Files with a newer version are rejected.
+///
+/// Version 2 added normalizer flags to `CONF`, version 3 the tokenizer
+/// settings, version 4 the index flags, version 5 the field layout of
+/// each document in `DOCS`, version 6 dropped the query counter from
+/// `CONF`, version 7 added `NSPC`, version 8 the score floor to `CONF`,
+/// version 9 the indexing time of each document to `DOCS`, version 10
+/// the query trigram limit to `CONF`, version 11 the query length
+/// limit, version 12 `SORT`, version 13 `VECT` and version 14 the
+/// per-field normalizers to `CONF`. Version 15 widened the normalizer
+/// flags to 16 bits, and version 16 added the scoring options to `CONF`.
+pub(crate) const FORMAT_VERSION: u32 = 16;
+
+/// `CONF` normalizer flag bits
+const NORM_STRIP_DIACRITICS: u16 = 1;
+const NORM_NFKC: u16 = 1 << 1;
+const NORM_NFKD: u16 = 1 << 2;
+const NORM_REMOVE_FORMAT: u16 = 1 << 3;
+const NORM_DIGITS_COLLAPSE: u16 = 1 << 4;
+const NORM_DIGITS_BOTH: u16 = 1 << 5;
+const NORM_EMOJI_DROP: u16 = 1 << 6;
+const NORM_EMOJI_SHORTCODE: u16 = 1 << 7;
+const NORM_KEEP_CASE: u16 = 1 << 8;
+
+/// `CONF` tokenizer flag bits
+const TOKEN_LIMIT: u8 = 1;
+const TOKEN_TRUNCATE: u8 = 1 << 1;
+const TOKEN_KEEP_COMPOUNDS: u8 = 1 << 2;
+const TOKEN_CJK_BIGRAMS: u8 = 1 << 3;
+
+/// `CONF` index flag bits
+const INDEX_POSITIONS: u8 = 1;
+const INDEX_TERM_FREQUENCIES: u8 = 1 << 1;
+const INDEX_DISPLAY_TEXT: u8 = 1 << 2;
+const INDEX_INDEXED_AT: u8 = 1 << 3;
+
+const DOC_ID_BYTES: usize = size_of::<DocId>();
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// 64-bit FNV-1a over everything written; the hasher behind
+/// [`Lattice::build_fingerprint`], chosen for being fixed by specification
+struct Fnv1a(u64);
+
+impl Write for Fnv1a {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Section tags in file order, with the names used in errors.
+const SECTIONS: [([u8; 4], &str); 10] = [
+    (*b"CONF", "config"),
+    (*b"DOCS", "documents"),
+    (*b"DTCT", "doc trigram counts"),
+    (*b"BOST", "boosts"),
+    (*b"BLKS", "blocks"),
+    (*b"POST", "postings"),
+    (*b"TEMP", "uncommitted"),
+    (*b"NSPC", "namespaces"),
+    (*b"SORT", "sort values"),
+    (*b"VECT", "vectors"),
+];
+
+/// Index of `NSPC` in [`SECTIONS`]; absent before version 7.
+const NAMESPACE_SECTION: usize = 7;
+
+/// Index of `SORT` in [`SECTIONS`]; absent before version 12.
+const SORT_SECTION: usize = 8;
+
+/// Index of `VECT` in [`SECTIONS`]; absent before version 13.
+const VECTOR_SECTION: usize = 9;
+
+/// Errors returned by [`Lattice::load`].
+#[derive(Debug)]
+pub enum IndexLoadError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The input does not start with the index file signature.
+    BadMagic,
+    /// The file was written by a newer, incompatible format version.
+    UnsupportedVersion {
+        /// Version found in the file.
+        found: u32,
+        /// Newest version this build can read.
+        supported: u32,
+    },
+    /// The file was written with a different `DocId` width (see the
+    /// `large-corpus` feature).
+    DocIdWidth {
+        /// Width in bytes found in the file.
+        found: u8,
+        /// Width in bytes used by this build.
+        expected: u8,
+    },
+    /// The input ended in the middle of a section.
+    Truncated {
+        /// The section being read.
+        section: &'static str,
+    },
+    /// A section's contents do not match its stored checksum.
+    ChecksumMismatch {
+        /// The damaged section.
+        section: &'static str,
+        /// Checksum stored in the file.
+        expected: u32,
+        /// Checksum of the bytes read.
+        actual: u32,
+    },
+    /// A section passed its checksum but could not be decoded.
+    Corrupt {
+        /// The malformed section.
+        section: &'static str,
+        /// What was wrong with it.
+        reason: &'static str,
+    },
+    /// The decoded index failed integrity verification.
+    Inconsistent(VerifyReport),
+}
+
+impl core::fmt::Display for IndexLoadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IndexLoadError::Io(e) => write!(f, "failed to read index: {}", e),
+            IndexLoadError::BadMagic => write!(f, "not a lattice index file"),
+            IndexLoadError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "unsupported index format version {} (newest supported: {})",
+                found, supported
+            ),
+            IndexLoadError::DocIdWidth { found, expected } => write!(
+                f,
+                "index uses {}-byte document IDs but this build uses {} bytes",
+                found, expected
+            ),
+            IndexLoadError::Truncated { section } => {
+                write!(f, "index file truncated in {} section", section)
+            }
+            IndexLoadError::ChecksumMismatch {
+                section,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "checksum mismatch in {} section: stored {:08x}, computed {:08x}",
+                section, expected, actual
+            ),
+            IndexLoadError::Corrupt { section, reason } => {
+                write!(f, "corrupt {} section: {}", section, reason)
+            }
+            IndexLoadError::Inconsistent(report) => {
+                write!(f, "index failed verification: {}", report)
+            }
+        }
+    }
+}
+
+impl core::error::Error for IndexLoadError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            IndexLoadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl Lattice {
+    /// Writes the index to `writer`.
+    ///
+    /// Uncommitted documents are merged into the saved posting lists
+    /// without committing them in this engine, so the file does not depend
+    /// on when commits happened.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    ///
+    /// let mut bytes = Vec::new();
+    /// engine.save(&mut bytes).unwrap();
+    ///
+    /// let mut loaded = Lattice::load(bytes.as_slice()).unwrap();
+    /// assert_eq!(loaded.search("hello", 10)[0].doc_id, 0);
+    /// ```
+    pub fn save<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header = Vec::with_capacity(MAGIC.len() + 5);
+        header.extend_from_slice(&MAGIC);
+        header.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header.push(DOC_ID_BYTES as u8);
+        writer.write_all(&header)?;
+        writer.write_all(&crc32(&header).to_le_bytes())?;
+
+        let merged = self.merged_postings();
+        let (blocks, postings) = match &merged {
+            Some((blocks, postings)) => (blocks.as_slice(), postings.as_slice()),
+            None => (&self.blocks[..], self.postings.as_slice()),
+        };
+
+        let mut payload = Vec::new();
+        for (i, (tag, _)) in SECTIONS.iter().enumerate() {
+            payload.clear();
+            self.encode_section(i, blocks, postings, &mut payload);
+            writer.write_all(tag)?;
+            writer.write_all(&(payload.len() as u64).to_le_bytes())?;
+            writer.write_all(&crc32(&payload).to_le_bytes())?;
+            writer.write_all(&payload)?;
+        }
+
+        writer.flush()
+    }
+
+    /// Reads an index written by [`save`](Self::save).
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`IndexLoadError`] describing the first problem found:
+    /// an I/O failure, a foreign or newer file, a truncated or damaged
+    /// section, or a decoded index that fails [`verify`](Self::verify).
+    pub fn load<R: Read>(mut reader: R) -> Result<Lattice, IndexLoadError> {
+        let mut header = [0u8; MAGIC.len() + 5];
+        read_exact(&mut reader, &mut header, "header")?;
+        let mut stored_crc = [0u8; 4];
+        read_exact(&mut reader, &mut stored_crc, "header")?;
+
+        if header[..MAGIC.len()] != MAGIC {
+            return Err(IndexLoadError::BadMagic);
+        }
+        verify_checksum("header", &header, u32::from_le_bytes(stored_crc))?;
+
+        let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if version > FORMAT_VERSION {
+            return Err(IndexLoadError::UnsupportedVersion {
+                found: version,
+                supported: FORMAT_VERSION,
+            });
+        }
+        if header[12] as usize != DOC_ID_BYTES {
+            return Err(IndexLoadError::DocIdWidth {
+                found: header[12],
+                expected: DOC_ID_BYTES as u8,
+            });
+        }
+
+        let mut engine = Lattice::new();
+        let mut payload = Vec::new();
+        for (i, &(tag, name)) in SECTIONS.iter().enumerate() {
+            if (i == NAMESPACE_SECTION && version < 7)
+                || (i == SORT_SECTION && version < 12)
+                || (i == VECTOR_SECTION && version < 13)
+            {
+                break;
+            }
+            read_section(&mut reader, tag, name, &mut payload)?;
+            engine.decode_section(i, version, Section::new(name, &payload))?;
+        }
+
+        engine.needs_rebuild = !engine.temp_trigrams.is_empty();
+        Metrics::set(&engine.counters.current_doc_count, engine.len() as u64);
+        let report = engine.verify();
+        if !report.is_ok() {
+            return Err(IndexLoadError::Inconsistent(report));
+        }
+        Ok(engine)
+    }
+
+    /// Returns a 64-bit fingerprint of the index: the FNV-1a hash of the
+    /// bytes [`save`](Self::save) would write.
+    ///
+    /// Replicas built from the same documents in the same order have equal
+    /// fingerprints, so comparing them verifies they serve identical data
+    /// without shipping the index. The hash is stable across releases that
+    /// keep the file format.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut primary = Lattice::new();
+    /// let mut replica = Lattice::new();
+    /// for doc in ["hello world", "hello there"] {
+    ///     primary.add(doc).unwrap();
+    ///     replica.add(doc).unwrap();
+    ///     primary.search("hello", 1); // queries and commits do not matter
+    /// }
+    /// assert_eq!(primary.build_fingerprint(), replica.build_fingerprint());
+    ///
+    /// replica.add("one more").unwrap();
+    /// assert_ne!(primary.build_fingerprint(), replica.build_fingerprint());
+    /// ```
+    pub fn build_fingerprint(&self) -> u64 {
+        let mut hasher = Fnv1a(FNV_OFFSET_BASIS);
+        // Writing into the hasher cannot fail
+        let _ = self.save(&mut hasher);
+        hasher.0
+    }
+
+    /// The committed posting lists with the uncommitted delta merged in,
+    /// or `None` if there is no delta.
+    pub(crate) fn merged_postings(&self) -> Option<(Vec<PostingBlock>, Vec<DocId>)> {
+        if !self.has_uncommitted() {
+            return None;
+        }
+        let mut delta: Vec<TempTrigramEntry> = self
+            .temp_trigrams
+            .iter()
+            .chain(self.committing.iter())
+            .copied()
+            .collect();
+        Self::sort_trigrams(&mut delta);
+        let (delta_blocks, delta_postings) = Self::build_blocks_from_sorted(&delta);
+        Some(Self::merge_indexes(
+            &self.blocks,
+            &self.postings,
+            &delta_blocks,
+            &delta_postings,
+        ))
+    }
+
+    fn encode_section(
+        &self,
+        index: usize,
+        blocks: &[PostingBlock],
+        postings: &[DocId],
+        out: &mut Vec<u8>,
+    ) {
+        match index {
+            0 => {
+                out.extend_from_slice(&self.config.min_overlap_ratio.to_le_bytes());
+                out.push(self.config.enable_fuzzy as u8);
+                out.push(self.config.max_edit_distance);
+                out.extend_from_slice(&self.generation.to_le_bytes());
+                let documents_indexed = self.metrics().documents_indexed;
+                out.extend_from_slice(&documents_indexed.to_le_bytes());
+                out.push(self.dictionary.has_bk_tree() as u8);
+
+                out.extend_from_slice(&normalizer_flags(self.normalizer.config()).to_le_bytes());
+
+                let tokenizer = self.tokenizer;
+                out.extend_from_slice(&tokenizer.delimiters.table());
+                let limit = if tokenizer.max_token_len.is_some() {
+                    TOKEN_LIMIT
+                } else {
+                    0
+                };
+                let truncate = match tokenizer.long_token_policy {
+                    LongTokenPolicy::Skip => 0,
+                    LongTokenPolicy::Truncate => TOKEN_TRUNCATE,
+                };
+                let compounds = if tokenizer.keep_compounds {
+                    TOKEN_KEEP_COMPOUNDS
+                } else {
+                    0
+                };
+                let cjk = if tokenizer.cjk_bigrams {
+                    TOKEN_CJK_BIGRAMS
+                } else {
+                    0
+                };
+                out.push(limit | truncate | compounds | cjk);
+                let max_len = tokenizer.max_token_len.unwrap_or(0) as u64;
+                out.extend_from_slice(&max_len.to_le_bytes());
+
+                let positions = if self.index_config.store_positions {
+                    INDEX_POSITIONS
+                } else {
+                    0
+                };
+                let frequencies = if self.index_config.store_term_frequencies {
+                    INDEX_TERM_FREQUENCIES
+                } else {
+                    0
+                };
+                let display = if self.index_config.store_display_text {
+                    INDEX_DISPLAY_TEXT
+                } else {
+                    0
+                };
+                let indexed_at = if self.index_config.store_indexed_at {
+                    INDEX_INDEXED_AT
+                } else {
+                    0
+                };
+                out.push(positions | frequencies | display | indexed_at);
+
+                match self.config.min_score {
+                    Some(min_score) => {
+                        out.push(1);
+                        out.extend_from_slice(&min_score.to_le_bytes());
+                    }
+                    None => out.push(0),
+                }
+                let max_query_trigrams =
+                    u32::try_from(self.config.max_query_trigrams).unwrap_or(u32::MAX);
+                out.extend_from_slice(&max_query_trigrams.to_le_bytes());
+                let max_query_length =
+                    u32::try_from(self.config.max_query_length).unwrap_or(u32::MAX);
+                out.extend_from_slice(&max_query_length.to_le_bytes());
+
+                let overrides = self.field_normalizers.iter().flatten().count();
+                out.push(overrides as u8);
+                for (field, normalizer) in self.field_normalizers.iter().enumerate() {
+                    if let Some(normalizer) = normalizer {
+                        out.push(field as u8);
+                        out.extend_from_slice(&normalizer_flags(normalizer.config()).to_le_bytes());
+                    }
+                }
+
+                encode_scoring(self.scoring, out);
+            }
+            1 => {
+                out.extend_from_slice(&(self.documents.len() as u64).to_le_bytes());
+                for doc_id in 0..self.documents.len() {
+                    let text = self.documents.get(doc_id as DocId).unwrap_or("");
+                    out.extend_from_slice(&(text.len() as u16).to_le_bytes());
+                    out.extend_from_slice(text.as_bytes());
+                    if self.index_config.store_display_text {
+                        let display = self.display.get(doc_id as DocId).unwrap_or("");
+                        out.extend_from_slice(&(display.len() as u16).to_le_bytes());
+                        out.extend_from_slice(display.as_bytes());
+                    }
+                    let layout = self.fields.get(doc_id);
+                    out.push(layout.len() as u8);
+                    for &(field, start, end) in layout {
+                        out.push(field as u8);
+                        out.extend_from_slice(&start.to_le_bytes());
+                        out.extend_from_slice(&end.to_le_bytes());
+                    }
+                    if self.index_config.store_indexed_at {
+                        let millis = self.indexed_at.get(doc_id).copied().unwrap_or(0);
+                        out.extend_from_slice(&millis.to_le_bytes());
+                    }
+                }
+            }
+            2 => {
+                for count in &self.doc_trigram_counts {
+                    out.extend_from_slice(&count.to_le_bytes());
+                }
+            }
+            3 => {
+                for boost in &self.boosts {
+                    out.extend_from_slice(&boost.to_le_bytes());
+                }
+            }
+            4 => {
+                for block in blocks {
+                    out.extend_from_slice(&block.trigram.0.to_le_bytes());
+                    out.extend_from_slice(&block.offset.to_le_bytes());
+                    out.extend_from_slice(&block.len.to_le_bytes());
+                }
+            }
+            5 => {
+                for doc_id in postings {
+                    out.extend_from_slice(&doc_id.to_le_bytes());
+                }
+            }
+            // The delta is merged into the posting lists
+            6 => {}
+            7 => {
+                let names = self.namespaces.names();
+                out.extend_from_slice(&(names.len() as u32).to_le_bytes());
+                for name in names {
+                    out.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                    out.extend_from_slice(name.as_bytes());
+                }
+                for id in self.namespaces.doc_namespaces() {
+                    out.extend_from_slice(&id.to_le_bytes());
+                }
+            }
+            8 => {
+                for (key, values) in self.sort_values.columns() {
+                    out.extend_from_slice(&(key.len() as u32).to_le_bytes());
+                    out.extend_from_slice(key.as_bytes());
+                    out.extend_from_slice(&(values.len() as u64).to_le_bytes());
+                    for value in values {
+                        out.extend_from_slice(&value.to_le_bytes());
+                    }
+                }
+            }
+            _ => {
+                let dimension = self.vectors.dimension() as u32;
+                out.extend_from_slice(&dimension.to_le_bytes());
+                for value in self.vectors.values() {
+                    out.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+    }
+
+    fn decode_section(
+        &mut self,
+        index: usize,
+        version: u32,
+        mut s: Section<'_>,
+    ) -> Result<(), IndexLoadError> {
+        match index {
+            0 => {
+                self.config = SearchConfig {
+                    min_overlap_ratio: f32::from_le_bytes(s.take()?),
+                    enable_fuzzy: s.u8()? != 0,
+                    max_edit_distance: s.u8()?,
+                    min_score: None,
+                    max_query_trigrams: SearchConfig::DEFAULT_MAX_QUERY_TRIGRAMS,
+                    max_query_length: SearchConfig::DEFAULT_MAX_QUERY_LENGTH,
+                };
+                self.generation = u32::from_le_bytes(s.take()?);
+                let documents_indexed = u64::from_le_bytes(s.take()?);
+                Metrics::set(&self.counters.documents_indexed, documents_indexed);
+                if version < 6 {
+                    // Query counter, no longer saved
+                    s.take::<8>()?;
+                }
+                if s.u8()? != 0 {
+                    self.enable_bk_tree();
+                }
+
+                if version >= 2 {
+                    self.normalizer = TextNormalizer::with_config(s.normalizer(version)?);
+                }
+
+                if version >= 3 {
+                    let delimiters = DelimiterSet::from_table(s.take()?);
+                    let flags = s.u8()?;
+                    let known =
+                        TOKEN_LIMIT | TOKEN_TRUNCATE | TOKEN_KEEP_COMPOUNDS | TOKEN_CJK_BIGRAMS;
+                    if flags & !known != 0 {
+                        return Err(s.corrupt("unknown tokenizer flags"));
+                    }
+                    let max_len = u64::from_le_bytes(s.take()?);
+                    self.tokenizer = TokenizerConfig {
+                        delimiters,
+                        max_token_len: (flags & TOKEN_LIMIT != 0)
+                            .then(|| usize::try_from(max_len).unwrap_or(usize::MAX)),
+                        long_token_policy: if flags & TOKEN_TRUNCATE != 0 {
+                            LongTokenPolicy::Truncate
+                        } else {
+                            LongTokenPolicy::Skip
+                        },
+                        keep_compounds: flags & TOKEN_KEEP_COMPOUNDS != 0,
+                        cjk_bigrams: flags & TOKEN_CJK_BIGRAMS != 0,
+                    };
+                }
+
+                if version >= 4 {
+                    let flags = s.u8()?;
+                    let known = INDEX_POSITIONS
+                        | INDEX_TERM_FREQUENCIES
+                        | INDEX_DISPLAY_TEXT
+                        | if version >= 9 { INDEX_INDEXED_AT } else { 0 };
+                    if flags & !known != 0 {
+                        return Err(s.corrupt("unknown index flags"));
+                    }
+                    self.index_config = IndexConfig {
+                        store_positions: flags & INDEX_POSITIONS != 0,
+                        store_term_frequencies: flags & INDEX_TERM_FREQUENCIES != 0,
+                        store_display_text: flags & INDEX_DISPLAY_TEXT != 0,
+                        store_indexed_at: flags & INDEX_INDEXED_AT != 0,
+                    };
+                }
+
+                if version >= 8 && s.u8()? != 0 {
+                    self.config.min_score = Some(f32::from_le_bytes(s.take()?));
+                }
+                if version >= 10 {
+                    let max_query_trigrams = u32::from_le_bytes(s.take()?);
+                    self.config.max_query_trigrams =
+                        usize::try_from(max_query_trigrams).unwrap_or(usize::MAX);
+                }
+                if version >= 11 {
+                    let max_query_length = u32::from_le_bytes(s.take()?);
+                    // Files written before the limit existed may exceed it
+                    self.config.max_query_length = usize::try_from(max_query_length)
+                        .unwrap_or(usize::MAX)
+                        .min(SearchConfig::MAX_QUERY_LENGTH);
+                }
+                if self.config.validate().is_err() {
+                    return Err(s.corrupt("search configuration out of range"));
+                }
+                if version >= 14 {
+                    for _ in 0..s.u8()? {
+                        let field =
+                            Field::from_u8(s.u8()?).ok_or_else(|| s.corrupt("unknown field"))?;
+                        self.field_normalizers[field as usize] =
+                            Some(TextNormalizer::with_config(s.normalizer(version)?));
+                    }
+                }
+                if version >= 16 {
+                    self.scoring = s.scoring()?;
+                }
+            }
+            1 => {
+                let count = u64::from_le_bytes(s.take()?);
+                for _ in 0..count {
+                    let len = u16::from_le_bytes(s.take()?) as usize;
+                    let text = core::str::from_utf8(s.bytes(len)?)
+                        .map_err(|_| s.corrupt("document is not valid UTF-8"))?;
+                    self.documents
+                        .push(text)
+                        .map_err(|_| s.corrupt("document storage capacity exceeded"))?;
+                    self.doc_lengths.push(len as u32);
+                    if self.index_config.store_display_text {
+                        let len = u16::from_le_bytes(s.take()?) as usize;
+                        let display = core::str::from_utf8(s.bytes(len)?)
+                            .map_err(|_| s.corrupt("display text is not valid UTF-8"))?;
+                        self.display
+                            .push(display)
+                            .map_err(|_| s.corrupt("document storage capacity exceeded"))?;
+                    }
+                    if version >= 5 {
+                        let spans = s.u8()?;
+                        if spans > 0 || !self.fields.is_empty() {
+                            let mut layout = Vec::with_capacity(spans as usize);
+                            for _ in 0..spans {
+                                let field = Field::from_u8(s.u8()?)
+                                    .ok_or_else(|| s.corrupt("unknown field"))?;
+                                let start = u16::from_le_bytes(s.take()?);
+                                let end = u16::from_le_bytes(s.take()?);
+                                let (lo, hi) = (start as usize, end as usize);
+                                if lo > hi
+                                    || !text.is_char_boundary(lo)
+                                    || !text.is_char_boundary(hi)
+                                {
+                                    return Err(s.corrupt("field range outside document"));
+                                }
+                                layout.push((field, start, end));
+                            }
+                            self.fields.push(self.documents.len() - 1, &layout);
+                        }
+                    }
+                    if self.index_config.store_indexed_at {
+                        self.indexed_at.push(u64::from_le_bytes(s.take()?));
+                    }
+
+                    if self.index_config.store_positions {
+                        self.positions
+                            .push_document(text, self.tokenizer.cjk_bigrams);
+                    }
+                    if self.index_config.store_term_frequencies {
+                        self.frequencies
+                            .push_document(text, self.tokenizer.cjk_bigrams);
+                    }
+
+                    self.norm_buf.clear();
+                    self.norm_buf.push_str(text);
+                    self.index_tokens();
+                }
+            }
+            2 => self.doc_trigram_counts = s.array(|s| Ok(u32::from_le_bytes(s.take()?)))?,
+            3 => self.boosts = s.array(|s| Ok(f32::from_le_bytes(s.take()?)))?,
+            4 => {
+                let blocks = s.array(|s| {
+                    Ok(PostingBlock {
+                        trigram: Trigram(u32::from_le_bytes(s.take()?)),
+                        offset: PostingOffset::from_le_bytes(s.take()?),
+                        len: PostingOffset::from_le_bytes(s.take()?),
+                    })
+                })?;
+                self.install_blocks(blocks);
+            }
+            5 => self.postings = Arc::new(s.array(|s| Ok(DocId::from_le_bytes(s.take()?)))?),
+            6 => {
+                self.temp_trigrams = s.array(|s| {
+                    Ok(TempTrigramEntry {
+                        trigram: Trigram(u32::from_le_bytes(s.take()?)),
+                        doc_id: DocId::from_le_bytes(s.take()?),
+                    })
+                })?;
+            }
+            7 => {
+                let count = u32::from_le_bytes(s.take()?);
+                let mut names = Vec::new();
+                for _ in 0..count {
+                    let len = u32::from_le_bytes(s.take()?) as usize;
+                    let name = core::str::from_utf8(s.bytes(len)?)
+                        .map_err(|_| s.corrupt("namespace name is not valid UTF-8"))?;
+                    names.push(name.to_owned());
+                }
+                let doc_namespaces = s.array(|s| Ok(u32::from_le_bytes(s.take()?)))?;
+                if doc_namespaces.len() > self.documents.len() {
+                    return Err(s.corrupt("more namespace entries than documents"));
+                }
+                if doc_namespaces.iter().any(|&id| id > count) {
+                    return Err(s.corrupt("unknown namespace"));
+                }
+                self.namespaces.restore(names, doc_namespaces);
+            }
+            8 => {
+                let columns = s.array(|s| {
+                    let len = u32::from_le_bytes(s.take()?) as usize;
+                    let key = core::str::from_utf8(s.bytes(len)?)
+                        .map_err(|_| s.corrupt("sort key is not valid UTF-8"))?
+                        .to_owned();
+                    let count = u64::from_le_bytes(s.take()?);
+                    if count > self.documents.len() as u64 {
+                        return Err(s.corrupt("more sort values than documents"));
+                    }
+                    let mut values = Vec::with_capacity(count as usize);
+                    for _ in 0..count {
+                        values.push(f64::from_le_bytes(s.take()?));
+                    }
+                    Ok((key, values))
+                })?;
+                if !columns.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+                    return Err(s.corrupt("sort keys are not sorted"));
+                }
+                self.sort_values.restore(columns);
+            }
+            _ => {
+                let dimension = u32::from_le_bytes(s.take()?) as usize;
+                let values = s.array(|s| Ok(f32::from_le_bytes(s.take()?)))?;
+                let fits = match dimension {
+                    0 => values.is_empty(),
+                    _ => {
+                        values.len().is_multiple_of(dimension)
+                            && values.len() / dimension <= self.documents.len()
+                    }
+                };
+                if !fits {
+                    return Err(s.corrupt("vectors do not fit the documents"));
+                }
+                if !values.iter().all(|v| v.is_finite()) {
+                    return Err(s.corrupt("vector value is not finite"));
+                }
+                self.vectors.restore(dimension, values);
+            }
+        }
+        s.finish()
+    }
+}
+
+/// Packs a normalizer configuration into `CONF` normalizer flags.
+fn normalizer_flags(config: NormalizerConfig) -> u16 {
+    let form = match config.unicode_form {
+        UnicodeForm::None => 0,
+        #[cfg(feature = "unicode-normalization")]
+        UnicodeForm::Nfkc => NORM_NFKC,
+        #[cfg(feature = "unicode-normalization")]
+        UnicodeForm::Nfkd => NORM_NFKD,
+    };
+    let strip = if config.strip_diacritics {
+        NORM_STRIP_DIACRITICS
+    } else {
+        0
+    };
+    let format = if config.remove_format_chars {
+        NORM_REMOVE_FORMAT
+    } else {
+        0
+    };
+    let digits = match config.digits {
+        DigitPolicy::Keep => 0,
+        DigitPolicy::Collapse => NORM_DIGITS_COLLAPSE,
+        DigitPolicy::Both => NORM_DIGITS_BOTH,
+    };
+    let emoji = match config.emoji {
+        EmojiPolicy::Keep => 0,
+        EmojiPolicy::Drop => NORM_EMOJI_DROP,
+        EmojiPolicy::Shortcode => NORM_EMOJI_SHORTCODE,
+    };
+    let case = if config.keep_case { NORM_KEEP_CASE } else { 0 };
+    strip | format | form | digits | emoji | case
+}
+
+/// Inverse of [`normalizer_flags`]; the error is the reason the flags are
+/// invalid.
+fn normalizer_from_flags(flags: u16) -> Result<NormalizerConfig, &'static str> {
+    let unicode_form = match flags & (NORM_NFKC | NORM_NFKD) {
+        0 => UnicodeForm::None,
+        #[cfg(feature = "unicode-normalization")]
+        NORM_NFKC => UnicodeForm::Nfkc,
+        #[cfg(feature = "unicode-normalization")]
+        NORM_NFKD => UnicodeForm::Nfkd,
+        _ => {
+            return Err("unsupported Unicode normalization form \
+                 (requires the unicode-normalization feature)")
+        }
+    };
+    let digits = match flags & (NORM_DIGITS_COLLAPSE | NORM_DIGITS_BOTH) {
+        0 => DigitPolicy::Keep,
+        NORM_DIGITS_COLLAPSE => DigitPolicy::Collapse,
+        NORM_DIGITS_BOTH => DigitPolicy::Both,
+        _ => return Err("conflicting digit policies"),
+    };
+    let emoji = match flags & (NORM_EMOJI_DROP | NORM_EMOJI_SHORTCODE) {
+        0 => EmojiPolicy::Keep,
+        NORM_EMOJI_DROP => EmojiPolicy::Drop,
+        NORM_EMOJI_SHORTCODE => EmojiPolicy::Shortcode,
+        _ => return Err("conflicting emoji policies"),
+    };
+    let known = NORM_STRIP_DIACRITICS
+        | NORM_NFKC
+        | NORM_NFKD
+        | NORM_REMOVE_FORMAT
+        | NORM_DIGITS_COLLAPSE
+        | NORM_DIGITS_BOTH
+        | NORM_EMOJI_DROP
+        | NORM_EMOJI_SHORTCODE
+        | NORM_KEEP_CASE;
+    if flags & !known != 0 {
+        return Err("unknown normalizer flags");
+    }
+    Ok(NormalizerConfig {
+        strip_diacritics: flags & NORM_STRIP_DIACRITICS != 0,
+        remove_format_chars: flags & NORM_REMOVE_FORMAT != 0,
+        keep_case: flags & NORM_KEEP_CASE != 0,
+        unicode_form,
+        digits,
+        emoji,
+    })
+}
+
+/// Writes the scoring options to `CONF`: the proximity weight, the
+/// scoring mode with its two parameters, the length norm with its
+/// parameter and the tie-break, each tag a byte and each parameter an
+/// `f32`, unused ones 0.
+fn encode_scoring(scoring: ScoringConfig, out: &mut Vec<u8>) {
+    out.extend_from_slice(&scoring.proximity_weight.to_le_bytes());
+    let (mode, k1, b) = match scoring.mode {
+        ScoringMode::Overlap => (0u8, 0.0f32, 0.0f32),
+        ScoringMode::TfIdf => (1, 0.0, 0.0),
+        ScoringMode::Bm25 { k1, b } => (2, k1, b),
+    };
+    out.push(mode);
+    out.extend_from_slice(&k1.to_le_bytes());
+    out.extend_from_slice(&b.to_le_bytes());
+    let (norm, b) = match scoring.length_norm {
+        LengthNorm::None => (0u8, 0.0f32),
+        LengthNorm::Sqrt => (1, 0.0),
+        LengthNorm::Log => (2, 0.0),
+        LengthNorm::Pivoted { b } => (3, b),
+    };
+    out.push(norm);
+    out.extend_from_slice(&b.to_le_bytes());
+    out.push(match scoring.tie_break {
+        TieBreak::OldestFirst => 0,
+        TieBreak::NewestFirst => 1,
+    });
+}
+
+/// Cursor over a checksum-verified section payload.
+struct Section<'a> {
+    name: &'static str,
+    bytes: &'a [u8],
+}
+
+impl<'a> Section<'a> {
+    fn new(name: &'static str, bytes: &'a [u8]) -> Self {
+        Self { name, bytes }
+    }
+
+    fn corrupt(&self, reason: &'static str) -> IndexLoadError {
+        IndexLoadError::Corrupt {
+            section: self.name,
+            reason,
+        }
+    }
+
+    fn bytes(&mut self, n: usize) -> Result<&'a [u8], IndexLoadError> {
+        if self.bytes.len() < n {
+            return Err(self.corrupt("section ends early"));
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], IndexLoadError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    fn u8(&mut self) -> Result<u8, IndexLoadError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    /// Reads the scoring options written by [`encode_scoring`].
+    fn scoring(&mut self) -> Result<ScoringConfig, IndexLoadError> {
+        let proximity_weight = f32::from_le_bytes(self.take()?);
+        let tag = self.u8()?;
+        let k1 = f32::from_le_bytes(self.take()?);
+        let b = f32::from_le_bytes(self.take()?);
+        let mode = match tag {
+            0 => ScoringMode::Overlap,
+            1 => ScoringMode::TfIdf,
+            2 => ScoringMode::Bm25 { k1, b },
+            _ => return Err(self.corrupt("unknown scoring mode")),
+        };
+        let tag = self.u8()?;
+        let b = f32::from_le_bytes(self.take()?);
+        let length_norm = match tag {
+            0 => LengthNorm::None,
+            1 => LengthNorm::Sqrt,
+            2 => LengthNorm::Log,
+            3 => LengthNorm::Pivoted { b },
+            _ => return Err(self.corrupt("unknown length norm")),
+        };
+        let tie_break = match self.u8()? {
+            0 => TieBreak::OldestFirst,
+            1 => TieBreak::NewestFirst,
+            _ => return Err(self.corrupt("unknown tie-break")),
+        };
+        Ok(ScoringConfig {
+            proximity_weight,
+            mode,
+            length_norm,
+            tie_break,
+        })
+    }
+
+    /// Reads normalizer flags, one byte wide before version 15.
+    fn normalizer(&mut self, version: u32) -> Result<NormalizerConfig, IndexLoadError> {
+        let flags = if version >= 15 {
+            u16::from_le_bytes(self.take()?)
+        } else {
+            u16::from(self.u8()?)
+        };
+        normalizer_from_flags(flags).map_err(|e| self.corrupt(e))
+    }
+
+    /// Decodes fixed-size records until the payload is exhausted.
+    fn array<T>(
+        &mut self,
+        mut decode: impl FnMut(&mut Self) -> Result<T, IndexLoadError>,
+    ) -> Result<Vec<T>, IndexLoadError> {
+        let mut out = Vec::new();
+        while !self.bytes.is_empty() {
+            out.push(decode(self)?);
+        }
+        Ok(out)
+    }
+
+    fn finish(self) -> Result<(), IndexLoadError> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(self.corrupt("unexpected trailing bytes"))
+        }
+    }
+}
+
+fn read_exact<R: Read>(
+    reader: &mut R,
+    buf: &mut [u8],
+    section: &'static str,
+) -> Result<(), IndexLoadError> {
+    reader.read_exact(buf).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => IndexLoadError::Truncated { section },
+        _ => IndexLoadError::Io(e),
+    })
+}
+
+fn read_section<R: Read>(
+    reader: &mut R,
+    tag: [u8; 4],
+    name: &'static str,
+    payload: &mut Vec<u8>,
+) -> Result<(), IndexLoadError> {
+    let mut frame = [0u8; 16];
+    read_exact(reader, &mut frame, name)?;
+    if frame[..4] != tag {
+        return Err(IndexLoadError::Corrupt {
+            section: name,
+            reason: "unexpected section tag",
+        });
+    }
+    let len = u64::from_le_bytes(frame[4..12].try_into().unwrap());
+    let stored_crc = u32::from_le_bytes(frame[12..16].try_into().unwrap());
+
+    // Read through `take` so a corrupted length cannot force a huge
+    // up-front allocation
+    payload.clear();
+    reader
+        .by_ref()
+        .take(len)
+        .read_to_end(payload)
+        .map_err(IndexLoadError::Io)?;
+    if (payload.len() as u64) < len {
+        return Err(IndexLoadError::Truncated { section: name });
+    }
+
+    verify_checksum(name, payload, stored_crc)
+}
+
+fn verify_checksum(
+    section: &'static str,
+    data: &[u8],
+    expected: u32,
+) -> Result<(), IndexLoadError> {
+    let actual = crc32(data);
+    if actual == expected {
+        Ok(())
+    } else {
+        Err(IndexLoadError::ChecksumMismatch {
+            section,
+            expected,
+            actual,
+        })
+    }
+}
@@ -0,0 +1,256 @@
+//! Positional index.
+//!
+//! With [`IndexConfig::store_positions`], every document also records where
+//! each of its trigrams occurs, grouped per (trigram, document) pair. That
+//! enables exact phrase queries, proximity scoring and highlighting without
+//! rescanning document text, at the cost of two bytes per trigram
+//! occurrence plus one entry per distinct trigram of each document.
+//!
+//! Positions are byte offsets into the stored (normalized) text rather than
+//! token numbers, since trigrams span token boundaries;
+//! [`Lattice::token_position`] converts one. They are derived from the
+//! stored text, so [`Lattice::load`] rebuilds them instead of reading them
+//! from the file.
+
+use crate::analyzer::trigram::extract_terms_with_pos;
+use crate::index::types::{IndexConfig, Lattice, PostingOffset};
+use lattice_types::{DocId, SearchResult, Trigram};
+use memchr::{memchr_iter, memmem};
+
+/// Trigram occurrences of every document, in `DocId` order.
+#[derive(Clone, Default)]
+pub(crate) struct PositionIndex {
+    /// Start of each document's range in `entries`, plus an end marker
+    doc_starts: Vec<PostingOffset>,
+    /// Distinct trigrams of each document, sorted, with the start of their
+    /// run in `offsets`
+    entries: Vec<(Trigram, PostingOffset)>,
+    /// Byte offsets of each occurrence, ascending within a run
+    offsets: Vec<u16>,
+}
+
+impl PositionIndex {
+    /// Number of documents recorded.
+    pub(crate) fn len(&self) -> usize {
+        self.doc_starts.len().saturating_sub(1)
+    }
+
+    /// Records the trigram occurrences of the next document.
+    pub(crate) fn push_document(&mut self, text: &str, cjk_bigrams: bool) {
+        if self.doc_starts.is_empty() {
+            self.doc_starts.push(0);
+        }
+
+        let mut occurrences: Vec<(u32, u16)> = Vec::with_capacity(text.len());
+        extract_terms_with_pos(text, cjk_bigrams, |trigram, offset| {
+            occurrences.push((trigram.0, offset as u16));
+        });
+        occurrences.sort_unstable();
+
+        for (i, &(trigram, offset)) in occurrences.iter().enumerate() {
+            if i == 0 || occurrences[i - 1].0 != trigram {
+                self.entries
+                    .push((Trigram(trigram), self.offsets.len() as PostingOffset));
+            }
+            self.offsets.push(offset);
+        }
+        self.doc_starts.push(self.entries.len() as PostingOffset);
+    }
+
+    /// Returns the offsets of `trigram` in document `doc`, ascending.
+    pub(crate) fn get(&self, doc: usize, trigram: Trigram) -> &[u16] {
+        let (Some(&lo), Some(&hi)) = (self.doc_starts.get(doc), self.doc_starts.get(doc + 1))
+        else {
+            return &[];
+        };
+        let (lo, hi) = (lo as usize, hi as usize);
+        match self.entries[lo..hi].binary_search_by_key(&trigram.0, |e| e.0 .0) {
+            Ok(k) => {
+                let start = self.entries[lo + k].1 as usize;
+                let end = self
+                    .entries
+                    .get(lo + k + 1)
+                    .map_or(self.offsets.len(), |e| e.1 as usize);
+                &self.offsets[start..end]
+            }
+            Err(_) => &[],
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.doc_starts.clear();
+        self.entries.clear();
+        self.offsets.clear();
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.doc_starts.shrink_to_fit();
+        self.entries.shrink_to_fit();
+        self.offsets.shrink_to_fit();
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.doc_starts.capacity() * size_of::<PostingOffset>()
+            + self.entries.capacity() * size_of::<(Trigram, PostingOffset)>()
+            + self.offsets.capacity() * size_of::<u16>()
+    }
+}
+
+impl Lattice {
+    /// Creates a new engine with the given indexing options.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{IndexConfig, Lattice};
+    ///
+    /// let mut engine = Lattice::with_index_config(IndexConfig::positional());
+    /// engine.add("new york is not york new").unwrap();
+    ///
+    /// let hits = engine.search_phrase("new york", 10);
+    /// assert_eq!(hits.len(), 1);
+    /// assert_eq!(engine.phrase_offsets(0, "new york"), vec![0]);
+    /// ```
+    pub fn with_index_config(config: IndexConfig) -> Self {
+        Self {
+            index_config: config,
+            ..Self::new()
+        }
+    }
+
+    /// Returns the indexing options.
+    #[inline]
+    #[must_use]
+    pub fn index_config(&self) -> IndexConfig {
+        self.index_config
+    }
+
+    /// Returns the byte offsets at which `trigram` occurs in the stored text
+    /// of `doc_id`, ascending.
+    ///
+    /// Returns `None` if positions are not stored or the document does not
+    /// exist, and an empty slice if the trigram does not occur in it.
+    pub fn trigram_positions(&self, doc_id: DocId, trigram: Trigram) -> Option<&[u16]> {
+        if !self.index_config.store_positions || doc_id as usize >= self.len() {
+            return None;
+        }
+        Some(self.positions.get(doc_id as usize, trigram))
+    }
+
+    /// Returns the number of the token containing byte `offset` of the
+    /// stored text of `doc_id`, counting from zero.
+    ///
+    /// An offset on a space belongs to the token after it. Returns `None`
+    /// if the document does not exist or the offset is past its end.
+    pub fn token_position(&self, doc_id: DocId, offset: usize) -> Option<u32> {
+        let text = self.get(doc_id)?;
+        if offset >= text.len() {
+            return None;
+        }
+        Some(memchr_iter(b' ', &text.as_bytes()[..=offset]).count() as u32)
+    }
+
+    /// Returns the byte offsets in the stored text of `doc_id` where the
+    /// normalized `phrase` starts, for highlighting.
+    ///
+    /// Uses the positional index when positions are stored and scans the
+    /// document text otherwise. Phrases shorter than a trigram match nothing.
+    pub fn phrase_offsets(&self, doc_id: DocId, phrase: &str) -> Vec<usize> {
+        let phrase = self.analyze_query(phrase);
+        if phrase.len() < 3 || doc_id as usize >= self.len() {
+            return Vec::new();
+        }
+        let mut trigrams = Vec::with_capacity(phrase.len() - 2);
+        extract_terms_with_pos(&phrase, self.tokenizer.cjk_bigrams, |trigram, offset| {
+            trigrams.push((trigram, offset))
+        });
+        self.match_phrase(doc_id, &phrase, &trigrams)
+    }
+
+    /// Returns the documents containing the normalized `phrase` verbatim,
+    /// ranked by number of occurrences (times the document boost).
+    ///
+    /// Candidates come from the posting list of the phrase's rarest
+    /// trigram; each is then verified against the positional index, or
+    /// against its text when positions are not stored. Phrases shorter
+    /// than a trigram match nothing.
+    pub fn search_phrase(&mut self, phrase: &str, limit: usize) -> Vec<SearchResult> {
+        let phrase = self.analyze_query(phrase);
+        if phrase.len() < 3 || limit == 0 || self.is_empty() {
+            return Vec::new();
+        }
+        self.prepare_for_query();
+
+        let mut trigrams = Vec::with_capacity(phrase.len() - 2);
+        extract_terms_with_pos(&phrase, self.tokenizer.cjk_bigrams, |trigram, offset| {
+            trigrams.push((trigram, offset))
+        });
+        let Some(&(seed, _)) = trigrams.iter().min_by_key(|(t, _)| self.doc_frequency(*t)) else {
+            return Vec::new();
+        };
+
+        let mut candidates = Vec::new();
+        self.for_each_posting(seed, |doc_id| candidates.push(doc_id));
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        let mut results: Vec<SearchResult> = candidates
+            .into_iter()
+            .filter_map(|doc_id| {
+                let count = self.match_phrase(doc_id, &phrase, &trigrams).len();
+                let boost = self.boost(doc_id).unwrap_or(1.0);
+                (count > 0).then(|| SearchResult::new(doc_id, count as f32 * boost))
+            })
+            .collect();
+        self.select_top_k(&mut results, limit);
+        results
+    }
+
+    /// Offsets where `phrase` (with its trigrams at their offsets) starts
+    /// in `doc_id`.
+    fn match_phrase(
+        &self,
+        doc_id: DocId,
+        phrase: &str,
+        trigrams: &[(Trigram, usize)],
+    ) -> Vec<usize> {
+        if !self.index_config.store_positions {
+            // Overlapping matches, like the positional path
+            let text = self.get(doc_id).unwrap_or("").as_bytes();
+            let finder = memmem::Finder::new(phrase.as_bytes());
+            let mut starts = Vec::new();
+            let mut from = 0;
+            while let Some(i) = finder.find(&text[from..]) {
+                starts.push(from + i);
+                from += i + 1;
+            }
+            return starts;
+        }
+
+        let doc = doc_id as usize;
+        let Some(&(first, first_off)) = trigrams.first() else {
+            return Vec::new();
+        };
+        // With CJK bigrams, text too short for a trigram between or before
+        // CJK characters has no terms, so the first term can start past
+        // offset 0 and matches are confirmed against the text
+        let text = self
+            .tokenizer
+            .cjk_bigrams
+            .then(|| self.get(doc_id).unwrap_or("").as_bytes());
+        self.positions
+            .get(doc, first)
+            .iter()
+            .filter_map(|&pos| (pos as usize).checked_sub(first_off))
+            .filter(|&start| {
+                trigrams[1..].iter().all(|&(trigram, offset)| {
+                    let target = start + offset;
+                    u16::try_from(target)
+                        .is_ok_and(|t| self.positions.get(doc, trigram).binary_search(&t).is_ok())
+                }) && text.is_none_or(|text| {
+                    text.get(start..start + phrase.len()) == Some(phrase.as_bytes())
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,474 @@
+//! Per-query search options.
+//!
+//! [`SearchRequest`] bundles a query with options that override the
+//! engine-wide [`SearchConfig`](lattice_types::SearchConfig) for that one
+//! search. [`MinimumShouldMatch`] follows the syntax of Elasticsearch's
+//! `minimum_should_match`, counted in query trigrams:
+//!
+//! | Spec          | Trigrams required                                     |
+//! |---------------|-------------------------------------------------------|
+//! | `3`           | 3                                                     |
+//! | `-2`          | all but 2                                             |
+//! | `75%`         | 75%, rounded down                                     |
+//! | `-25%`        | all but 25%, rounded down                             |
+//! | `2<75%`       | all for up to 2 trigrams, 75% for more                |
+//! | `2<-25% 9<-3` | all up to 2, all but 25% up to 9, all but 3 beyond    |
+//!
+//! Whatever the spec, at least one and at most all trigrams are required.
+
+use crate::analyzer::tokenizer::Field;
+use crate::index::scoring::ScoreContext;
+use crate::index::sort::SortBy;
+use crate::index::types::Lattice;
+use core::fmt;
+use core::str::FromStr;
+use lattice_types::SearchResult;
+use smallvec::SmallVec;
+
+/// How many query trigrams a document must contain to match.
+///
+/// Parse one from its string form with [`str::parse`].
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::MinimumShouldMatch;
+///
+/// let msm: MinimumShouldMatch = "2<75%".parse().unwrap();
+/// assert_eq!(msm.required(2), 2);
+/// assert_eq!(msm.required(8), 6);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MinimumShouldMatch {
+    /// Rule for queries with no more trigrams than the first threshold
+    base: Requirement,
+    /// Rules for queries with more trigrams than each threshold, ascending
+    conditions: SmallVec<[(usize, Requirement); 2]>,
+}
+
+/// A single count or percentage; negative values count missing trigrams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Requirement {
+    Count(i64),
+    Percent(i8),
+}
+
+impl Requirement {
+    const ALL: Self = Requirement::Percent(100);
+
+    fn required(self, total: usize) -> usize {
+        match self {
+            Requirement::Count(n) if n < 0 => total.saturating_sub(n.unsigned_abs() as usize),
+            Requirement::Count(n) => n as usize,
+            Requirement::Percent(p) => {
+                let share = total * p.unsigned_abs() as usize / 100;
+                if p < 0 {
+                    total - share
+                } else {
+                    share
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Requirement {
+    type Err = MinimumShouldMatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || MinimumShouldMatchError::Invalid {
+            clause: s.to_string(),
+        };
+        match s.strip_suffix('%') {
+            Some(percent) => {
+                let p: i64 = percent.parse().map_err(|_| invalid())?;
+                if !(-100..=100).contains(&p) {
+                    return Err(MinimumShouldMatchError::PercentOutOfRange { percent: p });
+                }
+                Ok(Requirement::Percent(p as i8))
+            }
+            None => s.parse().map(Requirement::Count).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requirement::Count(n) => write!(f, "{}", n),
+            Requirement::Percent(p) => write!(f, "{}%", p),
+        }
+    }
+}
+
+impl MinimumShouldMatch {
+    /// Returns how many of `total` query trigrams must match: at least 1
+    /// and at most `total`.
+    pub fn required(&self, total: usize) -> usize {
+        let rule = self
+            .conditions
+            .iter()
+            .take_while(|(threshold, _)| total > *threshold)
+            .last()
+            .map_or(self.base, |&(_, rule)| rule);
+        rule.required(total).clamp(1, total.max(1))
+    }
+}
+
+impl FromStr for MinimumShouldMatch {
+    type Err = MinimumShouldMatchError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let clauses: SmallVec<[&str; 2]> = s.split_whitespace().collect();
+        match clauses.as_slice() {
+            [] => return Err(MinimumShouldMatchError::Empty),
+            [clause] if !clause.contains('<') => {
+                return Ok(Self {
+                    base: clause.parse()?,
+                    conditions: SmallVec::new(),
+                })
+            }
+            _ => {}
+        }
+
+        let mut conditions: SmallVec<[(usize, Requirement); 2]> = SmallVec::new();
+        for clause in clauses {
+            let invalid = || MinimumShouldMatchError::Invalid {
+                clause: clause.to_string(),
+            };
+            let (threshold, rule) = clause.split_once('<').ok_or_else(invalid)?;
+            let threshold: usize = threshold.parse().map_err(|_| invalid())?;
+            if conditions
+                .last()
+                .is_some_and(|&(last, _)| threshold <= last)
+            {
+                return Err(MinimumShouldMatchError::UnorderedThresholds);
+            }
+            conditions.push((threshold, rule.parse()?));
+        }
+        Ok(Self {
+            base: Requirement::ALL,
+            conditions,
+        })
+    }
+}
+
+impl fmt::Display for MinimumShouldMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.conditions.is_empty() {
+            return write!(f, "{}", self.base);
+        }
+        for (i, (threshold, rule)) in self.conditions.iter().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            write!(f, "{}<{}", threshold, rule)?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors returned when parsing a [`MinimumShouldMatch`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MinimumShouldMatchError {
+    /// The spec has no clauses.
+    Empty,
+    /// A clause is not a count, a percentage or a `threshold<value` pair,
+    /// or several clauses are given without thresholds.
+    Invalid {
+        /// The offending clause.
+        clause: String,
+    },
+    /// A percentage is outside -100% to 100%.
+    PercentOutOfRange {
+        /// The parsed percentage.
+        percent: i64,
+    },
+    /// Thresholds are not strictly ascending.
+    UnorderedThresholds,
+}
+
+impl fmt::Display for MinimumShouldMatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MinimumShouldMatchError::Empty => write!(f, "empty minimum_should_match spec"),
+            MinimumShouldMatchError::Invalid { clause } => {
+                write!(f, "invalid minimum_should_match clause {:?}", clause)
+            }
+            MinimumShouldMatchError::PercentOutOfRange { percent } => {
+                write!(f, "percentage {}% is outside -100% to 100%", percent)
+            }
+            MinimumShouldMatchError::UnorderedThresholds => {
+                write!(f, "minimum_should_match thresholds must be ascending")
+            }
+        }
+    }
+}
+
+impl core::error::Error for MinimumShouldMatchError {}
+
+/// A query together with per-search options.
+///
+/// Options left unset fall back to the engine's configuration.
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::{Lattice, SearchRequest};
+///
+/// let mut engine = Lattice::new();
+/// engine.add("hello world").unwrap();
+///
+/// let request = SearchRequest::new("hello", 10).minimum_should_match("100%".parse().unwrap());
+/// assert_eq!(engine.search_request(&request).len(), 1);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchRequest<'q> {
+    pub(crate) query: &'q str,
+    pub(crate) limit: usize,
+    pub(crate) minimum_should_match: Option<MinimumShouldMatch>,
+    pub(crate) field_weights: SmallVec<[(Field, f32); 3]>,
+    pub(crate) min_score: Option<f32>,
+    pub(crate) sort: SortBy,
+    pub(crate) collapse: Option<String>,
+    /// Sort key and inclusive bounds its value must lie within
+    pub(crate) range: Option<(String, f64, f64)>,
+    /// Blend weight of vector similarity, and the query vector
+    pub(crate) hybrid: Option<(f32, &'q [f32])>,
+    /// Set by [`Namespace`](crate::Namespace) searches
+    pub(crate) namespace: Option<u32>,
+}
+
+impl<'q> SearchRequest<'q> {
+    /// Creates a request for the top `limit` matches of `query`.
+    pub fn new(query: &'q str, limit: usize) -> Self {
+        Self {
+            query,
+            limit,
+            minimum_should_match: None,
+            field_weights: SmallVec::new(),
+            min_score: None,
+            sort: SortBy::Score,
+            collapse: None,
+            range: None,
+            hybrid: None,
+            namespace: None,
+        }
+    }
+
+    /// Sets how many query trigrams a document must contain, replacing
+    /// [`SearchConfig::min_overlap_ratio`](lattice_types::SearchConfig::min_overlap_ratio)
+    /// for this search.
+    ///
+    /// Like the ratio, the requirement is enforced on the rarest query
+    /// trigrams: an indexed document must contain that many of them.
+    pub fn minimum_should_match(mut self, spec: MinimumShouldMatch) -> Self {
+        self.minimum_should_match = Some(spec);
+        self
+    }
+
+    /// Weighs matches by the field they occur in (see
+    /// [`Lattice::add_fields`]), multiplying each score by the mean weight
+    /// of the fields holding the matched query trigrams.
+    ///
+    /// Fields left out weigh 0, so `&[(Field::Title, 1.0)]` searches titles
+    /// only. Documents added with [`Lattice::add`] count as
+    /// [`Field::Body`]. [`Field::weight`] gives customary weights.
+    pub fn fields(mut self, weights: &[(Field, f32)]) -> Self {
+        self.field_weights = SmallVec::from_slice(weights);
+        self
+    }
+
+    /// Drops matches scoring below `min_score` before the top `limit` are
+    /// selected, replacing
+    /// [`SearchConfig::min_score`](lattice_types::SearchConfig::min_score)
+    /// for this search.
+    pub fn min_score(mut self, min_score: f32) -> Self {
+        self.min_score = Some(min_score);
+        self
+    }
+
+    /// Orders the results by `sort` instead of by score.
+    ///
+    /// Matches are found, scored and filtered by
+    /// [`min_score`](Self::min_score) as usual; the top `limit` are then
+    /// taken in this order. Scores are still reported.
+    pub fn sort(mut self, sort: SortBy) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Returns only the first match of each value of the sort key `key`
+    /// (see [`Lattice::set_sort_value`]), first in the request's order.
+    ///
+    /// Matches without a value for `key` are each kept. The limit counts
+    /// the matches left after collapsing.
+    ///
+    /// Sort values are numbers, so only numeric keys can be collapsed on.
+    /// A string attribute, such as a source URL, has to be mapped to a
+    /// number first: an ID from the caller's own table, or a stable hash
+    /// as below. Two strings whose hashes collide fall into one group.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, SearchRequest};
+    /// use lattice_types::checksum::crc32;
+    ///
+    /// let mut engine = Lattice::new();
+    /// let pages = [
+    ///     ("the rust book", "doc.rust-lang.org/book"),
+    ///     ("the rust book, 2nd edition", "doc.rust-lang.org/book"),
+    ///     ("rust book review", "blog.example.com"),
+    /// ];
+    /// for (text, source) in pages {
+    ///     let id = engine.add(text).unwrap();
+    ///     // A u32 hash converts to f64 exactly
+    ///     let group = f64::from(crc32(source.as_bytes()));
+    ///     engine.set_sort_value(id, "source_id", group).unwrap();
+    /// }
+    ///
+    /// let request = SearchRequest::new("rust book", 10).collapse_by("source_id");
+    /// let ids: Vec<_> = engine.search_request(&request).iter().map(|r| r.doc_id).collect();
+    /// assert_eq!(ids, [0, 2]);
+    /// ```
+    pub fn collapse_by(mut self, key: &str) -> Self {
+        self.collapse = Some(key.to_owned());
+        self
+    }
+
+    /// Returns only matches whose value for the sort key `key` (see
+    /// [`Lattice::set_sort_value`]) lies between `min` and `max`,
+    /// inclusive, such as log entries of a time window (see
+    /// [`Lattice::add_log_line`]).
+    ///
+    /// Matches without a value for `key` are dropped. The filter applies
+    /// before the top `limit` are selected.
+    pub fn range(mut self, key: &str, min: f64, max: f64) -> Self {
+        self.range = Some((key.to_owned(), min, max));
+        self
+    }
+
+    /// Ranks matches by a blend of text relevance and vector similarity:
+    /// `(1 - alpha) * text + alpha * cosine`, with `alpha` clamped to 0-1.
+    ///
+    /// `text` is the match's score relative to the best the query could
+    /// reach, and `cosine` the similarity of its vector (see
+    /// [`Lattice::set_vector`]) to `query_vector`, 0 for documents without
+    /// one. Only documents matching the query text are ranked, and
+    /// [`min_score`](Self::min_score) applies to the text score.
+    ///
+    /// A query vector that is empty, holds a non-finite value or differs
+    /// in dimension from the stored vectors is ignored, ranking by text
+    /// alone.
+    pub fn hybrid(mut self, alpha: f32, query_vector: &'q [f32]) -> Self {
+        let alpha = if alpha.is_nan() {
+            0.0
+        } else {
+            alpha.clamp(0.0, 1.0)
+        };
+        self.hybrid = Some((alpha, query_vector));
+        self
+    }
+
+    /// Returns the query text.
+    #[inline]
+    pub fn query(&self) -> &'q str {
+        self.query
+    }
+
+    /// Returns the maximum number of results.
+    #[inline]
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+}
+
+/// Results of [`Lattice::search_response`], with the score ceiling needed
+/// to compare them across queries.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResponse {
+    /// Matches, best first, with their raw scores.
+    pub results: Vec<SearchResult>,
+    /// Highest score any document could reach for this query; 0 if the
+    /// query has no trigrams.
+    pub max_score: f32,
+}
+
+impl SearchResponse {
+    /// Returns `result`'s score relative to [`max_score`](Self::max_score),
+    /// in 0.0-1.0.
+    ///
+    /// Boosted documents can exceed the ceiling and are clamped to 1.0.
+    #[inline]
+    pub fn normalized(&self, result: &SearchResult) -> f32 {
+        if self.max_score > 0.0 {
+            (result.score / self.max_score).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Iterates over the results with their normalized scores.
+    pub fn normalized_results(&self) -> impl Iterator<Item = (SearchResult, f32)> + '_ {
+        self.results.iter().map(|&r| (r, self.normalized(&r)))
+    }
+}
+
+impl Lattice {
+    /// Searches with per-query options, returning scores normalized
+    /// against the best possible score for the query alongside the raw
+    /// ones.
+    ///
+    /// Raw scores depend on the query's length and the corpus, so a fixed
+    /// cutoff means something different for every query; normalized
+    /// scores do not. A document consisting of exactly the query scores
+    /// 1.0 under the default scoring.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, SearchRequest};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.add("hello world, again").unwrap();
+    ///
+    /// let response = engine.search_response(&SearchRequest::new("hello world", 10));
+    /// let scores: Vec<f32> = response.normalized_results().map(|(_, s)| s).collect();
+    /// assert_eq!(scores[0], 1.0);
+    /// assert!(scores[1] < 1.0);
+    /// ```
+    pub fn search_response(&mut self, request: &SearchRequest<'_>) -> SearchResponse {
+        let results = self.search_request(request);
+        // The query buffer holds the query actually run, spelling
+        // corrections included
+        let max_score = if results.is_empty() {
+            self.score_ceiling(&self.analyze_query(request.query), &request.field_weights)
+        } else {
+            self.score_ceiling(&self.scratch.query_buf, &request.field_weights)
+        };
+        SearchResponse { results, max_score }
+    }
+
+    /// Searches with per-query options.
+    ///
+    /// Behaves like [`search`](Self::search), including the spelling
+    /// correction retry, with the request's options applied.
+    pub fn search_request(&mut self, request: &SearchRequest<'_>) -> Vec<SearchResult> {
+        self.search_request_with_scorer(request, |ctx| ctx.default_score())
+    }
+
+    /// Searches with per-query options and a caller-provided scoring
+    /// function. See [`search_with_scorer`](Self::search_with_scorer).
+    pub fn search_request_with_scorer<F>(
+        &mut self,
+        request: &SearchRequest<'_>,
+        scorer: F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        self.run_search(request, &scorer)
+    }
+}
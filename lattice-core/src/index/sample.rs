@@ -0,0 +1,82 @@
+//! Random sampling of stored documents.
+//!
+//! [`Lattice::sample`] draws documents uniformly without replacement, for
+//! spot-checking a live index or seeding an evaluation set. Sampling is
+//! driven by a caller-supplied seed, so the same seed over the same index
+//! always returns the same documents.
+
+use crate::index::types::Lattice;
+use lattice_types::DocId;
+use rustc_hash::FxHashSet;
+
+/// SplitMix64, a small, fast, well-mixed generator; plenty for sampling.
+pub(crate) struct SplitMix64(pub(crate) u64);
+
+impl SplitMix64 {
+    pub(crate) fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a value in `0..=max`.
+    pub(crate) fn up_to(&mut self, max: usize) -> usize {
+        // Multiply-shift range reduction; the bias is below 2^-32 for any
+        // index that fits in memory
+        ((self.next() as u128 * (max as u128 + 1)) >> 64) as usize
+    }
+}
+
+impl Lattice {
+    /// Returns `n` distinct documents chosen uniformly at random, in
+    /// ascending ID order, or every document if the index holds `n` or
+    /// fewer.
+    ///
+    /// The choice depends only on `seed` and the number of documents.
+    /// Runs in O(n log n), independent of the index size.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// for i in 0..100 {
+    ///     engine.add(&format!("document {}", i)).unwrap();
+    /// }
+    ///
+    /// let sample = engine.sample(5, 42);
+    /// assert_eq!(sample.len(), 5);
+    /// assert_eq!(sample, engine.sample(5, 42));
+    /// assert_eq!(engine.get(sample[0].0), Some(sample[0].1));
+    /// ```
+    pub fn sample(&self, n: usize, seed: u64) -> Vec<(DocId, &str)> {
+        let len = self.len();
+        if n >= len {
+            return self.iter().collect();
+        }
+
+        // Floyd's algorithm: exactly n draws, each set equally likely
+        let mut rng = SplitMix64(seed);
+        let mut chosen = FxHashSet::default();
+        chosen.reserve(n);
+        for upper in len - n..len {
+            let pick = rng.up_to(upper);
+            if !chosen.insert(pick) {
+                chosen.insert(upper);
+            }
+        }
+
+        let mut doc_ids: Vec<usize> = chosen.into_iter().collect();
+        doc_ids.sort_unstable();
+        doc_ids
+            .into_iter()
+            .filter_map(|doc| {
+                let doc_id = doc as DocId;
+                self.get(doc_id).map(|text| (doc_id, text))
+            })
+            .collect()
+    }
+}
@@ -1,25 +1,432 @@
 //! Scoring functions.
 
+use crate::analyzer::tokenizer::Field;
+use crate::analyzer::trigram::extract_terms_with_pos;
 use crate::index::types::Lattice;
-use lattice_types::DocId;
+use lattice_types::{DocId, SearchResult, Trigram};
+use smallvec::SmallVec;
+
+/// Offset error, in bytes, at which a pair of query terms earns half the
+/// proximity score (about one short word out of place).
+const PROXIMITY_SCALE: f32 = 8.0;
+
+/// Query-time scoring options, set with [`Lattice::set_scoring`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoringConfig {
+    /// Weight of the proximity component: documents where consecutive
+    /// query terms occur close together and in query order score up to
+    /// `1 + proximity_weight` times their base score, so "new york" ranks
+    /// above a document mentioning "new" and "york" far apart.
+    ///
+    /// Applied on top of any scorer. Needs
+    /// [`IndexConfig::store_positions`](crate::IndexConfig::store_positions);
+    /// without positions, and at the default of 0, it has no effect.
+    pub proximity_weight: f32,
+    /// Relevance formula behind [`ScoreContext::default_score`].
+    pub mode: ScoringMode,
+    /// How [`ScoringMode::Overlap`] damps scores of long documents.
+    pub length_norm: LengthNorm,
+    /// Order of results with equal scores.
+    pub tie_break: TieBreak,
+}
+
+impl ScoringConfig {
+    /// The default configuration, usable in `const` contexts.
+    pub const fn new() -> Self {
+        Self {
+            proximity_weight: 0.0,
+            mode: ScoringMode::Overlap,
+            length_norm: LengthNorm::Sqrt,
+            tie_break: TieBreak::OldestFirst,
+        }
+    }
+}
+
+/// Order of results with equal scores.
+///
+/// Document IDs are assigned in insertion order, so ties are broken by
+/// document age. Either way the order is the same from run to run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TieBreak {
+    /// Ascending document ID: the earliest added first.
+    #[default]
+    OldestFirst,
+    /// Descending document ID: the most recently added first.
+    NewestFirst,
+}
+
+impl TieBreak {
+    /// Orders two documents with equal scores.
+    #[inline]
+    pub(crate) fn order(self, a: DocId, b: DocId) -> core::cmp::Ordering {
+        match self {
+            TieBreak::OldestFirst => a.cmp(&b),
+            TieBreak::NewestFirst => b.cmp(&a),
+        }
+    }
+}
+
+/// Document length normalization under [`ScoringMode::Overlap`].
+///
+/// Each variant yields the factor `100 / penalty(doc_len)` by which the
+/// squared match ratio is multiplied, with `doc_len` in bytes of normalized
+/// text. The penalty is 1 for an empty document, except for
+/// [`Pivoted`](Self::Pivoted), where it is 1 at the average length.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LengthNorm {
+    /// No length penalty: only the matches count.
+    None,
+    /// `1 + sqrt(doc_len)`. Strongly favors short documents.
+    #[default]
+    Sqrt,
+    /// `1 + ln(1 + doc_len)`. Grows slowly, so long documents are barely
+    /// penalized against each other.
+    Log,
+    /// `1 - b + b * doc_len / avg_doc_len`, the BM25 length normalization.
+    /// Documents are judged against the average length of the corpus.
+    Pivoted {
+        /// Normalization strength, from 0 (none) to 1 (full).
+        b: f32,
+    },
+}
+
+impl LengthNorm {
+    /// Pivoted normalization with the customary `b = 0.75`.
+    pub const PIVOTED: Self = Self::Pivoted { b: 0.75 };
+
+    /// Returns the factor for a document of `doc_len` bytes in a corpus
+    /// averaging `avg_doc_len` bytes per document.
+    #[inline]
+    pub fn factor(self, doc_len: u32, avg_doc_len: f32) -> f32 {
+        let len = doc_len as f32;
+        let penalty = match self {
+            LengthNorm::None => 1.0,
+            LengthNorm::Sqrt => 1.0 + len.sqrt(),
+            LengthNorm::Log => 1.0 + len.ln_1p(),
+            LengthNorm::Pivoted { b } => 1.0 - b + b * len / avg_doc_len.max(1.0),
+        };
+        100.0 / penalty.max(f32::EPSILON)
+    }
+}
+
+/// Relevance formula used by [`Lattice::search`].
+///
+/// The statistical modes weigh each matched query trigram by its term
+/// frequency in the document and its rarity in the corpus. They need
+/// [`IndexConfig::store_term_frequencies`](crate::IndexConfig::store_term_frequencies);
+/// without it every mode scores like [`Overlap`](Self::Overlap).
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ScoringMode {
+    /// Squared share of query trigrams matched, damped by document length
+    /// (see [`ScoreContext::default_score`]).
+    #[default]
+    Overlap,
+    /// Sum over matched query trigrams of `(1 + ln tf) * ln(1 + N / df)`.
+    TfIdf,
+    /// Okapi BM25 over trigrams, with document length measured in trigram
+    /// occurrences.
+    Bm25 {
+        /// Term frequency saturation.
+        k1: f32,
+        /// Length normalization strength, from 0 (none) to 1 (full).
+        b: f32,
+    },
+}
+
+impl ScoringMode {
+    /// BM25 with the customary `k1 = 1.2`, `b = 0.75`.
+    pub const BM25: Self = Self::Bm25 { k1: 1.2, b: 0.75 };
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Per-candidate inputs available to a scoring function.
+///
+/// Passed to the callback of [`Lattice::search_with_scorer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreContext {
+    /// Candidate document.
+    pub doc_id: DocId,
+    /// Weighted number of query trigrams found in the document
+    /// (prefix trigrams count extra).
+    pub matches: u32,
+    /// Length of the normalized document in bytes.
+    pub doc_len: u32,
+    /// Number of trigrams in the query.
+    pub query_trigrams: u32,
+    /// Score multiplier set with [`Lattice::set_boost`] (1.0 by default).
+    pub boost: f32,
+    /// TF-IDF or BM25 score under [`ScoringConfig::mode`], before the
+    /// boost. `None` in [`ScoringMode::Overlap`] or when term frequencies
+    /// are not stored.
+    pub term_score: Option<f32>,
+    /// Length normalization factor under [`ScoringConfig::length_norm`]
+    /// (see [`LengthNorm::factor`]).
+    pub len_factor: f32,
+}
+
+impl ScoreContext {
+    /// The built-in relevance score used by [`Lattice::search`].
+    ///
+    /// [`term_score`](Self::term_score) times the boost when set. Otherwise
+    /// the squared match ratio times [`len_factor`](Self::len_factor)
+    /// (`100 / (1 + sqrt(doc_len))` by default, so that shorter documents
+    /// rank above longer ones with the same matches) and the document's
+    /// boost.
+    #[inline(always)]
+    pub fn default_score(&self) -> f32 {
+        if let Some(score) = self.term_score {
+            return score * self.boost;
+        }
+
+        let match_ratio = self.matches as f32 / self.query_trigrams.max(1) as f32;
+        match_ratio * match_ratio * self.len_factor * self.boost
+    }
+}
 
 impl Lattice {
+    /// Sets the query-time scoring options.
+    ///
+    /// The options are saved with the index by [`Lattice::save`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{IndexConfig, Lattice, ScoringConfig};
+    ///
+    /// let mut engine = Lattice::with_index_config(IndexConfig::positional());
+    /// engine.add("new yolk and york new").unwrap();
+    /// engine.add("visit the new york office today please").unwrap();
+    /// // The shorter document wins on trigram overlap alone
+    /// assert_eq!(engine.search("new york", 2)[0].doc_id, 0);
+    ///
+    /// engine.set_scoring(ScoringConfig {
+    ///     proximity_weight: 2.0,
+    ///     ..ScoringConfig::new()
+    /// });
+    /// assert_eq!(engine.search("new york", 2)[0].doc_id, 1);
+    /// ```
+    pub fn set_scoring(&mut self, config: ScoringConfig) {
+        self.scoring = config;
+    }
+
+    /// Returns the query-time scoring options.
+    #[inline]
+    #[must_use]
+    pub fn scoring_config(&self) -> ScoringConfig {
+        self.scoring
+    }
+
+    /// Returns the distinct query trigrams with their IDF when a statistical
+    /// scoring mode applies, together with the average document length.
+    pub(crate) fn term_weights(&self, normalized_query: &str) -> (Vec<(Trigram, f32)>, f32) {
+        if self.scoring.mode == ScoringMode::Overlap || !self.index_config.store_term_frequencies {
+            return (Vec::new(), 0.0);
+        }
+
+        let mut trigrams: Vec<Trigram> = self
+            .select_query_terms(normalized_query)
+            .iter()
+            .map(|term| term.trigram)
+            .collect();
+        trigrams.sort_unstable_by_key(|t| t.0);
+
+        let docs = self.len() as f32;
+        let bm25 = matches!(self.scoring.mode, ScoringMode::Bm25 { .. });
+        let weights = trigrams
+            .into_iter()
+            .map(|t| {
+                let df = self.doc_frequency(t) as f32;
+                let idf = if bm25 {
+                    (1.0 + (docs - df + 0.5) / (df + 0.5)).ln()
+                } else {
+                    (1.0 + docs / df.max(1.0)).ln()
+                };
+                (t, idf)
+            })
+            .collect();
+        (weights, self.frequencies.average_len())
+    }
+
+    /// Scores `doc_id` against `weights` under the statistical mode.
+    pub(crate) fn term_score(
+        &self,
+        doc_id: DocId,
+        weights: &[(Trigram, f32)],
+        avg_len: f32,
+    ) -> f32 {
+        let doc = doc_id as usize;
+        let doc_len = self
+            .doc_lengths
+            .get(doc)
+            .map_or(0, |&l| l.saturating_sub(2)) as f32;
+        let mut score = 0.0;
+        for &(trigram, idf) in weights {
+            let tf = self.frequencies.get(doc, trigram) as f32;
+            if tf == 0.0 {
+                continue;
+            }
+            score += match self.scoring.mode {
+                ScoringMode::Bm25 { k1, b } => {
+                    let norm = 1.0 - b + b * doc_len / avg_len.max(1.0);
+                    idf * tf * (k1 + 1.0) / (tf + k1 * norm)
+                }
+                _ => (1.0 + tf.ln()) * idf,
+            };
+        }
+        score
+    }
+
+    /// Returns the (first trigram, byte offset) of each query term long
+    /// enough to have a trigram, when proximity scoring applies. A CJK
+    /// term's first trigram is the term of its first character.
+    pub(crate) fn proximity_terms(
+        &self,
+        normalized_query: &str,
+    ) -> SmallVec<[(Trigram, usize); 8]> {
+        let mut terms = SmallVec::new();
+        if self.scoring.proximity_weight == 0.0 || !self.index_config.store_positions {
+            return terms;
+        }
+        let mut offset = 0;
+        for term in normalized_query.split(' ') {
+            let mut first = None;
+            extract_terms_with_pos(term, self.tokenizer.cjk_bigrams, |trigram, at| {
+                first.get_or_insert((trigram, offset + at));
+            });
+            terms.extend(first);
+            offset += term.len() + 1;
+        }
+        if terms.len() < 2 {
+            terms.clear();
+        }
+        terms
+    }
+
+    /// Scores how closely `doc_id` reproduces the layout of `terms`, from 0
+    /// (no pair of consecutive terms present) to 1 (every pair exactly as
+    /// far apart as in the query).
+    pub(crate) fn proximity(&self, doc_id: DocId, terms: &[(Trigram, usize)]) -> f32 {
+        if terms.len() < 2 {
+            return 0.0;
+        }
+        let doc = doc_id as usize;
+        let mut total = 0.0;
+        for pair in terms.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            let gap = (second.1 - first.1) as i64;
+            let lefts = self.positions.get(doc, first.0);
+            let rights = self.positions.get(doc, second.0);
+            if lefts.is_empty() || rights.is_empty() {
+                continue;
+            }
+
+            // Closest right occurrence to where each left one says it should be
+            let mut best = i64::MAX;
+            for &left in lefts {
+                let expected = left as i64 + gap;
+                let i = rights.partition_point(|&r| (r as i64) < expected);
+                for &right in rights[i.saturating_sub(1)..].iter().take(2) {
+                    best = best.min((right as i64 - expected).abs());
+                }
+                if best == 0 {
+                    break;
+                }
+            }
+            total += 1.0 / (1.0 + best as f32 / PROXIMITY_SCALE);
+        }
+        total / (terms.len() - 1) as f32
+    }
+
+    /// Highest score the built-in scoring can give a document for the
+    /// normalized query: a document consisting of the query itself, or the
+    /// saturated term scores under the statistical modes, with proximity
+    /// and the heaviest of `field_weights` at their best. Boosts are not
+    /// included.
+    pub(crate) fn score_ceiling(
+        &self,
+        normalized_query: &str,
+        field_weights: &[(Field, f32)],
+    ) -> f32 {
+        let terms = self.select_query_terms(normalized_query);
+        let total: usize = terms.iter().map(|term| term.occurrences as usize).sum();
+        if total == 0 {
+            return 0.0;
+        }
+
+        let (term_weights, _) = self.term_weights(normalized_query);
+        let idf_sum: f32 = term_weights.iter().map(|&(_, idf)| idf).sum();
+        let mut ceiling = match self.scoring.mode {
+            _ if term_weights.is_empty() => {
+                let matches: usize = terms.iter().map(|term| term.bonus as usize).sum();
+                let ratio = matches as f32 / total as f32;
+                let len_factor = self
+                    .scoring
+                    .length_norm
+                    .factor(normalized_query.len() as u32, self.average_doc_len());
+                ratio * ratio * len_factor
+            }
+            ScoringMode::Bm25 { k1, .. } => idf_sum * (k1 + 1.0),
+            _ => idf_sum * (1.0 + (u8::MAX as f32).ln()),
+        };
+        if !self.proximity_terms(normalized_query).is_empty() {
+            ceiling *= 1.0 + self.scoring.proximity_weight;
+        }
+        if !field_weights.is_empty() {
+            ceiling *= field_weights.iter().fold(0.0f32, |max, &(_, w)| max.max(w));
+        }
+        ceiling
+    }
+
+    /// Average normalized document length in bytes, when the length
+    /// normalization needs it (0 otherwise).
+    pub(crate) fn average_doc_len(&self) -> f32 {
+        match self.scoring.length_norm {
+            LengthNorm::Pivoted { .. } if !self.is_empty() => {
+                self.documents.bytes_used() as f32 / self.len() as f32
+            }
+            _ => 0.0,
+        }
+    }
+
     #[inline(always)]
-    pub(crate) fn compute_score_fast(
+    pub(crate) fn score_context(
         &self,
         doc_id: DocId,
         matches: usize,
         query_trigrams: usize,
-    ) -> f32 {
-        let doc_len = self.doc_lengths.get(doc_id as usize).copied().unwrap_or(0) as usize;
+        avg_doc_len: f32,
+    ) -> ScoreContext {
+        let doc_len = self.doc_lengths.get(doc_id as usize).copied().unwrap_or(0);
+        ScoreContext {
+            doc_id,
+            matches: matches as u32,
+            doc_len,
+            query_trigrams: query_trigrams as u32,
+            boost: self.boosts.get(doc_id as usize).copied().unwrap_or(1.0),
+            term_score: None,
+            len_factor: self.scoring.length_norm.factor(doc_len, avg_doc_len),
+        }
+    }
 
-        let len_factor = if doc_len > 0 {
-            100.0 / (1.0 + (doc_len as f32).sqrt())
-        } else {
-            100.0
+    /// Keeps the `limit` best results, sorted by descending score with ties
+    /// broken by the configured [`TieBreak`].
+    pub(crate) fn select_top_k(&self, results: &mut Vec<SearchResult>, limit: usize) {
+        let tie_break = self.scoring.tie_break;
+        let by_rank = |a: &SearchResult, b: &SearchResult| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| tie_break.order(a.doc_id, b.doc_id))
         };
 
-        let match_ratio = matches as f32 / query_trigrams.max(1) as f32;
-        match_ratio * match_ratio * len_factor
+        if results.len() > limit {
+            results.select_nth_unstable_by(limit, by_rank);
+            results.truncate(limit);
+        }
+        results.sort_unstable_by(by_rank);
     }
 }
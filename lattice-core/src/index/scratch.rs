@@ -0,0 +1,159 @@
+//! Per-query scratch buffers and searching through `&Lattice`.
+//!
+//! A query needs a few buffers: the normalized query text, the candidate
+//! list and the ranked results. They live in a [`SearchScratch`] rather
+//! than in the engine, so nothing in the index itself changes while a
+//! query runs. [`Lattice::search_with_scratch`] and
+//! [`Lattice::search_ref`] therefore take `&self`, and any number of
+//! threads can query one engine at once, each with its own scratch:
+//!
+//! ```text
+//!  thread 1 ── scratch 1 ──┐
+//!  thread 2 ── scratch 2 ──┼──▶ &Lattice
+//!  thread 3 ── scratch 3 ──┘
+//! ```
+//!
+//! The `&mut self` methods such as [`Lattice::search`] keep a scratch of
+//! their own inside the engine and behave exactly as before.
+
+use crate::index::metrics::Metrics;
+use crate::index::request::SearchRequest;
+use crate::index::scoring::ScoreContext;
+use crate::index::types::{Candidate, Lattice};
+use core::cell::RefCell;
+use core::mem::size_of;
+use lattice_types::SearchResult;
+use smallvec::SmallVec;
+
+/// Reusable buffers for one query at a time.
+///
+/// Keep one per thread and pass it to
+/// [`Lattice::search_with_scratch`]; buffers keep their capacity between
+/// queries, so steady-state searches do not allocate for them.
+#[derive(Debug, Default)]
+pub struct SearchScratch {
+    pub(crate) candidates: SmallVec<[Candidate; 256]>,
+    pub(crate) results: SmallVec<[SearchResult; 64]>,
+    /// The normalized query last run, spelling corrections included
+    pub(crate) query_buf: String,
+    /// Tokenizer working space
+    pub(crate) token_buf: String,
+}
+
+impl SearchScratch {
+    /// Creates empty buffers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Releases the buffers' spare capacity.
+    pub fn shrink_to_fit(&mut self) {
+        self.candidates.shrink_to_fit();
+        self.results.shrink_to_fit();
+        self.query_buf.shrink_to_fit();
+        self.token_buf.shrink_to_fit();
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        let candidates = if self.candidates.spilled() {
+            self.candidates.capacity() * size_of::<Candidate>()
+        } else {
+            0
+        };
+        let results = if self.results.spilled() {
+            self.results.capacity() * size_of::<SearchResult>()
+        } else {
+            0
+        };
+        candidates + results + self.query_buf.capacity() + self.token_buf.capacity()
+    }
+}
+
+thread_local! {
+    static SCRATCH: RefCell<SearchScratch> = RefCell::new(SearchScratch::new());
+}
+
+impl Lattice {
+    /// Runs `request` through a shared reference, using `scratch` for the
+    /// query's working buffers.
+    ///
+    /// Results match [`search_request`](Self::search_request), spelling
+    /// correction retry included, with two differences that follow from not
+    /// modifying the engine:
+    ///
+    /// - Documents added since the last [`commit`](Self::commit) are
+    ///   searched only if the uncommitted delta is already sorted;
+    ///   committing (or one `&mut` search under the default policy) makes
+    ///   every add visible.
+    /// - The query is counted in [`metrics`](Self::metrics), but its latency
+    ///   is not recorded and the stats reporter is not run.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, SearchRequest, SearchScratch};
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.add("hello there").unwrap();
+    /// engine.commit();
+    ///
+    /// let engine = &engine;
+    /// std::thread::scope(|s| {
+    ///     for _ in 0..4 {
+    ///         s.spawn(move || {
+    ///             let mut scratch = SearchScratch::new();
+    ///             let request = SearchRequest::new("hello", 10);
+    ///             let results = engine.search_with_scratch(&request, &mut scratch);
+    ///             assert_eq!(results.len(), 2);
+    ///         });
+    ///     }
+    /// });
+    /// ```
+    pub fn search_with_scratch(
+        &self,
+        request: &SearchRequest<'_>,
+        scratch: &mut SearchScratch,
+    ) -> Vec<SearchResult> {
+        Metrics::add(&self.counters.queries_executed, 1);
+        self.search_in(request, &|ctx| ctx.default_score(), scratch)
+    }
+
+    /// Searches through a shared reference, like
+    /// [`search`](Self::search), using a scratch kept per thread.
+    ///
+    /// See [`search_with_scratch`](Self::search_with_scratch) for how this
+    /// differs from the `&mut self` methods.
+    pub fn search_ref(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let request = SearchRequest::new(query, limit);
+        self.search_thread_local(&request, &|ctx| ctx.default_score())
+    }
+
+    /// Runs `request` through a shared reference with this thread's
+    /// scratch, counting the query.
+    pub(crate) fn search_thread_local<F>(
+        &self,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        Metrics::add(&self.counters.queries_executed, 1);
+        SCRATCH.with(|scratch| match scratch.try_borrow_mut() {
+            Ok(mut scratch) => self.search_in(request, scorer, &mut scratch),
+            // A scorer searching again on the same thread
+            Err(_) => self.search_in(request, scorer, &mut SearchScratch::new()),
+        })
+    }
+
+    /// Brings the index up to date for querying, then runs `f` with the
+    /// engine's own scratch.
+    pub(crate) fn with_scratch<R>(&mut self, f: impl FnOnce(&Self, &mut SearchScratch) -> R) -> R {
+        self.prepare_for_query();
+        let mut scratch = core::mem::take(&mut self.scratch);
+        let result = f(self, &mut scratch);
+        self.scratch = scratch;
+        result
+    }
+}
@@ -1,210 +1,513 @@
 //! Search algorithm logic.
 
+use crate::analyzer::normalizer::TextNormalizer;
+use crate::analyzer::trigram::extract_terms;
+use crate::index::metrics::Metrics;
+use crate::index::request::{MinimumShouldMatch, SearchRequest};
+use crate::index::scoring::ScoreContext;
+use crate::index::scratch::SearchScratch;
+use crate::index::sort::SortValues;
 use crate::index::types::{
-    Candidate, Lattice, QueryTrigram, MAX_CANDIDATES, MAX_QUERY_LENGTH, MAX_QUERY_TRIGRAMS,
+    Candidate, Lattice, QueryTerm, QueryTrigram, MAX_CANDIDATES, MAX_QUERY_TRIGRAMS,
     MAX_SEED_POSTING_LIST, PREFIX_BONUS,
 };
-use lattice_types::{DocId, SearchResult, Trigram};
+use crate::index::vectors;
+use lattice_types::{DocId, SearchError, SearchResult, Trigram};
 use rustc_hash::FxHashMap;
 use smallvec::SmallVec;
+use std::time::Instant;
+
+/// Posting list length, relative to the candidates, from which lookups
+/// gallop instead of walking the list.
+const GALLOP_RATIO: usize = 16;
 
 impl Lattice {
     /// Searches for documents matching the query.
     ///
     /// Returns owned results - no lifetime coupling with the engine.
+    ///
+    /// If nothing matches and a BK-tree is enabled, the query is retried
+    /// once with its terms spelling-corrected.
     #[inline(never)]
     pub fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
-        self.query_count += 1;
+        self.search_with_scorer(query, limit, |ctx| ctx.default_score())
+    }
+
+    /// Searches like [`search`](Self::search), but reports why a query
+    /// could not be run instead of returning no results for it.
+    ///
+    /// `Ok` with no results means the query ran and nothing matched.
+    ///
+    /// # Errors
+    ///
+    /// - [`SearchError::IndexEmpty`] if no document has been added.
+    /// - [`SearchError::QueryTooShort`] if the normalized query is shorter
+    ///   than one trigram.
+    /// - [`SearchError::QueryTooLong`] if the query exceeds
+    ///   [`SearchConfig::max_query_length`](lattice_types::SearchConfig::max_query_length).
+    /// - [`SearchError::TooManyCandidates`] if even the query's rarest
+    ///   trigram occurs in too many documents to rank.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use lattice_types::SearchError;
+    ///
+    /// let mut engine = Lattice::new();
+    /// assert_eq!(engine.try_search("hello", 10), Err(SearchError::IndexEmpty));
+    ///
+    /// engine.add("hello world").unwrap();
+    /// assert_eq!(engine.try_search("hello", 10).unwrap().len(), 1);
+    /// assert_eq!(engine.try_search("zebra", 10), Ok(Vec::new()));
+    /// assert_eq!(
+    ///     engine.try_search("hi", 10),
+    ///     Err(SearchError::QueryTooShort { length: 2, min_length: 3 })
+    /// );
+    /// let long = "hello ".repeat(200);
+    /// assert_eq!(
+    ///     engine.try_search(&long, 10),
+    ///     Err(SearchError::QueryTooLong { length: 1200, max_length: 1000 })
+    /// );
+    /// ```
+    pub fn try_search(
+        &mut self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<SearchResult>, SearchError> {
+        self.try_run_search(&SearchRequest::new(query, limit), &|ctx| {
+            ctx.default_score()
+        })
+    }
+
+    /// Returns every document scoring at least `min_score`, best first,
+    /// rather than a fixed number of them.
+    ///
+    /// Suits deduplication and record matching, where every match above a
+    /// known-good cutoff is wanted. Candidate selection is the same as for
+    /// [`search`](Self::search), as is the spelling correction retry.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("acme corporation").unwrap();
+    /// engine.add("acme corporation ltd").unwrap();
+    /// engine.add("acme widgets and gadgets incorporated").unwrap();
+    ///
+    /// let best = engine.search("acme corporation", 1)[0].score;
+    /// let near = engine.search_threshold("acme corporation", best / 2.0);
+    /// assert_eq!(near.len(), 2);
+    /// ```
+    pub fn search_threshold(&mut self, query: &str, min_score: f32) -> Vec<SearchResult> {
+        let request = SearchRequest::new(query, usize::MAX).min_score(min_score);
+        self.search_request(&request)
+    }
+
+    /// Searches using a caller-provided scoring function.
+    ///
+    /// Candidate selection is unchanged; `scorer` replaces the built-in
+    /// relevance formula when ranking. Higher scores rank first.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.add("hello there").unwrap();
+    ///
+    /// // Prefer newer documents on top of the default relevance score
+    /// let results = engine.search_with_scorer("hello", 10, |ctx| {
+    ///     ctx.default_score() * (1.0 + ctx.doc_id as f32)
+    /// });
+    /// assert_eq!(results[0].doc_id, 1);
+    /// ```
+    pub fn search_with_scorer<F>(
+        &mut self,
+        query: &str,
+        limit: usize,
+        scorer: F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        self.run_search(&SearchRequest::new(query, limit), &scorer)
+    }
 
-        if self.is_empty() || limit == 0 {
-            return Vec::new();
+    /// Runs `request`, retrying once with a spelling-corrected query.
+    pub(crate) fn run_search<F>(
+        &mut self,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        self.try_run_search(request, scorer).unwrap_or_default()
+    }
+
+    /// Runs `request` like [`run_search`](Self::run_search), reporting why
+    /// it could not run.
+    pub(crate) fn try_run_search<F>(
+        &mut self,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+    ) -> Result<Vec<SearchResult>, SearchError>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        Metrics::add(&self.counters.queries_executed, 1);
+        let started = Instant::now();
+
+        let results =
+            self.with_scratch(|engine, scratch| engine.try_search_in(request, scorer, scratch));
+
+        self.telemetry.query_latency.record(started.elapsed());
+        self.report_stats_if_due();
+        results
+    }
+
+    /// Runs `request` against the index as it stands, retrying once with a
+    /// spelling-corrected query.
+    pub(crate) fn search_in<F>(
+        &self,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+        scratch: &mut SearchScratch,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        self.try_search_in(request, scorer, scratch)
+            .unwrap_or_default()
+    }
+
+    /// Runs `request` like [`search_in`](Self::search_in), reporting why
+    /// it could not run. A query that fails or finds nothing is retried
+    /// spelling-corrected; the retry's results replace the first attempt's
+    /// only if it finds something.
+    pub(crate) fn try_search_in<F>(
+        &self,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+        scratch: &mut SearchScratch,
+    ) -> Result<Vec<SearchResult>, SearchError>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        let results = self.search_trigrams(request.query, request, scorer, scratch);
+        if results.as_ref().is_ok_and(|r| !r.is_empty()) || !self.dictionary.has_bk_tree() {
+            return results;
+        }
+        if let Some(corrected) = self.correct(request.query) {
+            if let Ok(retried) = self.search_trigrams(&corrected, request, scorer, scratch) {
+                if !retried.is_empty() {
+                    return Ok(retried);
+                }
+            }
+        }
+        results
+    }
+
+    fn search_trigrams<F>(
+        &self,
+        query: &str,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+        scratch: &mut SearchScratch,
+    ) -> Result<Vec<SearchResult>, SearchError>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        let limit = request.limit;
+        if limit == 0 {
+            return Ok(Vec::new());
         }
 
-        if self.needs_rebuild {
-            self.rebuild_index();
+        let total_trigrams =
+            self.collect_candidates(query, request.minimum_should_match.as_ref(), scratch);
+        Metrics::add(
+            &self.counters.candidates_examined,
+            scratch.candidates.len() as u64,
+        );
+        Ok(match total_trigrams? {
+            Some(total_trigrams) => self.rank_candidates(total_trigrams, request, scorer, scratch),
+            None => Vec::new(),
+        })
+    }
+
+    /// Fills `scratch.candidates` with the documents matching `query`.
+    ///
+    /// `minimum` overrides the configured overlap ratio. Returns the number
+    /// of query trigrams, `None` if the query cannot match anything, or why
+    /// it cannot be run.
+    pub(crate) fn collect_candidates(
+        &self,
+        query: &str,
+        minimum: Option<&MinimumShouldMatch>,
+        scratch: &mut SearchScratch,
+    ) -> Result<Option<usize>, SearchError> {
+        scratch.candidates.clear();
+
+        if self.is_empty() {
+            return Err(SearchError::IndexEmpty);
         }
 
-        if query.len() > MAX_QUERY_LENGTH {
-            return Vec::new();
+        let max_length = self.config.max_query_length;
+        if query.len() > max_length {
+            return Err(SearchError::QueryTooLong {
+                length: query.len(),
+                max_length,
+            });
         }
 
         // Use reusable buffer to avoid allocation per search
-        self.query_buf.clear();
-        self.normalizer.normalize_into(query, &mut self.query_buf);
-        let query_bytes = self.query_buf.as_bytes();
+        scratch.query_buf.clear();
+        self.query_normalizer(query)
+            .normalize_into(query, &mut scratch.query_buf);
+        Self::apply_tokenizer(
+            self.tokenizer,
+            &mut scratch.query_buf,
+            &mut scratch.token_buf,
+        );
+        let candidates = &mut scratch.candidates;
+        let query_bytes = scratch.query_buf.as_bytes();
 
         if query_bytes.len() < 3 {
-            return Vec::new();
+            return Err(SearchError::QueryTooShort {
+                length: query_bytes.len(),
+                min_length: 3,
+            });
         }
 
-        let max_trigrams = (query_bytes.len() - 2).min(MAX_QUERY_TRIGRAMS);
+        // Distinct query trigrams, kept for uncommitted search
+        let query_trigram_values = self.select_query_terms(&scratch.query_buf);
         let mut query_trigrams: SmallVec<[QueryTrigram; MAX_QUERY_TRIGRAMS]> =
-            SmallVec::with_capacity(max_trigrams);
-        // Store trigram values alongside for uncommitted search
-        let mut query_trigram_values: SmallVec<[(Trigram, u8); MAX_QUERY_TRIGRAMS]> =
-            SmallVec::with_capacity(max_trigrams);
-
-        for i in 0..max_trigrams {
-            let trigram =
-                Trigram::from_bytes(query_bytes[i], query_bytes[i + 1], query_bytes[i + 2]);
-            let bonus = if i < 3 { PREFIX_BONUS } else { 1 };
-            if let Some(idx) = self.find_block(trigram) {
+            SmallVec::with_capacity(query_trigram_values.len());
+        for term in &query_trigram_values {
+            if let Some(idx) = term.block {
                 let b = &self.blocks[idx];
                 query_trigrams.push(QueryTrigram {
                     offset: b.offset,
                     len: b.len,
-                    bonus,
+                    bonus: term.bonus,
+                    occurrences: term.occurrences,
                 });
             }
-            // Always track for uncommitted search
-            query_trigram_values.push((trigram, bonus));
         }
 
         // Check if we have any trigrams to search (committed or uncommitted)
         let has_committed = !query_trigrams.is_empty();
-        let has_uncommitted = !self.temp_trigrams.is_empty();
+        let has_uncommitted = !self.searchable_delta().is_empty() || !self.committing.is_empty();
 
         if !has_committed && !has_uncommitted {
-            return Vec::new();
+            return Ok(None);
         }
 
-        // Calculate required_end based on total trigrams
-        let total_trigrams = query_trigram_values.len();
-        let required_end = ((total_trigrams as f32 * self.config.min_overlap_ratio)
-            .ceil()
-            .max(1.0) as usize)
-            .min(total_trigrams);
+        // Calculate required_end based on total trigrams, repeats included
+        let total_trigrams = query_trigram_values
+            .iter()
+            .map(|term| term.occurrences as usize)
+            .sum();
+        let required_end = match minimum {
+            Some(minimum) => minimum.required(total_trigrams),
+            None => ((total_trigrams as f32 * self.config.min_overlap_ratio)
+                .ceil()
+                .max(1.0) as usize)
+                .min(total_trigrams),
+        };
 
         // If only uncommitted data, build candidates from uncommitted only
         if !has_committed {
-            let uncommitted = self.scan_uncommitted_trigrams(&query_trigram_values, required_end);
-            self.candidates.clear();
-            self.candidates.reserve(uncommitted.len());
-            for (doc_id, matches) in uncommitted {
-                self.candidates.push(Candidate { doc_id, matches });
-            }
-
-            // Score and return results
-            self.results.clear();
-            self.results.reserve(self.candidates.len().min(limit));
-            for candidate in &self.candidates {
-                let score = self.compute_score_fast(
-                    candidate.doc_id,
-                    candidate.matches as usize,
-                    total_trigrams,
-                );
-                self.results
-                    .push(SearchResult::new(candidate.doc_id, score));
-            }
-
-            if self.results.len() > limit {
-                self.results.select_nth_unstable_by(limit, |a, b| {
-                    b.score
-                        .partial_cmp(&a.score)
-                        .unwrap_or(core::cmp::Ordering::Equal)
-                });
-                self.results.truncate(limit);
-            }
-            self.results.sort_unstable_by(|a, b| {
-                b.score
-                    .partial_cmp(&a.score)
-                    .unwrap_or(core::cmp::Ordering::Equal)
-            });
-
-            return std::mem::take(&mut self.results).into_vec();
+            let uncommitted = self.scan_uncommitted_trigrams(&query_trigram_values);
+            Self::merge_uncommitted_into_candidates(candidates, uncommitted, required_end);
+            return Ok(Some(total_trigrams));
         }
 
         query_trigrams.sort_unstable_by_key(|qt| qt.len);
 
-        if query_trigrams[0].len as usize > MAX_SEED_POSTING_LIST {
-            return Vec::new();
-        }
-
-        // total_trigrams and required_end already calculated above
-
-        self.candidates.clear();
         let qt0 = query_trigrams[0];
-
-        if qt0.len > MAX_CANDIDATES {
-            return Vec::new();
+        let max = MAX_SEED_POSTING_LIST.min(MAX_CANDIDATES);
+        if qt0.len as usize > max {
+            return Err(SearchError::TooManyCandidates {
+                candidates: qt0.len as usize,
+                max,
+            });
         }
 
         let seed = &self.postings[qt0.offset as usize..(qt0.offset + qt0.len) as usize];
-        self.candidates.reserve(qt0.len as usize);
-        self.candidates.extend(seed.iter().map(|&doc_id| Candidate {
+        candidates.reserve(qt0.len as usize);
+        candidates.extend(seed.iter().map(|&doc_id| Candidate {
             doc_id,
-            matches: qt0.bonus as u16,
+            matches: qt0.bonus,
         }));
 
-        for i in 1..required_end {
+        // The rarest lists covering `required_end` query trigrams are
+        // required; query trigrams missing from the posting lists (only
+        // present in the delta) cannot be required of committed documents
+        let mut hard_end = 0;
+        let mut covered = 0;
+        while hard_end < query_trigrams.len() && covered < required_end {
+            covered += query_trigrams[hard_end].occurrences as usize;
+            hard_end += 1;
+        }
+
+        for i in 1..hard_end {
             let qt = query_trigrams[i];
             let postings = &self.postings[qt.offset as usize..(qt.offset + qt.len) as usize];
-            Self::hard_intersect(&mut self.candidates, postings, qt.bonus);
+            Self::hard_intersect(candidates, postings, qt.bonus);
 
-            if self.candidates.is_empty() {
-                return Vec::new();
+            if candidates.is_empty() {
+                if !has_uncommitted {
+                    return Ok(None);
+                }
+                // Nothing left for the longer lists to add to
+                break;
             }
         }
 
-        for i in required_end..query_trigrams.len() {
+        for i in hard_end..query_trigrams.len() {
+            if candidates.is_empty() {
+                break;
+            }
             let qt = query_trigrams[i];
             let postings = &self.postings[qt.offset as usize..(qt.offset + qt.len) as usize];
-            Self::soft_merge(&mut self.candidates, postings, qt.bonus);
+            Self::soft_merge(candidates, postings, qt.bonus);
         }
 
         // Merge in uncommitted trigrams (lazy rebuild optimization)
         // This is O(threshold) which is bounded and small
-        if !self.temp_trigrams.is_empty() {
-            let uncommitted = self.scan_uncommitted_trigrams(&query_trigram_values, required_end);
-            self.merge_uncommitted_into_candidates(uncommitted, required_end);
+        if has_uncommitted {
+            let uncommitted = self.scan_uncommitted_trigrams(&query_trigram_values);
+            Self::merge_uncommitted_into_candidates(candidates, uncommitted, required_end);
         }
 
-        self.results.clear();
-        self.results.reserve(self.candidates.len().min(limit));
-        for candidate in &self.candidates {
-            let score = self.compute_score_fast(
+        Ok(Some(total_trigrams))
+    }
+
+    /// Scores `scratch.candidates` and returns the top `limit` results.
+    fn rank_candidates<F>(
+        &self,
+        total_trigrams: usize,
+        request: &SearchRequest<'_>,
+        scorer: &F,
+        scratch: &mut SearchScratch,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        let limit = request.limit;
+        let results = &mut scratch.results;
+        results.clear();
+        results.reserve(scratch.candidates.len().min(limit));
+        let terms = self.proximity_terms(&scratch.query_buf);
+        let weight = self.scoring.proximity_weight;
+        let (term_weights, avg_len) = self.term_weights(&scratch.query_buf);
+        let avg_doc_len = self.average_doc_len();
+        let field_weights = &request.field_weights;
+        let min_score = request.min_score.or(self.config.min_score);
+        let hybrid = request
+            .hybrid
+            .filter(|(_, vector)| self.vectors.accepts_query(vector))
+            .map(|(alpha, vector)| {
+                let ceiling = self.score_ceiling(&scratch.query_buf, field_weights);
+                (alpha, vector, vectors::norm(vector), ceiling)
+            });
+        let range = request
+            .range
+            .as_ref()
+            .map(|(key, min, max)| (self.sort_values.column(key).unwrap_or(&[]), *min, *max));
+        let mut query_trigrams = Vec::new();
+        if !field_weights.is_empty() {
+            extract_terms(&scratch.query_buf, self.tokenizer.cjk_bigrams, |t| {
+                query_trigrams.push(t)
+            });
+            query_trigrams.sort_unstable_by_key(|t| t.0);
+            query_trigrams.dedup();
+        }
+        for candidate in &scratch.candidates {
+            if request
+                .namespace
+                .is_some_and(|ns| self.namespaces.of(candidate.doc_id as usize) != ns)
+            {
+                continue;
+            }
+            if let Some((column, min, max)) = range {
+                let value = SortValues::value(column, candidate.doc_id as usize);
+                if !value.is_some_and(|v| v >= min && v <= max) {
+                    continue;
+                }
+            }
+            let mut ctx = self.score_context(
                 candidate.doc_id,
                 candidate.matches as usize,
                 total_trigrams,
+                avg_doc_len,
             );
-            self.results
-                .push(SearchResult::new(candidate.doc_id, score));
-        }
-
-        if self.results.len() > limit {
-            self.results.select_nth_unstable_by(limit, |a, b| {
-                b.score
-                    .partial_cmp(&a.score)
-                    .unwrap_or(core::cmp::Ordering::Equal)
-            });
-            self.results.truncate(limit);
+            if !term_weights.is_empty() {
+                ctx.term_score = Some(self.term_score(candidate.doc_id, &term_weights, avg_len));
+            }
+            let mut score = scorer(ctx);
+            if !terms.is_empty() {
+                score *= 1.0 + weight * self.proximity(candidate.doc_id, &terms);
+            }
+            if !field_weights.is_empty() {
+                let factor = self.field_factor(candidate.doc_id, &query_trigrams, field_weights);
+                if factor == 0.0 {
+                    continue;
+                }
+                score *= factor;
+            }
+            if min_score.is_some_and(|min| score < min) {
+                continue;
+            }
+            if let Some((alpha, vector, norm, ceiling)) = hybrid {
+                let text = if ceiling > 0.0 {
+                    (score / ceiling).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                };
+                let cosine = self.vectors.cosine(candidate.doc_id as usize, vector, norm);
+                score = (1.0 - alpha) * text + alpha * cosine;
+            }
+            results.push(SearchResult::new(candidate.doc_id, score));
         }
-        self.results.sort_unstable_by(|a, b| {
-            b.score
-                .partial_cmp(&a.score)
-                .unwrap_or(core::cmp::Ordering::Equal)
-        });
 
-        std::mem::take(&mut self.results).into_vec()
+        let mut results = std::mem::take(results).into_vec();
+        self.sort_results(
+            &mut results,
+            limit,
+            &request.sort,
+            request.collapse.as_deref(),
+        );
+        results
     }
 
+    /// Keeps the candidates found in `postings`, adding `bonus` to their
+    /// matches.
+    ///
+    /// Walks `postings` linearly while the candidates are dense in it, and
+    /// gallops to each candidate once they are much sparser (a rare seed
+    /// against a common trigram), so a short candidate list never pays for
+    /// scanning a long posting list end to end.
     #[inline(always)]
-    fn hard_intersect(candidates: &mut SmallVec<[Candidate; 256]>, postings: &[DocId], bonus: u8) {
-        let bonus_u16 = bonus as u16;
+    fn hard_intersect(candidates: &mut SmallVec<[Candidate; 256]>, postings: &[DocId], bonus: u16) {
+        let gallop = Self::should_gallop(candidates.len(), postings.len());
         let mut write_idx = 0usize;
         let mut posting_idx = 0usize;
 
         for read_idx in 0..candidates.len() {
             let candidate = candidates[read_idx];
-
-            while posting_idx < postings.len() && postings[posting_idx] < candidate.doc_id {
-                posting_idx += 1;
-            }
+            posting_idx = Self::seek(postings, posting_idx, candidate.doc_id, gallop);
 
             if posting_idx < postings.len() && postings[posting_idx] == candidate.doc_id {
                 candidates[write_idx] = Candidate {
                     doc_id: candidate.doc_id,
-                    matches: candidate.matches + bonus_u16,
+                    matches: candidate.matches.saturating_add(bonus),
                 };
                 write_idx += 1;
                 posting_idx += 1;
@@ -214,79 +517,214 @@ impl Lattice {
         candidates.truncate(write_idx);
     }
 
+    /// Adds `bonus` to the matches of candidates found in `postings`,
+    /// keeping the rest. Gallops like [`hard_intersect`](Self::hard_intersect).
     #[inline(always)]
-    fn soft_merge(candidates: &mut SmallVec<[Candidate; 256]>, postings: &[DocId], bonus: u8) {
-        let bonus_u16 = bonus as u16;
+    fn soft_merge(candidates: &mut SmallVec<[Candidate; 256]>, postings: &[DocId], bonus: u16) {
+        let gallop = Self::should_gallop(candidates.len(), postings.len());
         let mut posting_idx = 0usize;
 
         for candidate in candidates.iter_mut() {
-            while posting_idx < postings.len() && postings[posting_idx] < candidate.doc_id {
-                posting_idx += 1;
-            }
+            posting_idx = Self::seek(postings, posting_idx, candidate.doc_id, gallop);
             if posting_idx < postings.len() && postings[posting_idx] == candidate.doc_id {
-                candidate.matches += bonus_u16;
+                candidate.matches = candidate.matches.saturating_add(bonus);
                 posting_idx += 1;
             }
         }
     }
 
+    /// Whether `candidates` lookups into a list of `postings` IDs are
+    /// cheaper by galloping (about `candidates · log(postings /
+    /// candidates)` comparisons) than by a linear walk (`postings`).
+    #[inline(always)]
+    fn should_gallop(candidates: usize, postings: usize) -> bool {
+        candidates.saturating_mul(GALLOP_RATIO) < postings
+    }
+
+    /// Returns the first index at or after `from` whose ID is at least
+    /// `target`.
+    #[inline(always)]
+    fn seek(postings: &[DocId], from: usize, target: DocId, gallop: bool) -> usize {
+        if !gallop {
+            let mut i = from;
+            while i < postings.len() && postings[i] < target {
+                i += 1;
+            }
+            return i;
+        }
+
+        // Double the step until it passes the target, then binary-search
+        // the last step
+        let rest = &postings[from..];
+        let mut bound = 1;
+        while bound < rest.len() && rest[bound - 1] < target {
+            bound *= 2;
+        }
+        let lo = bound / 2;
+        let hi = bound.min(rest.len());
+        from + lo + rest[lo..hi].partition_point(|&doc| doc < target)
+    }
+
+    /// Returns the normalizer for `query`: the one for its detected language
+    /// when language detection is enabled, the engine's otherwise.
+    #[cfg_attr(not(feature = "language-detection"), allow(unused_variables))]
+    pub(crate) fn query_normalizer(&self, query: &str) -> TextNormalizer {
+        #[cfg(feature = "language-detection")]
+        if let Some(normalizers) = &self.languages {
+            let language = crate::analyzer::language::Language::detect(query);
+            return TextNormalizer::with_config(normalizers.config_for(language));
+        }
+        self.normalizer
+    }
+
+    /// Normalizes and tokenizes `query` the way documents are.
+    pub(crate) fn analyze_query(&self, query: &str) -> String {
+        let mut normalized = self.query_normalizer(query).normalize(query);
+        Self::apply_tokenizer(self.tokenizer, &mut normalized, &mut String::new());
+        normalized
+    }
+
     #[inline(always)]
     pub(crate) fn find_block(&self, trigram: Trigram) -> Option<usize> {
-        self.blocks
-            .binary_search_by_key(&trigram.0, |b| b.trigram.0)
-            .ok()
+        self.blocks.find(trigram)
+    }
+
+    /// Visits every document containing `trigram`, in both the committed
+    /// index and the (sorted) uncommitted delta.
+    pub(crate) fn for_each_posting(&self, trigram: Trigram, mut f: impl FnMut(DocId)) {
+        if let Some(idx) = self.find_block(trigram) {
+            for &doc_id in Self::block_postings(&self.blocks[idx], &self.postings) {
+                f(doc_id);
+            }
+        }
+
+        let delta = self.searchable_delta();
+        let start = delta.partition_point(|e| e.trigram.0 < trigram.0);
+        for entry in &delta[start..] {
+            if entry.trigram.0 != trigram.0 {
+                break;
+            }
+            f(entry.doc_id);
+        }
+
+        for entry in self.committing.iter().filter(|e| e.trigram == trigram) {
+            f(entry.doc_id);
+        }
+    }
+
+    /// Returns the documents containing `trigram`, sorted and deduplicated.
+    pub(crate) fn posting_docs(&self, trigram: Trigram) -> Vec<DocId> {
+        let mut docs = Vec::with_capacity(self.doc_frequency(trigram));
+        self.for_each_posting(trigram, |doc_id| docs.push(doc_id));
+        docs.sort_unstable();
+        docs.dedup();
+        docs
+    }
+
+    /// Returns the number of documents containing `trigram`.
+    pub(crate) fn doc_frequency(&self, trigram: Trigram) -> usize {
+        let committed = self
+            .find_block(trigram)
+            .map_or(0, |idx| self.blocks[idx].len as usize);
+
+        let delta = self.searchable_delta();
+        let start = delta.partition_point(|e| e.trigram.0 < trigram.0);
+        let end = delta[start..].partition_point(|e| e.trigram.0 == trigram.0) + start;
+        let committing = self
+            .committing
+            .iter()
+            .filter(|e| e.trigram == trigram)
+            .count();
+
+        committed + (end - start) + committing
+    }
+
+    /// Returns the trigrams a normalized query is matched by: each distinct
+    /// trigram with its summed position bonus, its number of occurrences
+    /// and its posting block.
+    ///
+    /// A trigram repeated in the query ("aaaa") is looked up and
+    /// intersected once. When the query has more distinct trigrams than
+    /// [`SearchConfig::max_query_trigrams`](lattice_types::SearchConfig::max_query_trigrams),
+    /// the rarest are kept, wherever they occur, so a long query is not
+    /// judged on its opening words alone; trigrams absent from the index
+    /// select nothing and go last.
+    pub(crate) fn select_query_terms(
+        &self,
+        query: &str,
+    ) -> SmallVec<[QueryTerm; MAX_QUERY_TRIGRAMS]> {
+        let mut terms: SmallVec<[QueryTerm; MAX_QUERY_TRIGRAMS]> = SmallVec::new();
+        // Index of each trigram in `terms`
+        let mut seen: FxHashMap<Trigram, usize> = FxHashMap::default();
+        let mut i = 0;
+        extract_terms(query, self.tokenizer.cjk_bigrams, |trigram| {
+            let bonus = if i < 3 { PREFIX_BONUS as u16 } else { 1 };
+            i += 1;
+            match seen.get(&trigram) {
+                Some(&idx) => {
+                    let term = &mut terms[idx];
+                    term.bonus = term.bonus.saturating_add(bonus);
+                    term.occurrences = term.occurrences.saturating_add(1);
+                }
+                None => {
+                    seen.insert(trigram, terms.len());
+                    terms.push(QueryTerm {
+                        trigram,
+                        bonus,
+                        occurrences: 1,
+                        block: self.find_block(trigram),
+                    });
+                }
+            }
+        });
+
+        let limit = self.config.max_query_trigrams;
+        if terms.len() > limit {
+            // Stable, so equally rare trigrams keep their query order
+            terms.sort_by_key(|term| match term.block {
+                Some(idx) => (false, self.blocks[idx].len),
+                None => (true, 0),
+            });
+            terms.truncate(limit);
+        }
+        terms
     }
 
     /// Scans uncommitted trigrams and builds posting lists for query trigrams.
     ///
     /// This is called when we have uncommitted trigrams (lazy rebuild mode).
-    /// Returns a map of doc_id -> match count for uncommitted data.
-    /// Time complexity: O(num_query_trigrams * log(temp_trigrams)) due to binary search.
+    /// Returns a map of doc_id -> (weighted match count, matched query
+    /// trigrams) for uncommitted data.
+    /// Time complexity: O(num_query_trigrams * log(temp_trigrams)) due to binary search,
+    /// plus a linear pass over the delta of a running background commit.
     fn scan_uncommitted_trigrams(
         &self,
-        query_trigrams: &[(Trigram, u8)],
-        required_end: usize,
-    ) -> FxHashMap<DocId, u16> {
-        let mut uncommitted_matches: FxHashMap<DocId, u16> =
+        query_trigrams: &[QueryTerm],
+    ) -> FxHashMap<DocId, (u16, u16)> {
+        let mut uncommitted_matches: FxHashMap<DocId, (u16, u16)> =
             FxHashMap::with_capacity_and_hasher(64, Default::default());
+        let delta = self.searchable_delta();
 
-        for (i, (trigram, bonus)) in query_trigrams.iter().enumerate() {
-            let is_required = i < required_end;
-            let bonus_u16 = *bonus as u16;
-
-            // Binary search for the trigram in temp_trigrams
+        for term in query_trigrams {
             // temp_trigrams is sorted by trigram, then doc_id
-            let pos = self
-                .temp_trigrams
-                .binary_search_by_key(&trigram.0, |e| e.trigram.0);
-
-            if let Ok(mut idx) = pos {
-                // Scan backward to find the first occurrence
-                while idx > 0 && self.temp_trigrams[idx - 1].trigram.0 == trigram.0 {
-                    idx -= 1;
-                }
+            let start = delta.partition_point(|e| e.trigram.0 < term.trigram.0);
+            for entry in delta[start..]
+                .iter()
+                .take_while(|e| e.trigram.0 == term.trigram.0)
+            {
+                let counts = uncommitted_matches.entry(entry.doc_id).or_insert((0, 0));
+                counts.0 = counts.0.saturating_add(term.bonus);
+                counts.1 = counts.1.saturating_add(term.occurrences);
+            }
+        }
 
-                // Scan forward through all matches
-                while idx < self.temp_trigrams.len()
-                    && self.temp_trigrams[idx].trigram.0 == trigram.0
-                {
-                    let doc_id = self.temp_trigrams[idx].doc_id;
-
-                    if is_required {
-                        // For required trigrams, mark them but we'll filter later
-                        uncommitted_matches
-                            .entry(doc_id)
-                            .and_modify(|c| *c += bonus_u16)
-                            .or_insert(bonus_u16);
-                    } else {
-                        // For soft merge, just add the bonus
-                        uncommitted_matches
-                            .entry(doc_id)
-                            .and_modify(|c| *c += bonus_u16)
-                            .or_insert(bonus_u16);
-                    }
-
-                    idx += 1;
+        // The frozen delta of a background commit is unsorted
+        for entry in self.committing.iter() {
+            for term in query_trigrams {
+                if term.trigram == entry.trigram {
+                    let counts = uncommitted_matches.entry(entry.doc_id).or_insert((0, 0));
+                    counts.0 = counts.0.saturating_add(term.bonus);
+                    counts.1 = counts.1.saturating_add(term.occurrences);
                 }
             }
         }
@@ -295,34 +733,27 @@ impl Lattice {
     }
 
     /// Merges uncommitted matches into the candidate list.
+    ///
+    /// A document lives either in the posting lists or in the delta, so
+    /// documents not already among the candidates are fully described by
+    /// `uncommitted`; they are kept if they match at least
+    /// `required_query_trigrams` query trigrams.
     fn merge_uncommitted_into_candidates(
-        &mut self,
-        uncommitted: FxHashMap<DocId, u16>,
+        candidates: &mut SmallVec<[Candidate; 256]>,
+        uncommitted: FxHashMap<DocId, (u16, u16)>,
         required_query_trigrams: usize,
     ) {
-        if required_query_trigrams == 0 {
-            // No required trigrams - just add all uncommitted matches
-            for (doc_id, matches) in uncommitted {
-                self.candidates.push(Candidate { doc_id, matches });
-            }
-            return;
-        }
-
-        // We need to handle the case where some docs in uncommitted have required trigrams
-        // but weren't in the committed candidates (because they didn't have the seed trigram)
-        // For simplicity, we add all uncommitted matches and rely on the fact that
-        // the scoring will be lower for docs with fewer matches
         let mut existing: FxHashMap<DocId, usize> =
-            FxHashMap::with_capacity_and_hasher(self.candidates.len(), Default::default());
-        for (idx, c) in self.candidates.iter().enumerate() {
+            FxHashMap::with_capacity_and_hasher(candidates.len(), Default::default());
+        for (idx, c) in candidates.iter().enumerate() {
             existing.insert(c.doc_id, idx);
         }
 
-        for (doc_id, matches) in uncommitted {
+        for (doc_id, (matches, hits)) in uncommitted {
             if let Some(&idx) = existing.get(&doc_id) {
-                self.candidates[idx].matches += matches;
-            } else {
-                self.candidates.push(Candidate { doc_id, matches });
+                candidates[idx].matches = candidates[idx].matches.saturating_add(matches);
+            } else if hits as usize >= required_query_trigrams {
+                candidates.push(Candidate { doc_id, matches });
             }
         }
     }
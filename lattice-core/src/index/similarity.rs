@@ -0,0 +1,148 @@
+//! Document-to-document similarity (near-duplicate detection).
+//!
+//! Similarity is the Jaccard coefficient over the sets of distinct trigrams
+//! of two documents: `|A ∩ B| / |A ∪ B|`. Candidate documents are gathered
+//! from the posting lists of the source document's trigrams, so only
+//! documents sharing at least one trigram are ever scored.
+//!
+//! More-like-this uses the source document's rarest trigrams as a
+//! pseudo-query, weighting each by inverse document frequency.
+
+use crate::analyzer::trigram::extract_terms;
+use crate::index::types::Lattice;
+use lattice_types::{DocId, SearchResult, Trigram};
+use rustc_hash::FxHashMap;
+
+impl Lattice {
+    /// Returns the Jaccard similarity (0.0-1.0) of two stored documents.
+    ///
+    /// Returns `None` if either document does not exist. Two documents with
+    /// no trigrams (shorter than 3 bytes) have a similarity of 0.0.
+    pub fn similarity(&self, a: DocId, b: DocId) -> Option<f32> {
+        let ta = self.distinct_doc_trigrams(a)?;
+        let tb = self.distinct_doc_trigrams(b)?;
+
+        let mut shared = 0usize;
+        let (mut i, mut j) = (0usize, 0usize);
+        while i < ta.len() && j < tb.len() {
+            match ta[i].0.cmp(&tb[j].0) {
+                core::cmp::Ordering::Less => i += 1,
+                core::cmp::Ordering::Greater => j += 1,
+                core::cmp::Ordering::Equal => {
+                    shared += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+
+        Some(Self::jaccard(shared, ta.len(), tb.len()))
+    }
+
+    /// Finds the documents most similar to `doc_id`, excluding the document itself.
+    ///
+    /// Scores are Jaccard similarities in `0.0..=1.0`, sorted descending with
+    /// ties broken by ascending doc ID. Returns an empty list for unknown IDs.
+    pub fn find_similar(&mut self, doc_id: DocId, limit: usize) -> Vec<SearchResult> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let Some(source) = self.distinct_doc_trigrams(doc_id) else {
+            return Vec::new();
+        };
+
+        self.prepare_for_query();
+
+        let mut shared: FxHashMap<DocId, u32> =
+            FxHashMap::with_capacity_and_hasher(64, Default::default());
+        for &trigram in &source {
+            self.for_each_posting(trigram, |other| {
+                if other != doc_id {
+                    *shared.entry(other).or_insert(0) += 1;
+                }
+            });
+        }
+
+        let mut results: Vec<SearchResult> = shared
+            .into_iter()
+            .map(|(other, count)| {
+                let other_len = self.doc_trigram_counts[other as usize] as usize;
+                let score = Self::jaccard(count as usize, source.len(), other_len);
+                SearchResult::new(other, score)
+            })
+            .collect();
+
+        self.select_top_k(&mut results, limit);
+        results
+    }
+
+    /// Returns documents related to `doc_id`, excluding the document itself.
+    ///
+    /// The document's rarest trigrams (up to
+    /// [`SearchConfig::max_query_trigrams`](lattice_types::SearchConfig::max_query_trigrams), ignoring
+    /// trigrams no other document contains) form a pseudo-query. Each
+    /// candidate scores the IDF-weighted fraction of that query it contains.
+    pub fn more_like_this(&mut self, doc_id: DocId, limit: usize) -> Vec<SearchResult> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let Some(source) = self.distinct_doc_trigrams(doc_id) else {
+            return Vec::new();
+        };
+
+        self.prepare_for_query();
+
+        let mut terms: Vec<(Trigram, usize)> = source
+            .iter()
+            .map(|&t| (t, self.doc_frequency(t)))
+            .filter(|&(_, df)| df > 1)
+            .collect();
+        terms.sort_unstable_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0 .0.cmp(&b.0 .0)));
+        terms.truncate(self.config.max_query_trigrams);
+
+        let num_docs = self.len() as f32;
+        let mut total_weight = 0.0f32;
+        let mut scores: FxHashMap<DocId, f32> =
+            FxHashMap::with_capacity_and_hasher(64, Default::default());
+
+        for &(trigram, df) in &terms {
+            let idf = (1.0 + num_docs / df as f32).ln();
+            total_weight += idf;
+            self.for_each_posting(trigram, |other| {
+                if other != doc_id {
+                    *scores.entry(other).or_insert(0.0) += idf;
+                }
+            });
+        }
+
+        let mut results: Vec<SearchResult> = scores
+            .into_iter()
+            .map(|(other, weight)| SearchResult::new(other, weight / total_weight))
+            .collect();
+
+        self.select_top_k(&mut results, limit);
+        results
+    }
+
+    /// Returns the sorted, deduplicated trigrams of a stored document.
+    pub(crate) fn distinct_doc_trigrams(&self, doc_id: DocId) -> Option<Vec<Trigram>> {
+        let text = self.documents.get(doc_id)?;
+        let mut trigrams = Vec::with_capacity(text.len().saturating_sub(2));
+        extract_terms(text, self.tokenizer.cjk_bigrams, |t| trigrams.push(t));
+        trigrams.sort_unstable_by_key(|t| t.0);
+        trigrams.dedup();
+        Some(trigrams)
+    }
+
+    #[inline(always)]
+    fn jaccard(shared: usize, a_len: usize, b_len: usize) -> f32 {
+        let union = a_len + b_len - shared;
+        if union == 0 {
+            0.0
+        } else {
+            shared as f32 / union as f32
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Point-in-time read-only views.
+//!
+//! A [`Snapshot`] shares the engine's posting lists, document pages and
+//! dictionary instead of copying them. The engine keeps indexing as usual:
+//! rebuilds produce fresh posting lists, and the first write to a shared
+//! document page or the dictionary copies just that component. The
+//! snapshot therefore keeps returning results for exactly the documents it
+//! was taken with.
+//!
+//! ```text
+//!  engine ──┐                      engine ──▶ postings v2
+//!           ├──▶ postings v1   ⇒
+//! snapshot ─┘                    snapshot ──▶ postings v1
+//! ```
+
+use crate::index::request::SearchRequest;
+use crate::index::scoring::ScoreContext;
+use crate::index::types::Lattice;
+use core::ops::Deref;
+use lattice_types::SearchResult;
+
+/// A cheap, immutable copy of a [`Lattice`].
+///
+/// Dereferences to the underlying engine for read-only methods such as
+/// [`get`](Lattice::get), [`len`](Lattice::len) and
+/// [`stats`](Lattice::stats). [`search_ref`](Lattice::search_ref) and
+/// [`search_with_scratch`](Lattice::search_with_scratch) work through a
+/// shared reference, so one snapshot can serve several threads.
+#[derive(Clone)]
+pub struct Snapshot {
+    engine: Lattice,
+}
+
+impl Lattice {
+    /// Takes a snapshot of the current index contents.
+    ///
+    /// Costs O(documents) for the small per-document arrays plus a copy of
+    /// the uncommitted delta; postings, document text and the dictionary
+    /// are shared.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    ///
+    /// let mut snapshot = engine.snapshot();
+    /// engine.add("hello again").unwrap();
+    ///
+    /// assert_eq!(snapshot.search("hello", 10).len(), 1);
+    /// assert_eq!(engine.search("hello", 10).len(), 2);
+    /// ```
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            engine: self.clone(),
+        }
+    }
+}
+
+impl Snapshot {
+    /// Searches the snapshot. See [`Lattice::search`].
+    pub fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+        self.engine.search(query, limit)
+    }
+
+    /// Searches with a custom scorer. See [`Lattice::search_with_scorer`].
+    pub fn search_with_scorer<F>(
+        &mut self,
+        query: &str,
+        limit: usize,
+        scorer: F,
+    ) -> Vec<SearchResult>
+    where
+        F: Fn(ScoreContext) -> f32,
+    {
+        self.engine.search_with_scorer(query, limit, scorer)
+    }
+
+    /// Searches with per-query options. See [`Lattice::search_request`].
+    pub fn search_request(&mut self, request: &SearchRequest<'_>) -> Vec<SearchResult> {
+        self.engine.search_request(request)
+    }
+
+    /// Turns the snapshot into a writable engine (a fork).
+    ///
+    /// Data stays shared with the original until either side modifies it.
+    pub fn into_lattice(self) -> Lattice {
+        self.engine
+    }
+}
+
+impl Deref for Snapshot {
+    type Target = Lattice;
+
+    fn deref(&self) -> &Lattice {
+        &self.engine
+    }
+}
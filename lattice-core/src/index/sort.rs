@@ -0,0 +1,279 @@
+//! Sorting results by document attributes.
+//!
+//! [`Lattice::set_sort_value`] attaches a named number to a document, such
+//! as a timestamp or a price. A [`SearchRequest`](crate::SearchRequest)
+//! with [`sort`](crate::SearchRequest::sort) set still finds and filters
+//! matches by relevance, but returns the top `limit` of them in the
+//! requested order instead of by score:
+//!
+//! ```text
+//! candidates ──score, min_score──▶ matches ──SortBy──▶ top `limit`
+//! ```
+//!
+//! Each key is a column with one value per document, created by the first
+//! value set under the key. Documents without a value sort after those
+//! with one, in either direction.
+//!
+//! The same values filter results:
+//! [`range`](crate::SearchRequest::range) keeps only matches whose value
+//! lies in an interval, such as log entries from the last hour.
+//!
+//! And they group results:
+//! [`collapse_by`](crate::SearchRequest::collapse_by) keeps only the first
+//! match, in the request's order, of each value of a key, so many versions
+//! of one page cannot crowd out everything else. Values are numbers; a
+//! string attribute is grouped on by storing a number derived from it.
+
+use crate::index::types::Lattice;
+use core::cmp::Ordering;
+use core::mem::size_of;
+use lattice_types::{DocId, DocumentError, SearchResult};
+use rustc_hash::FxHashMap;
+use std::collections::hash_map::Entry;
+
+/// Order of the results of a [`SearchRequest`](crate::SearchRequest).
+///
+/// Ties are broken by score, then by the configured
+/// [`TieBreak`](crate::TieBreak).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum SortBy {
+    /// Descending score.
+    #[default]
+    Score,
+    /// Ascending document ID, which is insertion order.
+    DocId,
+    /// Ascending value of the sort key (see [`Lattice::set_sort_value`]).
+    Field(String),
+    /// Descending value of the sort key, such as newest first for a
+    /// timestamp.
+    FieldDesc(String),
+}
+
+/// Sort values of every document, one column per key.
+#[derive(Clone, Default)]
+pub(crate) struct SortValues {
+    /// Columns sorted by key; NaN marks a document without a value, and
+    /// documents past the end of a column have none
+    columns: Vec<(String, Vec<f64>)>,
+}
+
+impl SortValues {
+    pub(crate) fn column(&self, key: &str) -> Option<&[f64]> {
+        self.columns
+            .binary_search_by(|(k, _)| k.as_str().cmp(key))
+            .ok()
+            .map(|i| self.columns[i].1.as_slice())
+    }
+
+    /// Returns the column for `key`, creating it if new.
+    fn column_mut(&mut self, key: &str) -> &mut Vec<f64> {
+        let i = match self.columns.binary_search_by(|(k, _)| k.as_str().cmp(key)) {
+            Ok(i) => i,
+            Err(i) => {
+                self.columns.insert(i, (key.to_owned(), Vec::new()));
+                i
+            }
+        };
+        &mut self.columns[i].1
+    }
+
+    /// Returns document `doc`'s value in `column`.
+    #[inline]
+    pub(crate) fn value(column: &[f64], doc: usize) -> Option<f64> {
+        column.get(doc).copied().filter(|v| !v.is_nan())
+    }
+
+    fn set(&mut self, key: &str, doc: usize, value: f64) {
+        let column = self.column_mut(key);
+        if column.len() <= doc {
+            column.resize(doc + 1, f64::NAN);
+        }
+        column[doc] = value;
+    }
+
+    pub(crate) fn columns(&self) -> &[(String, Vec<f64>)] {
+        &self.columns
+    }
+
+    /// Restores a saved index's sort values; `columns` must be sorted by
+    /// key.
+    pub(crate) fn restore(&mut self, columns: Vec<(String, Vec<f64>)>) {
+        self.columns = columns;
+    }
+
+    /// Appends the values of `other`'s `count` documents after this
+    /// index's first `first`, matching columns by key.
+    pub(crate) fn append(&mut self, other: &SortValues, first: usize, count: usize) {
+        for (key, values) in &other.columns {
+            let column = self.column_mut(key);
+            column.resize(first, f64::NAN);
+            column.extend_from_slice(&values[..values.len().min(count)]);
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.columns.clear();
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        for (_, column) in &mut self.columns {
+            column.shrink_to_fit();
+        }
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.columns
+            .iter()
+            .map(|(key, column)| key.capacity() + column.capacity() * size_of::<f64>())
+            .sum::<usize>()
+            + self.columns.capacity() * size_of::<(String, Vec<f64>)>()
+    }
+}
+
+impl Lattice {
+    /// Sets document `doc_id`'s value for the sort key `key`, replacing
+    /// any earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DocumentError::NotFound` if the document does not exist and
+    /// `DocumentError::InvalidInput` if `value` is NaN.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, SearchRequest, SortBy};
+    ///
+    /// let mut engine = Lattice::new();
+    /// for (text, published) in [("rust news", 300.0), ("rust guide", 100.0), ("rust blog", 200.0)] {
+    ///     let id = engine.add(text).unwrap();
+    ///     engine.set_sort_value(id, "published", published).unwrap();
+    /// }
+    ///
+    /// let newest = SearchRequest::new("rust", 2).sort(SortBy::FieldDesc("published".into()));
+    /// let ids: Vec<_> = engine.search_request(&newest).iter().map(|r| r.doc_id).collect();
+    /// assert_eq!(ids, [0, 2]);
+    /// ```
+    pub fn set_sort_value(
+        &mut self,
+        doc_id: DocId,
+        key: &str,
+        value: f64,
+    ) -> Result<(), DocumentError> {
+        if doc_id as usize >= self.len() {
+            return Err(DocumentError::NotFound { doc_id });
+        }
+        if value.is_nan() {
+            return Err(DocumentError::InvalidInput {
+                reason: "sort value must not be NaN",
+            });
+        }
+        self.sort_values.set(key, doc_id as usize, value);
+        Ok(())
+    }
+
+    /// Returns document `doc_id`'s value for the sort key `key`, if set.
+    pub fn sort_value(&self, doc_id: DocId, key: &str) -> Option<f64> {
+        SortValues::value(self.sort_values.column(key)?, doc_id as usize)
+    }
+
+    /// Keeps the `limit` first results in `sort` order, sorted, after
+    /// collapsing them on the `collapse` key if given.
+    pub(crate) fn sort_results(
+        &self,
+        results: &mut Vec<SearchResult>,
+        limit: usize,
+        sort: &SortBy,
+        collapse: Option<&str>,
+    ) {
+        let order = self.result_order(sort);
+        if let Some(key) = collapse {
+            self.collapse(results, key, &order);
+        }
+        select_first(results, limit, order);
+    }
+
+    /// Returns the comparison putting results in `sort` order.
+    fn result_order<'a>(
+        &'a self,
+        sort: &'a SortBy,
+    ) -> impl Fn(&SearchResult, &SearchResult) -> Ordering + 'a {
+        let (column, descending) = match sort {
+            SortBy::Field(key) => (self.sort_values.column(key), false),
+            SortBy::FieldDesc(key) => (self.sort_values.column(key), true),
+            SortBy::Score | SortBy::DocId => (None, false),
+        };
+        let column = column.unwrap_or(&[]);
+        let tie_break = self.scoring.tie_break;
+        move |a, b| {
+            let by_key = match sort {
+                SortBy::Score => Ordering::Equal,
+                SortBy::DocId => a.doc_id.cmp(&b.doc_id),
+                SortBy::Field(_) | SortBy::FieldDesc(_) => {
+                    let va = SortValues::value(column, a.doc_id as usize);
+                    let vb = SortValues::value(column, b.doc_id as usize);
+                    match (va, vb) {
+                        (Some(va), Some(vb)) if descending => vb.total_cmp(&va),
+                        (Some(va), Some(vb)) => va.total_cmp(&vb),
+                        (Some(_), None) => Ordering::Less,
+                        (None, Some(_)) => Ordering::Greater,
+                        (None, None) => Ordering::Equal,
+                    }
+                }
+            };
+            by_key
+                .then_with(|| b.score.total_cmp(&a.score))
+                .then_with(|| tie_break.order(a.doc_id, b.doc_id))
+        }
+    }
+
+    /// Keeps only the first result in `order` of each value of `key`;
+    /// results without a value are kept.
+    fn collapse(
+        &self,
+        results: &mut Vec<SearchResult>,
+        key: &str,
+        order: impl Fn(&SearchResult, &SearchResult) -> Ordering,
+    ) {
+        let Some(column) = self.sort_values.column(key) else {
+            return;
+        };
+        let mut first: FxHashMap<u64, usize> = FxHashMap::default();
+        let mut keep = vec![true; results.len()];
+        for i in 0..results.len() {
+            let Some(value) = SortValues::value(column, results[i].doc_id as usize) else {
+                continue;
+            };
+            // Adding 0.0 turns -0.0 into 0.0, so equal values share a group
+            match first.entry((value + 0.0).to_bits()) {
+                Entry::Vacant(entry) => {
+                    entry.insert(i);
+                }
+                Entry::Occupied(mut entry) => {
+                    let j = *entry.get();
+                    if order(&results[i], &results[j]).is_lt() {
+                        keep[j] = false;
+                        entry.insert(i);
+                    } else {
+                        keep[i] = false;
+                    }
+                }
+            }
+        }
+        let mut keep = keep.into_iter();
+        results.retain(|_| keep.next().unwrap_or(true));
+    }
+}
+
+/// Keeps the `limit` first results in `order`, sorted.
+fn select_first(
+    results: &mut Vec<SearchResult>,
+    limit: usize,
+    order: impl Fn(&SearchResult, &SearchResult) -> Ordering,
+) {
+    if results.len() > limit {
+        results.select_nth_unstable_by(limit, &order);
+        results.truncate(limit);
+    }
+    results.sort_unstable_by(order);
+}
@@ -1,7 +1,18 @@
 //! Statistics and IndexStats.
+//!
+//! [`IndexStats::to_json`] renders a snapshot as one JSON object for log
+//! pipelines, and [`Lattice::set_stats_reporter`] hands a fresh snapshot
+//! to a callback at a fixed interval.
 
-use crate::index::types::Lattice;
-use lattice_types::DocId;
+use crate::analyzer::trigram::extract_terms;
+use crate::index::doc_ids::DocIdSet;
+use crate::index::dump::json_f32;
+use crate::index::types::{Lattice, PostingBlock};
+use lattice_types::compression::varint_len;
+use lattice_types::{DocId, Trigram};
+use rustc_hash::FxHashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
 /// A snapshot of index statistics.
 #[derive(Debug, Clone, Copy)]
@@ -16,49 +27,255 @@ pub struct IndexStats {
     pub compressed_postings_bytes: Option<usize>,
     /// Compression ratio, if computed.
     pub compression_ratio: Option<f32>,
+    /// Distribution of committed posting-list lengths.
+    pub posting_lengths: PostingLengths,
+    /// Heap memory held by the whole index (see
+    /// [`Lattice::memory_breakdown`]).
+    pub allocated_bytes: usize,
+}
+
+/// Distribution of posting-list lengths across trigrams.
+///
+/// Percentiles use the nearest-rank method; all values are 0 for an empty
+/// index.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PostingLengths {
+    /// Median posting-list length.
+    pub p50: usize,
+    /// 90th percentile posting-list length.
+    pub p90: usize,
+    /// 99th percentile posting-list length.
+    pub p99: usize,
+    /// Longest posting list.
+    pub max: usize,
+    /// Number of trigrams that occur in exactly one document.
+    pub singletons: usize,
+    /// Average number of postings (distinct trigrams) per document.
+    pub avg_postings_per_doc: f32,
+}
+
+impl PostingLengths {
+    fn from_blocks(blocks: &[PostingBlock], total_postings: usize, num_documents: usize) -> Self {
+        if blocks.is_empty() {
+            return Self::default();
+        }
+
+        let mut lens: Vec<usize> = blocks.iter().map(|b| b.len as usize).collect();
+        lens.sort_unstable();
+        let rank = |p: usize| lens[(lens.len() * p).div_ceil(100).max(1) - 1];
+
+        Self {
+            p50: rank(50),
+            p90: rank(90),
+            p99: rank(99),
+            max: lens[lens.len() - 1],
+            singletons: lens.partition_point(|&len| len <= 1),
+            avg_postings_per_doc: total_postings as f32 / num_documents.max(1) as f32,
+        }
+    }
+}
+
+/// Posting statistics for a single trigram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrigramStat {
+    /// The trigram.
+    pub trigram: Trigram,
+    /// Number of documents containing the trigram, including uncommitted ones.
+    pub doc_frequency: usize,
+    /// Length of the committed posting list: how many doc IDs a query
+    /// containing the trigram walks.
+    pub posting_len: usize,
+}
+
+impl core::fmt::Display for TrigramStat {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{:?} df={} postings={}",
+            String::from_utf8_lossy(&self.trigram.to_bytes()),
+            self.doc_frequency,
+            self.posting_len
+        )
+    }
 }
 
 impl Lattice {
+    /// Returns the number of documents containing `trigram`, committed or not.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use lattice_types::Trigram;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.add("hello there").unwrap();
+    ///
+    /// assert_eq!(engine.trigram_df(Trigram::from_str("hel")), 2);
+    /// assert_eq!(engine.trigram_df(Trigram::from_str("wor")), 1);
+    /// ```
+    pub fn trigram_df(&self, trigram: Trigram) -> usize {
+        let committed = self
+            .find_block(trigram)
+            .map_or(0, |idx| self.blocks[idx].len as usize);
+
+        let pending = if self.needs_rebuild {
+            self.temp_trigrams
+                .iter()
+                .filter(|e| e.trigram == trigram)
+                .count()
+        } else {
+            let start = self
+                .temp_trigrams
+                .partition_point(|e| e.trigram.0 < trigram.0);
+            self.temp_trigrams[start..]
+                .iter()
+                .take_while(|e| e.trigram == trigram)
+                .count()
+        };
+        let committing = self
+            .committing
+            .iter()
+            .filter(|e| e.trigram == trigram)
+            .count();
+
+        committed + pending + committing
+    }
+
+    /// Returns the distinct trigrams indexed for `doc_id`, sorted, or
+    /// `None` if the document does not exist.
+    ///
+    /// Together with [`docs_for_trigram`](Self::docs_for_trigram), this
+    /// explains why a document does or does not match a query: compare
+    /// them with the query's trigrams after normalization.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use lattice_types::Trigram;
+    ///
+    /// let mut engine = Lattice::new();
+    /// let id = engine.add("Hello").unwrap();
+    ///
+    /// let trigrams = engine.doc_trigrams(id).unwrap();
+    /// assert_eq!(trigrams, ["ell", "hel", "llo"].map(Trigram::from_str));
+    /// assert!(engine.docs_for_trigram(Trigram::from_str("hel")).contains(id));
+    /// ```
+    pub fn doc_trigrams(&self, doc_id: DocId) -> Option<Vec<Trigram>> {
+        let text = self.get(doc_id)?;
+        let mut trigrams = Vec::with_capacity(text.len().saturating_sub(2));
+        extract_terms(text, self.tokenizer.cjk_bigrams, |t| trigrams.push(t));
+        trigrams.sort_unstable_by_key(|t| t.0);
+        trigrams.dedup();
+        Some(trigrams)
+    }
+
+    /// Returns the documents containing `trigram`, committed or not.
+    pub fn docs_for_trigram(&self, trigram: Trigram) -> DocIdSet {
+        let mut docs = Vec::with_capacity(self.trigram_df(trigram));
+        if let Some(idx) = self.find_block(trigram) {
+            docs.extend_from_slice(Self::block_postings(&self.blocks[idx], &self.postings));
+        }
+        docs.extend(
+            self.temp_trigrams
+                .iter()
+                .chain(self.committing.iter())
+                .filter(|e| e.trigram == trigram)
+                .map(|e| e.doc_id),
+        );
+        docs.into_iter().collect()
+    }
+
+    /// Returns statistics for every indexed trigram, ordered by trigram.
+    ///
+    /// Trigrams that only occur in uncommitted documents are included with
+    /// a `posting_len` of 0.
+    pub fn trigram_stats(&self) -> Vec<TrigramStat> {
+        let mut pending: FxHashMap<u32, usize> = FxHashMap::default();
+        for entry in self.temp_trigrams.iter().chain(self.committing.iter()) {
+            *pending.entry(entry.trigram.0).or_insert(0) += 1;
+        }
+
+        let mut stats: Vec<TrigramStat> = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let posting_len = block.len as usize;
+                TrigramStat {
+                    trigram: block.trigram,
+                    doc_frequency: posting_len + pending.remove(&block.trigram.0).unwrap_or(0),
+                    posting_len,
+                }
+            })
+            .collect();
+
+        if !pending.is_empty() {
+            stats.extend(pending.into_iter().map(|(trigram, count)| TrigramStat {
+                trigram: Trigram(trigram),
+                doc_frequency: count,
+                posting_len: 0,
+            }));
+            stats.sort_unstable_by_key(|s| s.trigram.0);
+        }
+
+        stats
+    }
+
+    /// Returns the `n` trigrams with the longest posting lists.
+    ///
+    /// These dominate query cost: a query whose rarest trigram is among
+    /// them examines many candidates, and one exceeding the seed limit
+    /// returns nothing.
+    pub fn heaviest_trigrams(&self, n: usize) -> Vec<TrigramStat> {
+        let mut stats = self.trigram_stats();
+        let by_weight = |a: &TrigramStat, b: &TrigramStat| {
+            b.posting_len
+                .cmp(&a.posting_len)
+                .then(b.doc_frequency.cmp(&a.doc_frequency))
+                .then(a.trigram.0.cmp(&b.trigram.0))
+        };
+
+        if stats.len() > n {
+            stats.select_nth_unstable_by(n, by_weight);
+            stats.truncate(n);
+        }
+        stats.sort_unstable_by(by_weight);
+        stats
+    }
+
     /// Returns index statistics.
+    ///
+    /// Computing the posting-length distribution sorts one length per
+    /// trigram, so this is O(T log T) in the number of trigrams.
     pub fn stats(&self) -> IndexStats {
-        IndexStats {
-            num_documents: self.documents.len(),
-            num_trigrams: self.blocks.len(),
-            total_postings: self.postings.len(),
-            compressed_postings_bytes: None,
-            compression_ratio: None,
-        }
+        IndexStats::from_engine(self, false)
     }
 
     /// Returns index statistics including compression analysis.
     pub fn stats_with_compression(&self) -> IndexStats {
-        let (compressed, ratio) = self.compress_postings();
-        IndexStats {
-            num_documents: self.documents.len(),
-            num_trigrams: self.blocks.len(),
-            total_postings: self.postings.len(),
-            compressed_postings_bytes: Some(compressed),
-            compression_ratio: Some(ratio),
-        }
+        IndexStats::from_engine(self, true)
     }
 
     /// Estimates compressed size of posting lists.
+    ///
+    /// Each list is sized as delta + varint encoded (the `compress_sorted`
+    /// format) without materializing the encoded bytes.
+    #[allow(clippy::unnecessary_cast)] // DocId is already u64 with `large-corpus`
     pub fn compress_postings(&self) -> (usize, f32) {
-        use lattice_types::compression::compress_sorted;
-
         if self.postings.is_empty() {
             return (0, 1.0);
         }
 
         let mut total_compressed = 0usize;
-        let mut buf = Vec::new();
-
-        for block in &self.blocks {
-            buf.clear();
-            if let Ok(bytes) =
-                compress_sorted(Self::block_postings(block, &self.postings), &mut buf)
-            {
-                total_compressed += bytes;
+
+        for block in self.blocks.iter() {
+            let mut prev = 0u64;
+            for &doc_id in Self::block_postings(block, &self.postings) {
+                let delta = doc_id as u64 - prev;
+                total_compressed += varint_len(delta);
+                prev = doc_id as u64;
             }
         }
 
@@ -73,6 +290,82 @@ impl Lattice {
     }
 }
 
+type ReportFn = Box<dyn FnMut(&IndexStats) + Send>;
+
+/// Callback registered with [`Lattice::set_stats_reporter`].
+pub(crate) struct StatsReporter {
+    interval: Duration,
+    /// When the callback last ran; `None` until the first report
+    last: Option<Instant>,
+    /// Behind a mutex only so the engine stays `Sync`; always reached
+    /// through `&mut`, so it is never contended
+    callback: Mutex<ReportFn>,
+}
+
+impl Lattice {
+    /// Calls `reporter` with [`stats_with_compression`](Self::stats_with_compression)
+    /// at most once per `interval`, replacing any earlier reporter.
+    ///
+    /// There is no background thread: the check runs after each add,
+    /// merge and search, so an idle index reports nothing. Gathering stats
+    /// walks every posting list, so keep the interval in seconds rather
+    /// than milliseconds for large indexes. Clones and snapshots start
+    /// without a reporter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use std::sync::mpsc;
+    /// use std::time::Duration;
+    ///
+    /// let (sender, lines) = mpsc::channel();
+    /// let mut engine = Lattice::new();
+    /// engine.set_stats_reporter(Duration::ZERO, move |stats| {
+    ///     let _ = sender.send(stats.to_json());
+    /// });
+    ///
+    /// engine.add("hello world").unwrap();
+    /// assert!(lines.try_recv().unwrap().contains(r#""num_documents":1"#));
+    /// ```
+    pub fn set_stats_reporter<F>(&mut self, interval: Duration, reporter: F)
+    where
+        F: FnMut(&IndexStats) + Send + 'static,
+    {
+        self.stats_reporter = Some(Box::new(StatsReporter {
+            interval,
+            last: None,
+            callback: Mutex::new(Box::new(reporter)),
+        }));
+    }
+
+    /// Removes the reporter set by [`set_stats_reporter`](Self::set_stats_reporter).
+    pub fn clear_stats_reporter(&mut self) {
+        self.stats_reporter = None;
+    }
+
+    /// Calls the stats reporter if its interval has elapsed.
+    #[inline]
+    pub(crate) fn report_stats_if_due(&mut self) {
+        let due = self.stats_reporter.as_ref().is_some_and(|reporter| {
+            reporter
+                .last
+                .is_none_or(|last| last.elapsed() >= reporter.interval)
+        });
+        if due {
+            let stats = self.stats_with_compression();
+            if let Some(reporter) = &mut self.stats_reporter {
+                reporter.last = Some(Instant::now());
+                let callback = reporter
+                    .callback
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner);
+                callback(&stats);
+            }
+        }
+    }
+}
+
 impl IndexStats {
     /// Constructs stats from an engine.
     pub fn from_engine(engine: &Lattice, compute_compression: bool) -> Self {
@@ -89,13 +382,61 @@ impl IndexStats {
             total_postings: engine.postings.len(),
             compressed_postings_bytes: compressed,
             compression_ratio: ratio,
+            posting_lengths: PostingLengths::from_blocks(
+                &engine.blocks,
+                engine.postings.len(),
+                engine.documents.len(),
+            ),
+            allocated_bytes: engine.allocated_bytes(),
         }
     }
 
-    /// Returns approximate memory usage in bytes.
+    /// Renders the stats as a single-line JSON object, with the field
+    /// names used here; statistics that were not computed are `null`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.commit();
+    ///
+    /// let json = engine.stats().to_json();
+    /// assert!(json.starts_with(r#"{"num_documents":1,"num_trigrams":9,"#));
+    /// assert!(json.contains(r#""compression_ratio":null"#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| "null".to_string());
+        let lengths = &self.posting_lengths;
+        format!(
+            "{{\"num_documents\":{},\"num_trigrams\":{},\"total_postings\":{},\
+             \"compressed_postings_bytes\":{},\"compression_ratio\":{},\
+             \"allocated_bytes\":{},\"posting_lengths\":{{\"p50\":{},\"p90\":{},\
+             \"p99\":{},\"max\":{},\"singletons\":{},\"avg_postings_per_doc\":{}}}}}",
+            self.num_documents,
+            self.num_trigrams,
+            self.total_postings,
+            optional(self.compressed_postings_bytes.map(|b| b.to_string())),
+            optional(self.compression_ratio.map(json_f32)),
+            self.allocated_bytes,
+            lengths.p50,
+            lengths.p90,
+            lengths.p99,
+            lengths.max,
+            lengths.singletons,
+            json_f32(lengths.avg_postings_per_doc),
+        )
+    }
+
+    /// Returns approximate memory usage of the posting index in bytes.
+    ///
+    /// Only blocks and postings are counted; see
+    /// [`Lattice::memory_breakdown`] for every component.
     pub fn memory_usage_bytes(&self) -> usize {
-        let blocks_size = self.num_trigrams * std::mem::size_of::<u32>() * 3;
-        let postings_size = self.total_postings * std::mem::size_of::<u32>();
+        let blocks_size = self.num_trigrams * std::mem::size_of::<PostingBlock>();
+        let postings_size = self.total_postings * std::mem::size_of::<DocId>();
         blocks_size + postings_size
     }
 }
@@ -111,7 +452,7 @@ impl core::fmt::Display for IndexStats {
         if let (Some(compressed), Some(ratio)) =
             (self.compressed_postings_bytes, self.compression_ratio)
         {
-            let original = self.total_postings * 4;
+            let original = self.total_postings * std::mem::size_of::<DocId>();
             let savings = original.saturating_sub(compressed);
             write!(
                 f,
@@ -122,6 +463,20 @@ impl core::fmt::Display for IndexStats {
             )?;
         }
 
+        if self.num_trigrams > 0 {
+            write!(f, "; {}", self.posting_lengths)?;
+        }
+
         Ok(())
     }
 }
+
+impl core::fmt::Display for PostingLengths {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "posting lengths p50={} p90={} p99={} max={}, {} singleton trigrams, {:.1} postings/doc",
+            self.p50, self.p90, self.p99, self.max, self.singletons, self.avg_postings_per_doc
+        )
+    }
+}
@@ -1,19 +1,34 @@
 //! Index types and constants.
 
-use crate::analyzer::normalizer::TextNormalizer;
+#[cfg(feature = "language-detection")]
+use crate::analyzer::language::{Language, LanguageNormalizers};
+use crate::analyzer::normalizer::{NormalizerConfig, TextNormalizer};
+use crate::analyzer::tokenizer::{Field, TokenizerConfig};
 
 use crate::arena::Arena;
-use lattice_types::{DocId, SearchConfig, SearchResult, Trigram};
+use crate::index::block_table::BlockTable;
+use crate::index::changefeed::{IndexEvent, Subscribers};
+use crate::index::commit::CommitPolicy;
+use crate::index::dictionary::TokenDictionary;
+use crate::index::fields::FieldIndex;
+use crate::index::frequency::FrequencyIndex;
+use crate::index::metrics::{Metrics, Telemetry};
+use crate::index::namespace::NamespaceIndex;
+use crate::index::positions::PositionIndex;
+use crate::index::scoring::ScoringConfig;
+use crate::index::scratch::SearchScratch;
+use crate::index::sort::SortValues;
+use crate::index::stats::StatsReporter;
+use crate::index::vectors::VectorIndex;
+use lattice_types::{ConfigError, DocId, SearchConfig, Trigram};
 
-use smallvec::SmallVec;
+use std::sync::Arc;
 
 pub const MAX_QUERY_TRIGRAMS: usize = 30;
 
 pub const PREFIX_BONUS: u8 = 2;
 
-pub const MAX_CANDIDATES: u32 = 100_000;
-
-pub const MAX_QUERY_LENGTH: usize = 1_000;
+pub const MAX_CANDIDATES: usize = 100_000;
 
 /// Maximum document length (64KB - matches Arena u16 limit)
 pub const MAX_DOCUMENT_LENGTH: usize = 65535;
@@ -22,6 +37,18 @@ pub const MAX_SEED_POSTING_LIST: usize = 100_000;
 
 pub const RADIX_SORT_THRESHOLD: usize = 512;
 
+/// Minimum trigram batch size sorted on the rayon pool (`rayon` feature).
+#[cfg(feature = "rayon")]
+pub const PARALLEL_SORT_THRESHOLD: usize = 1 << 18;
+
+/// Index into the shared postings array.
+#[cfg(not(feature = "large-corpus"))]
+pub type PostingOffset = u32;
+
+/// Index into the shared postings array.
+#[cfg(feature = "large-corpus")]
+pub type PostingOffset = u64;
+
 /// Maximum uncommitted trigrams before forced rebuild.
 /// Keeps search latency bounded by limiting linear scan of uncommitted data.
 pub const REBUILD_THRESHOLD: usize = 50_000;
@@ -29,8 +56,8 @@ pub const REBUILD_THRESHOLD: usize = 50_000;
 #[derive(Clone, Copy, Debug)]
 pub struct PostingBlock {
     pub trigram: Trigram,
-    pub offset: u32,
-    pub len: u32,
+    pub offset: PostingOffset,
+    pub len: PostingOffset,
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -47,30 +74,97 @@ pub struct TempTrigramEntry {
 
 #[derive(Clone, Copy)]
 pub struct QueryTrigram {
-    pub offset: u32,
-    pub len: u32,
-    pub bonus: u8,
+    pub offset: PostingOffset,
+    pub len: PostingOffset,
+    /// Summed over the trigram's occurrences in the query
+    pub bonus: u16,
+    /// Times the trigram occurs in the query
+    pub occurrences: u16,
+}
+
+/// A distinct query trigram, before posting lookup.
+#[derive(Clone, Copy)]
+pub struct QueryTerm {
+    pub trigram: Trigram,
+    /// Summed over the trigram's occurrences in the query
+    pub bonus: u16,
+    /// Times the trigram occurs in the query
+    pub occurrences: u16,
+    /// Committed posting block, if any
+    pub block: Option<usize>,
 }
 
 /// High-performance fuzzy search engine.
+///
+/// Cloning is cheap: posting blocks, postings, document pages and the
+/// spelling dictionary are reference-counted and only copied when one of
+/// the clones next modifies them. Per-document arrays and the uncommitted
+/// delta are copied eagerly.
 pub struct Lattice {
-    pub(crate) blocks: Vec<PostingBlock>,
-    pub(crate) postings: Vec<DocId>,
+    /// Shared with clones; replaced wholesale on rebuild
+    pub(crate) blocks: Arc<BlockTable>,
+    pub(crate) postings: Arc<Vec<DocId>>,
     pub(crate) documents: Arena,
+    /// Documents as added, before normalization (empty unless stored)
+    pub(crate) display: Arena,
     pub(crate) doc_lengths: Vec<u32>,
+    /// Number of distinct trigrams per document (used for Jaccard similarity)
+    pub(crate) doc_trigram_counts: Vec<u32>,
+    /// Per-document score multipliers (empty until the first `set_boost`)
+    pub(crate) boosts: Vec<f32>,
+    /// When each document was added, in milliseconds since the Unix epoch
+    /// (empty unless stored)
+    pub(crate) indexed_at: Vec<u64>,
+    /// Trigram offsets per document (empty unless positions are stored)
+    pub(crate) positions: PositionIndex,
+    /// Trigram counts per document (empty unless term frequencies are stored)
+    pub(crate) frequencies: FrequencyIndex,
+    /// Field ranges per document (empty until the first `add_fields`)
+    pub(crate) fields: FieldIndex,
+    /// Tenant of each document (empty until the first namespaced add)
+    pub(crate) namespaces: NamespaceIndex,
+    /// Named sort values per document (empty until the first is set)
+    pub(crate) sort_values: SortValues,
+    /// Dense vector per document (empty until the first is set)
+    pub(crate) vectors: VectorIndex,
+    /// Per-language normalizer configs, when language detection is enabled
+    #[cfg(feature = "language-detection")]
+    pub(crate) languages: Option<Box<LanguageNormalizers>>,
+    /// Detected document languages (empty until detection is enabled)
+    #[cfg(feature = "language-detection")]
+    pub(crate) doc_languages: Vec<Option<Language>>,
     pub(crate) normalizer: TextNormalizer,
+    /// Normalizers replacing `normalizer` for the text of one field,
+    /// indexed by `Field as usize`
+    pub(crate) field_normalizers: [Option<TextNormalizer>; Field::COUNT],
+    /// Delimiters and length limits applied after normalization
+    pub(crate) tokenizer: TokenizerConfig,
+    pub(crate) index_config: IndexConfig,
+    /// Query-time scoring options
+    pub(crate) scoring: ScoringConfig,
+    /// Token frequencies for spelling correction (copy-on-write)
+    pub(crate) dictionary: Arc<TokenDictionary>,
     pub(crate) config: SearchConfig,
     pub(crate) temp_trigrams: Vec<TempTrigramEntry>,
+    /// Delta handed to a background commit; scanned linearly until swapped in
+    pub(crate) committing: Arc<Vec<TempTrigramEntry>>,
     pub(crate) needs_rebuild: bool,
-    pub(crate) candidates: SmallVec<[Candidate; 256]>,
-    pub(crate) results: SmallVec<[SearchResult; 64]>,
+    /// When the delta is merged and whether searches see it
+    pub(crate) commit_policy: CommitPolicy,
+    /// Normalized text of the document being added
     pub(crate) norm_buf: String,
-    /// Reusable buffer for query normalization (avoids allocation per search)
-    pub(crate) query_buf: String,
-    /// Total number of queries executed
-    pub(crate) query_count: u64,
-    /// Total number of documents added
-    pub(crate) documents_added: u64,
+    /// Buffers for the `&mut self` search methods
+    pub(crate) scratch: SearchScratch,
+    /// Document, query and work counters, shared with monitoring threads
+    pub(crate) counters: Arc<Metrics>,
+    /// Latency histograms
+    pub(crate) telemetry: Telemetry,
+    /// Incremented whenever DocIds are reset; never decreases
+    pub(crate) generation: u32,
+    /// Receivers of change events
+    pub(crate) subscribers: Subscribers,
+    /// Periodic stats callback (not cloned)
+    pub(crate) stats_reporter: Option<Box<StatsReporter>>,
 }
 
 impl Default for Lattice {
@@ -79,35 +173,248 @@ impl Default for Lattice {
     }
 }
 
+impl Clone for Lattice {
+    fn clone(&self) -> Self {
+        Self {
+            blocks: Arc::clone(&self.blocks),
+            postings: Arc::clone(&self.postings),
+            documents: self.documents.clone(),
+            display: self.display.clone(),
+            doc_lengths: self.doc_lengths.clone(),
+            doc_trigram_counts: self.doc_trigram_counts.clone(),
+            boosts: self.boosts.clone(),
+            indexed_at: self.indexed_at.clone(),
+            positions: self.positions.clone(),
+            frequencies: self.frequencies.clone(),
+            fields: self.fields.clone(),
+            namespaces: self.namespaces.clone(),
+            sort_values: self.sort_values.clone(),
+            vectors: self.vectors.clone(),
+            #[cfg(feature = "language-detection")]
+            languages: self.languages.clone(),
+            #[cfg(feature = "language-detection")]
+            doc_languages: self.doc_languages.clone(),
+            normalizer: self.normalizer,
+            field_normalizers: self.field_normalizers,
+            tokenizer: self.tokenizer,
+            index_config: self.index_config,
+            scoring: self.scoring,
+            dictionary: Arc::clone(&self.dictionary),
+            config: self.config,
+            temp_trigrams: self.temp_trigrams.clone(),
+            committing: Arc::clone(&self.committing),
+            needs_rebuild: self.needs_rebuild,
+            commit_policy: self.commit_policy,
+            // Scratch space is per-instance
+            norm_buf: String::with_capacity(256),
+            scratch: SearchScratch::new(),
+            counters: Arc::new(self.counters.detached()),
+            telemetry: self.telemetry.clone(),
+            generation: self.generation,
+            // Subscriptions follow the original engine only
+            subscribers: Subscribers::default(),
+            stats_reporter: None,
+        }
+    }
+}
+
 impl Lattice {
     /// Creates a new, empty search engine.
     pub fn new() -> Self {
         Self {
-            blocks: Vec::new(),
-            postings: Vec::new(),
+            blocks: Arc::default(),
+            postings: Arc::default(),
             documents: Arena::with_capacity(1024 * 1024, 1024),
+            display: Arena::with_capacity(0, 0),
             doc_lengths: Vec::new(),
+            doc_trigram_counts: Vec::new(),
+            boosts: Vec::new(),
+            indexed_at: Vec::new(),
+            positions: PositionIndex::default(),
+            frequencies: FrequencyIndex::default(),
+            fields: FieldIndex::default(),
+            namespaces: NamespaceIndex::default(),
+            sort_values: SortValues::default(),
+            vectors: VectorIndex::default(),
+            #[cfg(feature = "language-detection")]
+            languages: None,
+            #[cfg(feature = "language-detection")]
+            doc_languages: Vec::new(),
             normalizer: TextNormalizer::new(),
+            field_normalizers: [None; Field::COUNT],
+            tokenizer: TokenizerConfig::new(),
+            index_config: IndexConfig::new(),
+            scoring: ScoringConfig::new(),
+            dictionary: Arc::default(),
             config: SearchConfig::default(),
             temp_trigrams: Vec::new(),
+            committing: Arc::default(),
             needs_rebuild: false,
-            candidates: SmallVec::new(),
-            results: SmallVec::new(),
+            commit_policy: CommitPolicy::Auto,
             norm_buf: String::with_capacity(256),
-            query_buf: String::with_capacity(256),
-            query_count: 0,
-            documents_added: 0,
+            scratch: SearchScratch::new(),
+            counters: Arc::default(),
+            telemetry: Telemetry::default(),
+            generation: 0,
+            subscribers: Subscribers::default(),
+            stats_reporter: None,
         }
     }
 
     /// Creates a new engine with custom configuration.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `search_config` fails [`SearchConfig::validate`]; use
+    /// [`try_with_config`](Self::try_with_config) for untrusted input.
     pub fn with_config(search_config: SearchConfig) -> Self {
-        Self {
+        match Self::try_with_config(search_config) {
+            Ok(engine) => engine,
+            Err(e) => panic!("invalid search configuration: {}", e),
+        }
+    }
+
+    /// Creates a new engine with custom configuration, rejecting
+    /// out-of-range options.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error from [`SearchConfig::validate`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    /// use lattice_types::SearchConfig;
+    ///
+    /// let config = SearchConfig {
+    ///     max_edit_distance: 40,
+    ///     ..SearchConfig::default()
+    /// };
+    /// assert!(Lattice::try_with_config(config).is_err());
+    /// ```
+    pub fn try_with_config(search_config: SearchConfig) -> Result<Self, ConfigError> {
+        search_config.validate()?;
+        Ok(Self {
             config: search_config,
             ..Self::new()
+        })
+    }
+
+    /// Replaces the search configuration, e.g. of a loaded index.
+    ///
+    /// Takes effect from the next query; the index is unaffected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `config` fails [`SearchConfig::validate`].
+    pub fn set_search_config(&mut self, config: SearchConfig) {
+        if let Err(e) = config.validate() {
+            panic!("invalid search configuration: {}", e);
+        }
+        self.config = config;
+    }
+
+    /// Returns the search configuration.
+    #[inline]
+    #[must_use]
+    pub fn search_config(&self) -> SearchConfig {
+        self.config
+    }
+
+    /// Creates a new engine that normalizes documents and queries with
+    /// `config`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, NormalizerConfig};
+    ///
+    /// let mut engine = Lattice::with_normalizer(NormalizerConfig::strip_diacritics());
+    /// engine.add("Crème brûlée").unwrap();
+    ///
+    /// assert_eq!(engine.get(0), Some("creme brulee"));
+    /// assert_eq!(engine.search("creme brulee", 1).len(), 1);
+    /// ```
+    pub fn with_normalizer(config: NormalizerConfig) -> Self {
+        Self {
+            normalizer: TextNormalizer::with_config(config),
+            ..Self::new()
         }
     }
 
+    /// Creates a new engine that matches documents and queries normalized
+    /// with `config` but keeps each document's original text for
+    /// [`display_text`](Self::display_text).
+    ///
+    /// With [`NormalizerConfig::strip_diacritics`], "café" and "cafe" then
+    /// find the same documents while results still show "Café".
+    pub fn with_display_text(config: NormalizerConfig) -> Self {
+        Self {
+            normalizer: TextNormalizer::with_config(config),
+            index_config: IndexConfig {
+                store_display_text: true,
+                ..IndexConfig::new()
+            },
+            ..Self::new()
+        }
+    }
+
+    /// Returns the normalization options documents and queries go through.
+    #[inline]
+    #[must_use]
+    pub fn normalizer_config(&self) -> NormalizerConfig {
+        self.normalizer.config()
+    }
+
+    /// Returns the normalization options the text of `field` goes
+    /// through: its own, if set with
+    /// [`LatticeBuilder::field_normalizer`](crate::LatticeBuilder::field_normalizer),
+    /// and the engine's otherwise.
+    #[inline]
+    #[must_use]
+    pub fn field_normalizer_config(&self, field: Field) -> NormalizerConfig {
+        self.field_normalizers[field as usize]
+            .unwrap_or(self.normalizer)
+            .config()
+    }
+
+    /// Creates a new engine that tokenizes documents and queries with
+    /// `config` after normalization.
+    ///
+    /// Documents are stored as their tokens joined by single spaces, so
+    /// extra delimiters become word breaks and over-long tokens are
+    /// skipped or truncated before any trigram is extracted.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::analyzer::{DelimiterSet, LongTokenPolicy};
+    /// use lattice_core::{Lattice, TokenizerConfig};
+    ///
+    /// let config = TokenizerConfig::new()
+    ///     .split_on(DelimiterSet::PUNCTUATION)
+    ///     .limit_token_len(32, LongTokenPolicy::Skip);
+    /// let mut engine = Lattice::with_tokenizer(config);
+    /// engine.add("src/index/api.rs: sha=9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08").unwrap();
+    ///
+    /// assert_eq!(engine.get(0), Some("src index api rs sha"));
+    /// assert_eq!(engine.metrics().tokens_skipped, 1);
+    /// ```
+    pub fn with_tokenizer(config: TokenizerConfig) -> Self {
+        Self {
+            tokenizer: config,
+            ..Self::new()
+        }
+    }
+
+    /// Returns the tokenizer options documents and queries go through.
+    #[inline]
+    #[must_use]
+    pub fn tokenizer_config(&self) -> TokenizerConfig {
+        self.tokenizer
+    }
+
     /// Returns the number of documents in the index.
     #[inline(always)]
     #[must_use]
@@ -123,31 +430,101 @@ impl Lattice {
     }
 
     /// Removes all documents and resets the index.
+    ///
+    /// DocIds restart at 0 and the index generation is incremented, so
+    /// handles issued before the clear no longer resolve.
     pub fn clear(&mut self) {
-        self.blocks.clear();
-        self.postings.clear();
+        for doc_id in 0..self.len() as DocId {
+            self.publish(IndexEvent::Removed(doc_id));
+        }
+        self.install_blocks(Vec::new());
+        self.postings = Arc::default();
         self.documents.clear();
+        self.display.clear();
         self.doc_lengths.clear();
+        self.doc_trigram_counts.clear();
+        self.positions.clear();
+        self.frequencies.clear();
+        self.fields.clear();
+        self.namespaces.clear();
+        self.sort_values.clear();
+        self.vectors.clear();
+        self.boosts.clear();
+        self.indexed_at.clear();
+        #[cfg(feature = "language-detection")]
+        self.doc_languages.clear();
+        TokenDictionary::clear_shared(&mut self.dictionary);
         self.temp_trigrams.clear();
+        self.committing = Arc::default();
         self.needs_rebuild = false;
-        self.query_count = 0;
-        self.documents_added = 0;
+        Metrics::set(&self.counters.documents_indexed, 0);
+        Metrics::set(&self.counters.queries_executed, 0);
+        Metrics::set(&self.counters.current_doc_count, 0);
+        self.reset_metrics();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// Returns the current index generation.
+    #[inline(always)]
+    #[must_use]
+    pub fn generation(&self) -> u32 {
+        self.generation
     }
 
     /// Returns basic metrics about the engine's operation.
     #[inline(always)]
     #[must_use]
     pub fn metrics(&self) -> EngineMetrics {
-        EngineMetrics {
-            documents_indexed: self.documents_added,
-            queries_executed: self.query_count,
-            current_doc_count: self.documents.len() as u64,
+        self.counters.snapshot()
+    }
+}
+
+/// Indexing options, fixed when the engine is created
+/// (see [`Lattice::with_index_config`]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct IndexConfig {
+    /// Record the byte offset of every trigram occurrence per (trigram,
+    /// document) pair, for [`Lattice::search_phrase`], highlighting and
+    /// proximity scoring. Costs two bytes per trigram occurrence.
+    pub store_positions: bool,
+    /// Record how often each trigram occurs in each document (one byte per
+    /// distinct trigram of a document), for the TF-IDF and BM25
+    /// [`ScoringMode`](crate::ScoringMode)s.
+    pub store_term_frequencies: bool,
+    /// Keep each document's text as it was added, before normalization,
+    /// for [`Lattice::display_text`]. Lets an index fold diacritics or case
+    /// for matching while still showing "Café" rather than "cafe".
+    pub store_display_text: bool,
+    /// Record when each document was added, for [`Lattice::indexed_at`].
+    /// Costs eight bytes per document, and saved files then differ between
+    /// otherwise identical builds.
+    pub store_indexed_at: bool,
+}
+
+impl IndexConfig {
+    /// The default configuration, usable in `const` contexts.
+    pub const fn new() -> Self {
+        Self {
+            store_positions: false,
+            store_term_frequencies: false,
+            store_display_text: false,
+            store_indexed_at: false,
+        }
+    }
+
+    /// Stores positions; see [`store_positions`](Self::store_positions).
+    pub const fn positional() -> Self {
+        Self {
+            store_positions: true,
+            ..Self::new()
         }
     }
 }
 
 /// Basic operational metrics for the search engine.
-#[derive(Debug, Clone, Copy)]
+///
+/// See [`Lattice::metrics_snapshot`] for latency percentiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct EngineMetrics {
     /// Total number of documents added (including those that may have been cleared).
     pub documents_indexed: u64,
@@ -155,4 +532,12 @@ pub struct EngineMetrics {
     pub queries_executed: u64,
     /// Current number of documents in the index.
     pub current_doc_count: u64,
+    /// Total number of candidate documents scored by search queries.
+    pub candidates_examined: u64,
+    /// Tokens dropped while indexing for exceeding
+    /// [`TokenizerConfig::max_token_len`].
+    pub tokens_skipped: u64,
+    /// Tokens shortened while indexing to
+    /// [`TokenizerConfig::max_token_len`].
+    pub tokens_truncated: u64,
 }
@@ -0,0 +1,211 @@
+//! Dense document vectors for hybrid search.
+//!
+//! [`Lattice::set_vector`] attaches an embedding, computed by any model the
+//! caller likes, to a document. A request made with
+//! [`SearchRequest::hybrid`](crate::SearchRequest::hybrid) then ranks the
+//! trigram matches by a blend of their text score and the cosine
+//! similarity of their vector to the query's:
+//!
+//! ```text
+//! score = (1 - alpha) * text + alpha * cosine(document, query)
+//! ```
+//!
+//! `text` is the trigram score relative to the best the query could reach
+//! (as in [`SearchResponse`](crate::SearchResponse)), so both parts lie in
+//! comparable ranges. A document without a vector has a similarity of 0.
+//!
+//! Vectors are stored in one flat `f32` array, `dimension` values per
+//! document, with each vector's norm kept alongside. Norms and dot
+//! products are computed in `f64`, so large finite values cannot overflow
+//! them.
+
+use crate::index::types::Lattice;
+use core::mem::size_of;
+use lattice_types::{DocId, DocumentError};
+
+/// Vectors of every document, all of one dimension.
+#[derive(Clone, Default)]
+pub(crate) struct VectorIndex {
+    /// Values per vector; 0 until the first vector is set
+    dimension: usize,
+    /// Vector of document `d` at `d * dimension..`; zeros for none, and
+    /// documents past the end have none
+    values: Vec<f32>,
+    /// Euclidean norm of each vector, 0 for none
+    norms: Vec<f64>,
+}
+
+impl VectorIndex {
+    pub(crate) fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    pub(crate) fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Returns document `doc`'s vector, if it has one.
+    pub(crate) fn get(&self, doc: usize) -> Option<&[f32]> {
+        if self.norms.get(doc).is_none_or(|&norm| norm == 0.0) {
+            return None;
+        }
+        Some(&self.values[doc * self.dimension..(doc + 1) * self.dimension])
+    }
+
+    /// Checks that `vector` can be stored.
+    fn check(&self, vector: &[f32]) -> Result<(), DocumentError> {
+        if vector.is_empty() || (self.dimension != 0 && vector.len() != self.dimension) {
+            return Err(DocumentError::InvalidInput {
+                reason: "vector dimension differs from the index's",
+            });
+        }
+        if !vector.iter().all(|v| v.is_finite()) {
+            return Err(DocumentError::InvalidInput {
+                reason: "vector values must be finite",
+            });
+        }
+        Ok(())
+    }
+
+    fn set(&mut self, doc: usize, vector: &[f32]) {
+        self.dimension = vector.len();
+        if self.norms.len() <= doc {
+            self.norms.resize(doc + 1, 0.0);
+            self.values.resize((doc + 1) * self.dimension, 0.0);
+        }
+        self.values[doc * self.dimension..(doc + 1) * self.dimension].copy_from_slice(vector);
+        self.norms[doc] = norm(vector);
+    }
+
+    /// Returns `true` if `query` can be compared with the stored vectors:
+    /// non-empty, finite and of their dimension, if any are stored.
+    pub(crate) fn accepts_query(&self, query: &[f32]) -> bool {
+        !query.is_empty()
+            && (self.dimension == 0 || query.len() == self.dimension)
+            && query.iter().all(|v| v.is_finite())
+    }
+
+    /// Returns the cosine similarity of document `doc`'s vector to `query`,
+    /// whose norm is `query_norm`; 0 if either is missing or zero.
+    #[inline]
+    pub(crate) fn cosine(&self, doc: usize, query: &[f32], query_norm: f64) -> f32 {
+        let doc_norm = self.norms.get(doc).copied().unwrap_or(0.0);
+        if doc_norm == 0.0 || query_norm == 0.0 || query.len() != self.dimension {
+            return 0.0;
+        }
+        let vector = &self.values[doc * self.dimension..(doc + 1) * self.dimension];
+        let dot: f64 = vector
+            .iter()
+            .zip(query)
+            .map(|(&a, &b)| f64::from(a) * f64::from(b))
+            .sum();
+        let cosine = dot / (doc_norm * query_norm);
+        if cosine.is_finite() {
+            cosine.clamp(-1.0, 1.0) as f32
+        } else {
+            0.0
+        }
+    }
+
+    /// Restores a saved index's vectors.
+    pub(crate) fn restore(&mut self, dimension: usize, values: Vec<f32>) {
+        self.norms = if dimension == 0 {
+            Vec::new()
+        } else {
+            values.chunks_exact(dimension).map(norm).collect()
+        };
+        self.dimension = dimension;
+        self.values = values;
+    }
+
+    /// Appends the vectors of `other`'s `count` documents after this
+    /// index's first `first`. The dimensions must agree (see
+    /// [`compatible`](Self::compatible)).
+    pub(crate) fn append(&mut self, other: &VectorIndex, first: usize, count: usize) {
+        if other.dimension == 0 {
+            return;
+        }
+        self.dimension = other.dimension;
+        let stored = other.norms.len().min(count);
+        self.norms.resize(first, 0.0);
+        self.norms.extend_from_slice(&other.norms[..stored]);
+        self.values.resize(first * self.dimension, 0.0);
+        self.values
+            .extend_from_slice(&other.values[..stored * self.dimension]);
+    }
+
+    /// Returns `true` if `other`'s vectors can be appended to these.
+    pub(crate) fn compatible(&self, other: &VectorIndex) -> bool {
+        self.dimension == 0 || other.dimension == 0 || self.dimension == other.dimension
+    }
+
+    pub(crate) fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.values.shrink_to_fit();
+        self.norms.shrink_to_fit();
+    }
+
+    pub(crate) fn allocated_bytes(&self) -> usize {
+        self.values.capacity() * size_of::<f32>() + self.norms.capacity() * size_of::<f64>()
+    }
+}
+
+/// Euclidean norm.
+#[inline]
+pub(crate) fn norm(vector: &[f32]) -> f64 {
+    vector
+        .iter()
+        .map(|&v| f64::from(v) * f64::from(v))
+        .sum::<f64>()
+        .sqrt()
+}
+
+impl Lattice {
+    /// Attaches a dense vector, such as a sentence embedding, to document
+    /// `doc_id`, replacing any earlier one.
+    ///
+    /// Every vector in an index has the dimension of the first one set.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DocumentError::NotFound` if the document does not exist and
+    /// `DocumentError::InvalidInput` if the vector is empty, of another
+    /// dimension or holds a non-finite value.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::{Lattice, SearchRequest};
+    ///
+    /// let mut engine = Lattice::new();
+    /// let cat = engine.add("a cat on a mat").unwrap();
+    /// let car = engine.add("a car on a mat").unwrap();
+    /// engine.set_vector(cat, &[1.0, 0.0]).unwrap();
+    /// engine.set_vector(car, &[0.0, 1.0]).unwrap();
+    ///
+    /// // The text alone cannot tell them apart; the vectors can
+    /// let request = SearchRequest::new("on a mat", 10).hybrid(0.5, &[0.1, 0.9]);
+    /// assert_eq!(engine.search_request(&request)[0].doc_id, car);
+    /// ```
+    pub fn set_vector(&mut self, doc_id: DocId, vector: &[f32]) -> Result<(), DocumentError> {
+        if doc_id as usize >= self.len() {
+            return Err(DocumentError::NotFound { doc_id });
+        }
+        self.vectors.check(vector)?;
+        self.vectors.set(doc_id as usize, vector);
+        Ok(())
+    }
+
+    /// Returns document `doc_id`'s vector, if it has one.
+    pub fn vector(&self, doc_id: DocId) -> Option<&[f32]> {
+        self.vectors.get(doc_id as usize)
+    }
+
+    /// Returns the dimension of the index's vectors, or 0 if none is set.
+    pub fn vector_dimension(&self) -> usize {
+        self.vectors.dimension()
+    }
+}
@@ -0,0 +1,308 @@
+//! Index integrity verification.
+//!
+//! The search path trusts the index layout completely: block lookup is a
+//! binary search, intersection assumes sorted postings, and document
+//! retrieval skips UTF-8 validation. [`Lattice::verify`] checks those
+//! assumptions explicitly so that a corrupted index is reported instead of
+//! silently returning wrong results.
+
+use crate::index::types::Lattice;
+use lattice_types::{DocId, Trigram};
+
+/// A single violated index invariant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// Blocks are not strictly ascending by trigram at this block index.
+    BlocksUnsorted {
+        /// Index of the first out-of-order block.
+        block: usize,
+    },
+    /// A block's posting range lies outside the postings array.
+    BlockOutOfBounds {
+        /// The block's trigram.
+        trigram: Trigram,
+        /// Start of the posting range.
+        offset: usize,
+        /// Length of the posting range.
+        len: usize,
+    },
+    /// A posting list is not strictly ascending (unsorted or duplicated).
+    PostingsUnsorted {
+        /// The block's trigram.
+        trigram: Trigram,
+    },
+    /// A posting refers to a document that does not exist.
+    DanglingPosting {
+        /// The trigram whose posting list (or uncommitted delta) holds it.
+        trigram: Trigram,
+        /// The missing document.
+        doc_id: DocId,
+    },
+    /// A document span lies outside the arena or is not valid UTF-8.
+    InvalidSpan {
+        /// The affected document.
+        doc_id: DocId,
+    },
+    /// A per-document array does not have one entry per document.
+    LengthMismatch {
+        /// Name of the array.
+        array: &'static str,
+        /// Number of documents.
+        expected: usize,
+        /// Number of entries found.
+        actual: usize,
+    },
+    /// A recorded document length disagrees with the stored text.
+    DocLengthMismatch {
+        /// The affected document.
+        doc_id: DocId,
+        /// Length recorded in `doc_lengths`.
+        recorded: u32,
+        /// Length of the stored text.
+        actual: usize,
+    },
+}
+
+impl core::fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            IntegrityIssue::BlocksUnsorted { block } => {
+                write!(f, "block {} is out of trigram order", block)
+            }
+            IntegrityIssue::BlockOutOfBounds {
+                trigram,
+                offset,
+                len,
+            } => write!(
+                f,
+                "block {:?} range {}..{} is outside the postings",
+                trigram,
+                offset,
+                offset + len
+            ),
+            IntegrityIssue::PostingsUnsorted { trigram } => {
+                write!(f, "postings of {:?} are not strictly ascending", trigram)
+            }
+            IntegrityIssue::DanglingPosting { trigram, doc_id } => {
+                write!(f, "{:?} lists missing document {}", trigram, doc_id)
+            }
+            IntegrityIssue::InvalidSpan { doc_id } => {
+                write!(f, "document {} has an invalid text span", doc_id)
+            }
+            IntegrityIssue::LengthMismatch {
+                array,
+                expected,
+                actual,
+            } => write!(f, "{} has {} entries, expected {}", array, actual, expected),
+            IntegrityIssue::DocLengthMismatch {
+                doc_id,
+                recorded,
+                actual,
+            } => write!(
+                f,
+                "document {} length recorded as {} but stored text is {} bytes",
+                doc_id, recorded, actual
+            ),
+        }
+    }
+}
+
+/// Result of [`Lattice::verify`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Number of posting blocks checked.
+    pub blocks_checked: usize,
+    /// Number of committed postings checked.
+    pub postings_checked: usize,
+    /// Number of documents checked.
+    pub documents_checked: usize,
+    /// Every violated invariant, in the order found.
+    pub issues: Vec<IntegrityIssue>,
+}
+
+impl VerifyReport {
+    /// Returns true if no invariant was violated.
+    #[inline]
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl core::fmt::Display for VerifyReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "checked {} blocks, {} postings, {} documents: ",
+            self.blocks_checked, self.postings_checked, self.documents_checked
+        )?;
+        if self.is_ok() {
+            return write!(f, "ok");
+        }
+        write!(f, "{} issues", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+impl Lattice {
+    /// Checks the index's structural invariants.
+    ///
+    /// Verifies that blocks are sorted by trigram, that every block's
+    /// posting range is in bounds and strictly ascending, that postings and
+    /// the uncommitted delta only reference existing documents, that every
+    /// document span lies within the arena and holds valid UTF-8, and that
+    /// the per-document arrays have one entry per document.
+    ///
+    /// Runs in O(postings + text) and does not modify the index.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_core::Lattice;
+    ///
+    /// let mut engine = Lattice::new();
+    /// engine.add("hello world").unwrap();
+    /// engine.search("hello", 10);
+    ///
+    /// let report = engine.verify();
+    /// assert!(report.is_ok(), "{}", report);
+    /// ```
+    pub fn verify(&self) -> VerifyReport {
+        let mut report = VerifyReport {
+            blocks_checked: self.blocks.len(),
+            postings_checked: self.postings.len(),
+            documents_checked: self.documents.len(),
+            issues: Vec::new(),
+        };
+        self.verify_blocks(&mut report.issues);
+        self.verify_documents(&mut report.issues);
+        report
+    }
+
+    fn verify_blocks(&self, issues: &mut Vec<IntegrityIssue>) {
+        let num_docs = self.documents.len();
+        let exists = |doc_id: DocId| (doc_id as usize) < num_docs;
+
+        for (i, block) in self.blocks.iter().enumerate() {
+            if i > 0 && self.blocks[i - 1].trigram.0 >= block.trigram.0 {
+                issues.push(IntegrityIssue::BlocksUnsorted { block: i });
+            }
+
+            let offset = block.offset as usize;
+            let len = block.len as usize;
+            let Some(list) = offset
+                .checked_add(len)
+                .and_then(|end| self.postings.get(offset..end))
+            else {
+                issues.push(IntegrityIssue::BlockOutOfBounds {
+                    trigram: block.trigram,
+                    offset,
+                    len,
+                });
+                continue;
+            };
+
+            if list.windows(2).any(|w| w[0] >= w[1]) {
+                issues.push(IntegrityIssue::PostingsUnsorted {
+                    trigram: block.trigram,
+                });
+            }
+            // Lists are expected to be sorted, but check every entry so an
+            // unsorted list cannot hide a dangling ID
+            if let Some(&doc_id) = list.iter().find(|&&d| !exists(d)) {
+                issues.push(IntegrityIssue::DanglingPosting {
+                    trigram: block.trigram,
+                    doc_id,
+                });
+            }
+        }
+
+        let mut delta = self.temp_trigrams.iter().chain(self.committing.iter());
+        if let Some(entry) = delta.find(|e| !exists(e.doc_id)) {
+            issues.push(IntegrityIssue::DanglingPosting {
+                trigram: entry.trigram,
+                doc_id: entry.doc_id,
+            });
+        }
+    }
+
+    fn verify_documents(&self, issues: &mut Vec<IntegrityIssue>) {
+        let num_docs = self.documents.len();
+
+        let mut invalid = vec![false; num_docs];
+        for i in self.documents.invalid_spans() {
+            invalid[i] = true;
+            issues.push(IntegrityIssue::InvalidSpan { doc_id: i as DocId });
+        }
+
+        let indexed_at = self
+            .index_config
+            .store_indexed_at
+            .then_some(("indexed_at", self.indexed_at.len()));
+        let arrays = [
+            ("doc_lengths", self.doc_lengths.len()),
+            ("doc_trigram_counts", self.doc_trigram_counts.len()),
+        ];
+        for (array, actual) in arrays.into_iter().chain(indexed_at) {
+            if actual != num_docs {
+                issues.push(IntegrityIssue::LengthMismatch {
+                    array,
+                    expected: num_docs,
+                    actual,
+                });
+            }
+        }
+        let derived = [
+            (
+                self.index_config.store_positions,
+                "positions",
+                self.positions.len(),
+            ),
+            (
+                self.index_config.store_term_frequencies,
+                "term_frequencies",
+                self.frequencies.len(),
+            ),
+            (
+                self.index_config.store_display_text,
+                "display_text",
+                self.display.len(),
+            ),
+            (!self.fields.is_empty(), "fields", self.fields.len()),
+        ];
+        for (stored, array, actual) in derived {
+            if stored && actual != num_docs {
+                issues.push(IntegrityIssue::LengthMismatch {
+                    array,
+                    expected: num_docs,
+                    actual,
+                });
+            }
+        }
+        // Boosts are only materialized after the first `set_boost`
+        if !self.boosts.is_empty() && self.boosts.len() != num_docs {
+            issues.push(IntegrityIssue::LengthMismatch {
+                array: "boosts",
+                expected: num_docs,
+                actual: self.boosts.len(),
+            });
+        }
+
+        for (i, &recorded) in self.doc_lengths.iter().enumerate().take(num_docs) {
+            if invalid[i] {
+                continue;
+            }
+            let doc_id = i as DocId;
+            let actual = self.documents.get(doc_id).map_or(0, str::len);
+            if recorded as usize != actual {
+                issues.push(IntegrityIssue::DocLengthMismatch {
+                    doc_id,
+                    recorded,
+                    actual,
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,306 @@
+//! Exchanging term dictionaries with other engines.
+//!
+//! A [`TermDictionary`] is an inverted index reduced to its terms and
+//! posting lists. [`TermDictionary::from_lattice`] takes one from the
+//! committed trigram index, and [`write`](TermDictionary::write) and
+//! [`read`](TermDictionary::read) move it through a plain text format that
+//! is easy to produce from another engine, such as by walking the term
+//! dictionary of a Tantivy or Lucene trigram field:
+//!
+//! ```text
+//! lattice-terms 1
+//! app 0 2 7
+//! ell 1
+//! h\xc3\xa9 4 9
+//! ```
+//!
+//! - The first line names the format and its version.
+//! - Every further line is a term, a space, then the IDs of the documents
+//!   containing the term, strictly ascending and separated by spaces.
+//! - Terms are raw bytes, since a byte trigram can split a UTF-8
+//!   character. Bytes outside printable ASCII, and space and `\`, are
+//!   written as `\xNN`, `\` as `\\`.
+//! - Lines are sorted by term bytes, each term appearing once.
+//! - Empty lines and lines starting with `#` are ignored.
+//!
+//! Document IDs must be global, so a dump of a segmented index has to
+//! offset each segment's IDs. Comparing dictionaries (they implement
+//! `PartialEq`) checks that a migrated corpus was indexed the same way in
+//! both engines; the documents themselves move as text, through
+//! [`Lattice::export_jsonl`].
+//!
+//! Only the export side and the format live here. Nothing in this crate
+//! reads a Tantivy or Lucene index, so the dump from another engine has
+//! to be written with that engine's own API; and a dictionary cannot be
+//! loaded into a [`Lattice`], which is rebuilt from the document text
+//! instead.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::interop::TermDictionary;
+//! use lattice_core::Lattice;
+//!
+//! let mut engine = Lattice::new();
+//! engine.add("hello").unwrap();
+//! engine.add("help").unwrap();
+//! engine.commit();
+//!
+//! let terms = TermDictionary::from_lattice(&engine);
+//! assert_eq!(terms.postings(b"hel"), Some(&[0, 1][..]));
+//!
+//! let mut file = Vec::new();
+//! terms.write(&mut file).unwrap();
+//! assert_eq!(TermDictionary::read(&file[..]).unwrap(), terms);
+//! ```
+
+use crate::index::Lattice;
+use core::fmt;
+use lattice_types::DocId;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+
+/// First line of the text format.
+const HEADER: &str = "lattice-terms 1";
+
+/// Terms and the sorted IDs of the documents containing each.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TermDictionary {
+    /// Sorted by term, each term once
+    terms: Vec<(Vec<u8>, Vec<DocId>)>,
+}
+
+/// Errors returned by [`TermDictionary::read`].
+#[derive(Debug)]
+pub enum TermDictionaryError {
+    /// The reader failed.
+    Io(io::Error),
+    /// A line does not follow the format.
+    Parse {
+        /// Line number, counting from 1.
+        line: usize,
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for TermDictionaryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TermDictionaryError::Io(e) => write!(f, "failed to read term dictionary: {}", e),
+            TermDictionaryError::Parse { line, reason } => {
+                write!(f, "invalid term dictionary at line {}: {}", line, reason)
+            }
+        }
+    }
+}
+
+impl core::error::Error for TermDictionaryError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            TermDictionaryError::Io(e) => Some(e),
+            TermDictionaryError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for TermDictionaryError {
+    fn from(e: io::Error) -> Self {
+        TermDictionaryError::Io(e)
+    }
+}
+
+impl TermDictionary {
+    /// Returns the trigrams of `engine`'s committed documents with their
+    /// posting lists. Commit first to include every document.
+    pub fn from_lattice(engine: &Lattice) -> Self {
+        let terms = engine
+            .blocks
+            .iter()
+            .map(|block| {
+                let start = block.offset as usize;
+                let postings = &engine.postings[start..start + block.len as usize];
+                (block.trigram.to_bytes().to_vec(), postings.to_vec())
+            })
+            .collect();
+        Self { terms }
+    }
+
+    /// Reads a dictionary in the text format.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TermDictionaryError::Io`] if the reader fails and
+    /// [`TermDictionaryError::Parse`] for the first line breaking the
+    /// format.
+    pub fn read<R: Read>(reader: R) -> Result<Self, TermDictionaryError> {
+        let mut terms: Vec<(Vec<u8>, Vec<DocId>)> = Vec::new();
+        let mut header = false;
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let error = |reason| TermDictionaryError::Parse {
+                line: i + 1,
+                reason,
+            };
+            if !header {
+                if line != HEADER {
+                    return Err(error("expected the header `lattice-terms 1`"));
+                }
+                header = true;
+                continue;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut fields = line.split(' ');
+            let term = fields.next().and_then(unescape).ok_or(error("bad term"))?;
+            if term.is_empty() {
+                return Err(error("empty term"));
+            }
+            if terms.last().is_some_and(|(last, _)| *last >= term) {
+                return Err(error("terms out of order or repeated"));
+            }
+            let mut postings: Vec<DocId> = Vec::new();
+            for field in fields {
+                let doc_id = field.parse().map_err(|_| error("bad document ID"))?;
+                if postings.last().is_some_and(|&last| last >= doc_id) {
+                    return Err(error("document IDs out of order or repeated"));
+                }
+                postings.push(doc_id);
+            }
+            if postings.is_empty() {
+                return Err(error("term without documents"));
+            }
+            terms.push((term, postings));
+        }
+        if !header {
+            return Err(TermDictionaryError::Parse {
+                line: 1,
+                reason: "expected the header `lattice-terms 1`",
+            });
+        }
+        Ok(Self { terms })
+    }
+
+    /// Writes the dictionary in the text format.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error from `writer`.
+    pub fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        let mut w = BufWriter::new(writer);
+        writeln!(w, "{}", HEADER)?;
+        for (term, postings) in &self.terms {
+            for &byte in term {
+                match byte {
+                    b'\\' => w.write_all(b"\\\\")?,
+                    b'!'..=b'~' => w.write_all(&[byte])?,
+                    _ => write!(w, "\\x{:02x}", byte)?,
+                }
+            }
+            for doc_id in postings {
+                write!(w, " {}", doc_id)?;
+            }
+            writeln!(w)?;
+        }
+        w.flush()
+    }
+
+    /// Returns the IDs of the documents containing `term`, ascending.
+    pub fn postings(&self, term: &[u8]) -> Option<&[DocId]> {
+        self.terms
+            .binary_search_by(|(t, _)| t.as_slice().cmp(term))
+            .ok()
+            .map(|i| self.terms[i].1.as_slice())
+    }
+
+    /// Returns the terms with their postings, sorted by term.
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[DocId])> + '_ {
+        self.terms
+            .iter()
+            .map(|(term, postings)| (term.as_slice(), postings.as_slice()))
+    }
+
+    /// Returns the number of terms.
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    /// Returns `true` if there are no terms.
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+/// Decodes an escaped term; `None` for a bad escape.
+fn unescape(field: &str) -> Option<Vec<u8>> {
+    let mut bytes = field.bytes();
+    let mut term = Vec::with_capacity(field.len());
+    while let Some(byte) = bytes.next() {
+        if byte != b'\\' {
+            term.push(byte);
+            continue;
+        }
+        match bytes.next()? {
+            b'\\' => term.push(b'\\'),
+            b'x' => {
+                let hex = [bytes.next()?, bytes.next()?];
+                let hex = core::str::from_utf8(&hex).ok()?;
+                term.push(u8::from_str_radix(hex, 16).ok()?);
+            }
+            _ => return None,
+        }
+    }
+    Some(term)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_the_text_format() {
+        let mut engine = Lattice::new();
+        for doc in ["café au lait", "a\\b path", "cafe"] {
+            engine.add(doc).unwrap();
+        }
+        engine.commit();
+        engine.add("uncommitted").unwrap();
+
+        let terms = TermDictionary::from_lattice(&engine);
+        assert_eq!(terms.postings(b"caf"), Some(&[0, 2][..]));
+        assert_eq!(terms.postings(&[b'a', b'f', 0xc3]), Some(&[0][..]));
+        assert_eq!(terms.postings(b"unc"), None);
+        assert!(terms.iter().is_sorted_by(|a, b| a.0 < b.0));
+
+        let mut file = Vec::new();
+        terms.write(&mut file).unwrap();
+        let text = String::from_utf8(file).unwrap();
+        assert!(text.starts_with("lattice-terms 1\n"));
+        assert!(text.contains("\naf\\xc3 0\n"));
+        assert!(text.contains("\na\\\\b 1\n"));
+        assert!(text.contains("\\x20"));
+        assert_eq!(TermDictionary::read(text.as_bytes()).unwrap(), terms);
+    }
+
+    #[test]
+    fn read_rejects_malformed_lines() {
+        let line_of = |text: &str| match TermDictionary::read(text.as_bytes()) {
+            Err(TermDictionaryError::Parse { line, .. }) => line,
+            other => panic!("expected a parse error, got {:?}", other),
+        };
+        assert_eq!(line_of(""), 1);
+        assert_eq!(line_of("lattice-terms 2\nabc 1\n"), 1);
+        assert_eq!(line_of("lattice-terms 1\nabc 1\nabc 2\n"), 3);
+        assert_eq!(line_of("lattice-terms 1\nabd 1\nabc 2\n"), 3);
+        assert_eq!(line_of("lattice-terms 1\n\n# comment\nabc 2 1\n"), 4);
+        assert_eq!(line_of("lattice-terms 1\nabc\n"), 2);
+        assert_eq!(line_of("lattice-terms 1\na\\x4 1\n"), 2);
+        assert_eq!(line_of("lattice-terms 1\nabc one\n"), 2);
+
+        let terms =
+            TermDictionary::read(&b"lattice-terms 1\n# comment\nh\\xc3\\xa9 4 9\n"[..]).unwrap();
+        assert_eq!(terms.len(), 1);
+        assert_eq!(terms.postings("hé".as_bytes()), Some(&[4, 9][..]));
+    }
+}
@@ -7,8 +7,40 @@
 
 pub mod analyzer;
 pub mod arena;
+#[cfg(feature = "tokio")]
+pub mod async_lattice;
+pub mod catalog;
+pub mod distance;
+pub mod eval;
+pub mod fusion;
 pub mod index;
+pub mod interop;
+pub mod pipeline;
+pub mod replay;
+#[cfg(feature = "resp")]
+pub mod resp;
+pub mod shared;
+#[cfg(feature = "testing")]
+pub mod testing;
 
-pub use analyzer::{Field, TextNormalizer, Tokenizer, TrigramExtractor};
-pub use arena::Arena;
-pub use index::{EngineMetrics, IndexStats, Lattice};
+pub use analyzer::{
+    DelimiterSet, DigitPolicy, EmojiPolicy, Field, LongTokenPolicy, NormalizerConfig,
+    TextNormalizer, TokenStats, Tokenizer, TokenizerConfig, TrigramExtractor, UnicodeForm,
+};
+#[cfg(feature = "language-detection")]
+pub use analyzer::{Language, LanguageNormalizers};
+pub use arena::{Arena, ArenaError};
+#[cfg(feature = "tokio")]
+pub use async_lattice::AsyncLattice;
+#[cfg(feature = "arrow")]
+pub use index::{ColumnMapping, ColumnarError, ColumnarReport};
+pub use index::{
+    CommitHandle, CommitPolicy, CommitReport, DocIdSet, DumpFormat, EngineMetrics, FrozenLattice,
+    ImpactIndex, IndexConfig, IndexDiff, IndexEvent, IndexLoadError, IndexStats, IngestError,
+    IntegrityIssue, LatencyHistogram, Lattice, LatticeBuilder, LengthNorm, MemoryBreakdown,
+    Metrics, MetricsSnapshot, MinimumShouldMatch, MinimumShouldMatchError, Namespace,
+    PostingLengths, ScoreContext, ScoringConfig, ScoringMode, SearchRequest, SearchResponse,
+    SearchScratch, Snapshot, SortBy, Suggestion, TieBreak, TrigramDictionary, TrigramStat,
+    VerifyReport, TIMESTAMP_KEY,
+};
+pub use shared::SharedLattice;
@@ -0,0 +1,269 @@
+//! Two-phase retrieve-then-rerank search.
+//!
+//! The trigram index is fast but coarse: it only knows how many query
+//! trigrams a document shares. A [`SearchPipeline`] asks the engine for more
+//! candidates than needed (`limit × oversample`) and passes them through one
+//! or more [`Reranker`] stages that can afford to look at the document text.
+//!
+//! ```text
+//! query ──▶ Lattice::search (N × limit) ──▶ stage 1 ──▶ stage 2 ──▶ top limit
+//! ```
+//!
+//! Each stage assigns a new score; candidates are re-sorted by it, with the
+//! previous stage's order breaking ties.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::pipeline::{EditDistanceReranker, SearchPipeline};
+//! use lattice_core::Lattice;
+//!
+//! let mut engine = Lattice::new();
+//! engine.add("hello world").unwrap();
+//! engine.add("help the world").unwrap();
+//!
+//! let pipeline = SearchPipeline::new()
+//!     .oversample(8)
+//!     .then(EditDistanceReranker)
+//!     .then(|_query: &str, doc: &str, score: f32| score / doc.len() as f32);
+//!
+//! let results = pipeline.search(&mut engine, "helo world", 10);
+//! assert_eq!(results[0].doc_id, 0);
+//! ```
+
+use crate::analyzer::tokenizer::{Field, Tokenizer};
+use crate::distance::levenshtein;
+use crate::index::Lattice;
+use lattice_types::SearchResult;
+
+/// A second-stage scoring function.
+///
+/// Implemented for any `Fn(&str, &str, f32) -> f32` closure, so a model
+/// call can be plugged in directly.
+pub trait Reranker {
+    /// Scores one candidate. Higher is better.
+    ///
+    /// `query` and `document` are both normalized; `previous` is the score
+    /// assigned by the preceding stage (or the trigram search).
+    fn score(&self, query: &str, document: &str, previous: f32) -> f32;
+}
+
+impl<F> Reranker for F
+where
+    F: Fn(&str, &str, f32) -> f32,
+{
+    #[inline]
+    fn score(&self, query: &str, document: &str, previous: f32) -> f32 {
+        self(query, document, previous)
+    }
+}
+
+/// Scores by edit-distance similarity between query and document tokens.
+///
+/// Each query token is matched to its closest document token; the score is
+/// the mean of `1 - distance / max_len` over query tokens (0.0-1.0).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EditDistanceReranker;
+
+impl Reranker for EditDistanceReranker {
+    fn score(&self, query: &str, document: &str, _previous: f32) -> f32 {
+        let doc_tokens = tokens(document);
+        let query_tokens = tokens(query);
+        if query_tokens.is_empty() || doc_tokens.is_empty() {
+            return 0.0;
+        }
+
+        let total: f32 = query_tokens
+            .iter()
+            .map(|q| {
+                doc_tokens
+                    .iter()
+                    .map(|d| {
+                        let max_len = q.chars().count().max(d.chars().count()).max(1);
+                        1.0 - levenshtein(q, d) as f32 / max_len as f32
+                    })
+                    .fold(0.0f32, f32::max)
+            })
+            .sum();
+
+        total / query_tokens.len() as f32
+    }
+}
+
+/// Scores by the fraction of query tokens that occur verbatim in the document.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TokenOverlapReranker;
+
+impl Reranker for TokenOverlapReranker {
+    fn score(&self, query: &str, document: &str, _previous: f32) -> f32 {
+        let doc_tokens = tokens(document);
+        let query_tokens = tokens(query);
+        if query_tokens.is_empty() {
+            return 0.0;
+        }
+
+        let found = query_tokens
+            .iter()
+            .filter(|q| doc_tokens.contains(q))
+            .count();
+        found as f32 / query_tokens.len() as f32
+    }
+}
+
+/// Retrieve-then-rerank search pipeline.
+pub struct SearchPipeline {
+    oversample: usize,
+    stages: Vec<Box<dyn Reranker>>,
+}
+
+impl Default for SearchPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SearchPipeline {
+    /// Default number of candidates fetched per requested result.
+    pub const DEFAULT_OVERSAMPLE: usize = 4;
+
+    /// Creates a pipeline with no rerank stages.
+    pub fn new() -> Self {
+        Self {
+            oversample: Self::DEFAULT_OVERSAMPLE,
+            stages: Vec::new(),
+        }
+    }
+
+    /// Sets how many first-stage candidates to fetch per requested result.
+    ///
+    /// A factor of 0 is treated as 1.
+    #[must_use]
+    pub fn oversample(mut self, factor: usize) -> Self {
+        self.oversample = factor.max(1);
+        self
+    }
+
+    /// Appends a rerank stage.
+    #[must_use]
+    pub fn then(mut self, reranker: impl Reranker + 'static) -> Self {
+        self.stages.push(Box::new(reranker));
+        self
+    }
+
+    /// Returns the number of rerank stages.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Returns true if the pipeline has no rerank stages.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Runs the query through the engine and every rerank stage.
+    pub fn search(&self, engine: &mut Lattice, query: &str, limit: usize) -> Vec<SearchResult> {
+        if limit == 0 {
+            return Vec::new();
+        }
+
+        let mut candidates = engine.search(query, limit.saturating_mul(self.oversample));
+        let query = engine.analyze_query(query);
+
+        for stage in &self.stages {
+            // Carry each candidate's previous rank so ties keep the prior order
+            let mut rescored: Vec<(SearchResult, usize)> = candidates
+                .iter()
+                .enumerate()
+                .map(|(rank, r)| {
+                    let document = engine.get(r.doc_id).unwrap_or("");
+                    let score = stage.score(&query, document, r.score);
+                    (SearchResult::new(r.doc_id, score), rank)
+                })
+                .collect();
+
+            rescored.sort_by(|a, b| b.0.score.total_cmp(&a.0.score).then(a.1.cmp(&b.1)));
+            candidates = rescored.into_iter().map(|(r, _)| r).collect();
+        }
+
+        candidates.truncate(limit);
+        candidates
+    }
+}
+
+fn tokens(text: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    Tokenizer::new(Field::Body).tokenize(text, |token, _, _| out.push(token));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(docs: &[&str]) -> Lattice {
+        let mut engine = Lattice::new();
+        for doc in docs {
+            engine.add(doc).expect("should add doc");
+        }
+        engine
+    }
+
+    #[test]
+    fn empty_pipeline_matches_search() {
+        let mut e = engine(&["hello world", "hello there!", "goodbye world"]);
+        let direct = e.search("hello", 2);
+        let piped = SearchPipeline::new().search(&mut e, "hello", 2);
+        assert_eq!(direct, piped);
+    }
+
+    #[test]
+    fn token_overlap_promotes_exact_tokens() {
+        let mut e = engine(&["hello worldwide", "hello world and a much longer tail"]);
+        assert_eq!(e.search("hello world", 10)[0].doc_id, 0);
+
+        let results =
+            SearchPipeline::new()
+                .then(TokenOverlapReranker)
+                .search(&mut e, "hello world", 10);
+        assert_eq!(results[0].doc_id, 1);
+        assert_eq!(results[0].score, 1.0);
+    }
+
+    #[test]
+    fn edit_distance_scores_typos() {
+        assert_eq!(EditDistanceReranker.score("hello", "hello world", 0.0), 1.0);
+        let typo = EditDistanceReranker.score("helo", "hello world", 0.0);
+        assert!(typo > 0.5 && typo < 1.0);
+        assert_eq!(EditDistanceReranker.score("", "hello", 0.0), 0.0);
+    }
+
+    #[test]
+    fn closures_are_rerankers_and_ties_keep_order() {
+        let mut e = engine(&["abc one", "abc two", "abc three"]);
+        let first_stage = e.search("abc", 10);
+
+        let flat = SearchPipeline::new().then(|_: &str, _: &str, _: f32| 1.0);
+        let results = flat.search(&mut e, "abc", 10);
+        let ids: Vec<_> = results.iter().map(|r| r.doc_id).collect();
+        let expected: Vec<_> = first_stage.iter().map(|r| r.doc_id).collect();
+        assert_eq!(ids, expected);
+
+        let by_len = SearchPipeline::new().then(|_: &str, doc: &str, _: f32| doc.len() as f32);
+        assert_eq!(by_len.search(&mut e, "abc", 1)[0].doc_id, 2);
+        assert_eq!(by_len.len(), 1);
+    }
+
+    #[test]
+    fn oversample_widens_candidate_pool() {
+        let mut e = engine(&["abc", "abc long document text here"]);
+        let prefer_long = |_: &str, doc: &str, _: f32| doc.len() as f32;
+
+        let narrow = SearchPipeline::new().oversample(1).then(prefer_long);
+        assert_eq!(narrow.search(&mut e, "abc", 1)[0].doc_id, 0);
+
+        let wide = SearchPipeline::new().oversample(2).then(prefer_long);
+        assert_eq!(wide.search(&mut e, "abc", 1)[0].doc_id, 1);
+    }
+}
@@ -0,0 +1,216 @@
+//! Query workload replay.
+//!
+//! [`replay`] runs a recorded query log against an index from several
+//! threads, each searching its own [`Snapshot`](crate::Snapshot), and
+//! records the latency of every query together with the documents it
+//! returned. Comparing two reports with [`ReplayReport::recall_against`]
+//! shows how much of a baseline configuration's results a candidate
+//! configuration keeps, so latency and quality regressions can be tracked
+//! together.
+//!
+//! The `replay` binary wraps this for saved indexes.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::replay::{replay, ReplayOptions};
+//! use lattice_core::Lattice;
+//! use lattice_types::SearchConfig;
+//!
+//! let mut engine = Lattice::new();
+//! for doc in ["hello world", "help wanted", "yellow hello"] {
+//!     engine.add(doc).unwrap();
+//! }
+//! let queries = ["hello", "helo", "wanted"];
+//! let options = ReplayOptions::new().concurrency(2);
+//!
+//! let baseline = replay(&engine, &queries, options);
+//! engine.set_search_config(SearchConfig::exact());
+//! let candidate = replay(&engine, &queries, options);
+//!
+//! assert_eq!(baseline.latency.len(), 3);
+//! assert!(candidate.recall_against(&baseline) <= 1.0);
+//! ```
+
+use crate::index::{LatencyHistogram, Lattice};
+use lattice_types::DocId;
+use std::time::{Duration, Instant};
+
+/// Options for [`replay`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayOptions {
+    /// Threads searching in parallel (at least 1).
+    pub concurrency: usize,
+    /// Results requested per query.
+    pub limit: usize,
+}
+
+impl ReplayOptions {
+    /// One thread, ten results per query.
+    pub const fn new() -> Self {
+        Self {
+            concurrency: 1,
+            limit: 10,
+        }
+    }
+
+    /// Sets the number of threads.
+    pub const fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets the number of results per query.
+    pub const fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Outcome of a [`replay`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayReport {
+    /// Latency of every query.
+    pub latency: LatencyHistogram,
+    /// Wall time of the whole replay.
+    pub wall_time: Duration,
+    /// Documents returned for each query, best first, in log order.
+    pub results: Vec<Vec<DocId>>,
+}
+
+impl ReplayReport {
+    /// Queries completed per second of wall time, across all threads.
+    pub fn queries_per_second(&self) -> f64 {
+        self.results.len() as f64 / self.wall_time.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Mean share of `baseline`'s results per query that this replay also
+    /// returned, in 0.0-1.0.
+    ///
+    /// Both replays must run the same log. Queries for which the baseline
+    /// returned nothing are left out; 1.0 if all are.
+    pub fn recall_against(&self, baseline: &ReplayReport) -> f64 {
+        let (mut total, mut counted) = (0.0, 0usize);
+        for (ours, theirs) in self.results.iter().zip(&baseline.results) {
+            if theirs.is_empty() {
+                continue;
+            }
+            let kept = theirs.iter().filter(|id| ours.contains(id)).count();
+            total += kept as f64 / theirs.len() as f64;
+            counted += 1;
+        }
+        if counted == 0 {
+            1.0
+        } else {
+            total / counted as f64
+        }
+    }
+}
+
+/// Latencies and (log index, results) of the queries one thread ran
+type WorkerOutcome = (LatencyHistogram, Vec<(usize, Vec<DocId>)>);
+
+/// Runs every query of `queries` against `engine` and reports latencies
+/// and results.
+///
+/// Queries are dealt round-robin to `options.concurrency` threads, each
+/// searching its own snapshot, so the engine itself is not modified.
+pub fn replay<Q: AsRef<str> + Sync>(
+    engine: &Lattice,
+    queries: &[Q],
+    options: ReplayOptions,
+) -> ReplayReport {
+    let threads = options.concurrency.clamp(1, queries.len().max(1));
+    let started = Instant::now();
+
+    let outcomes: Vec<WorkerOutcome> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|thread| {
+                let mut snapshot = engine.snapshot();
+                scope.spawn(move || {
+                    let mut latency = LatencyHistogram::new();
+                    let mut results = Vec::new();
+                    for (i, query) in queries.iter().enumerate().skip(thread).step_by(threads) {
+                        let started = Instant::now();
+                        let hits = snapshot.search(query.as_ref(), options.limit);
+                        latency.record(started.elapsed());
+                        results.push((i, hits.iter().map(|r| r.doc_id).collect()));
+                    }
+                    (latency, results)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().expect("replay worker panicked"))
+            .collect()
+    });
+
+    let mut report = ReplayReport {
+        wall_time: started.elapsed(),
+        results: vec![Vec::new(); queries.len()],
+        ..ReplayReport::default()
+    };
+    for (latency, results) in outcomes {
+        report.latency.merge(&latency);
+        for (i, ids) in results {
+            report.results[i] = ids;
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lattice_types::SearchConfig;
+
+    #[test]
+    fn concurrent_replay_matches_sequential_search() {
+        let mut engine = Lattice::new();
+        for i in 0..50 {
+            engine
+                .add(&format!("order {} shipped to warehouse {}", i, i % 7))
+                .unwrap();
+        }
+        let queries: Vec<String> = (0..20).map(|i| format!("warehouse {}", i % 9)).collect();
+
+        let sequential = replay(&engine, &queries, ReplayOptions::new().limit(5));
+        let parallel = replay(
+            &engine,
+            &queries,
+            ReplayOptions::new().limit(5).concurrency(4),
+        );
+        assert_eq!(parallel.latency.len(), 20);
+        assert_eq!(parallel.results, sequential.results);
+        assert_eq!(parallel.recall_against(&sequential), 1.0);
+        assert!(parallel.queries_per_second() > 0.0);
+
+        let expected: Vec<DocId> = engine
+            .search(&queries[3], 5)
+            .iter()
+            .map(|r| r.doc_id)
+            .collect();
+        assert_eq!(sequential.results[3], expected);
+
+        // Requiring every trigram drops the inexact matches
+        engine.set_search_config(SearchConfig {
+            min_overlap_ratio: 1.0,
+            ..SearchConfig::default()
+        });
+        let strict = replay(&engine, &["warehouse 12"], ReplayOptions::new());
+        let loose = ReplayReport {
+            results: vec![vec![0, 1]],
+            ..ReplayReport::default()
+        };
+        assert!(strict.results[0].is_empty());
+        assert_eq!(strict.recall_against(&loose), 0.0);
+        assert_eq!(loose.recall_against(&strict), 1.0);
+    }
+}
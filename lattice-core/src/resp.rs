@@ -0,0 +1,492 @@
+//! A Redis-protocol (RESP) front end.
+//!
+//! [`RespServer`] serves a [`SharedLattice`] over TCP using the Redis
+//! serialization protocol, so any Redis client library, or `redis-cli`,
+//! can index and search without a Rust dependency:
+//!
+//! | Command | Reply |
+//! |---------|-------|
+//! | `LAT.ADD text` | ID of the new document |
+//! | `LAT.SEARCH query [LIMIT n]` | Flat array of ID, score pairs, best first; `n` defaults to 10 |
+//! | `LAT.GET id` | The document's text, or nil |
+//! | `LAT.LEN` | Number of searchable documents |
+//! | `LAT.COMMIT` | `OK` once pending documents are searchable |
+//! | `PING [message]` | `PONG`, or the message |
+//! | `QUIT` | `OK`, then the connection is closed |
+//!
+//! Documents become searchable in batches, as for
+//! [`SharedLattice::add`]; send `LAT.COMMIT` to publish them at once.
+//! Scores are sent as bulk strings, like Redis sends sorted set scores.
+//!
+//! Each connection is served on its own thread, up to
+//! [`DEFAULT_MAX_CONNECTIONS`] at once unless set otherwise with
+//! [`RespServer::with_max_connections`]; clients beyond that are answered
+//! with an error and disconnected. Requests may be RESP arrays of bulk
+//! strings, as client libraries send them, or inline commands typed into
+//! a terminal. A malformed or oversized request is answered with an error
+//! and the connection closed.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::resp::RespServer;
+//! use lattice_core::SharedLattice;
+//! use std::io::{Read, Write};
+//! use std::net::TcpStream;
+//! use std::sync::Arc;
+//!
+//! let server = RespServer::bind("127.0.0.1:0", Arc::new(SharedLattice::default())).unwrap();
+//! let addr = server.local_addr().unwrap();
+//! std::thread::spawn(move || server.serve());
+//!
+//! let mut client = TcpStream::connect(addr).unwrap();
+//! client.write_all(b"*2\r\n$7\r\nLAT.ADD\r\n$11\r\nhello world\r\n").unwrap();
+//! client.write_all(b"LAT.COMMIT\r\nLAT.SEARCH hello\r\nQUIT\r\n").unwrap();
+//!
+//! let mut replies = String::new();
+//! client.read_to_string(&mut replies).unwrap();
+//! assert!(replies.starts_with(":0\r\n+OK\r\n*2\r\n:0\r\n$"));
+//! ```
+
+use crate::shared::SharedLattice;
+use lattice_types::DocId;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Most arguments accepted in one request.
+pub const MAX_ARGS: usize = 1024;
+
+/// Longest argument accepted, in bytes.
+pub const MAX_ARG_LENGTH: usize = 1 << 20;
+
+/// Most bytes of arguments accepted in one request.
+pub const MAX_REQUEST_LENGTH: usize = 4 << 20;
+
+/// Connections served at once unless set with
+/// [`RespServer::with_max_connections`].
+pub const DEFAULT_MAX_CONNECTIONS: usize = 256;
+
+/// Results returned by `LAT.SEARCH` without a `LIMIT`.
+pub const DEFAULT_LIMIT: usize = 10;
+
+/// Most results returned by one `LAT.SEARCH`.
+const MAX_LIMIT: usize = 10_000;
+
+/// A TCP server answering RESP commands against a [`SharedLattice`].
+pub struct RespServer {
+    listener: TcpListener,
+    engine: Arc<SharedLattice>,
+    max_connections: usize,
+    connections: Arc<AtomicUsize>,
+}
+
+impl RespServer {
+    /// Listens on `addr` for clients of `engine`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of binding the socket.
+    pub fn bind(addr: impl ToSocketAddrs, engine: Arc<SharedLattice>) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            engine,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            connections: Arc::new(AtomicUsize::new(0)),
+        })
+    }
+
+    /// Limits the connections served at once to `limit`. Clients beyond
+    /// it are answered with an error and disconnected.
+    #[must_use]
+    pub fn with_max_connections(mut self, limit: usize) -> Self {
+        self.max_connections = limit;
+        self
+    }
+
+    /// Returns the address the server listens on.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of querying the socket.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns the engine being served.
+    pub fn engine(&self) -> &Arc<SharedLattice> {
+        &self.engine
+    }
+
+    /// Accepts connections until accepting fails, serving each on its own
+    /// thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error that stopped the accept loop.
+    pub fn serve(&self) -> io::Result<()> {
+        loop {
+            let (mut stream, _) = self.listener.accept()?;
+            if self.connections.load(Ordering::Acquire) >= self.max_connections {
+                let _ = stream.write_all(b"-ERR max number of clients reached\r\n");
+                continue;
+            }
+            let slot = ConnectionSlot::new(&self.connections);
+            let engine = Arc::clone(&self.engine);
+            thread::spawn(move || {
+                let _slot = slot;
+                serve_stream(&engine, stream)
+            });
+        }
+    }
+}
+
+/// Counts a connection as open until dropped.
+struct ConnectionSlot(Arc<AtomicUsize>);
+
+impl ConnectionSlot {
+    fn new(connections: &Arc<AtomicUsize>) -> Self {
+        connections.fetch_add(1, Ordering::AcqRel);
+        Self(Arc::clone(connections))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn serve_stream(engine: &SharedLattice, stream: TcpStream) -> io::Result<()> {
+    let _ = stream.set_nodelay(true);
+    serve_connection(engine, stream.try_clone()?, stream)
+}
+
+/// Answers the requests read from `input` on `output` until the client
+/// sends `QUIT`, closes the connection or sends a malformed request.
+///
+/// [`RespServer`] calls this for every connection; it is public for
+/// serving other transports, such as Unix sockets.
+///
+/// # Errors
+///
+/// Returns errors of reading and writing, except for end of input.
+pub fn serve_connection(
+    engine: &SharedLattice,
+    input: impl Read,
+    output: impl Write,
+) -> io::Result<()> {
+    let mut input = BufReader::new(input);
+    let mut output = BufWriter::new(output);
+    loop {
+        // Flush only when no further pipelined request is waiting
+        if input.buffer().is_empty() {
+            output.flush()?;
+        }
+        let args = match read_request(&mut input) {
+            Ok(Some(args)) => args,
+            Ok(None) => break,
+            Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                Reply::Error(format!("ERR Protocol error: {error}")).write(&mut output)?;
+                break;
+            }
+            Err(error) => return Err(error),
+        };
+        if args.is_empty() {
+            continue;
+        }
+        let quit = args[0].eq_ignore_ascii_case(b"QUIT");
+        execute(engine, &args).write(&mut output)?;
+        if quit {
+            break;
+        }
+    }
+    output.flush()
+}
+
+/// A reply to one request.
+#[derive(Debug, PartialEq)]
+enum Reply {
+    Status(&'static str),
+    Error(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+
+impl Reply {
+    fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        match self {
+            Reply::Status(status) => write!(out, "+{status}\r\n"),
+            Reply::Error(message) => write!(out, "-{message}\r\n"),
+            Reply::Integer(n) => write!(out, ":{n}\r\n"),
+            Reply::Bulk(None) => out.write_all(b"$-1\r\n"),
+            Reply::Bulk(Some(bytes)) => {
+                write!(out, "${}\r\n", bytes.len())?;
+                out.write_all(bytes)?;
+                out.write_all(b"\r\n")
+            }
+            Reply::Array(items) => {
+                write!(out, "*{}\r\n", items.len())?;
+                items.iter().try_for_each(|item| item.write(out))
+            }
+        }
+    }
+
+    fn arity(command: &str) -> Reply {
+        Reply::Error(format!(
+            "ERR wrong number of arguments for '{command}' command"
+        ))
+    }
+}
+
+/// Runs one request.
+fn execute(engine: &SharedLattice, args: &[Vec<u8>]) -> Reply {
+    let name = String::from_utf8_lossy(&args[0]).to_ascii_lowercase();
+    let args = &args[1..];
+    match name.as_str() {
+        "ping" => match args {
+            [] => Reply::Status("PONG"),
+            [message] => Reply::Bulk(Some(message.clone())),
+            _ => Reply::arity(&name),
+        },
+        "quit" => Reply::Status("OK"),
+        // Sent by redis-cli on connecting; no command metadata is offered
+        "command" => Reply::Array(Vec::new()),
+        "lat.add" => {
+            let [text] = args else {
+                return Reply::arity(&name);
+            };
+            let Ok(text) = std::str::from_utf8(text) else {
+                return Reply::Error("ERR document is not valid UTF-8".into());
+            };
+            match engine.add(text) {
+                Ok(doc_id) => Reply::Integer(doc_id as i64),
+                Err(error) => Reply::Error(format!("ERR {error}")),
+            }
+        }
+        "lat.search" => {
+            let (query, limit) = match args {
+                [query] => (query, DEFAULT_LIMIT),
+                [query, option, limit] if option.eq_ignore_ascii_case(b"LIMIT") => {
+                    match parse_number::<usize>(limit) {
+                        Some(limit) => (query, limit.min(MAX_LIMIT)),
+                        None => return Reply::Error("ERR limit is not a valid count".into()),
+                    }
+                }
+                [_, _, _] => return Reply::Error("ERR syntax error".into()),
+                _ => return Reply::arity(&name),
+            };
+            let query = String::from_utf8_lossy(query);
+            let results = engine.search(&query, limit);
+            let mut items = Vec::with_capacity(results.len() * 2);
+            for result in results {
+                items.push(Reply::Integer(result.doc_id as i64));
+                items.push(Reply::Bulk(Some(result.score.to_string().into_bytes())));
+            }
+            Reply::Array(items)
+        }
+        "lat.get" => {
+            let [doc_id] = args else {
+                return Reply::arity(&name);
+            };
+            match parse_number::<DocId>(doc_id) {
+                Some(doc_id) => Reply::Bulk(
+                    engine
+                        .snapshot()
+                        .get(doc_id)
+                        .map(|text| text.as_bytes().to_vec()),
+                ),
+                None => Reply::Error("ERR document ID is not a valid integer".into()),
+            }
+        }
+        "lat.len" if args.is_empty() => Reply::Integer(engine.snapshot().len() as i64),
+        "lat.commit" if args.is_empty() => {
+            engine.commit();
+            Reply::Status("OK")
+        }
+        "lat.len" | "lat.commit" => Reply::arity(&name),
+        _ => {
+            // The name is echoed back, so it must not break the reply line
+            let shown: String = name.chars().filter(|c| !c.is_control()).take(64).collect();
+            Reply::Error(format!("ERR unknown command '{shown}'"))
+        }
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(bytes: &[u8]) -> Option<T> {
+    std::str::from_utf8(bytes).ok()?.parse().ok()
+}
+
+fn protocol_error(reason: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason)
+}
+
+/// Reads one request's arguments; `None` at end of input.
+fn read_request(input: &mut impl BufRead) -> io::Result<Option<Vec<Vec<u8>>>> {
+    let Some(line) = read_line(input)? else {
+        return Ok(None);
+    };
+    let Some(count) = line.strip_prefix(b"*") else {
+        // An inline command
+        let line = String::from_utf8_lossy(&line);
+        return Ok(Some(
+            line.split_ascii_whitespace()
+                .map(|arg| arg.as_bytes().to_vec())
+                .collect(),
+        ));
+    };
+    let count =
+        parse_number::<i64>(count).ok_or_else(|| protocol_error("invalid multibulk length"))?;
+    if count > MAX_ARGS as i64 {
+        return Err(protocol_error("too many arguments"));
+    }
+    let mut args = Vec::with_capacity(count.max(0) as usize);
+    let mut total = 0;
+    for _ in 0..count.max(0) {
+        let line = read_line(input)?.ok_or_else(|| protocol_error("unexpected end of request"))?;
+        let length = line
+            .strip_prefix(b"$")
+            .and_then(parse_number::<usize>)
+            .ok_or_else(|| protocol_error("expected a bulk string"))?;
+        if length > MAX_ARG_LENGTH {
+            return Err(protocol_error("argument too long"));
+        }
+        total += length;
+        if total > MAX_REQUEST_LENGTH {
+            return Err(protocol_error("request too long"));
+        }
+        let mut arg = vec![0; length + 2];
+        input
+            .read_exact(&mut arg)
+            .map_err(|error| match error.kind() {
+                io::ErrorKind::UnexpectedEof => protocol_error("unexpected end of request"),
+                _ => error,
+            })?;
+        if !arg.ends_with(b"\r\n") {
+            return Err(protocol_error("bulk string not terminated by CRLF"));
+        }
+        arg.truncate(length);
+        args.push(arg);
+    }
+    Ok(Some(args))
+}
+
+/// Reads a line without its line ending; `None` at end of input.
+fn read_line(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut line = Vec::new();
+    let read = input
+        .take(MAX_ARG_LENGTH as u64 + 2)
+        .read_until(b'\n', &mut line)?;
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.pop() != Some(b'\n') {
+        return Err(protocol_error("line too long or not terminated"));
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    Ok(Some(line))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(engine: &SharedLattice, input: &[u8]) -> String {
+        let mut output = Vec::new();
+        serve_connection(engine, input, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn answers_commands() {
+        let engine = SharedLattice::default();
+        let replies = session(
+            &engine,
+            b"*2\r\n$7\r\nlat.add\r\n$11\r\nhello world\r\n\
+              *2\r\n$7\r\nLAT.ADD\r\n$12\r\nhello\r\nthere\r\n\
+              LAT.COMMIT\r\n\
+              LAT.SEARCH hello LIMIT 1\r\n\
+              LAT.GET 1\r\nLAT.GET 9\r\nLAT.LEN\r\nPING\r\n\n",
+        );
+        // Line breaks are normalized away when indexing
+        let score = engine.search("hello", 1)[0].score.to_string();
+        assert_eq!(
+            replies,
+            format!(
+                ":0\r\n:1\r\n+OK\r\n*2\r\n:0\r\n${}\r\n{score}\r\n\
+                 $11\r\nhello there\r\n$-1\r\n:2\r\n+PONG\r\n",
+                score.len()
+            )
+        );
+
+        let errors = session(
+            &engine,
+            b"LAT.ADD\r\nLAT.SEARCH x TOP 1\r\nLAT.SEARCH x LIMIT -1\r\nLAT.GET x\r\nFLUSHALL\r\n",
+        );
+        assert_eq!(
+            errors,
+            "-ERR wrong number of arguments for 'lat.add' command\r\n\
+             -ERR syntax error\r\n-ERR limit is not a valid count\r\n\
+             -ERR document ID is not a valid integer\r\n-ERR unknown command 'flushall'\r\n"
+        );
+    }
+
+    #[test]
+    fn stops_at_quit_or_malformed_requests() {
+        let engine = SharedLattice::default();
+        assert_eq!(session(&engine, b"QUIT\r\nPING\r\n"), "+OK\r\n");
+        assert_eq!(
+            session(&engine, b"*1\r\n$4\r\nPING\r\n*1\r\n$9\r\nPING\r\n"),
+            "+PONG\r\n-ERR Protocol error: unexpected end of request\r\n"
+        );
+        assert_eq!(
+            session(&engine, b"*9999\r\n"),
+            "-ERR Protocol error: too many arguments\r\n"
+        );
+        assert_eq!(
+            session(&engine, b"*1\r\n$4\r\nPINGxx"),
+            "-ERR Protocol error: bulk string not terminated by CRLF\r\n"
+        );
+        assert_eq!(engine.snapshot().len(), 0);
+    }
+
+    #[test]
+    fn limits_request_size_and_connections() {
+        let engine = SharedLattice::default();
+        let args = MAX_REQUEST_LENGTH / MAX_ARG_LENGTH + 1;
+        let mut request = format!("*{args}\r\n").into_bytes();
+        for _ in 0..args {
+            request.extend_from_slice(format!("${MAX_ARG_LENGTH}\r\n").as_bytes());
+            request.resize(request.len() + MAX_ARG_LENGTH, b'a');
+            request.extend_from_slice(b"\r\n");
+        }
+        assert_eq!(
+            session(&engine, &request),
+            "-ERR Protocol error: request too long\r\n"
+        );
+
+        let server = RespServer::bind("127.0.0.1:0", Arc::new(engine))
+            .unwrap()
+            .with_max_connections(1);
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.serve());
+
+        let mut first = TcpStream::connect(addr).unwrap();
+        first.write_all(b"PING\r\n").unwrap();
+        let mut pong = [0; 7];
+        first.read_exact(&mut pong).unwrap();
+        assert_eq!(&pong, b"+PONG\r\n");
+
+        let mut refused = String::new();
+        TcpStream::connect(addr)
+            .unwrap()
+            .read_to_string(&mut refused)
+            .unwrap();
+        assert_eq!(refused, "-ERR max number of clients reached\r\n");
+    }
+}
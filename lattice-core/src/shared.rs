@@ -0,0 +1,272 @@
+//! A thread-safe engine handle with batched commits.
+//!
+//! A [`SharedLattice`] pairs a writable [`Lattice`] behind a mutex with a
+//! published, read-only copy of it. Searches take the published copy (an
+//! `Arc` clone under a momentary read lock) and then run without holding
+//! any lock, so readers never wait for indexing. Writers add to the
+//! private engine; every `batch_size` documents, or on an explicit
+//! [`commit`](SharedLattice::commit), the engine is committed and a fresh
+//! copy is published. Publishing shares the posting lists and document
+//! pages, so it costs O(documents) for the small per-document arrays.
+//!
+//! ```text
+//!  writers ──▶ Mutex<Lattice> ──commit + publish──▶ RwLock<Arc<FrozenLattice>>
+//!                                                           │
+//!  readers ◀──────────────── Arc clone, search unlocked ◀───┘
+//! ```
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::SharedLattice;
+//! use std::sync::Arc;
+//!
+//! let shared = Arc::new(SharedLattice::with_batch_size(Default::default(), 100));
+//! let writer = {
+//!     let shared = Arc::clone(&shared);
+//!     std::thread::spawn(move || {
+//!         for i in 0..250 {
+//!             shared.add(&format!("document {}", i)).unwrap();
+//!         }
+//!     })
+//! };
+//! // Readers see whole batches as they are published
+//! let _ = shared.search("document", 10);
+//! writer.join().unwrap();
+//!
+//! assert_eq!(shared.pending(), 50);
+//! shared.commit();
+//! assert_eq!(shared.snapshot().len(), 250);
+//! ```
+
+use crate::index::{CommitReport, FrozenLattice, IndexStats, Lattice, SearchRequest};
+use lattice_types::{DocId, DocumentError, SearchEngine, SearchResult};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+
+/// Documents added between automatic commits by default.
+pub const DEFAULT_BATCH_SIZE: usize = 1000;
+
+/// A [`Lattice`] shared between threads, with lock-free searching of
+/// published snapshots and batched commits.
+///
+/// Put it in an `Arc` to share it. Documents become searchable when the
+/// batch they belong to is published.
+pub struct SharedLattice {
+    writer: Mutex<Writer>,
+    published: RwLock<Arc<FrozenLattice>>,
+    batch_size: usize,
+}
+
+struct Writer {
+    engine: Lattice,
+    /// Documents added since the last publish
+    pending: usize,
+}
+
+impl Default for SharedLattice {
+    fn default() -> Self {
+        Self::new(Lattice::new())
+    }
+}
+
+impl SharedLattice {
+    /// Shares `engine`, committing automatically every
+    /// [`DEFAULT_BATCH_SIZE`] documents.
+    pub fn new(engine: Lattice) -> Self {
+        Self::with_batch_size(engine, DEFAULT_BATCH_SIZE)
+    }
+
+    /// Shares `engine`, committing automatically every `batch_size`
+    /// documents (at least one).
+    ///
+    /// Anything already uncommitted in `engine` is committed and published
+    /// first.
+    pub fn with_batch_size(engine: Lattice, batch_size: usize) -> Self {
+        let mut writer = Writer { engine, pending: 0 };
+        writer.engine.commit();
+        let published = RwLock::new(Arc::new(Self::publication(&writer.engine)));
+        Self {
+            writer: Mutex::new(writer),
+            published,
+            batch_size: batch_size.max(1),
+        }
+    }
+
+    /// Returns the latest published snapshot.
+    ///
+    /// It stays valid, and unchanged, for as long as it is held.
+    pub fn snapshot(&self) -> Arc<FrozenLattice> {
+        let published = self
+            .published
+            .read()
+            .unwrap_or_else(PoisonError::into_inner);
+        Arc::clone(&published)
+    }
+
+    /// Searches the latest published snapshot. See [`Lattice::search`].
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchResult> {
+        self.snapshot().search(query, limit)
+    }
+
+    /// Searches the latest published snapshot with per-query options. See
+    /// [`Lattice::search_request`].
+    pub fn search_request(&self, request: &SearchRequest<'_>) -> Vec<SearchResult> {
+        self.snapshot().search_request(request)
+    }
+
+    /// Adds a document, publishing it and its batch if the batch is full.
+    /// See [`Lattice::add`].
+    ///
+    /// # Errors
+    ///
+    /// As for [`Lattice::add`].
+    pub fn add(&self, content: &str) -> Result<DocId, DocumentError> {
+        let mut writer = self.lock_writer();
+        let doc_id = writer.engine.add(content)?;
+        writer.pending += 1;
+        self.publish_if_due(&mut writer);
+        Ok(doc_id)
+    }
+
+    /// Adds several documents under one lock, publishing if the batch
+    /// fills up. See [`Lattice::add_batch`].
+    pub fn add_batch(&self, contents: &[&str]) -> (usize, usize, Option<DocumentError>) {
+        let mut writer = self.lock_writer();
+        let outcome = writer.engine.add_batch(contents);
+        writer.pending += outcome.0;
+        self.publish_if_due(&mut writer);
+        outcome
+    }
+
+    /// Runs `f` with exclusive access to the writable engine, then commits
+    /// and publishes.
+    ///
+    /// Use this for anything not covered by the dedicated methods, such as
+    /// boosts or configuration changes.
+    pub fn with<R>(&self, f: impl FnOnce(&mut Lattice) -> R) -> R {
+        let mut writer = self.lock_writer();
+        let result = f(&mut writer.engine);
+        self.publish(&mut writer);
+        result
+    }
+
+    /// Commits pending documents and publishes them now.
+    pub fn commit(&self) -> CommitReport {
+        let mut writer = self.lock_writer();
+        self.publish(&mut writer)
+    }
+
+    /// Returns the number of documents added but not yet published.
+    pub fn pending(&self) -> usize {
+        self.lock_writer().pending
+    }
+
+    /// Returns the automatic commit interval, in documents.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Returns the writable engine, with any pending documents still
+    /// uncommitted.
+    pub fn into_inner(self) -> Lattice {
+        let writer = self
+            .writer
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        writer.engine
+    }
+
+    fn lock_writer(&self) -> MutexGuard<'_, Writer> {
+        // The engine holds no invariants across a panicking add
+        self.writer.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn publish_if_due(&self, writer: &mut Writer) {
+        if writer.pending >= self.batch_size {
+            self.publish(writer);
+        }
+    }
+
+    fn publish(&self, writer: &mut Writer) -> CommitReport {
+        let report = writer.engine.commit();
+        writer.pending = 0;
+        let publication = Arc::new(Self::publication(&writer.engine));
+        *self
+            .published
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = publication;
+        report
+    }
+
+    /// A read-only copy of a fully committed engine, counting its queries
+    /// in the engine's metrics.
+    fn publication(engine: &Lattice) -> FrozenLattice {
+        let mut copy = engine.clone();
+        copy.counters = Arc::clone(&engine.counters);
+        FrozenLattice::from_committed(copy)
+    }
+}
+
+/// Searches, [`len`](SearchEngine::len) and [`stats`](SearchEngine::stats)
+/// see the latest published snapshot.
+impl SearchEngine for SharedLattice {
+    type Stats = IndexStats;
+
+    fn add(&mut self, content: &str) -> Result<DocId, DocumentError> {
+        SharedLattice::add(self, content)
+    }
+
+    fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+        SharedLattice::search(self, query, limit)
+    }
+
+    fn len(&self) -> usize {
+        self.snapshot().len()
+    }
+
+    fn stats(&self) -> IndexStats {
+        self.snapshot().stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn publishes_whole_batches() {
+        let shared = Arc::new(SharedLattice::with_batch_size(Lattice::new(), 10));
+        let before = shared.snapshot();
+
+        std::thread::scope(|s| {
+            for t in 0..3 {
+                let shared = &shared;
+                s.spawn(move || {
+                    for i in 0..7 {
+                        shared.add(&format!("writer {} entry {}", t, i)).unwrap();
+                    }
+                });
+            }
+            s.spawn(|| {
+                for _ in 0..20 {
+                    // Every published snapshot holds a multiple of the batch
+                    assert_eq!(shared.snapshot().len() % 10, 0);
+                }
+            });
+        });
+
+        assert_eq!(shared.snapshot().len(), 20);
+        assert_eq!(shared.pending(), 1);
+        assert!(before.is_empty());
+        assert_eq!(shared.search("entry", 30).len(), 20);
+
+        shared.commit();
+        assert_eq!(shared.search("entry", 30).len(), 21);
+        assert_eq!(shared.with(|engine| engine.set_boost(0, 2.0)), Ok(()));
+        assert_eq!(shared.search("entry", 1)[0].doc_id, 0);
+        assert!(shared.snapshot().metrics().queries_executed >= 3);
+
+        let engine = Arc::try_unwrap(shared).ok().unwrap().into_inner();
+        assert_eq!(engine.len(), 21);
+    }
+}
@@ -0,0 +1,616 @@
+//! Helpers for property-testing code built on the engine.
+//!
+//! A [`Generator`] produces valid documents and realistic queries from a
+//! seed, and the `check_*` functions assert the invariants every search
+//! must keep, whatever the corpus and query:
+//!
+//! | Invariant      | Checked by                                          |
+//! |----------------|-----------------------------------------------------|
+//! | subset         | every result is a document of the index, once       |
+//! | bounded        | no more than `limit` results                        |
+//! | finite         | every score is a finite number                      |
+//! | ordered        | scores never increase down the list                 |
+//! | deterministic  | the same query returns the same results again       |
+//!
+//! The checks work on any [`SearchEngine`] and return the violation
+//! rather than panicking, so they fit proptest, quickcheck or a plain
+//! seeded loop alike.
+//!
+//! [`NaiveEngine`] is a reference to test against: it scans every document
+//! for every query, so it never prunes a candidate.
+//! [`check_finds_substring_matches`] uses it to check that an engine finds
+//! every document containing the query verbatim.
+//!
+//! ## Example
+//!
+//! ```
+//! use lattice_core::testing::{check_search, Generator};
+//! use lattice_core::Lattice;
+//!
+//! for seed in 0..20 {
+//!     let mut gen = Generator::new(seed);
+//!     let mut engine = Lattice::new();
+//!     for doc in gen.corpus(50) {
+//!         engine.add(&doc).unwrap();
+//!     }
+//!     for _ in 0..10 {
+//!         let query = gen.query();
+//!         check_search(&mut engine, &query, 10).unwrap();
+//!     }
+//! }
+//! ```
+
+use crate::analyzer::TextNormalizer;
+use crate::distance::levenshtein_bounded;
+use crate::index::{contains_invalid_controls, SplitMix64, MAX_DOCUMENT_LENGTH};
+use core::fmt;
+use lattice_types::{DocId, DocumentError, SearchEngine, SearchResult};
+
+/// Words documents and queries are mostly drawn from, so queries overlap
+/// the corpus often enough to exercise ranking.
+const VOCABULARY: &[&str] = &[
+    "apple", "banana", "cherry", "lattice", "search", "engine", "trigram", "index", "query",
+    "document", "rust", "python", "fuzzy", "match", "score", "posting", "commit", "vector",
+    "network", "server", "client", "window", "garden", "river", "mountain", "yellow", "purple",
+    "seven", "hundred", "quick", "brown", "jumps", "over", "lazy", "dog", "hello", "world",
+];
+
+/// Words per generated document.
+const DOCUMENT_WORDS: core::ops::RangeInclusive<usize> = 3..=16;
+
+/// Seeded generator of valid documents and queries.
+///
+/// The same seed always produces the same sequence.
+pub struct Generator {
+    rng: SplitMix64,
+}
+
+impl Generator {
+    /// Creates a generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: SplitMix64(seed),
+        }
+    }
+
+    /// Returns a word: usually from a small shared vocabulary, sometimes
+    /// random lowercase letters.
+    pub fn word(&mut self) -> String {
+        if self.rng.up_to(3) > 0 {
+            return VOCABULARY[self.rng.up_to(VOCABULARY.len() - 1)].to_owned();
+        }
+        let len = 3 + self.rng.up_to(7);
+        (0..len)
+            .map(|_| (b'a' + self.rng.up_to(25) as u8) as char)
+            .collect()
+    }
+
+    /// Returns a document the engine accepts: a few words of printable
+    /// ASCII, now and then with capitals, digits or punctuation.
+    pub fn document(&mut self) -> String {
+        let words = DOCUMENT_WORDS.start()
+            + self
+                .rng
+                .up_to(DOCUMENT_WORDS.end() - DOCUMENT_WORDS.start());
+        let mut doc = String::new();
+        for i in 0..words {
+            if i > 0 {
+                doc.push(if self.rng.up_to(9) == 0 { ',' } else { ' ' });
+                if doc.ends_with(',') {
+                    doc.push(' ');
+                }
+            }
+            let mut word = self.word();
+            match self.rng.up_to(11) {
+                0 => word[..1].make_ascii_uppercase(),
+                1 => word.push_str(&self.rng.up_to(999).to_string()),
+                _ => {}
+            }
+            doc.push_str(&word);
+        }
+        doc
+    }
+
+    /// Returns `n` documents.
+    pub fn corpus(&mut self, n: usize) -> Vec<String> {
+        (0..n).map(|_| self.document()).collect()
+    }
+
+    /// Returns a query: one to three words, sometimes cut to a prefix or
+    /// given a typo, and occasionally too short to match anything.
+    pub fn query(&mut self) -> String {
+        if self.rng.up_to(19) == 0 {
+            let word = self.word();
+            return word[..self.rng.up_to(2)].to_owned();
+        }
+        let words = 1 + self.rng.up_to(2);
+        let mut query = Vec::with_capacity(words);
+        for _ in 0..words {
+            let mut word = self.word();
+            match self.rng.up_to(5) {
+                0 => word.truncate(3 + self.rng.up_to(word.len() - 3)),
+                1 => {
+                    // Swap two neighbouring letters; words are ASCII
+                    let at = self.rng.up_to(word.len() - 2);
+                    let mut bytes = word.into_bytes();
+                    bytes.swap(at, at + 1);
+                    word = String::from_utf8(bytes).unwrap_or_default();
+                }
+                _ => {}
+            }
+            query.push(word);
+        }
+        query.join(" ")
+    }
+}
+
+/// A broken search invariant, reported by the `check_*` functions.
+#[derive(Debug, Clone, PartialEq)]
+pub enum InvariantViolation {
+    /// A result names a document the index does not hold.
+    UnknownDocument {
+        /// The result's document.
+        doc_id: DocId,
+        /// Documents in the index.
+        len: usize,
+    },
+    /// A document appears more than once in the results.
+    DuplicateDocument {
+        /// The repeated document.
+        doc_id: DocId,
+    },
+    /// More results than the limit asked for.
+    TooManyResults {
+        /// Results returned.
+        returned: usize,
+        /// The limit.
+        limit: usize,
+    },
+    /// A score is NaN or infinite.
+    NonFiniteScore {
+        /// The result's document.
+        doc_id: DocId,
+        /// Its score.
+        score: f32,
+    },
+    /// A result scores higher than the one before it.
+    Unordered {
+        /// Position of the out-of-order result.
+        position: usize,
+    },
+    /// Repeating a query changed its results.
+    Nondeterministic {
+        /// The query.
+        query: String,
+        /// Results of the first run.
+        first: Vec<SearchResult>,
+        /// Results of the second run.
+        second: Vec<SearchResult>,
+    },
+    /// A document containing the query verbatim was not returned.
+    MissedMatch {
+        /// The query.
+        query: String,
+        /// The missed document.
+        doc_id: DocId,
+    },
+}
+
+impl fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::UnknownDocument { doc_id, len } => write!(
+                f,
+                "result names document {} of an index holding {}",
+                doc_id, len
+            ),
+            InvariantViolation::DuplicateDocument { doc_id } => {
+                write!(f, "document {} is returned more than once", doc_id)
+            }
+            InvariantViolation::TooManyResults { returned, limit } => {
+                write!(f, "{} results returned for a limit of {}", returned, limit)
+            }
+            InvariantViolation::NonFiniteScore { doc_id, score } => {
+                write!(f, "document {} has non-finite score {}", doc_id, score)
+            }
+            InvariantViolation::Unordered { position } => {
+                write!(
+                    f,
+                    "result {} scores higher than the one before it",
+                    position
+                )
+            }
+            InvariantViolation::Nondeterministic {
+                query,
+                first,
+                second,
+            } => write!(
+                f,
+                "query {:?} returned {} results, then {} different ones",
+                query,
+                first.len(),
+                second.len()
+            ),
+            InvariantViolation::MissedMatch { query, doc_id } => write!(
+                f,
+                "document {} contains {:?} but was not returned",
+                doc_id, query
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvariantViolation {}
+
+/// Checks the results of one search of `engine` with `limit`: a bounded,
+/// duplicate-free subset of the index, with finite scores in descending
+/// order.
+///
+/// # Errors
+///
+/// Returns the first invariant the results break.
+pub fn check_results<E: SearchEngine>(
+    engine: &E,
+    results: &[SearchResult],
+    limit: usize,
+) -> Result<(), InvariantViolation> {
+    if results.len() > limit {
+        return Err(InvariantViolation::TooManyResults {
+            returned: results.len(),
+            limit,
+        });
+    }
+    let len = engine.len();
+    let mut seen = Vec::with_capacity(results.len());
+    for (position, result) in results.iter().enumerate() {
+        if result.doc_id as usize >= len {
+            return Err(InvariantViolation::UnknownDocument {
+                doc_id: result.doc_id,
+                len,
+            });
+        }
+        if seen.contains(&result.doc_id) {
+            return Err(InvariantViolation::DuplicateDocument {
+                doc_id: result.doc_id,
+            });
+        }
+        seen.push(result.doc_id);
+        if !result.score.is_finite() {
+            return Err(InvariantViolation::NonFiniteScore {
+                doc_id: result.doc_id,
+                score: result.score,
+            });
+        }
+        if position > 0 && result.score > results[position - 1].score {
+            return Err(InvariantViolation::Unordered { position });
+        }
+    }
+    Ok(())
+}
+
+/// Runs `query` twice and checks both runs return the same results.
+///
+/// # Errors
+///
+/// Returns [`InvariantViolation::Nondeterministic`] if they differ.
+pub fn check_deterministic<E: SearchEngine>(
+    engine: &mut E,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchResult>, InvariantViolation> {
+    let first = engine.search(query, limit);
+    let second = engine.search(query, limit);
+    if !same_results(&first, &second) {
+        return Err(InvariantViolation::Nondeterministic {
+            query: query.to_owned(),
+            first,
+            second,
+        });
+    }
+    Ok(first)
+}
+
+/// Searches `engine` and checks every invariant, returning the results.
+///
+/// # Errors
+///
+/// Returns the first invariant the search breaks.
+pub fn check_search<E: SearchEngine>(
+    engine: &mut E,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchResult>, InvariantViolation> {
+    let results = check_deterministic(engine, query, limit)?;
+    check_results(engine, &results, limit)?;
+    Ok(results)
+}
+
+/// Compares scores bit for bit, so NaN scores still compare equal to
+/// themselves and a non-finite score is reported as such rather than as
+/// nondeterminism.
+fn same_results(a: &[SearchResult], b: &[SearchResult]) -> bool {
+    a.len() == b.len()
+        && a.iter()
+            .zip(b)
+            .all(|(x, y)| x.doc_id == y.doc_id && x.score.to_bits() == y.score.to_bits())
+}
+
+/// Checks that `engine` returns every document of `reference` that
+/// contains `query` verbatim, after normalization.
+///
+/// Such a document holds every trigram of the query, so no amount of
+/// candidate pruning may lose it. Both engines must hold the same
+/// documents, added in the same order.
+///
+/// # Errors
+///
+/// Returns [`InvariantViolation::MissedMatch`] for the first document
+/// missed.
+pub fn check_finds_substring_matches<E: SearchEngine>(
+    engine: &mut E,
+    reference: &NaiveEngine,
+    query: &str,
+) -> Result<(), InvariantViolation> {
+    let expected = reference.substring_matches(query);
+    if expected.is_empty() {
+        return Ok(());
+    }
+    let found: Vec<DocId> = engine
+        .search(query, reference.len())
+        .iter()
+        .map(|r| r.doc_id)
+        .collect();
+    match expected.into_iter().find(|doc_id| !found.contains(doc_id)) {
+        Some(doc_id) => Err(InvariantViolation::MissedMatch {
+            query: query.to_owned(),
+            doc_id,
+        }),
+        None => Ok(()),
+    }
+}
+
+/// A reference engine that scans every document for every query.
+///
+/// Documents and queries are normalized like the engine's defaults. A
+/// document matches if it contains the query, scoring 1.0, or if some of
+/// the query's words are within the edit distance of one of its words,
+/// scoring the average closeness of the query words, below 1.0. Results
+/// are ordered by score, then document ID.
+///
+/// It accepts and rejects the same documents as a default [`Lattice`],
+/// so both assign the same IDs.
+///
+/// [`Lattice`]: crate::Lattice
+///
+/// # Example
+///
+/// ```
+/// use lattice_core::testing::NaiveEngine;
+/// use lattice_types::SearchEngine;
+///
+/// let mut naive = NaiveEngine::new();
+/// naive.add("Hello World").unwrap();
+/// naive.add("help wanted").unwrap();
+///
+/// assert_eq!(naive.substring_matches("hello"), vec![0]);
+/// let results = naive.search("helo", 10);
+/// assert_eq!(results.len(), 2);
+/// assert!(results[0].score < 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct NaiveEngine {
+    documents: Vec<String>,
+    normalizer: TextNormalizer,
+    max_edit_distance: usize,
+}
+
+impl Default for NaiveEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NaiveEngine {
+    /// Creates an empty engine matching words within two edits, like the
+    /// default [`SearchConfig`](lattice_types::SearchConfig).
+    pub fn new() -> Self {
+        Self::with_max_edit_distance(2)
+    }
+
+    /// Creates an empty engine matching words within `max_edit_distance`
+    /// edits; 0 matches whole words exactly.
+    pub fn with_max_edit_distance(max_edit_distance: u8) -> Self {
+        Self {
+            documents: Vec::new(),
+            normalizer: TextNormalizer::new(),
+            max_edit_distance: max_edit_distance as usize,
+        }
+    }
+
+    /// Returns a document's normalized text.
+    pub fn get(&self, doc_id: DocId) -> Option<&str> {
+        self.documents.get(doc_id as usize).map(String::as_str)
+    }
+
+    /// Returns, in ascending order, the documents containing the
+    /// normalized query, or none if it is shorter than a trigram.
+    pub fn substring_matches(&self, query: &str) -> Vec<DocId> {
+        let query = self.normalizer.normalize(query);
+        if query.len() < 3 {
+            return Vec::new();
+        }
+        (0..self.documents.len())
+            .filter(|&i| self.documents[i].contains(&query))
+            .map(|i| i as DocId)
+            .collect()
+    }
+
+    /// Scores one document against a normalized query.
+    fn score(&self, document: &str, query: &str) -> Option<f32> {
+        if document.contains(query) {
+            return Some(1.0);
+        }
+        let max = self.max_edit_distance;
+        let mut total = 0.0;
+        let mut words = 0;
+        for word in query.split(' ') {
+            words += 1;
+            let closest = document
+                .split(' ')
+                .filter_map(|candidate| levenshtein_bounded(word, candidate, max))
+                .min();
+            if let Some(distance) = closest {
+                total += 1.0 - (distance as f32 + 1.0) / (max as f32 + 2.0);
+            }
+        }
+        (total > 0.0).then(|| total / words as f32)
+    }
+}
+
+impl SearchEngine for NaiveEngine {
+    type Stats = usize;
+
+    /// Adds a document, rejecting what a default [`Lattice`](crate::Lattice)
+    /// rejects.
+    fn add(&mut self, content: &str) -> Result<DocId, DocumentError> {
+        if content.len() > MAX_DOCUMENT_LENGTH {
+            return Err(DocumentError::TooLarge {
+                size: content.len(),
+                max_size: MAX_DOCUMENT_LENGTH,
+            });
+        }
+        if contains_invalid_controls(content) {
+            return Err(DocumentError::InvalidInput {
+                reason: "control characters (0x00-0x1F excluding whitespace) are not allowed",
+            });
+        }
+        let doc_id =
+            DocId::try_from(self.documents.len()).map_err(|_| DocumentError::CapacityExceeded)?;
+        self.documents.push(self.normalizer.normalize(content));
+        Ok(doc_id)
+    }
+
+    fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+        let query = self.normalizer.normalize(query);
+        if query.len() < 3 {
+            return Vec::new();
+        }
+        let mut results: Vec<SearchResult> = self
+            .documents
+            .iter()
+            .enumerate()
+            .filter_map(|(i, doc)| {
+                let score = self.score(doc, &query)?;
+                Some(SearchResult::new(i as DocId, score))
+            })
+            .collect();
+        results.sort_unstable_by(|a, b| b.score.total_cmp(&a.score).then(a.doc_id.cmp(&b.doc_id)));
+        results.truncate(limit);
+        results
+    }
+
+    fn len(&self) -> usize {
+        self.documents.len()
+    }
+
+    /// Returns the bytes of normalized text held.
+    fn stats(&self) -> usize {
+        self.documents.iter().map(String::len).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Lattice;
+
+    #[test]
+    fn generated_corpora_keep_every_invariant() {
+        for seed in 0..10 {
+            let mut gen = Generator::new(seed);
+            let mut engine = Lattice::new();
+            for doc in gen.corpus(100) {
+                engine.add(&doc).unwrap();
+            }
+            for _ in 0..20 {
+                let query = gen.query();
+                check_search(&mut engine, &query, 5).unwrap();
+            }
+        }
+        assert_eq!(Generator::new(7).corpus(3), Generator::new(7).corpus(3));
+    }
+
+    #[test]
+    fn reports_broken_invariants() {
+        let mut engine = Lattice::new();
+        engine.add("hello world").unwrap();
+        engine.add("hello there").unwrap();
+
+        let ok = [SearchResult::new(1, 2.0), SearchResult::new(0, 1.0)];
+        assert_eq!(check_results(&engine, &ok, 2), Ok(()));
+        assert_eq!(
+            check_results(&engine, &ok, 1),
+            Err(InvariantViolation::TooManyResults {
+                returned: 2,
+                limit: 1
+            })
+        );
+        assert_eq!(
+            check_results(&engine, &[SearchResult::new(2, 1.0)], 5),
+            Err(InvariantViolation::UnknownDocument { doc_id: 2, len: 2 })
+        );
+        assert_eq!(
+            check_results(&engine, &[ok[1], ok[0]], 5),
+            Err(InvariantViolation::Unordered { position: 1 })
+        );
+        assert!(matches!(
+            check_results(&engine, &[SearchResult::new(0, f32::NAN)], 5),
+            Err(InvariantViolation::NonFiniteScore { doc_id: 0, .. })
+        ));
+        assert_eq!(
+            check_results(&engine, &[ok[0], ok[0]], 5),
+            Err(InvariantViolation::DuplicateDocument { doc_id: 1 })
+        );
+    }
+
+    #[test]
+    fn lattice_finds_every_substring_match_of_the_naive_engine() {
+        for seed in 0..10 {
+            let mut gen = Generator::new(seed);
+            let mut engine = Lattice::new();
+            let mut naive = NaiveEngine::new();
+            for doc in gen.corpus(200) {
+                assert_eq!(engine.add(&doc), naive.add(&doc));
+            }
+            for _ in 0..30 {
+                let query = gen.query();
+                check_finds_substring_matches(&mut engine, &naive, &query).unwrap();
+                check_search(&mut naive, &query, 10).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn naive_engine_ranks_substrings_above_fuzzy_matches() {
+        let mut naive = NaiveEngine::with_max_edit_distance(1);
+        naive.add("the quick brown fox").unwrap();
+        naive.add("quack brown").unwrap();
+        naive.add("nothing related").unwrap();
+        assert_eq!(
+            naive.add("bell\u{7}"),
+            Err(DocumentError::InvalidInput {
+                reason: "control characters (0x00-0x1F excluding whitespace) are not allowed",
+            })
+        );
+
+        let results = naive.search("QUICK brown", 10);
+        assert_eq!(
+            results.iter().map(|r| r.doc_id).collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(results[0].score, 1.0);
+        assert!(results[1].score < 1.0);
+        assert!(naive.search("zz", 10).is_empty());
+        assert_eq!(naive.get(1), Some("quack brown"));
+    }
+}
@@ -0,0 +1,78 @@
+//! CRC-32 checksums for on-disk data.
+//!
+//! Uses the IEEE 802.3 polynomial (the one used by zlib, gzip and PNG), so
+//! checksums can be cross-checked with standard tools.
+
+/// Reflected IEEE 802.3 polynomial.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// Byte-at-a-time lookup table, built at compile time.
+const TABLE: [u32; 256] = build_table();
+
+const fn build_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the CRC-32 of `data`.
+///
+/// # Example
+/// ```
+/// use lattice_types::checksum::crc32;
+///
+/// assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+/// ```
+#[inline]
+pub fn crc32(data: &[u8]) -> u32 {
+    Crc32::new().update(data).finish()
+}
+
+/// Incremental CRC-32 hasher.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32 {
+    state: u32,
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Crc32 {
+    /// Creates a hasher with no input.
+    #[inline]
+    pub const fn new() -> Self {
+        Self { state: !0 }
+    }
+
+    /// Feeds more bytes into the checksum.
+    #[inline]
+    pub fn update(mut self, data: &[u8]) -> Self {
+        for &byte in data {
+            self.state = TABLE[((self.state ^ byte as u32) & 0xFF) as usize] ^ (self.state >> 8);
+        }
+        self
+    }
+
+    /// Returns the checksum of all bytes fed so far.
+    #[inline]
+    pub const fn finish(self) -> u32 {
+        !self.state
+    }
+}
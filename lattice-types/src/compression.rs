@@ -1,7 +1,22 @@
 //! Compression utilities for integer sequences.
 //!
 //! Provides delta encoding and variable-length integer compression
-//! optimized for sorted sequences like document ID lists.
+//! optimized for sorted sequences like document ID lists. The varint codec
+//! also covers `u64` and, through zigzag encoding, signed values, for
+//! data such as position deltas, timestamps and quantized scores.
+//!
+//! For decode-heavy data such as posting lists read on every query, the
+//! Stream VByte layout ([`stream_vbyte_encode`]) moves each group of four
+//! values' lengths into a control byte, so [`stream_vbyte_decode`] can
+//! expand four values per SIMD shuffle instead of testing continuation bits
+//! byte by byte. [`decode_varints`] speeds up the classic layout by
+//! decoding runs of one-byte varints eight at a time.
+//!
+//! [`VarintWriter`] and [`VarintReader`] apply the same encoding to any
+//! [`io::Write`] or [`io::Read`], so sequences can be streamed to and from
+//! disk without building a `Vec<u8>` first.
+
+use std::io;
 
 /// Error type for compression/decompression operations.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -27,6 +42,19 @@ impl core::fmt::Display for CompressionError {
     }
 }
 
+impl core::error::Error for CompressionError {}
+
+impl From<CompressionError> for io::Error {
+    fn from(e: CompressionError) -> Self {
+        let kind = match e {
+            CompressionError::NotSorted => io::ErrorKind::InvalidInput,
+            CompressionError::BufferTooSmall => io::ErrorKind::UnexpectedEof,
+            _ => io::ErrorKind::InvalidData,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
 /// Encodes a sorted sequence of u32 values using delta encoding.
 ///
 /// Delta encoding stores the difference between consecutive values rather
@@ -237,13 +265,7 @@ pub fn decompress_sorted(input: &[u8], output: &mut Vec<u32>) -> Result<(), Comp
 
     // Decode varints to get deltas
     let mut deltas = Vec::new();
-    let mut i = 0;
-
-    while i < input.len() {
-        let (value, bytes_read) = decode_varint(&input[i..])?;
-        deltas.push(value);
-        i += bytes_read;
-    }
+    decode_varints(input, &mut deltas)?;
 
     // Apply delta decoding
     delta_decode(&deltas, output)?;
@@ -256,6 +278,125 @@ pub const fn max_varint_len() -> usize {
     5 // u32::MAX requires 5 bytes in varint encoding
 }
 
+/// Returns the maximum bytes needed to encode a u64 as varint.
+pub const fn max_varint_u64_len() -> usize {
+    10 // u64::MAX requires 10 bytes in varint encoding
+}
+
+/// Returns the number of bytes `value` takes as a varint.
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::varint_len;
+///
+/// assert_eq!(varint_len(0), 1);
+/// assert_eq!(varint_len(128), 2);
+/// assert_eq!(varint_len(u64::MAX), 10);
+/// ```
+#[inline]
+pub const fn varint_len(value: u64) -> usize {
+    (64 - (value | 1).leading_zeros() as usize).div_ceil(7)
+}
+
+/// Maps a signed value to an unsigned one so that values near zero, of
+/// either sign, encode to short varints: 0, -1, 1, -2, ... become
+/// 0, 1, 2, 3, ...
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::{zigzag_decode, zigzag_encode};
+///
+/// assert_eq!(zigzag_encode(-1), 1);
+/// assert_eq!(zigzag_encode(1), 2);
+/// assert_eq!(zigzag_decode(zigzag_encode(i64::MIN)), i64::MIN);
+/// ```
+#[inline]
+pub const fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Reverses [`zigzag_encode`].
+#[inline]
+pub const fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Encodes a u64 value as a varint, like [`encode_varint`]. Takes up to
+/// [`max_varint_u64_len`] bytes.
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::{decode_varint_u64, encode_varint_u64};
+///
+/// let mut buf = [0u8; 10];
+/// let len = encode_varint_u64(1 << 40, &mut buf);
+/// assert_eq!(decode_varint_u64(&buf[..len]), Ok((1 << 40, 6)));
+/// ```
+pub fn encode_varint_u64(mut value: u64, buf: &mut [u8]) -> usize {
+    let mut i = 0;
+
+    while value >= 0x80 {
+        buf[i] = (value as u8) | 0x80;
+        value >>= 7;
+        i += 1;
+    }
+
+    buf[i] = value as u8;
+    i + 1
+}
+
+/// Decodes a u64 varint, returning the value and the bytes consumed.
+///
+/// # Errors
+/// Returns `CompressionError::BufferTooSmall` if the buffer ends inside
+/// the varint and `CompressionError::InvalidVarint` if it overflows a u64.
+pub fn decode_varint_u64(buf: &[u8]) -> Result<(u64, usize), CompressionError> {
+    let mut result: u64 = 0;
+
+    for (i, &byte) in buf.iter().enumerate() {
+        let shift = 7 * i as u32;
+        if !varint_byte_fits(byte, shift, 64) {
+            return Err(CompressionError::InvalidVarint);
+        }
+        result |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok((result, i + 1));
+        }
+    }
+
+    Err(CompressionError::BufferTooSmall)
+}
+
+/// Encodes a signed value as a zigzag varint.
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::{decode_varint_i64, encode_varint_i64};
+///
+/// let mut buf = [0u8; 10];
+/// let len = encode_varint_i64(-3, &mut buf);
+/// assert_eq!(&buf[..len], &[5]);
+/// assert_eq!(decode_varint_i64(&buf[..len]), Ok((-3, 1)));
+/// ```
+pub fn encode_varint_i64(value: i64, buf: &mut [u8]) -> usize {
+    encode_varint_u64(zigzag_encode(value), buf)
+}
+
+/// Decodes a zigzag varint written by [`encode_varint_i64`].
+///
+/// # Errors
+/// As for [`decode_varint_u64`].
+pub fn decode_varint_i64(buf: &[u8]) -> Result<(i64, usize), CompressionError> {
+    decode_varint_u64(buf).map(|(value, len)| (zigzag_decode(value), len))
+}
+
+/// Returns false if `byte`, found at bit offset `shift`, carries bits
+/// beyond a `bits`-bit value (or continues past its last byte).
+#[inline]
+fn varint_byte_fits(byte: u8, shift: u32, bits: u32) -> bool {
+    shift + 7 <= bits || (shift < bits && (byte as u64) < 1 << (bits - shift))
+}
+
 /// Estimates the compressed size of a sorted sequence.
 ///
 /// This is a rough estimate based on average delta size.
@@ -281,3 +422,540 @@ pub fn estimate_compressed_size(values: &[u32]) -> usize {
     // First value is always 5 bytes (worst case)
     5 + (values.len() - 1) * bytes_per_value
 }
+
+/// Streams varints, and delta-encoded sorted sequences of them, to an
+/// [`io::Write`].
+///
+/// Every value is written straight through; wrap unbuffered sinks such as
+/// files in an [`io::BufWriter`].
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::{VarintReader, VarintWriter};
+///
+/// let mut writer = VarintWriter::new(Vec::new());
+/// writer.write_sorted(&[100, 105, 110]).unwrap();
+/// writer.write_u32(7).unwrap();
+/// let bytes = writer.into_inner();
+/// assert_eq!(bytes, [3, 100, 5, 5, 7]);
+///
+/// let mut reader = VarintReader::new(bytes.as_slice());
+/// let mut values = Vec::new();
+/// reader.read_sorted(&mut values).unwrap();
+/// assert_eq!(values, [100, 105, 110]);
+/// assert_eq!(reader.read_u32().unwrap(), Some(7));
+/// assert_eq!(reader.read_u32().unwrap(), None);
+/// ```
+#[derive(Debug)]
+pub struct VarintWriter<W> {
+    inner: W,
+    /// Base for the next [`write_delta`](Self::write_delta)
+    previous: u32,
+    written: u64,
+}
+
+impl<W: io::Write> VarintWriter<W> {
+    /// Wraps `inner`, starting a delta sequence at 0.
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            previous: 0,
+            written: 0,
+        }
+    }
+
+    /// Writes `value` as a varint and returns the bytes written.
+    pub fn write_u32(&mut self, value: u32) -> io::Result<usize> {
+        self.write_u64(value as u64)
+    }
+
+    /// Writes `value` as a varint and returns the bytes written.
+    pub fn write_u64(&mut self, value: u64) -> io::Result<usize> {
+        let mut buf = [0u8; 10];
+        let len = encode_varint_u64(value, &mut buf);
+        self.inner.write_all(&buf[..len])?;
+        self.written += len as u64;
+        Ok(len)
+    }
+
+    /// Writes `value` as a zigzag varint and returns the bytes written.
+    pub fn write_i64(&mut self, value: i64) -> io::Result<usize> {
+        self.write_u64(zigzag_encode(value))
+    }
+
+    /// Writes the difference between `value` and the previous delta-encoded
+    /// value (0 at the start of a sequence).
+    ///
+    /// # Errors
+    /// Returns an [`io::ErrorKind::InvalidInput`] error wrapping
+    /// `CompressionError::NotSorted` if `value` is below the previous one;
+    /// nothing is written.
+    pub fn write_delta(&mut self, value: u32) -> io::Result<usize> {
+        let delta = value
+            .checked_sub(self.previous)
+            .ok_or(CompressionError::NotSorted)?;
+        let len = self.write_u32(delta)?;
+        self.previous = value;
+        Ok(len)
+    }
+
+    /// Starts a new delta sequence at 0.
+    pub fn reset_delta(&mut self) {
+        self.previous = 0;
+    }
+
+    /// Writes a sorted sequence as its length followed by its deltas, the
+    /// layout [`VarintReader::read_sorted`] expects, and returns the bytes
+    /// written.
+    ///
+    /// # Errors
+    /// As for [`write_delta`](Self::write_delta); values are checked before
+    /// anything is written. Sequences longer than `u32::MAX` values are
+    /// rejected with [`io::ErrorKind::InvalidInput`].
+    pub fn write_sorted(&mut self, values: &[u32]) -> io::Result<usize> {
+        if values.windows(2).any(|w| w[1] < w[0]) {
+            return Err(CompressionError::NotSorted.into());
+        }
+        let count = u32::try_from(values.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "sequence too long"))?;
+        let mut len = self.write_u32(count)?;
+        self.reset_delta();
+        for &value in values {
+            len += self.write_delta(value)?;
+        }
+        self.reset_delta();
+        Ok(len)
+    }
+
+    /// Returns the total number of bytes written.
+    pub fn bytes_written(&self) -> u64 {
+        self.written
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+
+    /// Returns a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads varints, and delta-encoded sorted sequences of them, from an
+/// [`io::Read`]. The counterpart of [`VarintWriter`].
+///
+/// Bytes are read one at a time; wrap unbuffered sources such as files in
+/// an [`io::BufReader`].
+#[derive(Debug)]
+pub struct VarintReader<R> {
+    inner: R,
+    /// Base for the next [`read_delta`](Self::read_delta)
+    previous: u32,
+    read: u64,
+}
+
+impl<R: io::Read> VarintReader<R> {
+    /// Wraps `inner`, starting a delta sequence at 0.
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            previous: 0,
+            read: 0,
+        }
+    }
+
+    /// Reads one varint, or returns `None` at the end of the input.
+    ///
+    /// # Errors
+    /// Returns an [`io::ErrorKind::UnexpectedEof`] error if the input ends
+    /// inside a varint and [`io::ErrorKind::InvalidData`] if the varint
+    /// overflows a `u32`.
+    pub fn read_u32(&mut self) -> io::Result<Option<u32>> {
+        Ok(self.read_varint(32)?.map(|value| value as u32))
+    }
+
+    /// Reads one u64 varint, or returns `None` at the end of the input.
+    ///
+    /// # Errors
+    /// As for [`read_u32`](Self::read_u32), with the varint limited to a
+    /// `u64`.
+    pub fn read_u64(&mut self) -> io::Result<Option<u64>> {
+        self.read_varint(64)
+    }
+
+    /// Reads one zigzag varint, or returns `None` at the end of the input.
+    ///
+    /// # Errors
+    /// As for [`read_u64`](Self::read_u64).
+    pub fn read_i64(&mut self) -> io::Result<Option<i64>> {
+        Ok(self.read_varint(64)?.map(zigzag_decode))
+    }
+
+    /// Reads a varint of at most `bits` bits.
+    fn read_varint(&mut self, bits: u32) -> io::Result<Option<u64>> {
+        let mut result: u64 = 0;
+        let mut shift = 0;
+        loop {
+            let Some(byte) = self.next_byte()? else {
+                return if shift == 0 {
+                    Ok(None)
+                } else {
+                    Err(CompressionError::BufferTooSmall.into())
+                };
+            };
+            if !varint_byte_fits(byte, shift, bits) {
+                return Err(CompressionError::InvalidVarint.into());
+            }
+            result |= ((byte & 0x7F) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(Some(result));
+            }
+            shift += 7;
+        }
+    }
+
+    /// Reads one delta and returns it added to the previous value of the
+    /// sequence, or `None` at the end of the input.
+    ///
+    /// # Errors
+    /// As for [`read_u32`](Self::read_u32), plus
+    /// [`io::ErrorKind::InvalidData`] if the sum overflows a `u32`.
+    pub fn read_delta(&mut self) -> io::Result<Option<u32>> {
+        let Some(delta) = self.read_u32()? else {
+            return Ok(None);
+        };
+        self.previous = self
+            .previous
+            .checked_add(delta)
+            .ok_or(CompressionError::InvalidVarint)?;
+        Ok(Some(self.previous))
+    }
+
+    /// Starts a new delta sequence at 0.
+    pub fn reset_delta(&mut self) {
+        self.previous = 0;
+    }
+
+    /// Reads a sequence written by [`VarintWriter::write_sorted`],
+    /// replacing the contents of `output`.
+    ///
+    /// # Errors
+    /// As for [`read_delta`](Self::read_delta); ending before the
+    /// announced number of values is [`io::ErrorKind::UnexpectedEof`].
+    pub fn read_sorted(&mut self, output: &mut Vec<u32>) -> io::Result<()> {
+        output.clear();
+        let Some(count) = self.read_u32()? else {
+            return Err(CompressionError::BufferTooSmall.into());
+        };
+        self.reset_delta();
+        for _ in 0..count {
+            match self.read_delta()? {
+                Some(value) => output.push(value),
+                None => return Err(CompressionError::BufferTooSmall.into()),
+            }
+        }
+        self.reset_delta();
+        Ok(())
+    }
+
+    /// Returns the total number of bytes consumed.
+    pub fn bytes_read(&self) -> u64 {
+        self.read
+    }
+
+    /// Returns a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn next_byte(&mut self) -> io::Result<Option<u8>> {
+        let mut byte = [0u8; 1];
+        loop {
+            match self.inner.read(&mut byte) {
+                Ok(0) => return Ok(None),
+                Ok(_) => {
+                    self.read += 1;
+                    return Ok(Some(byte[0]));
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Decodes every varint in `input`, appending the values to `output`.
+///
+/// Equivalent to calling [`decode_varint`] in a loop, but runs of
+/// one-byte varints (values below 128, the common case for posting list
+/// deltas) are decoded eight at a time.
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::decode_varints;
+///
+/// let mut values = Vec::new();
+/// decode_varints(&[1, 2, 3, 0x96, 0x01, 4], &mut values).unwrap();
+/// assert_eq!(values, [1, 2, 3, 150, 4]);
+/// ```
+///
+/// # Errors
+/// As for [`decode_varint`]; values decoded before the error are kept.
+pub fn decode_varints(input: &[u8], output: &mut Vec<u32>) -> Result<(), CompressionError> {
+    output.reserve(input.len());
+    let mut i = 0;
+
+    while i < input.len() {
+        if let Some(chunk) = input.get(i..i + 8) {
+            let word = u64::from_le_bytes(chunk.try_into().unwrap_or_default());
+            if word & 0x8080_8080_8080_8080 == 0 {
+                output.extend(chunk.iter().map(|&b| b as u32));
+                i += 8;
+                continue;
+            }
+        }
+        let (value, bytes_read) = decode_varint(&input[i..])?;
+        output.push(value);
+        i += bytes_read;
+    }
+
+    Ok(())
+}
+
+/// Byte length of each value in a Stream VByte group, by control byte.
+const STREAM_VBYTE_LENGTHS: [u8; 256] = {
+    let mut table = [0u8; 256];
+    let mut control = 0;
+    while control < 256 {
+        let mut lane = 0;
+        while lane < 4 {
+            table[control] += ((control >> (2 * lane)) & 3) as u8 + 1;
+            lane += 1;
+        }
+        control += 1;
+    }
+    table
+};
+
+/// Shuffle masks spreading a Stream VByte group's data bytes over four
+/// little-endian u32 lanes, by control byte; 0x80 zeroes a byte.
+#[cfg(any(
+    target_arch = "x86_64",
+    all(target_arch = "aarch64", target_feature = "neon")
+))]
+const STREAM_VBYTE_SHUFFLES: [[u8; 16]; 256] = {
+    let mut table = [[0x80u8; 16]; 256];
+    let mut control = 0;
+    while control < 256 {
+        let mut source = 0u8;
+        let mut lane = 0;
+        while lane < 4 {
+            let len = ((control >> (2 * lane)) & 3) + 1;
+            let mut byte = 0;
+            while byte < len {
+                table[control][lane * 4 + byte] = source;
+                source += 1;
+                byte += 1;
+            }
+            lane += 1;
+        }
+        control += 1;
+    }
+    table
+};
+
+/// Returns the bytes `value` takes in the Stream VByte layout, minus one.
+#[inline]
+fn stream_vbyte_code(value: u32) -> u8 {
+    match value {
+        0..=0xFF => 0,
+        0x100..=0xFFFF => 1,
+        0x1_0000..=0xFF_FFFF => 2,
+        _ => 3,
+    }
+}
+
+/// Appends `values` to `output` in the Stream VByte layout and returns the
+/// bytes written.
+///
+/// The layout is `values.len().div_ceil(4)` control bytes, each holding
+/// the byte lengths of four values (two bits apiece, lowest first),
+/// followed by the values' 1-4 little-endian bytes. The count is not
+/// stored; pass it to [`stream_vbyte_decode`]. Delta-encode sorted input
+/// first (see [`delta_encode`]) to keep the values small.
+///
+/// # Example
+/// ```
+/// use lattice_types::compression::{stream_vbyte_decode, stream_vbyte_encode};
+///
+/// let values = [1u32, 300, 70_000, u32::MAX, 5];
+/// let mut bytes = Vec::new();
+/// let written = stream_vbyte_encode(&values, &mut bytes);
+/// assert_eq!(written, 2 + (1 + 2 + 3 + 4 + 1));
+///
+/// let mut decoded = Vec::new();
+/// assert_eq!(stream_vbyte_decode(&bytes, values.len(), &mut decoded), Ok(written));
+/// assert_eq!(decoded, values);
+/// ```
+pub fn stream_vbyte_encode(values: &[u32], output: &mut Vec<u8>) -> usize {
+    let start = output.len();
+    let control_len = values.len().div_ceil(4);
+    output.resize(start + control_len, 0);
+
+    for (group, chunk) in values.chunks(4).enumerate() {
+        let mut control = 0u8;
+        for (lane, &value) in chunk.iter().enumerate() {
+            let code = stream_vbyte_code(value);
+            control |= code << (2 * lane);
+            output.extend_from_slice(&value.to_le_bytes()[..code as usize + 1]);
+        }
+        output[start + group] = control;
+    }
+
+    output.len() - start
+}
+
+/// Decodes `count` values written by [`stream_vbyte_encode`] from the
+/// start of `input`, replacing the contents of `output`, and returns the
+/// bytes consumed.
+///
+/// Uses SSSE3 (x86_64, detected at run time) or NEON (aarch64) to expand
+/// four values per shuffle, with a scalar fallback elsewhere.
+///
+/// # Errors
+/// Returns `CompressionError::BufferTooSmall` if `input` is shorter than
+/// `count` values need.
+pub fn stream_vbyte_decode(
+    input: &[u8],
+    count: usize,
+    output: &mut Vec<u32>,
+) -> Result<usize, CompressionError> {
+    output.clear();
+    let control_len = count.div_ceil(4);
+    if input.len() < control_len {
+        return Err(CompressionError::BufferTooSmall);
+    }
+    let (controls, data) = input.split_at(control_len);
+
+    // Check the data length up front so the decoders need no bounds checks
+    // on the values themselves; the last group may be partial
+    let full_groups = count / 4;
+    let mut data_len: usize = controls[..full_groups]
+        .iter()
+        .map(|&c| STREAM_VBYTE_LENGTHS[c as usize] as usize)
+        .sum();
+    if let Some(&control) = controls.get(full_groups) {
+        for lane in 0..count % 4 {
+            data_len += ((control >> (2 * lane)) & 3) as usize + 1;
+        }
+    }
+    if data.len() < data_len {
+        return Err(CompressionError::BufferTooSmall);
+    }
+
+    output.reserve(count);
+    let (groups, mut pos) = decode_groups_simd(&controls[..full_groups], data, output);
+
+    // Groups too close to the end for a 16-byte load, and the partial one
+    for (group, &control) in controls.iter().enumerate().skip(groups) {
+        let lanes = if group < full_groups { 4 } else { count % 4 };
+        for lane in 0..lanes {
+            let len = ((control >> (2 * lane)) & 3) as usize + 1;
+            let mut bytes = [0u8; 4];
+            bytes[..len].copy_from_slice(&data[pos..pos + len]);
+            output.push(u32::from_le_bytes(bytes));
+            pos += len;
+        }
+    }
+
+    Ok(control_len + pos)
+}
+
+/// Decodes whole Stream VByte groups with SIMD where available; returns
+/// the groups decoded and the data bytes consumed.
+#[allow(unused_variables)]
+fn decode_groups_simd(controls: &[u8], data: &[u8], output: &mut Vec<u32>) -> (usize, usize) {
+    #[cfg(target_arch = "x86_64")]
+    if std::is_x86_feature_detected!("ssse3") {
+        // SAFETY: SSSE3 support was just checked.
+        return unsafe { x86::decode(controls, data, output) };
+    }
+
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        // SAFETY: NEON is enabled at compile time.
+        return unsafe { neon::decode(controls, data, output) };
+    }
+
+    #[allow(unreachable_code)]
+    (0, 0)
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::{STREAM_VBYTE_LENGTHS, STREAM_VBYTE_SHUFFLES};
+    use core::arch::x86_64::*;
+
+    /// Decodes whole groups while a 16-byte load stays within `data`;
+    /// returns the groups decoded and the data bytes consumed.
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn decode(
+        controls: &[u8],
+        data: &[u8],
+        output: &mut Vec<u32>,
+    ) -> (usize, usize) {
+        let mut pos = 0;
+        let mut lanes = [0u32; 4];
+        for (group, &control) in controls.iter().enumerate() {
+            if pos + 16 > data.len() {
+                return (group, pos);
+            }
+            let bytes = _mm_loadu_si128(data.as_ptr().add(pos).cast());
+            let shuffle = _mm_loadu_si128(STREAM_VBYTE_SHUFFLES[control as usize].as_ptr().cast());
+            _mm_storeu_si128(lanes.as_mut_ptr().cast(), _mm_shuffle_epi8(bytes, shuffle));
+            output.extend_from_slice(&lanes);
+            pos += STREAM_VBYTE_LENGTHS[control as usize] as usize;
+        }
+        (controls.len(), pos)
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use super::{STREAM_VBYTE_LENGTHS, STREAM_VBYTE_SHUFFLES};
+    use core::arch::aarch64::*;
+
+    /// Decodes whole groups while a 16-byte load stays within `data`;
+    /// returns the groups decoded and the data bytes consumed.
+    pub(super) unsafe fn decode(
+        controls: &[u8],
+        data: &[u8],
+        output: &mut Vec<u32>,
+    ) -> (usize, usize) {
+        let mut pos = 0;
+        let mut lanes = [0u32; 4];
+        for (group, &control) in controls.iter().enumerate() {
+            if pos + 16 > data.len() {
+                return (group, pos);
+            }
+            let bytes = vld1q_u8(data.as_ptr().add(pos));
+            let shuffle = vld1q_u8(STREAM_VBYTE_SHUFFLES[control as usize].as_ptr());
+            vst1q_u8(lanes.as_mut_ptr().cast(), vqtbl1q_u8(bytes, shuffle));
+            output.extend_from_slice(&lanes);
+            pos += STREAM_VBYTE_LENGTHS[control as usize] as usize;
+        }
+        (controls.len(), pos)
+    }
+}
@@ -7,8 +7,45 @@ use core::fmt;
 /// Documents are identified by a 32-bit unsigned integer.
 /// With u32::MAX (~4 billion) documents, this provides sufficient
 /// capacity for most use cases while keeping memory overhead low.
+#[cfg(not(feature = "large-corpus"))]
 pub type DocId = u32;
 
+/// Unique document identifier.
+///
+/// The `large-corpus` feature widens document identifiers to 64 bits for
+/// corpora beyond u32::MAX documents, at the cost of doubling the size of
+/// every posting.
+#[cfg(feature = "large-corpus")]
+pub type DocId = u64;
+
+/// A generation-qualified document reference.
+///
+/// DocIds are assigned sequentially and restart at 0 when an index is
+/// cleared, so a bare `DocId` held by a caller may silently refer to a
+/// different document later. A handle also records the index generation it
+/// was issued in; resolving it against a newer generation fails instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DocHandle {
+    /// Index generation the handle was issued in.
+    pub generation: u32,
+    /// Document identifier within that generation.
+    pub doc_id: DocId,
+}
+
+impl DocHandle {
+    /// Creates a new handle.
+    #[inline(always)]
+    pub const fn new(generation: u32, doc_id: DocId) -> Self {
+        Self { generation, doc_id }
+    }
+}
+
+impl fmt::Display for DocHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.generation, self.doc_id)
+    }
+}
+
 /// Errors that can occur when adding a document to the index.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DocumentError {
@@ -31,6 +68,22 @@ pub enum DocumentError {
         /// Description of the invalid content.
         reason: &'static str,
     },
+    /// No document with this ID exists in the index.
+    NotFound {
+        /// The requested document ID.
+        doc_id: DocId,
+    },
+    /// A handle was issued by an earlier generation of the index.
+    StaleHandle {
+        /// The stale handle.
+        handle: DocHandle,
+        /// The index's current generation.
+        current_generation: u32,
+    },
+    /// The index cannot store any more documents.
+    CapacityExceeded,
+    /// The engine is read-only and cannot take documents.
+    ReadOnly,
 }
 
 impl fmt::Display for DocumentError {
@@ -53,6 +106,25 @@ impl fmt::Display for DocumentError {
             DocumentError::InvalidInput { reason } => {
                 write!(f, "document contains invalid input: {}", reason)
             }
+            DocumentError::NotFound { doc_id } => {
+                write!(f, "document {} not found", doc_id)
+            }
+            DocumentError::StaleHandle {
+                handle,
+                current_generation,
+            } => {
+                write!(
+                    f,
+                    "stale document handle {} (current generation: {})",
+                    handle, current_generation
+                )
+            }
+            DocumentError::CapacityExceeded => {
+                write!(f, "index capacity exceeded")
+            }
+            DocumentError::ReadOnly => {
+                write!(f, "index is read-only")
+            }
         }
     }
 }
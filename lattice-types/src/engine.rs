@@ -0,0 +1,85 @@
+//! The interface shared by search engines.
+
+use crate::{DocId, DocumentError, SearchResult};
+
+/// A full-text index that documents are added to and searched.
+///
+/// Implemented by the engines in `lattice-core`, so applications can be
+/// written against the interface and tested with a stand-in.
+///
+/// # Example
+///
+/// ```
+/// use lattice_types::{DocId, DocumentError, SearchEngine, SearchResult};
+///
+/// /// Matches documents containing the query as a substring.
+/// #[derive(Default)]
+/// struct Scan(Vec<String>);
+///
+/// impl SearchEngine for Scan {
+///     type Stats = usize;
+///
+///     fn add(&mut self, content: &str) -> Result<DocId, DocumentError> {
+///         self.0.push(content.to_owned());
+///         Ok((self.0.len() - 1) as DocId)
+///     }
+///
+///     fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult> {
+///         (0..self.0.len())
+///             .filter(|&i| self.0[i].contains(query))
+///             .map(|i| SearchResult::new(i as DocId, 1.0))
+///             .take(limit)
+///             .collect()
+///     }
+///
+///     fn len(&self) -> usize {
+///         self.0.len()
+///     }
+///
+///     fn stats(&self) -> usize {
+///         self.0.iter().map(String::len).sum()
+///     }
+/// }
+///
+/// fn index_all<E: SearchEngine>(engine: &mut E, docs: &[&str]) {
+///     for doc in docs {
+///         engine.add(doc).unwrap();
+///     }
+/// }
+///
+/// let mut engine = Scan::default();
+/// index_all(&mut engine, &["hello world", "goodbye"]);
+/// assert_eq!(engine.search("hello", 10)[0].doc_id, 0);
+/// assert_eq!(engine.len(), 2);
+/// ```
+pub trait SearchEngine {
+    /// Index statistics returned by [`stats`](Self::stats).
+    type Stats;
+
+    /// Adds a document and returns its ID.
+    ///
+    /// Not every engine accepts documents: read-only ones, such as the
+    /// frozen engine of `lattice-core`, refuse every call, so code adding
+    /// through this trait has to handle that error rather than assume
+    /// success.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DocumentError`] if the document is rejected, or
+    /// [`DocumentError::ReadOnly`] if the engine cannot be modified.
+    fn add(&mut self, content: &str) -> Result<DocId, DocumentError>;
+
+    /// Returns up to `limit` matches for `query`, best first.
+    fn search(&mut self, query: &str, limit: usize) -> Vec<SearchResult>;
+
+    /// Returns the number of documents in the index.
+    fn len(&self) -> usize;
+
+    /// Returns `true` if the index contains no documents.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns index statistics.
+    fn stats(&self) -> Self::Stats;
+}
@@ -9,13 +9,16 @@
 
 #![warn(missing_docs)]
 
+pub mod checksum;
 pub mod compression;
 pub mod doc;
+pub mod engine;
 pub mod search;
 pub mod trigram;
 
-pub use doc::{DocId, DocumentError};
-pub use search::{SearchConfig, SearchResult};
+pub use doc::{DocHandle, DocId, DocumentError};
+pub use engine::SearchEngine;
+pub use search::{ConfigError, SearchConfig, SearchError, SearchResult};
 pub use trigram::Trigram;
 
 #[cfg(test)]
@@ -36,6 +39,12 @@ mod tests {
         assert_eq!(r1.cmp(&r3), core::cmp::Ordering::Less); // doc 1 < doc 3
     }
 
+    #[test]
+    fn doc_id_width_follows_feature() {
+        let expected = if cfg!(feature = "large-corpus") { 8 } else { 4 };
+        assert_eq!(core::mem::size_of::<DocId>(), expected);
+    }
+
     #[test]
     fn trigram_from_bytes() {
         let t = Trigram::from_bytes(b'a', b'b', b'c');
@@ -49,6 +58,21 @@ mod tests {
         assert_eq!(t.as_u32(), 0x00616263);
     }
 
+    #[test]
+    fn crc32_known_values() {
+        use super::checksum::{crc32, Crc32};
+
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            crc32(b"The quick brown fox jumps over the lazy dog"),
+            0x414F_A339
+        );
+
+        let split = Crc32::new().update(b"12345").update(b"6789").finish();
+        assert_eq!(split, crc32(b"123456789"));
+    }
+
     // Delta encoding tests
     #[test]
     fn delta_encode_basic() {
@@ -237,4 +261,133 @@ mod tests {
             ratio * 100.0
         );
     }
+
+    #[test]
+    fn varint_streams_roundtrip() {
+        use std::io::ErrorKind;
+
+        let postings = [[3u32, 9, 9, 400].as_slice(), &[], &[u32::MAX]];
+        let mut writer = VarintWriter::new(Vec::new());
+        for list in postings {
+            writer.write_sorted(list).unwrap();
+        }
+        writer.write_delta(10).unwrap();
+        writer.write_delta(300).unwrap();
+        let err = writer.write_delta(299).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let err = writer.write_sorted(&[2, 1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        let written = writer.bytes_written();
+        let bytes = writer.into_inner();
+        assert_eq!(written, bytes.len() as u64);
+
+        let mut reader = VarintReader::new(bytes.as_slice());
+        let mut list = Vec::new();
+        for expected in postings {
+            reader.read_sorted(&mut list).unwrap();
+            assert_eq!(list, expected);
+        }
+        assert_eq!(reader.read_delta().unwrap(), Some(10));
+        assert_eq!(reader.read_delta().unwrap(), Some(300));
+        assert_eq!(reader.read_delta().unwrap(), None);
+        assert_eq!(reader.bytes_read(), written);
+
+        // Truncated and overlong input
+        let truncated = VarintReader::new([0x80u8].as_slice()).read_u32();
+        assert_eq!(truncated.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        let short = VarintReader::new([2u8, 1].as_slice()).read_sorted(&mut list);
+        assert_eq!(short.unwrap_err().kind(), ErrorKind::UnexpectedEof);
+        let overlong = [0xFFu8, 0xFF, 0xFF, 0xFF, 0x1F];
+        let err = VarintReader::new(overlong.as_slice()).read_u32();
+        assert_eq!(err.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn zigzag_and_u64_varints() {
+        for value in [0i64, -1, 1, -64, 64, i32::MIN as i64, i64::MAX, i64::MIN] {
+            assert_eq!(zigzag_decode(zigzag_encode(value)), value);
+        }
+        assert_eq!(zigzag_encode(-64), 127);
+        assert_eq!(varint_len(zigzag_encode(-64)), 1);
+
+        let mut buf = [0u8; 10];
+        for value in [0u64, 127, 128, u32::MAX as u64 + 1, u64::MAX] {
+            let len = encode_varint_u64(value, &mut buf);
+            assert_eq!(len, varint_len(value));
+            assert_eq!(decode_varint_u64(&buf[..len]), Ok((value, len)));
+        }
+        assert_eq!(encode_varint_u64(u64::MAX, &mut buf), max_varint_u64_len());
+        let mut overlong = [0xFFu8; 10];
+        overlong[9] = 0x02;
+        assert_eq!(
+            decode_varint_u64(&overlong),
+            Err(CompressionError::InvalidVarint)
+        );
+        assert_eq!(
+            decode_varint_u64(&[0x80]),
+            Err(CompressionError::BufferTooSmall)
+        );
+        // The u32 codec reads what the u64 one writes for small values
+        let len = encode_varint_i64(-2, &mut buf);
+        assert_eq!(decode_varint(&buf[..len]), Ok((3, 1)));
+
+        let mut writer = VarintWriter::new(Vec::new());
+        writer.write_u64(u64::MAX).unwrap();
+        writer.write_i64(-1_000_000).unwrap();
+        writer.write_u64(1 << 32).unwrap();
+        let bytes = writer.into_inner();
+        let mut reader = VarintReader::new(bytes.as_slice());
+        assert_eq!(reader.read_u64().unwrap(), Some(u64::MAX));
+        assert_eq!(reader.read_i64().unwrap(), Some(-1_000_000));
+        let err = reader.read_u32().unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn stream_vbyte_matches_scalar_decoding() {
+        // Mixed lengths, enough groups for the SIMD path plus every tail size
+        let mut state = 0x2545_F491u32;
+        let values: Vec<u32> = (0..203)
+            .map(|i| {
+                state ^= state << 13;
+                state ^= state >> 17;
+                state ^= state << 5;
+                state >> (8 * (i % 4))
+            })
+            .collect();
+        for count in [0, 1, 2, 3, 4, 5, 64, 199, 203] {
+            let mut bytes = vec![0xAA];
+            let written = stream_vbyte_encode(&values[..count], &mut bytes);
+            assert_eq!(written, bytes.len() - 1);
+
+            let mut decoded = vec![7];
+            assert_eq!(
+                stream_vbyte_decode(&bytes[1..], count, &mut decoded),
+                Ok(written)
+            );
+            assert_eq!(decoded, &values[..count]);
+            if count > 0 {
+                assert_eq!(
+                    stream_vbyte_decode(&bytes[1..written], count, &mut decoded),
+                    Err(CompressionError::BufferTooSmall)
+                );
+            }
+        }
+
+        // Batched classic varints agree with one-at-a-time decoding
+        let mut bytes = Vec::new();
+        let mut buf = [0u8; 5];
+        for &value in &values {
+            let len = encode_varint(value % 300, &mut buf);
+            bytes.extend_from_slice(&buf[..len]);
+        }
+        let mut decoded = Vec::new();
+        decode_varints(&bytes, &mut decoded).unwrap();
+        let expected: Vec<u32> = values.iter().map(|v| v % 300).collect();
+        assert_eq!(decoded, expected);
+        assert_eq!(
+            decode_varints(&[1, 2, 0x80], &mut decoded),
+            Err(CompressionError::BufferTooSmall)
+        );
+    }
 }
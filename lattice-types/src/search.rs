@@ -68,6 +68,20 @@ pub struct SearchConfig {
     pub enable_fuzzy: bool,
     /// Maximum edit distance for fuzzy matching (0 = exact only).
     pub max_edit_distance: u8,
+    /// Matches scoring below this are dropped before the top results are
+    /// selected, so permissive overlap ratios don't fill results with
+    /// near-zero-score matches.
+    /// Default: None (no floor)
+    pub min_score: Option<f32>,
+    /// Most distinct trigrams of a query used for matching. A longer query
+    /// is represented by its rarest trigrams, wherever they occur in it.
+    /// Default: 30
+    pub max_query_trigrams: usize,
+    /// Longest query accepted, in bytes. Longer queries match nothing, and
+    /// `try_search` reports them as [`SearchError::QueryTooLong`]. At most
+    /// [`MAX_QUERY_LENGTH`](Self::MAX_QUERY_LENGTH).
+    /// Default: 1000
+    pub max_query_length: usize,
 }
 
 impl Default for SearchConfig {
@@ -76,17 +90,96 @@ impl Default for SearchConfig {
             min_overlap_ratio: 0.3,
             enable_fuzzy: true,
             max_edit_distance: 2,
+            min_score: None,
+            max_query_trigrams: Self::DEFAULT_MAX_QUERY_TRIGRAMS,
+            max_query_length: Self::DEFAULT_MAX_QUERY_LENGTH,
         }
     }
 }
 
 impl SearchConfig {
+    /// Largest supported [`max_edit_distance`](Self::max_edit_distance).
+    /// Beyond it nearly every dictionary term is a correction candidate.
+    pub const MAX_EDIT_DISTANCE: u8 = 4;
+
+    /// Default [`max_query_trigrams`](Self::max_query_trigrams).
+    pub const DEFAULT_MAX_QUERY_TRIGRAMS: usize = 30;
+
+    /// Default [`max_query_length`](Self::max_query_length), in bytes.
+    pub const DEFAULT_MAX_QUERY_LENGTH: usize = 1_000;
+
+    /// Largest supported [`max_query_length`](Self::max_query_length), in
+    /// bytes. Match counts of longer queries would not fit their 16-bit
+    /// counters.
+    pub const MAX_QUERY_LENGTH: usize = 32_768;
+
+    /// Checks that every option is within its supported range.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first out-of-range option as a [`ConfigError`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use lattice_types::{ConfigError, SearchConfig};
+    ///
+    /// assert!(SearchConfig::default().validate().is_ok());
+    ///
+    /// let config = SearchConfig {
+    ///     min_overlap_ratio: 1.5,
+    ///     ..SearchConfig::default()
+    /// };
+    /// assert_eq!(
+    ///     config.validate(),
+    ///     Err(ConfigError::OverlapRatioOutOfRange { ratio: 1.5 })
+    /// );
+    /// ```
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(0.0..=1.0).contains(&self.min_overlap_ratio) {
+            return Err(ConfigError::OverlapRatioOutOfRange {
+                ratio: self.min_overlap_ratio,
+            });
+        }
+        if self.max_edit_distance > Self::MAX_EDIT_DISTANCE {
+            return Err(ConfigError::EditDistanceTooLarge {
+                distance: self.max_edit_distance,
+                max: Self::MAX_EDIT_DISTANCE,
+            });
+        }
+        if let Some(score) = self.min_score.filter(|score| !score.is_finite()) {
+            return Err(ConfigError::InvalidMinScore { score });
+        }
+        if self.max_query_trigrams == 0 {
+            return Err(ConfigError::InvalidOption {
+                option: "max_query_trigrams",
+                reason: "must be at least 1",
+            });
+        }
+        if self.max_query_length == 0 {
+            return Err(ConfigError::InvalidOption {
+                option: "max_query_length",
+                reason: "must be at least 1",
+            });
+        }
+        if self.max_query_length > Self::MAX_QUERY_LENGTH {
+            return Err(ConfigError::InvalidOption {
+                option: "max_query_length",
+                reason: "must be at most SearchConfig::MAX_QUERY_LENGTH",
+            });
+        }
+        Ok(())
+    }
+
     /// Creates a configuration for exact matching only (no fuzziness).
     pub const fn exact() -> Self {
         Self {
             min_overlap_ratio: 0.5,
             enable_fuzzy: false,
             max_edit_distance: 0,
+            min_score: None,
+            max_query_trigrams: Self::DEFAULT_MAX_QUERY_TRIGRAMS,
+            max_query_length: Self::DEFAULT_MAX_QUERY_LENGTH,
         }
     }
 
@@ -96,6 +189,114 @@ impl SearchConfig {
             min_overlap_ratio: 0.2,
             enable_fuzzy: true,
             max_edit_distance: 2,
+            min_score: None,
+            max_query_trigrams: Self::DEFAULT_MAX_QUERY_TRIGRAMS,
+            max_query_length: Self::DEFAULT_MAX_QUERY_LENGTH,
         }
     }
 }
+
+/// Errors returned by [`SearchConfig::validate`] and when building an
+/// engine from invalid options.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConfigError {
+    /// `min_overlap_ratio` is outside 0.0-1.0 (or NaN).
+    OverlapRatioOutOfRange {
+        /// The configured ratio.
+        ratio: f32,
+    },
+    /// `max_edit_distance` exceeds [`SearchConfig::MAX_EDIT_DISTANCE`].
+    EditDistanceTooLarge {
+        /// The configured distance.
+        distance: u8,
+        /// The largest supported distance.
+        max: u8,
+    },
+    /// `min_score` is NaN or infinite.
+    InvalidMinScore {
+        /// The configured floor.
+        score: f32,
+    },
+    /// Another engine option is out of range.
+    InvalidOption {
+        /// Name of the option.
+        option: &'static str,
+        /// What is wrong with it.
+        reason: &'static str,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::OverlapRatioOutOfRange { ratio } => {
+                write!(f, "min_overlap_ratio {} is outside 0.0-1.0", ratio)
+            }
+            ConfigError::EditDistanceTooLarge { distance, max } => write!(
+                f,
+                "max_edit_distance {} exceeds the supported maximum of {}",
+                distance, max
+            ),
+            ConfigError::InvalidMinScore { score } => {
+                write!(f, "min_score {} is not a finite number", score)
+            }
+            ConfigError::InvalidOption { option, reason } => write!(f, "{}: {}", option, reason),
+        }
+    }
+}
+
+impl core::error::Error for ConfigError {}
+
+/// Why a query could not be run, from the `try_search` methods.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchError {
+    /// The query exceeds [`SearchConfig::max_query_length`].
+    QueryTooLong {
+        /// The query's length in bytes.
+        length: usize,
+        /// The longest query accepted, in bytes.
+        max_length: usize,
+    },
+    /// The normalized query is too short to hold a trigram.
+    QueryTooShort {
+        /// The normalized query's length in bytes.
+        length: usize,
+        /// The shortest query that can match, in bytes.
+        min_length: usize,
+    },
+    /// Even the query's rarest trigram occurs in more documents than can
+    /// be ranked.
+    TooManyCandidates {
+        /// Documents containing the rarest trigram.
+        candidates: usize,
+        /// The most candidates ranked.
+        max: usize,
+    },
+    /// No document has been added.
+    IndexEmpty,
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::QueryTooLong { length, max_length } => write!(
+                f,
+                "query too long: {} bytes (max: {} bytes)",
+                length, max_length
+            ),
+            SearchError::QueryTooShort { length, min_length } => write!(
+                f,
+                "query too short: {} bytes (min: {} bytes)",
+                length, min_length
+            ),
+            SearchError::TooManyCandidates { candidates, max } => write!(
+                f,
+                "too many candidates: {} documents (max: {})",
+                candidates, max
+            ),
+            SearchError::IndexEmpty => write!(f, "index is empty"),
+        }
+    }
+}
+
+impl core::error::Error for SearchError {}
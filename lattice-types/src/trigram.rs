@@ -24,6 +24,7 @@ impl Trigram {
     /// Creates a trigram from a string slice.
     /// Panics if the slice is shorter than 3 bytes.
     #[inline(always)]
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         let bytes = s.as_bytes();
         debug_assert!(bytes.len() >= 3, "trigram requires at least 3 bytes");